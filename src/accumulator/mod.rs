@@ -0,0 +1,112 @@
+//! What every construction in the crate offers, so code can be written once over any of
+//! them. [`SetAccumulator`] is the RSA one, with constant size proofs over a modulus
+//! someone has to generate, [`MerkleAccumulator`](crate::merkle::MerkleAccumulator) needs
+//! no setup and proves membership with a path of hashes.
+
+use crate::error::AccumulatorError;
+#[cfg(feature = "std")]
+use crate::proof::MembershipProof;
+#[cfg(feature = "std")]
+use crate::store::Storer;
+#[cfg(feature = "std")]
+use crate::SetAccumulator;
+
+/// A dynamic accumulator over byte strings.
+pub trait Accumulator {
+    /// What proves that a value is a member.
+    type Proof;
+
+    fn add(&mut self, value: &[u8]) -> Result<(), AccumulatorError>;
+    fn delete(&mut self, value: &[u8]) -> Result<(), AccumulatorError>;
+    /// A proof that `value` is a member of the current state, None if it is not one.
+    fn prove(&self, value: &[u8]) -> Option<Self::Proof>;
+    /// Whether `proof` shows `value` to be a member of the current state.
+    fn verify(&self, value: &[u8], proof: &Self::Proof) -> bool;
+    /// A hash committing to the current state and to everything needed to check proofs
+    /// against it, so two accumulators can be compared without exchanging their states.
+    fn state_digest(&self) -> [u8; 32];
+}
+
+#[cfg(feature = "std")]
+impl<T: Storer> Accumulator for SetAccumulator<T> {
+    type Proof = MembershipProof;
+
+    fn add(&mut self, value: &[u8]) -> Result<(), AccumulatorError> {
+        SetAccumulator::add(self, value).map(|_| ())
+    }
+    /// Deletes with the trapdoor when the store holds it, by recomputing the state from
+    /// the remaining members when it does not.
+    fn delete(&mut self, value: &[u8]) -> Result<(), AccumulatorError> {
+        match SetAccumulator::delete(self, value) {
+            Err(AccumulatorError::MissingTrapdoor) => self.delete_without_trapdoor(value).map(|_| ()),
+            result => result,
+        }
+    }
+    fn prove(&self, value: &[u8]) -> Option<MembershipProof> {
+        self.get_membership_proof(value)
+    }
+    fn verify(&self, value: &[u8], proof: &MembershipProof) -> bool {
+        SetAccumulator::verify(self, value, &proof.witness, &proof.nonce)
+    }
+    /// See [`SetAccumulator::state_digest`].
+    fn state_digest(&self) -> [u8; 32] {
+        SetAccumulator::state_digest(self)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use crate::merkle::MerkleAccumulator;
+    use crate::params::{PublicParameters, Trapdoor};
+    use crate::setup::generate_modulus;
+    use crate::sharded::ShardedAccumulator;
+    use crate::store::mem_store::MemStore;
+
+    // exercises an accumulator through the trait alone
+    fn round_trip<A: Accumulator>(accumulator: &mut A) {
+        let empty: [u8; 32] = accumulator.state_digest();
+        accumulator.add(b"alice").unwrap();
+        accumulator.add(b"bob").unwrap();
+        let proof: A::Proof = accumulator.prove(b"alice").unwrap();
+        assert!(accumulator.verify(b"alice", &proof));
+        assert!(!accumulator.verify(b"carol", &proof));
+        assert!(accumulator.prove(b"carol").is_none());
+
+        let full: [u8; 32] = accumulator.state_digest();
+        assert_ne!(empty, full);
+        accumulator.delete(b"bob").unwrap();
+        assert_eq!(Err(AccumulatorError::NotAMember), accumulator.delete(b"bob"));
+        assert_ne!(full, accumulator.state_digest());
+        accumulator.delete(b"alice").unwrap();
+        assert_eq!(empty, accumulator.state_digest());
+    }
+
+    // a toy modulus has a group small enough for a witness to pass for another value now
+    // and then, which the round trip checks against, so this takes a real one
+    fn params() -> (PublicParameters, Trapdoor) {
+        let (modulus, trapdoor): (BigUint, Option<Trapdoor>) = generate_modulus(128, true);
+        (PublicParameters::new(modulus, BigUint::from(4_u64)), trapdoor.unwrap())
+    }
+
+    #[test]
+    fn test_set_accumulator() {
+        let (params, trapdoor): (PublicParameters, Trapdoor) = params();
+        round_trip(&mut SetAccumulator::new(MemStore::from_parameters(&params)));
+        round_trip(&mut SetAccumulator::new(MemStore::from_parameters(&params).with_trapdoor(trapdoor)));
+    }
+
+    #[test]
+    fn test_merkle_accumulator() {
+        let (params, _): (PublicParameters, Trapdoor) = params();
+        round_trip(&mut MerkleAccumulator::new(MemStore::from_parameters(&params)));
+    }
+
+    #[test]
+    fn test_sharded_accumulator() {
+        let (params, _): (PublicParameters, Trapdoor) = params();
+        let stores: Vec<MemStore> = (0..3).map(|_| MemStore::from_parameters(&params)).collect();
+        round_trip(&mut ShardedAccumulator::new(stores).unwrap());
+    }
+}
@@ -0,0 +1,119 @@
+//! Aggregation of membership witnesses. Witnesses for distinct members of the same state
+//! are merged with Shamir's trick into one witness for the product of their primes, so
+//! a proof for any number of members is a single group element plus their nonces.
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::{ExtendedGcd, Integer};
+use num_traits::One;
+use crate::parallel::*;
+
+use crate::params::{PrimeMapping, SecurityLevel};
+use crate::proof::MembershipProof;
+use crate::{arith, member_prime, product_tree};
+
+/// One witness for several members, checked with [`verify_aggregate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregateProof {
+    pub nonces: Vec<Vec<u8>>,
+    pub witness: BigUint,
+}
+
+// base^exponent for a signed exponent, None when base has no inverse mod modulus
+pub(crate) fn pow_signed(base: &BigUint, exponent: &BigInt, modulus: &BigUint) -> Option<BigUint> {
+    let magnitude: &BigUint = exponent.magnitude();
+    match exponent.sign() {
+        Sign::Minus => Some(base.modinv(modulus)?.modpow(magnitude, modulus)),
+        _ => Some(base.modpow(magnitude, modulus)),
+    }
+}
+
+/// Given w1^x1 = w2^x2 = state with coprime x1 and x2, returns w with w^(x1 x2) = state,
+/// namely w1^b w2^a for the Bezout coefficients a x1 + b x2 = 1. None if the exponents
+/// share a factor or the witnesses are not for the same state.
+pub fn shamir_trick(modulus: &BigUint, w1: &BigUint, x1: &BigUint, w2: &BigUint, x2: &BigUint) -> Option<BigUint> {
+    if w1.modpow(x1, modulus) != w2.modpow(x2, modulus) {
+        return None;
+    }
+    let x1_signed: BigInt = BigInt::from(x1.clone());
+    let x2_signed: BigInt = BigInt::from(x2.clone());
+    let bezout: ExtendedGcd<BigInt> = x1_signed.extended_gcd(&x2_signed);
+    if !bezout.gcd.is_one() {
+        return None;
+    }
+    let left: BigUint = pow_signed(w1, &bezout.y, modulus)?;
+    let right: BigUint = pow_signed(w2, &bezout.x, modulus)?;
+    Some((left * right) % modulus)
+}
+
+/// Aggregates the proofs of `values`, which must be distinct members of the same state of
+/// an accumulator mapping values to primes with `prime_mapping`.
+pub fn aggregate(modulus: &BigUint, values: &[&[u8]], proofs: &[MembershipProof], prime_mapping: PrimeMapping) -> Option<AggregateProof> {
+    if values.is_empty() || values.len() != proofs.len() {
+        return None;
+    }
+    let primes: Vec<BigUint> = values
+        .par_iter()
+        .zip(proofs.par_iter())
+        .map(|(value, proof)| member_prime(value, &proof.nonce, prime_mapping, SecurityLevel::default().primality()))
+        .collect();
+    // fold the witnesses in one at a time, carrying the product of the primes so far
+    let mut witness: BigUint = proofs[0].witness.clone();
+    let mut exponent: BigUint = primes[0].clone();
+    for (proof, prime) in proofs.iter().zip(primes.iter()).skip(1) {
+        witness = shamir_trick(modulus, &witness, &exponent, &proof.witness, prime)?;
+        exponent *= prime;
+    }
+    Some(AggregateProof { nonces: proofs.iter().map(|proof| proof.nonce.clone()).collect(), witness })
+}
+
+/// Checks that every one of `values` is a member of the accumulator at `state`, with a
+/// single exponentiation by the product of their primes.
+pub fn verify_aggregate(state: &BigUint, modulus: &BigUint, values: &[&[u8]], proof: &AggregateProof, prime_mapping: PrimeMapping) -> bool {
+    if values.len() != proof.nonces.len() {
+        return false;
+    }
+    let primes: Vec<BigUint> = values
+        .par_iter()
+        .zip(proof.nonces.par_iter())
+        .map(|(value, nonce)| member_prime(value, nonce, prime_mapping, SecurityLevel::default().primality()))
+        .collect();
+    arith::modpow(&proof.witness, &product_tree::product(primes), modulus) == *state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::{HashFunction, PublicParameters, Trapdoor};
+    use crate::setup::generate_modulus;
+    use crate::store::mem_store::MemStore;
+    use crate::store::Storer;
+    use crate::SetAccumulator;
+    use num_bigint::RandBigInt;
+
+    #[test]
+    fn test_aggregate() {
+        // Shamir's trick inverts witnesses, which a random odd modulus would often share a
+        // small factor with, so this needs a proper one
+        let (modulus, _): (BigUint, Option<Trapdoor>) = generate_modulus(128, false);
+        let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(
+            &PublicParameters::new(modulus.clone(), generator).with_hash_function(HashFunction::Blake3),
+        ));
+        let values: Vec<&[u8]> = vec![b"alice", b"bob", b"carol", b"dave"];
+        sa.add_batch(&values).unwrap();
+        let state: BigUint = sa.store.get_state().clone();
+
+        let proofs: Vec<MembershipProof> = values[..3].iter().map(|value| sa.get_membership_proof(value).unwrap()).collect();
+        let proof: AggregateProof = aggregate(&modulus, &values[..3], &proofs, sa.prime_mapping()).unwrap();
+        assert!(verify_aggregate(&state, &modulus, &values[..3], &proof, sa.prime_mapping()));
+        // the aggregate covers exactly the values it was built for
+        assert!(!verify_aggregate(&state, &modulus, &values[1..], &proof, sa.prime_mapping()));
+        assert!(!verify_aggregate(&state, &modulus, &values[..2], &proof, sa.prime_mapping()));
+
+        // the same member twice cannot be aggregated, neither can a stale witness
+        assert!(aggregate(&modulus, &[values[0], values[0]], &[proofs[0].clone(), proofs[0].clone()], sa.prime_mapping()).is_none());
+        sa.add(b"erin").unwrap();
+        let fresh: MembershipProof = sa.get_membership_proof(b"dave").unwrap();
+        assert!(aggregate(&modulus, &[values[0], values[3]], &[proofs[0].clone(), fresh], sa.prime_mapping()).is_none());
+    }
+}
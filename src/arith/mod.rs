@@ -0,0 +1,158 @@
+//! The big integer arithmetic the accumulator spends its time in: exponentiation modulo
+//! the modulus, and multiplying primes together. num-bigint does it by default. The
+//! `gmp` feature moves it onto GMP through `rug::Integer`, linked against the system's
+//! libgmp, which is faster at the sizes the accumulator works with and pulls further
+//! ahead on the long exponents of witness computation. Numbers go in and come out as
+//! [`BigUint`] either way, so the choice never shows outside this module.
+//!
+//! Both are variable time, which is harmless for the public exponents almost every
+//! operation uses but leaks the exponents derived from the trapdoor. [`modpow_secret`]
+//! takes those, and with the `constant_time` feature runs them on crypto-bigint's
+//! constant-time Montgomery exponentiation, over the full width of the modulus whatever
+//! the exponent's own length.
+
+#[cfg(feature = "constant_time")]
+use crypto_bigint::modular::{BoxedMontyForm, BoxedMontyParams};
+#[cfg(feature = "constant_time")]
+use crypto_bigint::{BoxedUint, Odd};
+use num_bigint::BigUint;
+#[cfg(feature = "gmp")]
+use rug::integer::Order;
+#[cfg(feature = "gmp")]
+use rug::Integer;
+#[cfg(feature = "constant_time")]
+use alloc::vec::Vec;
+#[cfg(feature = "constant_time")]
+use zeroize::Zeroizing;
+
+use crate::secret::SecretBigUint;
+
+/// One way of doing the arithmetic, so both can be run side by side, as the bench does.
+pub trait Arithmetic {
+    fn modpow(base: &BigUint, exponent: &BigUint, modulus: &BigUint) -> BigUint;
+    fn mul(a: &BigUint, b: &BigUint) -> BigUint;
+}
+
+/// The arithmetic of num-bigint.
+#[derive(Debug, Clone, Copy)]
+pub struct NumBigint;
+
+impl Arithmetic for NumBigint {
+    fn modpow(base: &BigUint, exponent: &BigUint, modulus: &BigUint) -> BigUint {
+        base.modpow(exponent, modulus)
+    }
+    fn mul(a: &BigUint, b: &BigUint) -> BigUint {
+        a * b
+    }
+}
+
+/// The arithmetic of GMP, with every number copied in and out limb by limb.
+#[cfg(feature = "gmp")]
+#[derive(Debug, Clone, Copy)]
+pub struct Gmp;
+
+#[cfg(feature = "gmp")]
+fn to_integer(value: &BigUint) -> Integer {
+    Integer::from_digits(&value.to_u64_digits(), Order::Lsf)
+}
+
+#[cfg(feature = "gmp")]
+fn from_integer(value: &Integer) -> BigUint {
+    BigUint::from_bytes_le(&value.to_digits::<u8>(Order::Lsf))
+}
+
+#[cfg(feature = "gmp")]
+impl Arithmetic for Gmp {
+    fn modpow(base: &BigUint, exponent: &BigUint, modulus: &BigUint) -> BigUint {
+        // num-bigint panics on a zero modulus, keep to that rather than GMP's error
+        assert!(*modulus != BigUint::default(), "attempt to calculate with zero modulus!");
+        let result: Integer = to_integer(base)
+            .pow_mod(&to_integer(exponent), &to_integer(modulus))
+            .expect("a non-negative exponent always has a result");
+        from_integer(&result)
+    }
+    fn mul(a: &BigUint, b: &BigUint) -> BigUint {
+        from_integer(&(to_integer(a) * to_integer(b)))
+    }
+}
+
+/// The arithmetic the accumulator runs on, GMP with the `gmp` feature and num-bigint
+/// without it.
+#[cfg(feature = "gmp")]
+pub type Backend = Gmp;
+#[cfg(not(feature = "gmp"))]
+pub type Backend = NumBigint;
+
+pub fn modpow(base: &BigUint, exponent: &BigUint, modulus: &BigUint) -> BigUint {
+    Backend::modpow(base, exponent, modulus)
+}
+
+pub fn mul(a: &BigUint, b: &BigUint) -> BigUint {
+    Backend::mul(a, b)
+}
+
+/// `base^exponent mod modulus` for an exponent that has to stay secret, in constant time
+/// with the `constant_time` feature and on the [`Backend`] without it. `modulus` must be
+/// odd for the former, as an RSA modulus always is.
+#[cfg(feature = "constant_time")]
+pub fn modpow_secret(base: &BigUint, exponent: &SecretBigUint, modulus: &BigUint) -> BigUint {
+    // whole 64 bit words, so the precision says nothing about where the exponent ends
+    let precision = |bits: u64| -> u32 { (bits.div_ceil(64) * 64) as u32 };
+    let modulus_precision: u32 = precision(modulus.bits());
+    let decode = |bytes: &[u8], bits: u32| BoxedUint::from_be_slice(bytes, bits).expect("the precision fits the value");
+    let odd_modulus: Odd<BoxedUint> = Odd::new(decode(&modulus.to_bytes_be(), modulus_precision))
+        .into_option()
+        .expect("constant time exponentiation needs an odd modulus");
+    let params: BoxedMontyParams = BoxedMontyParams::new(odd_modulus);
+    let base: BoxedMontyForm = BoxedMontyForm::new(decode(&(base % modulus).to_bytes_be(), modulus_precision), params);
+    let exponent_bytes: Zeroizing<Vec<u8>> = Zeroizing::new(exponent.to_bytes_be());
+    let exponent: Zeroizing<BoxedUint> = Zeroizing::new(decode(&exponent_bytes[..], precision(exponent.bits()).max(modulus_precision)));
+    BigUint::from_bytes_be(&base.pow(&exponent).retrieve().to_be_bytes())
+}
+
+#[cfg(not(feature = "constant_time"))]
+pub fn modpow_secret(base: &BigUint, exponent: &SecretBigUint, modulus: &BigUint) -> BigUint {
+    modpow(base, exponent, modulus)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use num_bigint::RandBigInt;
+
+    #[test]
+    fn test_backends_agree() {
+        let mut rng = rand::thread_rng();
+        let modulus: BigUint = rng.gen_biguint(1024) | BigUint::from(1_u64);
+        for _ in 0..8 {
+            let base: BigUint = rng.gen_biguint(1100);
+            let exponent: BigUint = rng.gen_biguint(300);
+            let expected: BigUint = base.modpow(&exponent, &modulus);
+            assert_eq!(expected, modpow(&base, &exponent, &modulus));
+            assert_eq!(&base * &exponent, mul(&base, &exponent));
+            #[cfg(feature = "gmp")]
+            {
+                assert_eq!(expected, Gmp::modpow(&base, &exponent, &modulus));
+                assert_eq!(&base * &exponent, Gmp::mul(&base, &exponent));
+            }
+        }
+        // zero and one at either end
+        let zero: BigUint = BigUint::default();
+        assert_eq!(BigUint::from(1_u64), modpow(&zero, &zero, &modulus));
+        assert_eq!(zero, modpow(&BigUint::from(5_u64), &BigUint::from(3_u64), &BigUint::from(1_u64)));
+        assert_eq!(zero, mul(&zero, &modulus));
+    }
+
+    #[test]
+    fn test_modpow_secret() {
+        let mut rng = rand::thread_rng();
+        let modulus: BigUint = rng.gen_biguint(1024) | BigUint::from(1_u64);
+        for exponent_bits in [0_u64, 1, 64, 1000, 2000].iter() {
+            let base: BigUint = rng.gen_biguint(1100);
+            let exponent: SecretBigUint = SecretBigUint::new(rng.gen_biguint(*exponent_bits));
+            assert_eq!(base.modpow(&exponent, &modulus), modpow_secret(&base, &exponent, &modulus));
+        }
+        let small: BigUint = BigUint::from(3233_u64);
+        assert_eq!(BigUint::from(42_u64).modpow(&BigUint::from(7_u64), &small), modpow_secret(&BigUint::from(42_u64), &SecretBigUint::new(BigUint::from(7_u64)), &small));
+    }
+}
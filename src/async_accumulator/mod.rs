@@ -0,0 +1,307 @@
+//! An accumulator over stores that have to be awaited, such as ones backed by a remote
+//! service. The hash to prime searches and exponentiations still run on the calling task,
+//! so services with large member sets should drive it from a blocking-friendly context.
+
+use std::collections::HashMap;
+use std::future::Future;
+
+use num_bigint::BigUint;
+use crate::parallel::*;
+
+use crate::error::AccumulatorError;
+use crate::params::{DuplicatePolicy, NonceMode, PrimeMapping, SecurityLevel, Trapdoor};
+use crate::secret::SecretBigUint;
+use crate::store::Storer;
+use crate::{arith, hash_value_to_prime_with, join_occurrences, member_prime, occurrences, product_tree, verify_membership_with, AddOutcome, MemberIndex};
+
+/// The asynchronous counterpart of [`Storer`]. Every [`Storer`] that is `Send` is also an
+/// `AsyncStorer` whose futures are always ready.
+pub trait AsyncStorer {
+    fn get_generator(&mut self) -> impl Future<Output = BigUint> + Send;
+    fn get_modulus(&mut self) -> impl Future<Output = BigUint> + Send;
+    fn get_state(&mut self) -> impl Future<Output = BigUint> + Send;
+    fn set_state(&mut self, new_state: &BigUint) -> impl Future<Output = ()> + Send;
+    fn get_nonce(&mut self, value: &[u8]) -> impl Future<Output = Option<Vec<u8>>> + Send;
+    fn get_members(&mut self) -> impl Future<Output = HashMap<Vec<u8>, Vec<u8>>> + Send;
+    fn insert_member(&mut self, value: &[u8], nonce: &[u8]) -> impl Future<Output = ()> + Send;
+    fn remove_member(&mut self, value: &[u8]) -> impl Future<Output = Option<Vec<u8>>> + Send;
+    fn get_trapdoor(&mut self) -> impl Future<Output = Option<Trapdoor>> + Send;
+    /// Everything deciding the primes members are mapped to, see [`Storer::get_prime_mapping`].
+    fn get_prime_mapping(&mut self) -> impl Future<Output = PrimeMapping> + Send {
+        async { PrimeMapping::default() }
+    }
+}
+
+impl<T: Storer + Send> AsyncStorer for T {
+    async fn get_generator(&mut self) -> BigUint {
+        Storer::get_generator(self).clone()
+    }
+    async fn get_modulus(&mut self) -> BigUint {
+        Storer::get_modulus(self).clone()
+    }
+    async fn get_state(&mut self) -> BigUint {
+        Storer::get_state(self).clone()
+    }
+    async fn set_state(&mut self, new_state: &BigUint) {
+        Storer::set_state(self, new_state)
+    }
+    async fn get_nonce(&mut self, value: &[u8]) -> Option<Vec<u8>> {
+        Storer::get_nonce(self, value)
+    }
+    async fn get_members(&mut self) -> HashMap<Vec<u8>, Vec<u8>> {
+        Storer::iter_members(self).map(|(value, nonce)| (value.to_vec(), nonce.to_vec())).collect()
+    }
+    async fn insert_member(&mut self, value: &[u8], nonce: &[u8]) {
+        Storer::insert_member(self, value, nonce)
+    }
+    async fn remove_member(&mut self, value: &[u8]) -> Option<Vec<u8>> {
+        Storer::remove_member(self, value)
+    }
+    async fn get_trapdoor(&mut self) -> Option<Trapdoor> {
+        Storer::get_trapdoor(self)
+    }
+    async fn get_prime_mapping(&mut self) -> PrimeMapping {
+        Storer::get_prime_mapping(self)
+    }
+}
+
+/// [`SetAccumulator`](crate::SetAccumulator) over an [`AsyncStorer`].
+pub struct AsyncSetAccumulator<T: AsyncStorer> {
+    pub store: T,
+    security_level: SecurityLevel,
+    prime_mapping: PrimeMapping,
+    nonce_mode: NonceMode,
+    duplicate_policy: DuplicatePolicy,
+    // primes and nonces of the current members, read from the store the first time they are needed
+    member_index: Option<MemberIndex>,
+}
+
+impl<T: AsyncStorer> AsyncSetAccumulator<T> {
+    pub async fn new(mut store: T) -> AsyncSetAccumulator<T> {
+        let prime_mapping: PrimeMapping = store.get_prime_mapping().await;
+        AsyncSetAccumulator {
+            store,
+            security_level: SecurityLevel::default(),
+            prime_mapping,
+            nonce_mode: NonceMode::default(),
+            duplicate_policy: DuplicatePolicy::default(),
+            member_index: None,
+        }
+    }
+    pub fn with_nonce_mode(mut self, nonce_mode: NonceMode) -> Self {
+        self.nonce_mode = nonce_mode;
+        self
+    }
+    /// Sets what adding a value that is already a member does, see
+    /// [`SetAccumulator::with_duplicate_policy`](crate::SetAccumulator::with_duplicate_policy).
+    pub fn with_duplicate_policy(mut self, duplicate_policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = duplicate_policy;
+        self
+    }
+    pub fn prime_mapping(&self) -> PrimeMapping {
+        self.prime_mapping
+    }
+    async fn get_member_index(&mut self) -> &mut MemberIndex {
+        if self.member_index.is_none() {
+            let members: HashMap<Vec<u8>, Vec<u8>> = self.store.get_members().await;
+            self.member_index = Some(MemberIndex::new(&members, self.prime_mapping, self.security_level.primality()));
+        }
+        self.member_index.as_mut().unwrap()
+    }
+    // the nonce and prime of the last occurrence of a member held with `nonce`, and the
+    // nonce it is held with once that is gone, None if it is its only one
+    fn last_occurrence(&self, value: &[u8], nonce: &[u8]) -> (Vec<u8>, BigUint, Option<Vec<u8>>) {
+        let all: Vec<&[u8]> = occurrences(nonce);
+        let last: &[u8] = all[all.len() - 1];
+        let prime: BigUint = hash_value_to_prime_with(value, last, self.prime_mapping, self.security_level.primality());
+        let rest: Option<Vec<u8>> = if all.len() == 1 { None } else { Some(join_occurrences(&all[..all.len() - 1])) };
+        (last.to_vec(), prime, rest)
+    }
+    // drops the last occurrence of a member held with `nonce`, returning its nonce and prime
+    async fn remove_occurrence(&mut self, value: &[u8], nonce: &[u8]) -> (Vec<u8>, BigUint) {
+        let (last, prime, rest): (Vec<u8>, BigUint, Option<Vec<u8>>) = self.last_occurrence(value, nonce);
+        match rest {
+            Some(rest) => self.store.insert_member(value, &rest).await,
+            None => {
+                self.store.remove_member(value).await;
+            }
+        }
+        if let Some(member_index) = self.member_index.as_mut() {
+            member_index.remove(&prime, &last);
+        }
+        (last, prime)
+    }
+    async fn recompute_state(&mut self) -> BigUint {
+        let members: HashMap<Vec<u8>, Vec<u8>> = self.store.get_members().await;
+        let (prime_mapping, primality) = (self.prime_mapping, self.security_level.primality());
+        let primes: Vec<BigUint> = members
+            .par_iter()
+            .map(|(member, nonce)| member_prime(member, nonce, prime_mapping, primality))
+            .collect();
+        let exponent: BigUint = product_tree::product(primes);
+        arith::modpow(&self.store.get_generator().await, &exponent, &self.store.get_modulus().await)
+    }
+    /// Adds `value`, returning its witness along with its prime and nonce, see
+    /// [`SetAccumulator::add`](crate::SetAccumulator::add). Under the
+    /// [`DuplicatePolicy::Idempotent`] policy a value that already is a member is left be,
+    /// and the outcome is that of its last occurrence.
+    pub async fn add(&mut self, value: &[u8]) -> Result<AddOutcome, AccumulatorError> {
+        let held: Option<Vec<u8>> = self.store.get_nonce(value).await;
+        if let Some(held) = held.as_ref() {
+            match self.duplicate_policy {
+                DuplicatePolicy::ErrorOnDuplicate => return Err(AccumulatorError::AlreadyAMember),
+                DuplicatePolicy::Idempotent => {
+                    let (_, prime, _): (Vec<u8>, BigUint, Option<Vec<u8>>) = self.last_occurrence(value, held);
+                    let (witness, nonce): (BigUint, Vec<u8>) = self.get_witness(value).await.ok_or(AccumulatorError::NotAMember)?;
+                    return Ok(AddOutcome { prime, nonce, witness });
+                }
+                DuplicatePolicy::Multiset => {}
+            }
+        }
+        let (nonce_mode, prime_mapping, security_level) = (self.nonce_mode, self.prime_mapping, self.security_level);
+        let (nonce, exponent): (Vec<u8>, BigUint) = self.get_member_index().await
+            .draw_nonce(value, nonce_mode, prime_mapping, security_level.primality(), &mut rand::thread_rng(), &())?;
+        let modulus: BigUint = self.store.get_modulus().await;
+        let previous_state: BigUint = self.store.get_state().await;
+        let new_state: BigUint = arith::modpow(&previous_state, &exponent, &modulus);
+        // one more occurrence of a member is held along with the others
+        let held: Vec<u8> = match held {
+            Some(held) => join_occurrences(&[occurrences(&held), vec![nonce.as_slice()]].concat()),
+            None => nonce.clone(),
+        };
+        // the member goes in before the state, as in the synchronous accumulator
+        self.store.insert_member(value, &held).await;
+        self.store.set_state(&new_state).await;
+        // state = previous_state ^ prime, so the state before is the witness
+        Ok(AddOutcome { prime: exponent, nonce, witness: previous_state })
+    }
+    /// Removes `value` with the trapdoor held by the store, see
+    /// [`SetAccumulator::delete`](crate::SetAccumulator::delete).
+    pub async fn delete(&mut self, value: &[u8]) -> Result<(), AccumulatorError> {
+        let trapdoor: Trapdoor = self.store.get_trapdoor().await.ok_or(AccumulatorError::MissingTrapdoor)?;
+        let held: Vec<u8> = self.store.get_nonce(value).await.ok_or(AccumulatorError::NotAMember)?;
+        let (_, exponent): (Vec<u8>, BigUint) = self.remove_occurrence(value, &held).await;
+        let new_state: BigUint = match exponent.modinv(&trapdoor.phi()).map(SecretBigUint::new) {
+            Some(inverse) => trapdoor.modpow(&self.store.get_state().await, &inverse),
+            None => self.recompute_state().await,
+        };
+        self.store.set_state(&new_state).await;
+        Ok(())
+    }
+    /// Removes `value` by recomputing the state from the remaining members and returns the
+    /// new state.
+    pub async fn delete_without_trapdoor(&mut self, value: &[u8]) -> Result<BigUint, AccumulatorError> {
+        let held: Vec<u8> = self.store.get_nonce(value).await.ok_or(AccumulatorError::NotAMember)?;
+        self.remove_occurrence(value, &held).await;
+        let new_state: BigUint = self.recompute_state().await;
+        self.store.set_state(&new_state).await;
+        Ok(new_state)
+    }
+    /// The witness of the last occurrence of `value`, see
+    /// [`SetAccumulator::get_witness`](crate::SetAccumulator::get_witness).
+    pub async fn get_witness(&mut self, value: &[u8]) -> Option<(BigUint, Vec<u8>)> {
+        let held: Vec<u8> = self.store.get_nonce(value).await?;
+        let (nonce, _, rest): (Vec<u8>, BigUint, Option<Vec<u8>>) = self.last_occurrence(value, &held);
+        let members: HashMap<Vec<u8>, Vec<u8>> = self.store.get_members().await;
+        let (prime_mapping, primality) = (self.prime_mapping, self.security_level.primality());
+        let mut primes: Vec<BigUint> = members
+            .par_iter()
+            .filter(|(member, _)| member.as_slice() != value)
+            .map(|(member, nonce)| member_prime(member, nonce, prime_mapping, primality))
+            .collect();
+        // the other occurrences of the value count as other members
+        primes.extend(rest.map(|rest| member_prime(value, &rest, prime_mapping, primality)));
+        let exponent: BigUint = product_tree::product(primes);
+        let witness: BigUint = arith::modpow(&self.store.get_generator().await, &exponent, &self.store.get_modulus().await);
+        Some((witness, nonce))
+    }
+    /// Checks a witness against the current state of the store.
+    pub async fn verify(&mut self, value: &[u8], witness: &BigUint, nonce: &[u8]) -> bool {
+        let state: BigUint = self.store.get_state().await;
+        let modulus: BigUint = self.store.get_modulus().await;
+        verify_membership_with(self.prime_mapping, &state, &modulus, value, nonce, witness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use crate::params::PublicParameters;
+    use crate::store::mem_store::MemStore;
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    // the in-memory store never suspends, so polling once is enough
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker: Waker = Arc::new(NoopWaker).into();
+        match pin!(future).poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("the in-memory store should never suspend"),
+        }
+    }
+
+    fn assert_send<F: Future + Send>(future: F) -> F {
+        future
+    }
+
+    #[test]
+    fn test_async_accumulator() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut sa: AsyncSetAccumulator<MemStore> = block_on(AsyncSetAccumulator::new(MemStore::from_parameters(&params)));
+        block_on(assert_send(sa.add(b"alice"))).unwrap();
+        block_on(sa.add(b"bob")).unwrap();
+        // the outcome of an add holds the witness of the value added, good at the state it left
+        let added: AddOutcome = block_on(sa.add(b"carol")).unwrap();
+        assert_eq!(Some(added.nonce.clone()), sa.store.get_nonce(b"carol"));
+        assert_eq!(*sa.store.get_state(), added.witness.modpow(&added.prime, sa.store.get_modulus()));
+        assert!(block_on(sa.verify(b"carol", &added.witness, &added.nonce)));
+        let (witness, nonce): (BigUint, Vec<u8>) = block_on(sa.get_witness(b"alice")).unwrap();
+        assert!(block_on(sa.verify(b"alice", &witness, &nonce)));
+
+        block_on(sa.delete_without_trapdoor(b"bob")).unwrap();
+        assert_eq!(None, block_on(sa.get_witness(b"bob")));
+        assert_eq!(Err(AccumulatorError::MissingTrapdoor), block_on(sa.delete(b"carol")));
+        let (witness, nonce): (BigUint, Vec<u8>) = block_on(sa.get_witness(b"carol")).unwrap();
+        assert!(block_on(sa.verify(b"carol", &witness, &nonce)));
+        assert!(!block_on(sa.verify(b"alice", &BigUint::from(1_u64), &nonce)));
+        // adding a member again is refused by default
+        assert_eq!(Err(AccumulatorError::AlreadyAMember), block_on(sa.add(b"alice")).map(|_| ()));
+    }
+
+    #[test]
+    fn test_duplicate_policy() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut sa: AsyncSetAccumulator<MemStore> = block_on(AsyncSetAccumulator::new(MemStore::from_parameters(&params)))
+            .with_duplicate_policy(DuplicatePolicy::Idempotent);
+        let added: AddOutcome = block_on(sa.add(b"alice")).unwrap();
+        let state: BigUint = sa.store.get_state().clone();
+        // adding it again hands back the outcome of the add that made it a member
+        assert_eq!(added, block_on(sa.add(b"alice")).unwrap());
+        assert_eq!(state, *sa.store.get_state());
+
+        // a multiset member takes as many deletes as adds, and its witnesses hold throughout
+        let mut sa: AsyncSetAccumulator<MemStore> = block_on(AsyncSetAccumulator::new(MemStore::from_parameters(&params)))
+            .with_duplicate_policy(DuplicatePolicy::Multiset);
+        block_on(sa.add(b"alice")).unwrap();
+        block_on(sa.add(b"bob")).unwrap();
+        block_on(sa.add(b"alice")).unwrap();
+        let (witness, nonce): (BigUint, Vec<u8>) = block_on(sa.get_witness(b"alice")).unwrap();
+        assert!(block_on(sa.verify(b"alice", &witness, &nonce)));
+        let (witness, nonce): (BigUint, Vec<u8>) = block_on(sa.get_witness(b"bob")).unwrap();
+        assert!(block_on(sa.verify(b"bob", &witness, &nonce)));
+        block_on(sa.delete_without_trapdoor(b"alice")).unwrap();
+        let (witness, nonce): (BigUint, Vec<u8>) = block_on(sa.get_witness(b"alice")).unwrap();
+        assert!(block_on(sa.verify(b"alice", &witness, &nonce)));
+        block_on(sa.delete_without_trapdoor(b"alice")).unwrap();
+        assert_eq!(None, block_on(sa.get_witness(b"alice")));
+        let (witness, nonce): (BigUint, Vec<u8>) = block_on(sa.get_witness(b"bob")).unwrap();
+        assert!(block_on(sa.verify(b"bob", &witness, &nonce)));
+    }
+}
@@ -0,0 +1,135 @@
+//! Checking many membership proofs against one state at once. Every proof claims
+//! witness^prime = state, so raising each claim to a random 128 bit exponent r and
+//! multiplying them all together leaves the single check
+//! product(witness^(prime r)) = state^(sum r), which a forged proof among them passes with
+//! probability about 2^-128. The left hand side is one simultaneous multi-exponentiation
+//! that shares its squarings across the proofs, which makes it several times cheaper than
+//! exponentiating every witness on its own.
+//!
+//! A witness that is off by a factor of -1 can slip through when the exponents cancel its
+//! sign, but such a witness is no easier to find than the real one.
+
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use rand::Rng;
+
+use crate::member_prime;
+use crate::parallel::*;
+use crate::params::{PrimeMapping, SecurityLevel};
+use crate::proof::MembershipProof;
+
+// bits of the random exponents, and so the soundness of the check
+const BATCH_EXPONENT_BITS: u64 = 128;
+// bits of the exponents consumed per step of the multi-exponentiation
+const WINDOW_BITS: u64 = 4;
+
+/// Verifies every `(value, proof)` pair against `state` at once, see the module
+/// documentation. An empty batch verifies.
+pub fn verify_batch(state: &BigUint, modulus: &BigUint, prime_mapping: PrimeMapping, proofs: &[(&[u8], MembershipProof)]) -> bool {
+    verify_batch_with_rng(state, modulus, prime_mapping, proofs, &mut rand::thread_rng())
+}
+
+fn verify_batch_with_rng<R: Rng>(
+    state: &BigUint,
+    modulus: &BigUint,
+    prime_mapping: PrimeMapping,
+    proofs: &[(&[u8], MembershipProof)],
+    rng: &mut R,
+) -> bool {
+    // the primes are by far the most expensive part, so find them first and in parallel
+    let primes: Vec<BigUint> = proofs
+        .par_iter()
+        .map(|(value, proof)| member_prime(value, &proof.nonce, prime_mapping, SecurityLevel::default().primality()))
+        .collect();
+    let mut randomizer_sum: BigUint = Zero::zero();
+    let mut exponents: Vec<BigUint> = Vec::with_capacity(proofs.len());
+    for prime in primes {
+        let randomizer: BigUint = rng.gen_biguint(BATCH_EXPONENT_BITS);
+        exponents.push(prime * &randomizer);
+        randomizer_sum += randomizer;
+    }
+    let bases: Vec<&BigUint> = proofs.iter().map(|(_, proof)| &proof.witness).collect();
+    multi_exp(&bases, &exponents, modulus) == state.modpow(&randomizer_sum, modulus)
+}
+
+// product(bases[i]^exponents[i]) mod modulus, squaring once per bit for all of the bases
+// together and multiplying in a table entry per base for every window of exponent bits
+fn multi_exp(bases: &[&BigUint], exponents: &[BigUint], modulus: &BigUint) -> BigUint {
+    let tables: Vec<Vec<BigUint>> = bases
+        .iter()
+        .map(|base| {
+            let base: BigUint = *base % modulus;
+            let mut table: Vec<BigUint> = vec![One::one(), base.clone()];
+            for i in 2..(1 << WINDOW_BITS) {
+                let next: BigUint = (&table[i - 1] * &base) % modulus;
+                table.push(next);
+            }
+            table
+        })
+        .collect();
+    let bits: u64 = exponents.iter().map(|exponent| exponent.bits()).max().unwrap_or(0);
+    let mut result: BigUint = BigUint::one() % modulus;
+    for window in (0..bits.div_ceil(WINDOW_BITS)).rev() {
+        for _ in 0..WINDOW_BITS {
+            result = (&result * &result) % modulus;
+        }
+        for (table, exponent) in tables.iter().zip(exponents) {
+            let mut digit: usize = 0;
+            for k in 0..WINDOW_BITS {
+                if exponent.bit(window * WINDOW_BITS + k) {
+                    digit |= 1 << k;
+                }
+            }
+            if digit != 0 {
+                result = (&result * &table[digit]) % modulus;
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::PublicParameters;
+    use crate::store::mem_store::MemStore;
+    use crate::store::Storer;
+    use crate::SetAccumulator;
+
+    #[test]
+    fn test_multi_exp() {
+        let modulus: BigUint = BigUint::from(1_000_003_u64);
+        let bases: Vec<BigUint> = vec![BigUint::from(2_u64), BigUint::from(3_u64), BigUint::from(5_000_000_u64)];
+        let exponents: Vec<BigUint> = vec![BigUint::from(1_u64 << 40), BigUint::from(12345_u64), BigUint::from(0_u64)];
+        let expected: BigUint = bases.iter().zip(exponents.iter()).map(|(b, e)| b.modpow(e, &modulus)).product::<BigUint>() % &modulus;
+        let base_refs: Vec<&BigUint> = bases.iter().collect();
+        assert_eq!(expected, multi_exp(&base_refs, &exponents, &modulus));
+        assert_eq!(BigUint::from(1_u64), multi_exp(&[], &[], &modulus));
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        // a modulus this small could let a bad witness through by chance, so use two
+        // Mersenne primes whose product is big enough for that never to happen
+        let modulus: BigUint = ((BigUint::from(1_u64) << 61) - 1_u64) * ((BigUint::from(1_u64) << 89) - 1_u64);
+        let params: PublicParameters = PublicParameters::new(modulus, BigUint::from(3_u64));
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        let values: Vec<Vec<u8>> = (0..6_u8).map(|i| vec![i]).collect();
+        for value in values.iter() {
+            sa.add(value).unwrap();
+        }
+        let mut proofs: Vec<(&[u8], MembershipProof)> = values
+            .iter()
+            .map(|value| (value.as_slice(), sa.get_membership_proof(value).unwrap()))
+            .collect();
+        assert!(sa.verify_batch(&proofs));
+        assert!(sa.verify_batch(&[]));
+        // one bad witness spoils the batch
+        proofs[2].1.witness += 1_u64;
+        assert!(!sa.verify_batch(&proofs));
+        proofs[2].1.witness -= 1_u64;
+        // as does a proof for a value that is not a member
+        proofs[3].0 = b"mallory";
+        assert!(!verify_batch(sa.store.get_state(), &params.modulus, sa.prime_mapping(), &proofs));
+    }
+}
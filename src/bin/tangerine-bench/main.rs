@@ -0,0 +1,138 @@
+//! Drives a configurable workload against an in-memory accumulator and reports
+//! sustained throughput and latency percentiles, for sizing hardware.
+//!
+//!     tangerine-bench [--members N] [--ops N] [--witness-ratio R] [--modulus-bits B] [--threads T]
+//!
+//! The accumulator is first preloaded with `--members` values, then `--ops` operations
+//! are run, each a witness request with probability `--witness-ratio` and an add
+//! otherwise. The modulus is a random odd number of the requested size rather than a
+//! real RSA modulus: exponentiation cost depends only on its size, and this skips the
+//! prime generation.
+//!
+//! Before the workload, the exponentiation behind every add and witness is timed on
+//! num-bigint and, when built with the `gmp` feature, on GMP.
+
+use std::env;
+use std::process;
+use std::time::{Duration, Instant};
+
+use num_bigint::{BigUint, RandBigInt};
+use rand::Rng;
+
+use tangerine::SetAccumulator;
+use tangerine::arith::{Arithmetic, NumBigint};
+use tangerine::params::PublicParameters;
+use tangerine::store::mem_store::MemStore;
+
+struct Config {
+    members: usize,
+    ops: usize,
+    witness_ratio: f64,
+    modulus_bits: u64,
+    threads: usize,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: tangerine-bench [--members N] [--ops N] [--witness-ratio R] [--modulus-bits B] [--threads T]");
+    process::exit(2);
+}
+
+fn parse_args() -> Config {
+    let mut config: Config = Config { members: 1000, ops: 100, witness_ratio: 0.1, modulus_bits: 2048, threads: 0 };
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value: String = args.next().unwrap_or_else(|| usage());
+        let parsed: Result<(), String> = match flag.as_str() {
+            "--members" => value.parse().map(|v| config.members = v).map_err(|e| e.to_string()),
+            "--ops" => value.parse().map(|v| config.ops = v).map_err(|e| e.to_string()),
+            "--witness-ratio" => value.parse().map(|v| config.witness_ratio = v).map_err(|e| e.to_string()),
+            "--modulus-bits" => value.parse().map(|v| config.modulus_bits = v).map_err(|e| e.to_string()),
+            "--threads" => value.parse().map(|v| config.threads = v).map_err(|e| e.to_string()),
+            _ => usage(),
+        };
+        if let Err(e) = parsed {
+            eprintln!("invalid value {:?} for {}: {}", value, flag, e);
+            usage();
+        }
+    }
+    config
+}
+
+fn report(name: &str, mut latencies: Vec<Duration>, elapsed: Duration) {
+    if latencies.is_empty() {
+        return;
+    }
+    latencies.sort_unstable();
+    let percentile = |p: f64| latencies[((latencies.len() - 1) as f64 * p).round() as usize];
+    println!(
+        "{:<8} n={:<7} {:>9.1} ops/s  p50={:?} p90={:?} p99={:?} max={:?}",
+        name,
+        latencies.len(),
+        latencies.len() as f64 / elapsed.as_secs_f64(),
+        percentile(0.50),
+        percentile(0.90),
+        percentile(0.99),
+        latencies[latencies.len() - 1],
+    );
+}
+
+// exponentiations per second by `A` of random bases to member sized exponents
+fn time_modpow<A: Arithmetic>(modulus: &BigUint) -> f64 {
+    const ROUNDS: u32 = 200;
+    let mut rng = rand::thread_rng();
+    let base: BigUint = rng.gen_biguint_below(modulus);
+    let exponent: BigUint = rng.gen_biguint(256);
+    let started: Instant = Instant::now();
+    for _ in 0..ROUNDS {
+        A::modpow(&base, &exponent, modulus);
+    }
+    ROUNDS as f64 / started.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let config: Config = parse_args();
+    #[cfg(feature = "parallel")]
+    if config.threads > 0 {
+        // witness generation runs on the global rayon pool
+        rayon::ThreadPoolBuilder::new().num_threads(config.threads).build_global().unwrap();
+    }
+
+    let mut rng = rand::thread_rng();
+    let modulus: BigUint = rng.gen_biguint(config.modulus_bits) | BigUint::from(1_u64) | (BigUint::from(1_u64) << (config.modulus_bits - 1));
+    let generator: BigUint = rng.gen_biguint_below(&modulus);
+    println!("modpow   num-bigint {:>9.1} ops/s", time_modpow::<NumBigint>(&modulus));
+    #[cfg(feature = "gmp")]
+    println!("modpow   gmp        {:>9.1} ops/s", time_modpow::<tangerine::arith::Gmp>(&modulus));
+    let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&PublicParameters::new(modulus, generator)));
+
+    let started: Instant = Instant::now();
+    let mut values: Vec<Vec<u8>> = Vec::new();
+    for _ in 0..config.members {
+        let value: Vec<u8> = rng.gen::<[u8; 16]>().to_vec();
+        sa.add(&value).unwrap();
+        values.push(value);
+    }
+    println!("preloaded {} members into a {} bit accumulator in {:?}", config.members, config.modulus_bits, started.elapsed());
+
+    let mut add_latencies: Vec<Duration> = Vec::new();
+    let mut witness_latencies: Vec<Duration> = Vec::new();
+    let started: Instant = Instant::now();
+    for _ in 0..config.ops {
+        if !values.is_empty() && rng.gen_bool(config.witness_ratio) {
+            let value: &Vec<u8> = &values[rng.gen_range(0..values.len())];
+            let op_started: Instant = Instant::now();
+            sa.get_witness(value).unwrap();
+            witness_latencies.push(op_started.elapsed());
+        } else {
+            let value: Vec<u8> = rng.gen::<[u8; 16]>().to_vec();
+            let op_started: Instant = Instant::now();
+            sa.add(&value).unwrap();
+            add_latencies.push(op_started.elapsed());
+            values.push(value);
+        }
+    }
+    let elapsed: Duration = started.elapsed();
+    println!("ran {} operations in {:?} ({:.1} ops/s)", config.ops, elapsed, config.ops as f64 / elapsed.as_secs_f64());
+    report("add", add_latencies, elapsed);
+    report("witness", witness_latencies, elapsed);
+}
@@ -0,0 +1,83 @@
+//! Checks a proof bundle against trusted parameters and a trusted head, exiting with
+//! status 0 if the proof holds and 1 if it does not (2 on usage or I/O errors).
+//!
+//!     tangerine-verify --params FILE --trusted-head HEX [--bundle FILE]
+//!
+//! The bundle is read from stdin when `--bundle` is omitted or is `-`. `--trusted-head`
+//! is the hex digest of the head the proof must be against, obtained from a source the
+//! caller trusts. A bundle's own head is only asserted by whoever made the bundle, so
+//! without this anchor a forged head and witness would verify.
+
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, Read};
+use std::process;
+
+use tangerine::bundle::{self, HeadVerifier, ProofBundle};
+use tangerine::params::PublicParameters;
+
+// accepts exactly the head the caller pinned, whatever its signature
+struct PinnedHead([u8; 32]);
+
+impl HeadVerifier for PinnedHead {
+    fn verify(&self, digest: &[u8; 32], _signature: &[u8]) -> bool {
+        *digest == self.0
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("tangerine-verify: {}", message);
+    process::exit(2);
+}
+
+fn usage() -> ! {
+    fail("usage: tangerine-verify --params FILE --trusted-head HEX [--bundle FILE]");
+}
+
+fn parse_hex_digest(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let bytes: Option<Vec<u8>> = (0..32).map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()).collect();
+    bytes?.as_slice().try_into().ok()
+}
+
+fn open(path: &str) -> io::Result<Box<dyn Read>> {
+    if path == "-" {
+        Ok(Box::new(io::stdin()))
+    } else {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+fn main() {
+    let mut params_path: Option<String> = None;
+    let mut trusted_head: Option<[u8; 32]> = None;
+    let mut bundle_path: String = "-".to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value: String = args.next().unwrap_or_else(|| usage());
+        match flag.as_str() {
+            "--params" => params_path = Some(value),
+            "--trusted-head" => trusted_head = Some(parse_hex_digest(&value).unwrap_or_else(|| fail("--trusted-head must be 64 hex digits"))),
+            "--bundle" => bundle_path = value,
+            _ => usage(),
+        }
+    }
+    let params_path: String = params_path.unwrap_or_else(|| usage());
+    let trusted_head: [u8; 32] = trusted_head.unwrap_or_else(|| usage());
+
+    let params: PublicParameters = open(&params_path)
+        .and_then(PublicParameters::read_from)
+        .unwrap_or_else(|e| fail(&format!("reading parameters from {}: {}", params_path, e)));
+    let bundle: ProofBundle = open(&bundle_path)
+        .and_then(ProofBundle::read_from)
+        .unwrap_or_else(|e| fail(&format!("reading bundle from {}: {}", bundle_path, e)));
+
+    if bundle::verify_bundle(&bundle, &params, &PinnedHead(trusted_head)) {
+        println!("valid");
+    } else {
+        println!("invalid");
+        process::exit(1);
+    }
+}
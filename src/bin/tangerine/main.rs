@@ -0,0 +1,238 @@
+//! Runs a file-backed accumulator from the shell, for pipelines and for operators keeping
+//! revocation lists.
+//!
+//!     tangerine init --store FILE [--modulus-bits B] [--params-out FILE]
+//!     tangerine add --store FILE [FILE...]
+//!     tangerine witness --store FILE VALUE
+//!     tangerine verify (--store FILE | --params FILE --state HEX) [PROOF]
+//!     tangerine state --store FILE
+//!
+//! `init` creates the store over the RSA-2048 challenge modulus, or over a fresh modulus
+//! of `--modulus-bits` bits whose factorization is thrown away, and can write the
+//! parameters out for verifiers. `add` adds every line of the given files, or of stdin
+//! without any, skipping values that are already members. `witness` prints a proof for
+//! `VALUE`, which `verify` checks against the store's current state or against
+//! parameters and a state the caller trusts. The proof is read from stdin when `PROOF`
+//! is omitted or is `-`.
+//!
+//! Proofs are three lines, `value`, `nonce` and `witness`, each followed by a space and
+//! the bytes in hex. `verify` exits with status 0 if the proof holds and 1 if it does
+//! not, every command exits with 2 on usage or I/O errors.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process;
+
+use num_bigint::BigUint;
+
+use tangerine::params::{PublicParameters, RSA2048_GENERATOR};
+use tangerine::setup::generate_modulus;
+use tangerine::store::file_store::FileStore;
+use tangerine::store::Storer;
+use tangerine::{verify_membership_with, SetAccumulator};
+
+const USAGE: &str = "usage:
+    tangerine init --store FILE [--modulus-bits B] [--params-out FILE]
+    tangerine add --store FILE [FILE...]
+    tangerine witness --store FILE VALUE
+    tangerine verify (--store FILE | --params FILE --state HEX) [PROOF]
+    tangerine state --store FILE";
+
+fn fail(message: &str) -> ! {
+    eprintln!("tangerine: {}", message);
+    process::exit(2);
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect()
+}
+
+fn open(path: &str) -> io::Result<Box<dyn Read>> {
+    if path == "-" {
+        Ok(Box::new(io::stdin()))
+    } else {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+// the flags given as `--name value` and the remaining arguments, in order
+struct Args {
+    flags: Vec<(String, String)>,
+    positional: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: impl Iterator<Item = String>, known: &[&str]) -> Args {
+        let mut parsed: Args = Args { flags: Vec::new(), positional: Vec::new() };
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            if arg.starts_with("--") {
+                if !known.contains(&arg.as_str()) {
+                    usage();
+                }
+                let value: String = args.next().unwrap_or_else(|| usage());
+                parsed.flags.push((arg, value));
+            } else {
+                parsed.positional.push(arg);
+            }
+        }
+        parsed
+    }
+    fn get(&self, flag: &str) -> Option<&str> {
+        self.flags.iter().rev().find(|(name, _)| name == flag).map(|(_, value)| value.as_str())
+    }
+    fn store(&self) -> &str {
+        self.get("--store").unwrap_or_else(|| usage())
+    }
+}
+
+fn open_store(path: &str) -> SetAccumulator<FileStore> {
+    let store: FileStore = FileStore::open_existing(path).unwrap_or_else(|e| fail(&format!("opening store {}: {}", path, e)));
+    SetAccumulator::new(store)
+}
+
+// for commands that only read, which may run alongside each other
+fn open_store_read_only(path: &str) -> SetAccumulator<FileStore> {
+    let store: FileStore = FileStore::open_read_only(path).unwrap_or_else(|e| fail(&format!("opening store {}: {}", path, e)));
+    SetAccumulator::new(store)
+}
+
+fn init(args: Args) {
+    let path: &str = args.store();
+    if Path::new(path).exists() {
+        fail(&format!("{} already exists", path));
+    }
+    let params: PublicParameters = match args.get("--modulus-bits") {
+        None => PublicParameters::rsa2048(),
+        Some(bits) => {
+            let bits: usize = bits.parse().ok().filter(|bits: &usize| *bits >= 16 && bits.is_multiple_of(2)).unwrap_or_else(|| {
+                fail("--modulus-bits must be an even number, at least 16")
+            });
+            PublicParameters::new(generate_modulus(bits, false).0, BigUint::from(RSA2048_GENERATOR))
+        }
+    };
+    FileStore::open(path, &params).unwrap_or_else(|e| fail(&format!("creating store {}: {}", path, e)));
+    if let Some(params_path) = args.get("--params-out") {
+        File::create(params_path)
+            .and_then(|file| params.write_to(file))
+            .unwrap_or_else(|e| fail(&format!("writing parameters to {}: {}", params_path, e)));
+    }
+    println!("{}", to_hex(&params.digest()));
+}
+
+fn add(args: Args) {
+    let mut sa: SetAccumulator<FileStore> = open_store(args.store());
+    let sources: Vec<String> = if args.positional.is_empty() { vec!["-".to_string()] } else { args.positional.clone() };
+    let mut values: Vec<Vec<u8>> = Vec::new();
+    let mut seen: HashSet<Vec<u8>> = HashSet::new();
+    for source in sources.iter() {
+        let reader: Box<dyn Read> = open(source).unwrap_or_else(|e| fail(&format!("reading {}: {}", source, e)));
+        for line in BufReader::new(reader).lines() {
+            let line: String = line.unwrap_or_else(|e| fail(&format!("reading {}: {}", source, e)));
+            let value: Vec<u8> = line.into_bytes();
+            if sa.contains(&value) || !seen.insert(value.clone()) {
+                eprintln!("tangerine: skipping {}, already a member", String::from_utf8_lossy(&value));
+                continue;
+            }
+            values.push(value);
+        }
+    }
+    let refs: Vec<&[u8]> = values.iter().map(|value| value.as_slice()).collect();
+    sa.add_batch(&refs).unwrap_or_else(|e| fail(&format!("adding: {}", e)));
+    sa.store.sync().unwrap_or_else(|e| fail(&format!("syncing store: {}", e)));
+    eprintln!("tangerine: added {} values, {} members", values.len(), sa.len());
+}
+
+fn witness(args: Args) {
+    let sa: SetAccumulator<FileStore> = open_store_read_only(args.store());
+    let value: &str = match args.positional.as_slice() {
+        [value] => value,
+        _ => usage(),
+    };
+    let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(value.as_bytes()).unwrap_or_else(|| fail(&format!("{} is not a member", value)));
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    writeln!(out, "value {}", to_hex(value.as_bytes()))
+        .and_then(|_| writeln!(out, "nonce {}", to_hex(&nonce)))
+        .and_then(|_| writeln!(out, "witness {}", to_hex(&witness.to_bytes_be())))
+        .unwrap_or_else(|e| fail(&format!("writing proof: {}", e)));
+}
+
+// the value, nonce and witness of a proof in the format `witness` prints
+fn read_proof(reader: Box<dyn Read>) -> io::Result<(Vec<u8>, Vec<u8>, BigUint)> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut fields: [Option<Vec<u8>>; 3] = [None, None, None];
+    for line in BufReader::new(reader).lines() {
+        let line: String = line?;
+        let (name, hex) = line.split_once(' ').ok_or_else(|| invalid("malformed line in proof"))?;
+        let index: usize = ["value", "nonce", "witness"].iter().position(|field| *field == name).ok_or_else(|| invalid("unknown field in proof"))?;
+        fields[index] = Some(from_hex(hex).ok_or_else(|| invalid("malformed hex in proof"))?);
+    }
+    match fields {
+        [Some(value), Some(nonce), Some(witness)] => Ok((value, nonce, BigUint::from_bytes_be(&witness))),
+        _ => Err(invalid("proof is missing a field")),
+    }
+}
+
+fn verify(args: Args) {
+    let (params, state): (PublicParameters, BigUint) = match (args.get("--store"), args.get("--params"), args.get("--state")) {
+        (Some(path), None, None) => {
+            let sa: SetAccumulator<FileStore> = open_store_read_only(path);
+            (sa.get_public_parameters(), sa.store.get_state().clone())
+        }
+        (None, Some(path), Some(state)) => {
+            let params: PublicParameters = open(path)
+                .and_then(PublicParameters::read_from)
+                .unwrap_or_else(|e| fail(&format!("reading parameters from {}: {}", path, e)));
+            let state: Vec<u8> = from_hex(state).unwrap_or_else(|| fail("--state must be hex"));
+            (params, BigUint::from_bytes_be(&state))
+        }
+        _ => usage(),
+    };
+    let proof_path: &str = match args.positional.as_slice() {
+        [] => "-",
+        [path] => path,
+        _ => usage(),
+    };
+    let (value, nonce, witness) = open(proof_path)
+        .and_then(read_proof)
+        .unwrap_or_else(|e| fail(&format!("reading proof from {}: {}", proof_path, e)));
+    if verify_membership_with(params.prime_mapping(), &state, &params.modulus, &value, &nonce, &witness) {
+        println!("valid");
+    } else {
+        println!("invalid");
+        process::exit(1);
+    }
+}
+
+fn state(args: Args) {
+    let sa: SetAccumulator<FileStore> = open_store_read_only(args.store());
+    println!("{}", to_hex(&sa.store.get_state().to_bytes_be()));
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let command: String = args.next().unwrap_or_else(|| usage());
+    match command.as_str() {
+        "init" => init(Args::parse(args, &["--store", "--modulus-bits", "--params-out"])),
+        "add" => add(Args::parse(args, &["--store"])),
+        "witness" => witness(Args::parse(args, &["--store"])),
+        "verify" => verify(Args::parse(args, &["--store", "--params", "--state"])),
+        "state" => state(Args::parse(args, &["--store"])),
+        _ => usage(),
+    }
+}
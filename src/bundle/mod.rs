@@ -0,0 +1,110 @@
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+
+use num_bigint::BigUint;
+
+use crate::head::Head;
+use crate::params::PublicParameters;
+use crate::snapshot::{read_biguint, read_bytes, write_bytes};
+use crate::verify_membership_with;
+
+const BUNDLE_MAGIC: &[u8; 4] = b"tgpb";
+const BUNDLE_VERSION: u8 = 1;
+
+/// Signs head digests on behalf of the accumulator manager.
+pub trait HeadSigner {
+    fn sign(&self, digest: &[u8; 32]) -> Vec<u8>;
+}
+
+/// Checks head signatures against the manager's public key.
+pub trait HeadVerifier {
+    fn verify(&self, digest: &[u8; 32], signature: &[u8]) -> bool;
+}
+
+/// Everything needed to check a membership proof offline: which parameters it is for,
+/// the signed head it is against, and the value, nonce and witness themselves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofBundle {
+    pub params_digest: [u8; 32],
+    pub head: Head,
+    pub head_signature: Vec<u8>,
+    /// The exact bytes that were added to the accumulator.
+    pub value: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub witness: BigUint,
+}
+
+impl ProofBundle {
+    /// Encodes the bundle as the magic bytes `tgpb` and a version byte, followed by the
+    /// parameters digest, the head (big-endian u64 sequence, length-prefixed state and
+    /// the two 32 byte digests), and the length-prefixed signature, value, nonce and
+    /// witness.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(BUNDLE_MAGIC)?;
+        writer.write_all(&[BUNDLE_VERSION])?;
+        writer.write_all(&self.params_digest)?;
+        writer.write_all(&self.head.sequence.to_be_bytes())?;
+        write_bytes(&mut writer, &self.head.state.to_bytes_be())?;
+        writer.write_all(&self.head.previous)?;
+        writer.write_all(&self.head.operations)?;
+        write_bytes(&mut writer, &self.head_signature)?;
+        write_bytes(&mut writer, &self.value)?;
+        write_bytes(&mut writer, &self.nonce)?;
+        write_bytes(&mut writer, &self.witness.to_bytes_be())?;
+        writer.flush()
+    }
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut magic: [u8; 5] = [0; 5];
+        reader.read_exact(&mut magic)?;
+        if &magic[..4] != BUNDLE_MAGIC || magic[4] != BUNDLE_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a supported tangerine proof bundle"));
+        }
+        let mut digest = || -> io::Result<[u8; 32]> {
+            let mut digest: [u8; 32] = [0; 32];
+            reader.read_exact(&mut digest)?;
+            Ok(digest)
+        };
+        let params_digest: [u8; 32] = digest()?;
+        let mut sequence: [u8; 8] = [0; 8];
+        reader.read_exact(&mut sequence)?;
+        let state: BigUint = read_biguint(&mut reader)?;
+        let mut digests: [u8; 64] = [0; 64];
+        reader.read_exact(&mut digests)?;
+        let head: Head = Head {
+            sequence: u64::from_be_bytes(sequence),
+            state,
+            previous: digests[..32].try_into().unwrap(),
+            operations: digests[32..].try_into().unwrap(),
+        };
+        Ok(ProofBundle {
+            params_digest,
+            head,
+            head_signature: read_bytes(&mut reader)?,
+            value: read_bytes(&mut reader)?,
+            nonce: read_bytes(&mut reader)?,
+            witness: read_biguint(&mut reader)?,
+        })
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        self.write_to(&mut bytes).unwrap();
+        bytes
+    }
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        ProofBundle::read_from(bytes)
+    }
+}
+
+/// Checks a bundle using nothing but the parameters the verifier trusts and the
+/// manager's head verification key: the bundle must be for those parameters, its head
+/// must carry a valid signature, and the witness raised to the value's prime must give
+/// the head's state.
+pub fn verify_bundle<V: HeadVerifier>(bundle: &ProofBundle, trusted_params: &PublicParameters, verifier: &V) -> bool {
+    if bundle.params_digest != trusted_params.digest() {
+        return false;
+    }
+    if !verifier.verify(&bundle.head.digest(), &bundle.head_signature) {
+        return false;
+    }
+    verify_membership_with(trusted_params.prime_mapping(), &bundle.head.state, &trusted_params.modulus, &bundle.value, &bundle.nonce, &bundle.witness)
+}
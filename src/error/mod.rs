@@ -0,0 +1,85 @@
+use alloc::string::String;
+use core::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccumulatorError {
+    /// The modulus in the store is smaller than the security level requires.
+    ModulusTooSmall { bits: u64, required: u64 },
+    /// The store maps values to primes, or finds them from digests, narrower than the
+    /// security level requires.
+    PrimesTooNarrow { bits: u64, required: u64 },
+    /// The random number generator kept producing nonces that were already in use.
+    DegenerateRng,
+    /// The value is not a member of the accumulator.
+    NotAMember,
+    /// The operation needs the factorization of the modulus and the store does not hold it.
+    MissingTrapdoor,
+    /// The modulus in the store is not the one the constructor was asked for.
+    UnexpectedModulus,
+    /// The value maps to a prime that is already accumulated.
+    DuplicatePrime,
+    /// The value is already a member, see [`DuplicatePolicy`](crate::params::DuplicatePolicy).
+    AlreadyAMember,
+    /// The nonce passed to [`add_with_nonce`](crate::SetAccumulator::add_with_nonce) starts
+    /// like the nonces tangerine keeps for members of its own making.
+    InvalidNonce,
+    /// The prime passed to [`add_prime`](crate::SetAccumulator::add_prime) is not one.
+    InvalidPrime,
+    /// The store keeps no primes, so it cannot hold a member added with a prime of the
+    /// caller's, see [`Storer::set_prime`](crate::store::Storer::set_prime).
+    PrimesNotKept,
+    /// Another writer changed the store since it was read, see
+    /// [`Storer::commit_transaction`](crate::store::Storer::commit_transaction).
+    Conflict,
+    /// The store was opened read-only, see [`FileStore::open_read_only`](crate::store::file_store::FileStore::open_read_only).
+    ReadOnly,
+    /// A delta does not lead from the state it is applied to to the one it claims.
+    InvalidTransition,
+    /// The store failed to write a change out and undid it, with the reason it gave.
+    Io(String),
+    /// The store holds no history reaching back to the epoch asked for, or the epoch is
+    /// yet to come.
+    UnknownEpoch,
+    /// The change would leave the accumulator with more members than its limit allows, see
+    /// [`with_member_limit`](crate::SetAccumulator::with_member_limit).
+    QuotaExceeded { limit: usize },
+    /// The store's writer lease expired or is held by another writer, see
+    /// `RedisStore::acquire_lease`.
+    Fenced,
+}
+
+impl fmt::Display for AccumulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccumulatorError::ModulusTooSmall { bits, required } => {
+                write!(f, "modulus has {} bits but the security level requires {}", bits, required)
+            }
+            AccumulatorError::PrimesTooNarrow { bits, required } => {
+                write!(f, "primes have {} bits but the security level requires {}", bits, required)
+            }
+            AccumulatorError::DegenerateRng => {
+                write!(f, "random number generator repeated a nonce, refusing to add")
+            }
+            AccumulatorError::NotAMember => write!(f, "value is not a member of the accumulator"),
+            AccumulatorError::MissingTrapdoor => {
+                write!(f, "store does not hold the factorization of the modulus")
+            }
+            AccumulatorError::UnexpectedModulus => write!(f, "store holds a different modulus than expected"),
+            AccumulatorError::DuplicatePrime => write!(f, "value maps to a prime that is already accumulated"),
+            AccumulatorError::AlreadyAMember => write!(f, "value is already a member of the accumulator"),
+            AccumulatorError::InvalidNonce => write!(f, "nonce starts with a prefix reserved by tangerine"),
+            AccumulatorError::InvalidPrime => write!(f, "number to accumulate is not a prime"),
+            AccumulatorError::PrimesNotKept => write!(f, "store does not keep the primes of its members"),
+            AccumulatorError::Conflict => write!(f, "store was changed by another writer since it was read"),
+            AccumulatorError::ReadOnly => write!(f, "store was opened read-only"),
+            AccumulatorError::InvalidTransition => write!(f, "delta does not lead to the state it claims"),
+            AccumulatorError::Io(reason) => write!(f, "store failed to write: {}", reason),
+            AccumulatorError::UnknownEpoch => write!(f, "store holds no history of that epoch"),
+            AccumulatorError::Fenced => write!(f, "store's writer lease is held by another writer"),
+            AccumulatorError::QuotaExceeded { limit } => write!(f, "change would take the accumulator past {} members", limit),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AccumulatorError {}
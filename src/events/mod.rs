@@ -0,0 +1,41 @@
+//! Notifications of changes to an accumulator, for caches, webhooks and witness refresh
+//! services that would otherwise poll it. [`SetAccumulator::subscribe`] hands out the
+//! receiving end of a channel, which gets the events of every committed transaction in
+//! order: one for each value added or deleted, then one for the state it left.
+//!
+//! Nothing is sent for a transaction that was rolled back, and a subscriber whose
+//! receiver is dropped is forgotten on the next send.
+//!
+//! [`SetAccumulator::subscribe`]: crate::SetAccumulator::subscribe
+
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use num_bigint::BigUint;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccumulatorEvent {
+    Added { value: Vec<u8>, nonce: Vec<u8>, prime: BigUint },
+    Deleted { value: Vec<u8>, prime: BigUint },
+    /// The state a transaction left, at the epoch it closed.
+    StateChanged { epoch: u64, previous: BigUint, state: BigUint },
+}
+
+/// The senders of every subscriber.
+#[derive(Debug, Default)]
+pub(crate) struct Subscribers {
+    senders: Vec<Sender<AccumulatorEvent>>,
+}
+
+impl Subscribers {
+    pub(crate) fn subscribe(&mut self) -> Receiver<AccumulatorEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.senders.push(sender);
+        receiver
+    }
+    pub(crate) fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+    pub(crate) fn publish(&mut self, event: AccumulatorEvent) {
+        self.senders.retain(|sender| sender.send(event.clone()).is_ok());
+    }
+}
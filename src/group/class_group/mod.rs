@@ -0,0 +1,169 @@
+//! The class group of an imaginary quadratic field, represented by reduced binary
+//! quadratic forms (a, b, c) of negative discriminant b^2 - 4ac. Composition follows
+//! Cohen, "A Course in Computational Algebraic Number Theory", algorithms 5.4.2 and
+//! 5.4.7. Computing the order of the group for a large discriminant is believed to be
+//! hard, which is what makes it usable without a trusted setup.
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::{ExtendedGcd, Integer};
+use num_traits::{One, Signed, Zero};
+use sha2::{Digest, Sha256};
+
+use crate::group::Group;
+use crate::is_prime_with;
+use crate::params::{PrimalityConfig, SecurityLevel};
+
+/// A binary quadratic form a x^2 + b x y + c y^2, always kept reduced.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QuadraticForm {
+    pub a: BigInt,
+    pub b: BigInt,
+    pub c: BigInt,
+}
+
+impl QuadraticForm {
+    // b into the range (-a, a]
+    fn normalize(&mut self) {
+        let two_a: BigInt = &self.a << 1;
+        if -&self.a < self.b && self.b <= self.a {
+            return;
+        }
+        let r: BigInt = (&self.a - &self.b).div_floor(&two_a);
+        self.c += &r * (&self.a * &r + &self.b);
+        self.b += &r * two_a;
+    }
+    fn reduce(mut self) -> Self {
+        loop {
+            self.normalize();
+            if self.a > self.c {
+                std::mem::swap(&mut self.a, &mut self.c);
+                self.b = -self.b;
+            } else {
+                if self.a == self.c && self.b.is_negative() {
+                    self.b = -self.b;
+                }
+                return self;
+            }
+        }
+    }
+}
+
+/// The class group of discriminant `discriminant`, which is negative and 1 mod 8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassGroup {
+    discriminant: BigInt,
+}
+
+impl ClassGroup {
+    /// Derives a discriminant of `bits` bits from `seed` as -p for the first prime
+    /// p = 7 mod 8 at or above the hash of the seed, so anyone can recompute it and
+    /// nobody learns anything about the order of the group in the process.
+    pub fn from_seed(seed: &[u8], bits: u64) -> Self {
+        let mut hasher_input: Vec<u8> = Vec::new();
+        let mut candidate_bytes: Vec<u8> = Vec::new();
+        let mut counter: u64 = 0;
+        // stretch the seed into enough bytes by hashing it with a counter
+        while (candidate_bytes.len() as u64) * 8 < bits {
+            hasher_input.clear();
+            hasher_input.extend_from_slice(b"tangerine/class-group/v1");
+            hasher_input.extend_from_slice(&counter.to_be_bytes());
+            hasher_input.extend_from_slice(seed);
+            candidate_bytes.extend(Sha256::digest(&hasher_input));
+            counter += 1;
+        }
+        let mut candidate: BigUint = BigUint::from_bytes_be(&candidate_bytes) >> (candidate_bytes.len() as u64 * 8 - bits);
+        // top bit set, and 7 mod 8
+        candidate.set_bit(bits - 1, true);
+        let remainder: BigUint = &candidate % 8_u64;
+        candidate = candidate - remainder + 7_u64;
+        // whoever picks the seed picks the candidates, so this takes Baillie-PSW too
+        let primality: PrimalityConfig = SecurityLevel::default().primality();
+        while !is_prime_with(&candidate, primality) {
+            candidate += 8_u64;
+        }
+        ClassGroup { discriminant: -BigInt::from(candidate) }
+    }
+    pub fn get_discriminant(&self) -> &BigInt {
+        &self.discriminant
+    }
+    /// The form (2, 1, c), which exists because the discriminant is 1 mod 8.
+    pub fn generator(&self) -> QuadraticForm {
+        self.form(BigInt::from(2), BigInt::one())
+    }
+    // the form with the given a and b, c following from the discriminant
+    fn form(&self, a: BigInt, b: BigInt) -> QuadraticForm {
+        let c: BigInt = (&b * &b - &self.discriminant) / (&a << 2);
+        QuadraticForm { a, b, c }.reduce()
+    }
+}
+
+impl Group for ClassGroup {
+    type Element = QuadraticForm;
+
+    fn identity(&self) -> QuadraticForm {
+        self.form(BigInt::one(), BigInt::one())
+    }
+    fn op(&self, f1: &QuadraticForm, f2: &QuadraticForm) -> QuadraticForm {
+        // algorithm 5.4.7 wants the form with the smaller a first
+        let (f1, f2): (&QuadraticForm, &QuadraticForm) = if f1.a > f2.a { (f2, f1) } else { (f1, f2) };
+        let s: BigInt = (&f1.b + &f2.b) >> 1;
+        let n: BigInt = &f2.b - &s;
+        let (y1, d): (BigInt, BigInt) = if f2.a.is_multiple_of(&f1.a) {
+            (BigInt::zero(), f1.a.clone())
+        } else {
+            // u a2 + v a1 = d
+            let bezout: ExtendedGcd<BigInt> = f2.a.extended_gcd(&f1.a);
+            (bezout.x, bezout.gcd)
+        };
+        let (x2, y2, d1): (BigInt, BigInt, BigInt) = if s.is_multiple_of(&d) {
+            (BigInt::zero(), -BigInt::one(), d)
+        } else {
+            // x2 s + y2 d = d1
+            let bezout: ExtendedGcd<BigInt> = s.extended_gcd(&d);
+            (bezout.x, -bezout.y, bezout.gcd)
+        };
+        let v1: BigInt = &f1.a / &d1;
+        let v2: BigInt = &f2.a / &d1;
+        let r: BigInt = (&y1 * &y2 * &n - &x2 * &f2.c).mod_floor(&v1);
+        let b3: BigInt = &f2.b + ((&v2 * &r) << 1);
+        let a3: BigInt = v1 * v2;
+        debug_assert_eq!(Sign::Plus, a3.sign());
+        self.form(a3, b3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_class_group() {
+        let group: ClassGroup = ClassGroup::from_seed(b"tangerine test", 128);
+        // the discriminant is reproducible from the seed alone
+        assert_eq!(group, ClassGroup::from_seed(b"tangerine test", 128));
+        assert_ne!(group, ClassGroup::from_seed(b"another seed", 128));
+        let discriminant: &BigInt = group.get_discriminant();
+        assert_eq!(128, discriminant.bits());
+        assert_eq!(BigInt::one(), discriminant.mod_floor(&BigInt::from(8)));
+
+        let g: QuadraticForm = group.generator();
+        let identity: QuadraticForm = group.identity();
+        assert_eq!(g, group.op(&g, &identity));
+        assert_eq!(g, group.op(&identity, &g));
+        // every form has the group's discriminant and stays reduced
+        let h: QuadraticForm = group.pow(&g, &BigUint::from(12345_u64));
+        assert_eq!(*discriminant, &h.b * &h.b - BigInt::from(4) * &h.a * &h.c);
+        assert!(h.b.abs() <= h.a && h.a <= h.c);
+        // exponents add up and composition commutes
+        let x: BigUint = BigUint::from(1_000_003_u64);
+        let y: BigUint = BigUint::from(77_777_u64);
+        let gx: QuadraticForm = group.pow(&g, &x);
+        let gy: QuadraticForm = group.pow(&g, &y);
+        assert_eq!(group.pow(&g, &(&x + &y)), group.op(&gx, &gy));
+        assert_eq!(group.op(&gx, &gy), group.op(&gy, &gx));
+        assert_eq!(group.pow(&gx, &y), group.pow(&gy, &x));
+        // the inverse of (a, b, c) is (a, -b, c)
+        let inverse: QuadraticForm = QuadraticForm { a: h.a.clone(), b: -h.b.clone(), c: h.c.clone() }.reduce();
+        assert_eq!(identity, group.op(&h, &inverse));
+    }
+}
@@ -0,0 +1,114 @@
+//! Accumulators over any group of unknown order. [`SetAccumulator`](crate::SetAccumulator)
+//! and its stores are tied to an RSA modulus, which someone has to generate and then
+//! forget the factorization of. Class groups of imaginary quadratic fields need no such
+//! setup: the group is fixed by a discriminant anyone can derive from a public seed.
+
+pub mod class_group;
+pub mod rsa_group;
+
+use std::fmt::Debug;
+
+use num_bigint::BigUint;
+use rand::Rng;
+
+use crate::hash_value_to_prime;
+use crate::params::{PrimeMapping, SecurityLevel};
+
+/// A finite abelian group of unknown order, written multiplicatively.
+pub trait Group {
+    type Element: Clone + Debug + PartialEq + Eq;
+
+    fn identity(&self) -> Self::Element;
+    fn op(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+    /// `base` raised to `exponent`, by square and multiply unless the group knows better.
+    fn pow(&self, base: &Self::Element, exponent: &BigUint) -> Self::Element {
+        let mut result: Self::Element = self.identity();
+        for i in (0..exponent.bits()).rev() {
+            result = self.op(&result, &result);
+            if exponent.bit(i) {
+                result = self.op(&result, base);
+            }
+        }
+        result
+    }
+}
+
+/// What [`GroupAccumulator::add`] hands back, see [`Issued`](crate::stateless::Issued).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupIssued<E> {
+    pub nonce: Vec<u8>,
+    pub prime: BigUint,
+    pub witness: E,
+}
+
+/// A stateless accumulator over an arbitrary [`Group`]. Like
+/// [`StatelessAccumulator`](crate::stateless::StatelessAccumulator) it keeps nothing but
+/// its state, and holders keep their witnesses current with [`GroupAccumulator::apply_add`].
+pub struct GroupAccumulator<G: Group> {
+    group: G,
+    generator: G::Element,
+    state: G::Element,
+    security_level: SecurityLevel,
+}
+
+impl<G: Group> GroupAccumulator<G> {
+    pub fn new(group: G, generator: G::Element, security_level: SecurityLevel) -> Self {
+        let state: G::Element = generator.clone();
+        GroupAccumulator { group, generator, state, security_level }
+    }
+    pub fn get_group(&self) -> &G {
+        &self.group
+    }
+    pub fn get_generator(&self) -> &G::Element {
+        &self.generator
+    }
+    pub fn get_state(&self) -> &G::Element {
+        &self.state
+    }
+    /// Adds `value` and returns its witness, the state before the add.
+    pub fn add(&mut self, value: &[u8]) -> GroupIssued<G::Element> {
+        let nonce: [u8; 32] = rand::thread_rng().gen();
+        let prime: BigUint = hash_value_to_prime(value, &nonce, PrimeMapping::default(), self.security_level);
+        let witness: G::Element = self.state.clone();
+        self.state = self.group.pow(&self.state, &prime);
+        GroupIssued { nonce: nonce.to_vec(), prime, witness }
+    }
+    /// Brings a holder's witness up to date with an add that happened after it was issued.
+    pub fn apply_add(&self, witness: &G::Element, added_prime: &BigUint) -> G::Element {
+        self.group.pow(witness, added_prime)
+    }
+    pub fn verify(&self, value: &[u8], nonce: &[u8], witness: &G::Element) -> bool {
+        let prime: BigUint = hash_value_to_prime(value, nonce, PrimeMapping::default(), self.security_level);
+        self.group.pow(witness, &prime) == self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::class_group::{ClassGroup, QuadraticForm};
+    use crate::group::rsa_group::RsaGroup;
+    use num_bigint::RandBigInt;
+
+    fn exercise<G: Group>(mut sa: GroupAccumulator<G>) {
+        let mut alice: GroupIssued<G::Element> = sa.add(b"alice");
+        let bob: GroupIssued<G::Element> = sa.add(b"bob");
+        alice.witness = sa.apply_add(&alice.witness, &bob.prime);
+        assert!(sa.verify(b"alice", &alice.nonce, &alice.witness));
+        assert!(sa.verify(b"bob", &bob.nonce, &bob.witness));
+        assert!(!sa.verify(b"mallory", &alice.nonce, &alice.witness));
+        assert!(!sa.verify(b"bob", &alice.nonce, &bob.witness));
+    }
+
+    #[test]
+    fn test_group_accumulator() {
+        let mut rng = rand::thread_rng();
+        let modulus: BigUint = rng.gen_biguint(256) | BigUint::from(1_u64);
+        let generator: BigUint = rng.gen_biguint_below(&modulus);
+        exercise(GroupAccumulator::new(RsaGroup::new(modulus), generator, SecurityLevel::default()));
+
+        let group: ClassGroup = ClassGroup::from_seed(b"tangerine test", 256);
+        let generator: QuadraticForm = group.generator();
+        exercise(GroupAccumulator::new(group, generator, SecurityLevel::default()));
+    }
+}
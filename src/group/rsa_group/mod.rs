@@ -0,0 +1,34 @@
+use num_bigint::BigUint;
+use num_traits::One;
+
+use crate::arith;
+use crate::group::Group;
+
+/// The multiplicative group of integers modulo an RSA modulus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsaGroup {
+    modulus: BigUint,
+}
+
+impl RsaGroup {
+    pub fn new(modulus: BigUint) -> Self {
+        RsaGroup { modulus }
+    }
+    pub fn get_modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+}
+
+impl Group for RsaGroup {
+    type Element = BigUint;
+
+    fn identity(&self) -> BigUint {
+        One::one()
+    }
+    fn op(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a * b) % &self.modulus
+    }
+    fn pow(&self, base: &BigUint, exponent: &BigUint) -> BigUint {
+        arith::modpow(base, exponent, &self.modulus)
+    }
+}
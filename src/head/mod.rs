@@ -0,0 +1,97 @@
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::params::PublicParameters;
+
+// keeps head digests from ever colliding with hashes computed for other purposes
+const HEAD_DOMAIN: &[u8] = b"tangerine/head/v1";
+const OPERATIONS_DOMAIN: &[u8] = b"tangerine/operations/v1";
+
+fn write_field(hasher: &mut Sha256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_be_bytes());
+    hasher.update(bytes);
+}
+
+fn finish(hasher: Sha256) -> [u8; 32] {
+    hasher.finalize().into()
+}
+
+/// Hash of a batch of `(value, nonce)` additions, in the order they were applied.
+pub fn operations_digest(operations: &[(Vec<u8>, Vec<u8>)]) -> [u8; 32] {
+    let mut hasher: Sha256 = Sha256::new();
+    hasher.update(OPERATIONS_DOMAIN);
+    for (value, nonce) in operations {
+        write_field(&mut hasher, value);
+        write_field(&mut hasher, nonce);
+    }
+    finish(hasher)
+}
+
+/// A published accumulator state that commits to the head before it and to the batch of
+/// operations in between. Holding any head is enough to detect a rewrite of the states
+/// that led up to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Head {
+    pub sequence: u64,
+    pub state: BigUint,
+    pub previous: [u8; 32],
+    pub operations: [u8; 32],
+}
+
+impl Head {
+    /// The first head of an accumulator: its genesis state, with nothing before it.
+    pub fn genesis(params: &PublicParameters) -> Head {
+        Head { sequence: 0, state: params.genesis_state(), previous: [0; 32], operations: operations_digest(&[]) }
+    }
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher: Sha256 = Sha256::new();
+        hasher.update(HEAD_DOMAIN);
+        hasher.update(self.sequence.to_be_bytes());
+        write_field(&mut hasher, &self.state.to_bytes_be());
+        hasher.update(self.previous);
+        hasher.update(self.operations);
+        finish(hasher)
+    }
+    /// The head reached from this one by applying `operations`, ending in `state`.
+    pub fn next(&self, state: BigUint, operations: &[(Vec<u8>, Vec<u8>)]) -> Head {
+        Head {
+            sequence: self.sequence + 1,
+            state,
+            previous: self.digest(),
+            operations: operations_digest(operations),
+        }
+    }
+    /// Whether this head directly extends `previous`.
+    pub fn follows(&self, previous: &Head) -> bool {
+        self.sequence == previous.sequence + 1 && self.previous == previous.digest()
+    }
+}
+
+/// Checks that every head in `heads` directly extends the one before it.
+pub fn verify_chain(heads: &[Head]) -> bool {
+    heads.windows(2).all(|pair| pair[1].follows(&pair[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_head_chain() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let genesis: Head = Head::genesis(&params);
+        let first: Head = genesis.next(BigUint::from(7_u64), &[(b"a".to_vec(), vec![1; 32])]);
+        let second: Head = first.next(BigUint::from(9_u64), &[(b"b".to_vec(), vec![2; 32]), (b"c".to_vec(), vec![3; 32])]);
+        assert_eq!(2, second.sequence);
+        assert!(verify_chain(&[genesis.clone(), first.clone(), second.clone()]));
+
+        // rewriting an earlier state breaks every head after it
+        let mut forged: Head = first.clone();
+        forged.state = BigUint::from(8_u64);
+        assert!(!verify_chain(&[genesis.clone(), forged, second.clone()]));
+        // and so does dropping a head from the middle
+        assert!(!verify_chain(&[genesis, second]));
+        // the operation batch is bound into the digest as well
+        assert_ne!(operations_digest(&[(b"a".to_vec(), vec![1; 32])]), operations_digest(&[(b"a".to_vec(), vec![2; 32])]));
+    }
+}
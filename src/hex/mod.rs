@@ -0,0 +1,34 @@
+//! Lowercase hex for the encodings that carry big integers and byte strings as text.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub(crate) fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!("00ff10", to_hex(&[0x00, 0xff, 0x10]));
+        assert_eq!(Some(vec![0x00, 0xff, 0x10]), from_hex("00ff10"));
+        assert_eq!(Some(vec![0xab]), from_hex("AB"));
+        assert_eq!(None, from_hex("abc"));
+        assert_eq!(None, from_hex("zz"));
+        assert_eq!(None, from_hex("é0"));
+    }
+}
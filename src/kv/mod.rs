@@ -0,0 +1,214 @@
+//! Commitments to maps from keys to values, with proofs that a key maps to a value and
+//! proofs that a key is absent, for ledgers whose clients hold a state rather than the
+//! data.
+//!
+//! Two accumulators over the same group make up the state. The key accumulator holds a
+//! prime derived from each key alone, the entry accumulator a prime derived from each
+//! key and its value together. That a key maps to a value is a witness in the entry
+//! accumulator, as for any member. That a key is absent is a proof that its prime does not
+//! divide the exponent of the key accumulator: with that exponent `u` and the key's prime
+//! `x` coprime, the committer finds `a·u - b·x = 1` and hands over `a` and `d = g^-b`,
+//! which satisfy `keys^a · d^x = g`. For a prime in `u` the same equation would give an
+//! `x`-th root of `g`.
+//!
+//! Like the other accumulators it binds everyone but whoever keeps it to the map it
+//! holds.
+
+use std::collections::HashMap;
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::One;
+use sha2::{Digest, Sha256};
+
+use crate::arith;
+use crate::hash_value_to_prime;
+use crate::params::{PublicParameters, SecurityLevel};
+use crate::product_tree;
+
+// keep key primes, entry primes and the primes of ordinary members apart
+const KEY_DOMAIN: &[u8] = b"tangerine/kv/key/v1";
+const ENTRY_DOMAIN: &[u8] = b"tangerine/kv/entry/v1";
+const STATE_DOMAIN: &[u8] = b"tangerine/kv/state/v1";
+
+fn key_prime(params: &PublicParameters, key: &[u8]) -> BigUint {
+    hash_value_to_prime(key, KEY_DOMAIN, params.prime_mapping(), SecurityLevel::default())
+}
+
+fn entry_prime(params: &PublicParameters, key: &[u8], value: &[u8]) -> BigUint {
+    // the key goes in hashed, so where the value ends and the key begins is never in doubt
+    let nonce: Vec<u8> = [ENTRY_DOMAIN, Sha256::digest(key).as_slice()].concat();
+    hash_value_to_prime(value, &nonce, params.prime_mapping(), SecurityLevel::default())
+}
+
+/// The states of the key and entry accumulators, everything a client checks proofs
+/// against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KvState {
+    pub keys: BigUint,
+    pub entries: BigUint,
+}
+
+impl KvState {
+    /// SHA-256 over both states, for pinning the map with one hash.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher: Sha256 = Sha256::new();
+        hasher.update(STATE_DOMAIN);
+        for field in [self.keys.to_bytes_be(), self.entries.to_bytes_be()].iter() {
+            hasher.update((field.len() as u64).to_be_bytes());
+            hasher.update(field);
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// That a key maps to a value: the witness of the entry in the entry accumulator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueProof {
+    pub witness: BigUint,
+}
+
+/// That a key is absent, see the module documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbsenceProof {
+    pub a: BigUint,
+    pub d: BigUint,
+}
+
+// an entry with the primes it contributes, kept so that witnesses need no hashing
+struct Entry {
+    value: Vec<u8>,
+    key_prime: BigUint,
+    entry_prime: BigUint,
+}
+
+/// A key-value accumulator held in memory by whoever keeps the map.
+pub struct KvAccumulator {
+    params: PublicParameters,
+    entries: HashMap<Vec<u8>, Entry>,
+    state: KvState,
+}
+
+impl KvAccumulator {
+    pub fn new(params: PublicParameters) -> Self {
+        let state: KvState = KvState { keys: params.genesis_state(), entries: params.genesis_state() };
+        KvAccumulator { params, entries: HashMap::new(), state }
+    }
+    pub fn state(&self) -> &KvState {
+        &self.state
+    }
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.entries.get(key).map(|entry| entry.value.as_slice())
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Maps `key` to `value`, returning the value it mapped to before. Setting a new key
+    /// takes two exponentiations, replacing a value recomputes the states from the rest.
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        let entry_prime: BigUint = entry_prime(&self.params, key, value);
+        if let Some(entry) = self.entries.get_mut(key) {
+            let previous: Vec<u8> = std::mem::replace(&mut entry.value, value.to_vec());
+            entry.entry_prime = entry_prime;
+            self.recompute_state();
+            return Some(previous);
+        }
+        let key_prime: BigUint = key_prime(&self.params, key);
+        self.state.keys = arith::modpow(&self.state.keys, &key_prime, &self.params.modulus);
+        self.state.entries = arith::modpow(&self.state.entries, &entry_prime, &self.params.modulus);
+        self.entries.insert(key.to_vec(), Entry { value: value.to_vec(), key_prime, entry_prime });
+        None
+    }
+    /// Drops `key`, returning the value it mapped to.
+    pub fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        let entry: Entry = self.entries.remove(key)?;
+        self.recompute_state();
+        Some(entry.value)
+    }
+    fn recompute_state(&mut self) {
+        let keys: Vec<BigUint> = self.entries.values().map(|entry| entry.key_prime.clone()).collect();
+        let entries: Vec<BigUint> = self.entries.values().map(|entry| entry.entry_prime.clone()).collect();
+        self.state = KvState {
+            keys: arith::modpow(&self.params.generator, &product_tree::product(keys), &self.params.modulus),
+            entries: arith::modpow(&self.params.generator, &product_tree::product(entries), &self.params.modulus),
+        };
+    }
+    /// The value `key` maps to and the proof that it does, None if the key is absent.
+    pub fn prove_value(&self, key: &[u8]) -> Option<(Vec<u8>, ValueProof)> {
+        let entry: &Entry = self.entries.get(key)?;
+        let others: Vec<BigUint> = self.entries.iter().filter(|(other, _)| other.as_slice() != key).map(|(_, other)| other.entry_prime.clone()).collect();
+        let witness: BigUint = arith::modpow(&self.params.generator, &product_tree::product(others), &self.params.modulus);
+        Some((entry.value.clone(), ValueProof { witness }))
+    }
+    /// The proof that `key` is absent, None if it is present or the generator has no
+    /// inverse, which no generator of a proper RSA group lacks.
+    pub fn prove_absent(&self, key: &[u8]) -> Option<AbsenceProof> {
+        if self.entries.contains_key(key) {
+            return None;
+        }
+        let x: BigUint = key_prime(&self.params, key);
+        let u: BigUint = product_tree::product(self.entries.values().map(|entry| entry.key_prime.clone()).collect());
+        // a = u^-1 mod x, so a·u - 1 is b times x
+        let a: BigUint = u.modinv(&x)?;
+        let (b, remainder): (BigUint, BigUint) = (&a * &u - BigUint::one()).div_rem(&x);
+        debug_assert!(remainder == BigUint::from(0_u64));
+        let inverse: BigUint = self.params.generator.modinv(&self.params.modulus)?;
+        let d: BigUint = arith::modpow(&inverse, &b, &self.params.modulus);
+        Some(AbsenceProof { a, d })
+    }
+}
+
+/// Whether `proof` shows `key` to map to `value` in the map at `state`.
+pub fn verify_value(params: &PublicParameters, state: &KvState, key: &[u8], value: &[u8], proof: &ValueProof) -> bool {
+    arith::modpow(&proof.witness, &entry_prime(params, key, value), &params.modulus) == state.entries
+}
+
+/// Whether `proof` shows `key` to be absent from the map at `state`.
+pub fn verify_absent(params: &PublicParameters, state: &KvState, key: &[u8], proof: &AbsenceProof) -> bool {
+    let x: BigUint = key_prime(params, key);
+    let lhs: BigUint = arith::modpow(&state.keys, &proof.a, &params.modulus) * arith::modpow(&proof.d, &x, &params.modulus);
+    lhs % &params.modulus == params.generator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kv_accumulator() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut kv: KvAccumulator = KvAccumulator::new(params.clone());
+        let absent: AbsenceProof = kv.prove_absent(b"alice").unwrap();
+        assert!(verify_absent(&params, kv.state(), b"alice", &absent));
+
+        assert_eq!(None, kv.insert(b"alice", b"1"));
+        assert_eq!(None, kv.insert(b"bob", b"2"));
+        assert!(!verify_absent(&params, kv.state(), b"alice", &absent));
+        assert!(kv.prove_absent(b"alice").is_none());
+        let (value, proof): (Vec<u8>, ValueProof) = kv.prove_value(b"alice").unwrap();
+        assert_eq!(b"1".to_vec(), value);
+        assert!(verify_value(&params, kv.state(), b"alice", b"1", &proof));
+        assert!(!verify_value(&params, kv.state(), b"alice", b"2", &proof));
+        assert!(!verify_value(&params, kv.state(), b"bob", b"1", &proof));
+
+        let absent: AbsenceProof = kv.prove_absent(b"carol").unwrap();
+        assert!(verify_absent(&params, kv.state(), b"carol", &absent));
+        assert!(!verify_absent(&params, kv.state(), b"dave", &absent));
+
+        // replacing a value invalidates the proof of the old one
+        assert_eq!(Some(b"1".to_vec()), kv.insert(b"alice", b"3"));
+        assert!(!verify_value(&params, kv.state(), b"alice", b"1", &proof));
+        let (_, proof): (Vec<u8>, ValueProof) = kv.prove_value(b"alice").unwrap();
+        assert!(verify_value(&params, kv.state(), b"alice", b"3", &proof));
+
+        let digest: [u8; 32] = kv.state().digest();
+        assert_eq!(Some(b"2".to_vec()), kv.remove(b"bob"));
+        assert_ne!(digest, kv.state().digest());
+        assert!(verify_absent(&params, kv.state(), b"bob", &kv.prove_absent(b"bob").unwrap()));
+        kv.remove(b"alice");
+        assert_eq!(KvAccumulator::new(params).state(), kv.state());
+    }
+}
@@ -1,28 +1,391 @@
+//! A dynamic RSA accumulator over sets of byte strings.
+//!
+//! Without the default `std` feature the crate builds under `no_std` with `alloc`, and
+//! keeps the verification path: hashing to primes, [`verify_membership`] and its variants,
+//! [`proof::MembershipProof`] and the parameter types. The accumulator, its stores and
+//! everything that draws from the OS random number generator need `std`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod accumulator;
+#[cfg(feature = "std")]
+pub mod aggregate;
+pub mod arith;
+#[cfg(feature = "std")]
+pub mod async_accumulator;
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod bundle;
+pub mod error;
+#[cfg(feature = "std")]
+pub mod events;
+#[cfg(feature = "std")]
+pub mod group;
+#[cfg(feature = "std")]
+pub mod head;
+#[cfg(any(feature = "serde", feature = "service", feature = "wasm"))]
+mod hex;
+#[cfg(feature = "std")]
+pub mod kv;
+#[cfg(feature = "std")]
+pub mod log_commitment;
+#[cfg(feature = "std")]
+pub mod manager;
+#[cfg(feature = "std")]
+pub mod merkle;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "openssl")]
+pub mod openssl_interop;
+mod parallel;
+pub mod params;
+pub mod pocklington;
+#[cfg(feature = "std")]
+pub mod poke;
+mod primality;
+pub mod product_tree;
+pub mod proof;
+#[cfg(feature = "std")]
+pub mod replica;
+pub mod secret;
+#[cfg(feature = "serde")]
+mod serde_support;
+#[cfg(feature = "service")]
+pub mod service;
+#[cfg(feature = "std")]
+pub mod setup;
+#[cfg(feature = "std")]
+pub mod sharded;
+#[cfg(feature = "std")]
+pub mod shared;
+#[cfg(feature = "std")]
+pub mod snapshot;
+#[cfg(feature = "std")]
+pub mod stateless;
+#[cfg(feature = "std")]
 pub mod store;
+#[cfg(feature = "std")]
+pub mod timestamp;
+#[cfg(feature = "std")]
+pub mod vector;
+#[cfg(feature = "std")]
+pub mod view;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod wire;
+#[cfg(feature = "std")]
+pub mod witness;
 
-use std::io::Write;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::sync::mpsc::Receiver;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-use crypto_hash::{Algorithm, Hasher};
 use num_bigint::{BigUint, RandBigInt};
-use num_traits::{Zero, One};
+use num_traits::{One, ToPrimitive, Zero};
+#[cfg(feature = "std")]
 use rand::Rng;
+#[cfg(feature = "std")]
+use sha2::{Digest, Sha256};
+
+pub use accumulator::Accumulator;
+pub use error::AccumulatorError;
+#[cfg(feature = "std")]
+use bundle::{HeadSigner, ProofBundle};
+#[cfg(feature = "std")]
+use events::{AccumulatorEvent, Subscribers};
+#[cfg(feature = "std")]
+use head::Head;
+#[cfg(feature = "std")]
+use parallel::*;
+#[cfg(feature = "std")]
+use params::{DuplicatePolicy, NonceMode, PublicParameters, Trapdoor};
+use params::{HashFunction, PrimalityConfig, PrimeMapping, SecurityLevel};
+#[cfg(feature = "std")]
+use metrics::Metrics;
+#[cfg(feature = "std")]
+use pocklington::PrimeCertificate;
+#[cfg(feature = "std")]
+use product_tree::ProductTree;
+#[cfg(feature = "std")]
+use proof::MembershipProof;
+#[cfg(feature = "std")]
+use secret::SecretBigUint;
+#[cfg(feature = "std")]
+use store::{AuditAction, AuditRecord, Delta, Epoch, GarbageReport, Operation, Retention, Storer};
+#[cfg(feature = "std")]
+use store::mem_store::MemStore;
+#[cfg(feature = "std")]
+use view::EpochView;
 
-use store::Storer;
+/// What [`SetAccumulator::add`] hands back: the prime and nonce the value was added with,
+/// and its witness at the state the add left.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddOutcome {
+    pub prime: BigUint,
+    pub nonce: Vec<u8>,
+    pub witness: BigUint,
+}
 
+#[cfg(feature = "std")]
 pub struct SetAccumulator<T: Storer> {
     pub store: T,
+    security_level: SecurityLevel,
+    primality: PrimalityConfig,
+    prime_mapping: PrimeMapping,
+    nonce_mode: NonceMode,
+    duplicate_policy: DuplicatePolicy,
+    // primes and nonces of the current members, derived from the store the first time they are needed
+    member_index: Option<MemberIndex>,
+    transaction: Option<Transaction>,
+    subscribers: Subscribers,
+    metrics: Option<Arc<dyn Metrics>>,
+    member_limit: Option<usize>,
+}
+
+#[cfg(feature = "std")]
+// what it takes to undo an open transaction: the state it started from and the nonce
+// (or absence) each member it touched had before
+struct Transaction {
+    state: BigUint,
+    members: HashMap<Vec<u8>, Option<Vec<u8>>>,
+    // the members added with a prime of the caller's, whose epoch records the prime
+    picked: HashSet<Vec<u8>>,
+    // the audit records of its changes, handed to the store only if it commits, and the
+    // events for subscribers, sent only then too
+    log: Vec<AuditRecord>,
+    events: Vec<AccumulatorEvent>,
+}
+
+// the members at the close of an epoch with their nonces, and the primes the epochs
+// recorded for them
+#[cfg(feature = "std")]
+type EpochMembers = (HashMap<Vec<u8>, Vec<u8>>, HashMap<Vec<u8>, BigUint>);
+
+// a member added more than once under the multiset policy holds the nonces of all of
+// its occurrences in one: this tag, then each nonce length-prefixed, oldest first
+const OCCURRENCES_TAG: &[u8] = b"tangerine/occurrences/v1";
+
+// the nonces of every occurrence of a member, just its nonce unless it is a multiset one
+pub(crate) fn occurrences(nonce: &[u8]) -> Vec<&[u8]> {
+    if !nonce.starts_with(OCCURRENCES_TAG) {
+        return vec![nonce];
+    }
+    let mut rest: &[u8] = &nonce[OCCURRENCES_TAG.len()..];
+    let mut nonces: Vec<&[u8]> = Vec::new();
+    while rest.len() >= 4 {
+        let len: usize = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        if rest.len() < 4 + len {
+            break;
+        }
+        nonces.push(&rest[4..4 + len]);
+        rest = &rest[4 + len..];
+    }
+    nonces
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn join_occurrences(nonces: &[&[u8]]) -> Vec<u8> {
+    if nonces.len() == 1 {
+        return nonces[0].to_vec();
+    }
+    let mut joined: Vec<u8> = OCCURRENCES_TAG.to_vec();
+    for nonce in nonces.iter() {
+        joined.extend_from_slice(&(nonce.len() as u32).to_be_bytes());
+        joined.extend_from_slice(nonce);
+    }
+    joined
+}
+
+// whether the caller may pick `nonce`, which it may not if it would be read as the nonces
+// of several occurrences
+#[cfg(feature = "std")]
+fn is_reserved_nonce(nonce: &[u8]) -> bool {
+    nonce.starts_with(OCCURRENCES_TAG)
+}
+
+// the product of the primes of every occurrence of a member, which is just its prime
+// unless it is a multiset one; everything that maps a member's nonce to its prime from
+// public data goes through here, so a nonce held for several occurrences maps to all of them
+pub(crate) fn member_prime(value: &[u8], nonce: &[u8], prime_mapping: PrimeMapping, primality: PrimalityConfig) -> BigUint {
+    occurrences(nonce).into_iter().map(|occurrence| hash_value_to_prime_with(value, occurrence, prime_mapping, primality)).product()
+}
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub(crate) struct MemberIndex {
+    primes: HashSet<BigUint>,
+    nonces: HashSet<Vec<u8>>,
+}
+
+// where a member index looks up the members it leaves out: stores that look members up
+// themselves are left every member written, see Storer::looks_up_members, and an index of
+// every member leaves nothing out
+#[cfg(feature = "std")]
+pub(crate) trait HeldLookup {
+    fn prime_taken(&self, prime: &BigUint) -> io::Result<bool>;
+    fn nonce_taken(&self, nonce: &[u8]) -> io::Result<bool>;
+}
+
+#[cfg(feature = "std")]
+impl HeldLookup for () {
+    fn prime_taken(&self, _prime: &BigUint) -> io::Result<bool> {
+        Ok(false)
+    }
+    fn nonce_taken(&self, _nonce: &[u8]) -> io::Result<bool> {
+        Ok(false)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: Storer> HeldLookup for S {
+    fn prime_taken(&self, prime: &BigUint) -> io::Result<bool> {
+        self.holds_prime(prime)
+    }
+    fn nonce_taken(&self, nonce: &[u8]) -> io::Result<bool> {
+        self.holds_nonce(nonce)
+    }
+}
+
+#[cfg(feature = "std")]
+impl MemberIndex {
+    pub(crate) fn new(members: &HashMap<Vec<u8>, Vec<u8>>, prime_mapping: PrimeMapping, primality: PrimalityConfig) -> Self {
+        // every occurrence of a member counts on its own
+        let occurrences: Vec<(&[u8], &[u8])> = members
+            .iter()
+            .flat_map(|(member, nonce)| occurrences(nonce).into_iter().map(move |occurrence| (member.as_slice(), occurrence)))
+            .collect();
+        MemberIndex {
+            primes: occurrences
+                .par_iter()
+                .map(|(member, nonce)| hash_value_to_prime_with(member, nonce, prime_mapping, primality))
+                .collect(),
+            nonces: occurrences.iter().map(|(_, nonce)| nonce.to_vec()).collect(),
+        }
+    }
+    pub(crate) fn remove(&mut self, prime: &BigUint, nonce: &[u8]) {
+        self.primes.remove(prime);
+        self.nonces.remove(nonce);
+    }
+    // reserves a nonce and prime picked rather than drawn; the nonce may be shared, as the
+    // empty one of deterministic nonces is, members only have to differ in their primes
+    pub(crate) fn reserve<H: HeldLookup>(&mut self, nonce: &[u8], prime: &BigUint, held: &H) -> Result<(), AccumulatorError> {
+        if held.prime_taken(prime).map_err(|e| AccumulatorError::Io(e.to_string()))? || !self.primes.insert(prime.clone()) {
+            return Err(AccumulatorError::DuplicatePrime);
+        }
+        self.nonces.insert(nonce.to_vec());
+        Ok(())
+    }
+    // picks the nonce for a new member and reserves it and its prime
+    pub(crate) fn draw_nonce<R: Rng, H: HeldLookup>(
+        &mut self,
+        value: &[u8],
+        nonce_mode: NonceMode,
+        prime_mapping: PrimeMapping,
+        primality: PrimalityConfig,
+        rng: &mut R,
+        held: &H,
+    ) -> Result<(Vec<u8>, BigUint), AccumulatorError> {
+        let failed = |e: io::Error| AccumulatorError::Io(e.to_string());
+        let fixed: Option<Vec<u8>> = match nonce_mode {
+            NonceMode::Random => None,
+            NonceMode::Deterministic => Some(Vec::new()),
+            NonceMode::Keyed(key) => Some(key.nonce(value)),
+        };
+        if let Some(nonce) = fixed {
+            // the prime is a function of the value (and key) alone, so there is nothing to redraw
+            let exponent: BigUint = hash_value_to_prime_with(value, &nonce, prime_mapping, primality);
+            self.reserve(&nonce, &exponent, held)?;
+            return Ok((nonce, exponent));
+        }
+        let mut previous: Option<[u8; 32]> = None;
+        for _draw in 0..MAX_NONCE_DRAWS {
+            // get random once time use byte sequence
+            let nonce = rng.gen::<[u8; 32]>();
+            // the same 256 bit nonce twice in a row is not bad luck, the generator is broken
+            if previous == Some(nonce) {
+                return Err(AccumulatorError::DegenerateRng);
+            }
+            previous = Some(nonce);
+            // nonces are one time use, so draw again if a member already holds this one
+            if self.nonces.contains(nonce.as_ref()) || held.nonce_taken(&nonce).map_err(failed)? {
+                continue;
+            }
+            // hash the value and nonce concatentated and then map to prime
+            let exponent: BigUint = hash_value_to_prime_with(value, &nonce, prime_mapping, primality);
+            // if another member already maps to this prime the two would share a witness
+            // and could not be told apart, so draw a new nonce until the prime is fresh
+            if !held.prime_taken(&exponent).map_err(failed)? && self.primes.insert(exponent.clone()) {
+                self.nonces.insert(nonce.to_vec());
+                return Ok((nonce.to_vec(), exponent));
+            }
+        }
+        Err(AccumulatorError::DegenerateRng)
+    }
+}
+
+#[cfg(feature = "std")]
+// give up on drawing a usable nonce after this many attempts
+const MAX_NONCE_DRAWS: usize = 16;
+// keeps state digests from ever colliding with hashes computed for other purposes
+#[cfg(feature = "std")]
+const STATE_DOMAIN: &[u8] = b"tangerine/state/v1";
+// the layout of what `SetAccumulator::state_digest` hashes, bumped if it ever changes
+#[cfg(feature = "std")]
+const STATE_DIGEST_VERSION: u8 = 1;
+// how many values `SetAccumulator::extend` hashes and adds with each exponentiation
+#[cfg(feature = "std")]
+const EXTEND_CHUNK: usize = 4096;
+#[cfg(feature = "std")]
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// thread-local random generator seeded by system: https://docs.rs/rand/0.8.4/rand/fn.thread_rng.html
+#[cfg(feature = "std")]
+fn miller_rabin_rng(_candidate: &BigUint) -> rand::rngs::ThreadRng {
+    rand::thread_rng()
 }
 
-fn hash_byte_sequence(bytes: &[u8]) -> Vec<u8> {
-    let mut hasher = Hasher::new(Algorithm::SHA256);
-    hasher.write_all(bytes).unwrap();
-    hasher.finish()
+// without an OS to seed from, the bases are drawn from a generator seeded with a hash of
+// the candidate. A composite built to pass these particular bases then passes every
+// time, which is fine for verification: the candidates are hashes of the proven value,
+// and the primes an accumulator built on std accepted are prime whichever bases are used
+#[cfg(not(feature = "std"))]
+fn miller_rabin_rng(candidate: &BigUint) -> rand::rngs::StdRng {
+    use rand::SeedableRng;
+    use sha2::Digest;
+    rand::rngs::StdRng::from_seed(sha2::Sha256::digest(candidate.to_bytes_be()).into())
 }
 
-fn miller_rabin(candidate: &BigUint) -> bool {
+fn miller_rabin(candidate: &BigUint, rounds: usize) -> bool {
     let f0: BigUint = Zero::zero();
     let f1: BigUint = One::one();
-    let f2: BigUint = BigUint::from_bytes_be(&2_u64.to_be_bytes().to_vec());
+    let f2: BigUint = BigUint::from_bytes_be(&2_u64.to_be_bytes());
+    let f3: BigUint = BigUint::from_bytes_be(&3_u64.to_be_bytes());
+
+    // settle small and even candidates up front, the rounds below need an odd candidate
+    // above three (otherwise t - 1 underflows and the witness range [2, candidate - 1) is empty)
+    if *candidate < f2 {
+        return false;
+    }
+    if *candidate == f2 || *candidate == f3 {
+        return true;
+    }
+    if candidate.modpow(&f1, &f2) == f0 {
+        return false;
+    }
 
     let mut d: BigUint = candidate.clone() - f1.clone();
     let mut t: BigUint = f0.clone();
@@ -31,33 +394,37 @@ fn miller_rabin(candidate: &BigUint) -> bool {
         t += f1.clone();
     }
 
-    for _trial in 0..5 {
-        let mut rng = rand::thread_rng(); // thread-local random generator seeded by system: https://docs.rs/rand/0.8.4/rand/fn.thread_rng.html
+    let mut rng = miller_rabin_rng(candidate);
+    for _trial in 0..rounds {
         let a: BigUint = rng.gen_biguint_range(&f2, &(candidate - f1.clone()));
-        let mut v: BigUint = a.modpow(&d, &candidate);
+        let mut v: BigUint = a.modpow(&d, candidate);
         if v != f1 {
             let mut i: BigUint = f0.clone();
             while v != (candidate.clone() - f1.clone()) {
                 if i == t.clone() - f1.clone() {
                     return false;
                 } else {
-                    i = i + f1.clone();
-                    v = v.modpow(&f2, &candidate);
+                    i += f1.clone();
+                    v = v.modpow(&f2, candidate);
                 }
             }
         }
 
     }
-    return true;
+    true
+}
+
+fn is_prime(candidate: &BigUint, rounds: usize) -> bool {
+    is_prime_with(candidate, PrimalityConfig::new(rounds))
 }
 
-fn is_prime(candidate: &BigUint) -> bool {
+fn is_prime_with(candidate: &BigUint, primality: PrimalityConfig) -> bool {
     let f0: BigUint = Zero::zero();
     let f1: BigUint = One::one();
 
-    // if less than two, not prime
-    if *candidate == f0 || *candidate == f1 {
-        return false;
+    // below 2^64 a fixed set of bases gives an exact answer, whatever the configuration
+    if let Some(candidate) = candidate.to_u64() {
+        return primality::is_prime_u64(candidate);
     }
 
     let small_primes: Vec<u64> = vec![
@@ -92,85 +459,1153 @@ fn is_prime(candidate: &BigUint) -> bool {
         }
     }
 
-    return miller_rabin(&candidate);
+    // what is left is odd and above the small primes, as Baillie-PSW needs
+    if primality.baillie_psw && !primality::baillie_psw(candidate) {
+        return false;
+    }
+    miller_rabin(candidate, primality.miller_rabin_rounds)
 }
 
-fn hash_value_to_prime(value: &[u8], nonce: &[u8]) -> BigUint {
-    let f1: BigUint = One::one();
+#[cfg(feature = "std")]
+pub(crate) fn hash_value_to_prime(value: &[u8], nonce: &[u8], prime_mapping: PrimeMapping, security_level: SecurityLevel) -> BigUint {
+    hash_value_to_prime_with(value, nonce, prime_mapping, security_level.primality())
+}
+
+pub(crate) fn hash_value_to_prime_with(value: &[u8], nonce: &[u8], prime_mapping: PrimeMapping, primality: PrimalityConfig) -> BigUint {
     let value_and_nonce: Vec<u8> = [value.to_vec(), nonce.to_vec()].concat();
-    let hashed_value_and_nonce: Vec<u8> = hash_byte_sequence(&value_and_nonce);
-    let mut candidate: BigUint = BigUint::from_bytes_be(&hashed_value_and_nonce);
+    if prime_mapping.certified {
+        return pocklington::certified_prime(&value_and_nonce, prime_mapping.width(), prime_mapping.hash_function).0;
+    }
+    next_prime(prime_mapping.candidate(&value_and_nonce), primality)
+}
+
+// the first prime from `candidate` on
+fn next_prime(mut candidate: BigUint, primality: PrimalityConfig) -> BigUint {
+    let f1: BigUint = One::one();
     loop {
-        if is_prime(&candidate) {
-            return candidate.clone();
+        if is_prime_with(&candidate, primality) {
+            return candidate;
         }
         candidate += f1.clone();
     }
 }
 
+/// Checks that `witness` proves membership of `value` in the accumulator at `state`,
+/// using nothing but public data: state = witness ^ map_to_prime(value, nonce) mod n.
+/// The nonce a member added several times under [`params::DuplicatePolicy::Multiset`] is held
+/// with maps to the product of the primes of all of its occurrences, so it proves all of
+/// them at once. Values are mapped to primes with the default hash function, see
+/// [`verify_membership_with_hash`] for accumulators configured otherwise.
+pub fn verify_membership(state: &BigUint, modulus: &BigUint, value: &[u8], nonce: &[u8], witness: &BigUint) -> bool {
+    verify_membership_with(PrimeMapping::default(), state, modulus, value, nonce, witness)
+}
+
+pub fn verify_membership_with_hash(
+    hash_function: HashFunction,
+    state: &BigUint,
+    modulus: &BigUint,
+    value: &[u8],
+    nonce: &[u8],
+    witness: &BigUint,
+) -> bool {
+    verify_membership_with(PrimeMapping::new(hash_function, None), state, modulus, value, nonce, witness)
+}
+
+/// [`verify_membership`] for accumulators with any [`PrimeMapping`], as found in their
+/// [`PublicParameters`].
+pub fn verify_membership_with(
+    prime_mapping: PrimeMapping,
+    state: &BigUint,
+    modulus: &BigUint,
+    value: &[u8],
+    nonce: &[u8],
+    witness: &BigUint,
+) -> bool {
+    // the number of rounds does not change which prime is found, so the default level will do
+    let exponent: BigUint = member_prime(value, nonce, prime_mapping, SecurityLevel::default().primality());
+    arith::modpow(witness, &exponent, modulus) == *state
+}
+
+/// Checks that `witness` proves `prime` to be accumulated at `state`, for members added
+/// with [`SetAccumulator::add_prime`]: state = witness ^ prime mod n.
+pub fn verify_prime_membership(state: &BigUint, modulus: &BigUint, prime: &BigUint, witness: &BigUint) -> bool {
+    arith::modpow(witness, prime, modulus) == *state
+}
+
+// see SetAccumulator::state_digest
+#[cfg(feature = "std")]
+pub(crate) fn state_digest(modulus: &BigUint, generator: &BigUint, state: &BigUint) -> [u8; 32] {
+    let mut hasher: Sha256 = Sha256::new();
+    hasher.update(STATE_DOMAIN);
+    for field in [modulus, generator, state].iter() {
+        let bytes: Vec<u8> = field.to_bytes_be();
+        hasher.update((bytes.len() as u64).to_be_bytes());
+        hasher.update(&bytes);
+    }
+    hasher.update([STATE_DIGEST_VERSION]);
+    hasher.finalize().into()
+}
+
+#[cfg(feature = "std")]
 impl<T: Storer> SetAccumulator<T> {
     pub fn new(s: T) -> SetAccumulator<T> {
-        SetAccumulator { store: s }
-    }
-    pub fn add(&mut self, value: &[u8]) {
-        // get random once time use byte sequence
-        let nonce = rand::thread_rng().gen::<[u8; 32]>();
-        // hash the value and nonce concatentated and then map to prime
-        let exponent: BigUint = hash_value_to_prime(value, &nonce);
-        // get modulus
-        let modulus: BigUint = self.store.get_modulus();
-        // get current state of generator
-        let state: BigUint = self.store.get_state();
-        // compute the new state
-        let new_state = state.modpow(&exponent, &modulus);
+        let prime_mapping: PrimeMapping = s.get_prime_mapping();
+        let security_level: SecurityLevel = SecurityLevel::default();
+        SetAccumulator { store: s, security_level, primality: security_level.primality(), prime_mapping, nonce_mode: NonceMode::default(), duplicate_policy: DuplicatePolicy::default(), member_index: None, transaction: None, subscribers: Subscribers::default(), metrics: None, member_limit: None }
+    }
+    pub fn with_security_level(s: T, security_level: SecurityLevel) -> Result<SetAccumulator<T>, AccumulatorError> {
+        // refuse to load a store whose modulus or primes are weaker than the requested level
+        let bits: u64 = s.get_modulus().bits();
+        if bits < security_level.modulus_bits() {
+            return Err(AccumulatorError::ModulusTooSmall { bits, required: security_level.modulus_bits() });
+        }
+        // primes stretched from a narrower digest collide as often as the digests do
+        let prime_mapping: PrimeMapping = s.get_prime_mapping();
+        let bits: u64 = prime_mapping.width().min(prime_mapping.hash_function.output_bits());
+        if bits < security_level.prime_bits() {
+            return Err(AccumulatorError::PrimesTooNarrow { bits, required: security_level.prime_bits() });
+        }
+        Ok(SetAccumulator { store: s, security_level, primality: security_level.primality(), prime_mapping, nonce_mode: NonceMode::default(), duplicate_policy: DuplicatePolicy::default(), member_index: None, transaction: None, subscribers: Subscribers::default(), metrics: None, member_limit: None })
+    }
+    /// Loads a store built over [`PublicParameters::rsa2048`], at the 112 bit security
+    /// level a 2048 bit modulus provides.
+    pub fn with_rsa2048(s: T) -> Result<SetAccumulator<T>, AccumulatorError> {
+        if *s.get_modulus() != PublicParameters::rsa2048().modulus {
+            return Err(AccumulatorError::UnexpectedModulus);
+        }
+        SetAccumulator::with_security_level(s, SecurityLevel::Bits112)
+    }
+    pub fn security_level(&self) -> SecurityLevel {
+        self.security_level
+    }
+    /// Sets how candidates are tested for primality when mapping values to primes, in
+    /// place of the Miller-Rabin rounds of the security level.
+    pub fn with_primality(mut self, primality: PrimalityConfig) -> Self {
+        self.primality = primality;
+        self
+    }
+    pub fn primality(&self) -> PrimalityConfig {
+        self.primality
+    }
+    pub fn hash_function(&self) -> HashFunction {
+        self.prime_mapping.hash_function
+    }
+    pub fn prime_mapping(&self) -> PrimeMapping {
+        self.prime_mapping
+    }
+    /// Sets how nonces are chosen for values added from now on.
+    pub fn with_nonce_mode(mut self, nonce_mode: NonceMode) -> Self {
+        self.nonce_mode = nonce_mode;
+        self
+    }
+    pub fn nonce_mode(&self) -> NonceMode {
+        self.nonce_mode
+    }
+    /// Sets what adding a value that is already a member does.
+    pub fn with_duplicate_policy(mut self, duplicate_policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = duplicate_policy;
+        self
+    }
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.duplicate_policy
+    }
+    /// Reports what the accumulator does to `metrics` from now on, see [`metrics`].
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    /// Refuses, with [`AccumulatorError::QuotaExceeded`], any change that would leave the
+    /// accumulator with more than `limit` members. Deletes are always let through, also
+    /// when the accumulator already holds more than the limit.
+    pub fn with_member_limit(mut self, limit: usize) -> Self {
+        self.member_limit = Some(limit);
+        self
+    }
+    pub fn member_limit(&self) -> Option<usize> {
+        self.member_limit
+    }
+    // runs an exponentiation, timing it when someone is counting
+    fn timed<F: FnOnce() -> BigUint>(&self, modpow: F) -> BigUint {
+        match self.metrics.as_ref() {
+            Some(metrics) => {
+                let start: Instant = Instant::now();
+                let result: BigUint = modpow();
+                metrics.record_modpow(start.elapsed());
+                result
+            }
+            None => modpow(),
+        }
+    }
+    // raises a base to a product of primes mod n. With the trapdoor, an exponent wider
+    // than the modulus is first reduced mod λ(n), so a rebuild over every member costs one
+    // exponentiation by a number the size of n, and every exponentiation is split over the
+    // factors, see [`Trapdoor::modpow`]. For a base coprime to n, x^λ(n) = 1 makes any
+    // reduction mod λ(n) right; reducing to (e - 1) mod λ(n) + 1 keeps it right for bases
+    // sharing a factor with n too, since n is square free and the exponent stays positive.
+    fn power_of_product(&self) -> impl Fn(&BigUint, &BigUint) -> BigUint + Sync + '_ {
+        let trapdoor: Option<(Trapdoor, SecretBigUint)> = self.store.get_trapdoor().map(|trapdoor| {
+            let lambda: SecretBigUint = trapdoor.lambda();
+            (trapdoor, lambda)
+        });
+        let modulus: &BigUint = self.store.get_modulus();
+        move |base: &BigUint, exponent: &BigUint| match trapdoor.as_ref() {
+            Some((trapdoor, lambda)) if exponent.bits() > modulus.bits() => {
+                trapdoor.modpow(base, &SecretBigUint::new((exponent - 1_u64) % &**lambda + 1_u64))
+            }
+            Some((trapdoor, _)) => trapdoor.modpow(base, exponent),
+            None => arith::modpow(base, exponent, modulus),
+        }
+    }
+    fn record_witnesses(&self, count: usize) {
+        if let Some(metrics) = self.metrics.as_ref() {
+            (0..count).for_each(|_| metrics.record_witness());
+        }
+    }
+    pub fn get_public_parameters(&self) -> PublicParameters {
+        PublicParameters::new(self.store.get_modulus().clone(), self.store.get_generator().clone()).with_prime_mapping(self.prime_mapping)
+    }
+    /// SHA-256 over the length-prefixed modulus, generator and state and the version of
+    /// this layout, for pinning the state a proof was checked against or comparing two
+    /// replicas without exchanging their states.
+    pub fn state_digest(&self) -> [u8; 32] {
+        state_digest(self.store.get_modulus(), self.store.get_generator(), self.store.get_state())
+    }
+    /// The whole accumulator in the [`snapshot`] format.
+    pub fn export_snapshot(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        snapshot::write_snapshot(&self.store, &mut bytes).unwrap();
+        bytes
+    }
+    /// Restores a snapshot from [`export_snapshot`](SetAccumulator::export_snapshot) into
+    /// `store`, which must be empty and built over the snapshot's parameters.
+    pub fn import_snapshot(bytes: &[u8], mut store: T) -> io::Result<SetAccumulator<T>> {
+        snapshot::SnapshotReader::new(bytes)?.restore_into(&mut store)?;
+        Ok(SetAccumulator::new(store))
+    }
+    /// Whether `value` is a member.
+    pub fn contains(&self, value: &[u8]) -> bool {
+        self.store.contains_member(value)
+    }
+    /// The number of members.
+    pub fn len(&self) -> usize {
+        self.store.member_count()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// How many times `value` is a member, more than once only under
+    /// [`DuplicatePolicy::Multiset`].
+    pub fn multiplicity(&self, value: &[u8]) -> usize {
+        self.store.get_nonce(value).map(|nonce| occurrences(&nonce).len()).unwrap_or(0)
+    }
+    /// The values of the members, in no particular order.
+    pub fn iter_members(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.store.iter_members().map(|(value, _)| value)
+    }
+    pub fn is_empty_state(&self) -> bool {
+        // the genesis state is the generator
+        self.store.get_state() == self.store.get_generator()
+    }
+    fn get_member_index(&mut self) -> &mut MemberIndex {
+        if self.member_index.is_none() && self.store.looks_up_members() {
+            // the store looks up the members written, the index only holds what the
+            // transaction under way reserved
+            self.member_index = Some(MemberIndex::default());
+        }
+        if self.member_index.is_none() {
+            // every occurrence of a member counts on its own
+            let members: Vec<(&[u8], &[u8])> = self.store
+                .iter_members()
+                .flat_map(|(member, nonce)| occurrences(nonce).into_iter().map(move |occurrence| (member, occurrence)))
+                .collect();
+            let primes: Vec<BigUint> = self.primes_of(&members);
+            let nonces: HashSet<Vec<u8>> = members.iter().map(|(_, nonce)| nonce.to_vec()).collect();
+            self.member_index = Some(MemberIndex { primes: primes.into_iter().collect(), nonces });
+        }
+        self.member_index.as_mut().unwrap()
+    }
+    // the primes of `members`, reusing those the store kept and spreading the searches for
+    // the rest across threads (with the parallel feature) since they dominate the cost
+    pub(crate) fn primes_of(&self, members: &[(&[u8], &[u8])]) -> Vec<BigUint> {
+        let kept: Vec<Option<&BigUint>> = members.iter().map(|(member, nonce)| self.store.get_prime(member, nonce)).collect();
+        let primality: PrimalityConfig = self.primality;
+        let prime_mapping: PrimeMapping = self.prime_mapping;
+        members
+            .par_iter()
+            .zip(kept.par_iter())
+            .map(|((member, nonce), kept)| match kept {
+                Some(prime) => (*prime).clone(),
+                None => member_prime(member, nonce, prime_mapping, primality),
+            })
+            .collect()
+    }
+    fn prime_of(&self, value: &[u8], nonce: &[u8]) -> BigUint {
+        match self.store.get_prime(value, nonce) {
+            Some(prime) => prime.clone(),
+            None => member_prime(value, nonce, self.prime_mapping, self.primality),
+        }
+    }
+    // the nonce and prime of the last occurrence of a member held with `nonce`, and the
+    // nonce it is held with once that is gone, None if it is its only one
+    fn last_occurrence(&self, value: &[u8], nonce: &[u8]) -> (Vec<u8>, BigUint, Option<Vec<u8>>) {
+        let all: Vec<&[u8]> = occurrences(nonce);
+        if all.len() == 1 {
+            return (nonce.to_vec(), self.prime_of(value, nonce), None);
+        }
+        let last: &[u8] = all[all.len() - 1];
+        let prime: BigUint = hash_value_to_prime_with(value, last, self.prime_mapping, self.primality);
+        (last.to_vec(), prime, Some(join_occurrences(&all[..all.len() - 1])))
+    }
+    // records `value` as a member with `nonce`, or as one more occurrence of it when it
+    // already is one
+    fn add_occurrence(&mut self, value: &[u8], nonce: &[u8], prime: &BigUint) {
+        match self.store.get_nonce(value) {
+            Some(held) => {
+                let product: BigUint = self.prime_of(value, &held) * prime;
+                let mut all: Vec<&[u8]> = occurrences(&held);
+                all.push(nonce);
+                let joined: Vec<u8> = join_occurrences(&all);
+                self.store.set_prime(value, &joined, &product);
+                self.insert_member(value, joined);
+            }
+            None => {
+                self.store.set_prime(value, nonce, prime);
+                self.insert_member(value, nonce.to_vec());
+            }
+        }
+    }
+    fn draw_nonce<R: Rng>(&mut self, value: &[u8], rng: &mut R) -> Result<(Vec<u8>, BigUint), AccumulatorError> {
+        let primality: PrimalityConfig = self.primality;
+        let prime_mapping: PrimeMapping = self.prime_mapping;
+        let nonce_mode: NonceMode = self.nonce_mode;
+        self.get_member_index();
+        let member_index: &mut MemberIndex = self.member_index.as_mut().unwrap();
+        member_index.draw_nonce(value, nonce_mode, prime_mapping, primality, rng, &self.store)
+    }
+    /// Runs `f` as one transaction: the store writes everything it changed at once, and if
+    /// `f` fails the members and state are put back as they were, so either all of its
+    /// changes land or none do. A transaction started inside `f` joins this one.
+    pub fn transaction<R, F>(&mut self, f: F) -> Result<R, AccumulatorError>
+    where
+        F: FnOnce(&mut Self) -> Result<R, AccumulatorError>,
+    {
+        if self.transaction.is_some() {
+            return f(self);
+        }
+        let state: BigUint = self.store.get_state().clone();
+        let members: Option<usize> = self.member_limit.map(|_| self.store.member_count());
+        self.store.begin_transaction();
+        self.transaction = Some(Transaction { state, members: HashMap::new(), picked: HashSet::new(), log: Vec::new(), events: Vec::new() });
+        let mut result: Result<R, AccumulatorError> = f(self);
+        // a change past the limit is undone like any other failure
+        if let (Ok(_), Some(limit), Some(before)) = (result.as_ref(), self.member_limit, members) {
+            let after: usize = self.store.member_count();
+            if after > limit && after > before {
+                result = Err(AccumulatorError::QuotaExceeded { limit });
+            }
+        }
+        let mut transaction: Transaction = self.transaction.take().unwrap();
+        let epoch: Option<Epoch> = match result {
+            Ok(_) => {
+                let epoch: Option<Epoch> = self.epoch_since(&transaction);
+                let number: u64 = self.store.epoch_count() + 1;
+                for mut record in transaction.log.drain(..) {
+                    record.epoch = number;
+                    self.store.append_log(&record);
+                    if let Some(metrics) = self.metrics.as_ref() {
+                        match record.action {
+                            AuditAction::Add => metrics.record_add(),
+                            AuditAction::Delete => metrics.record_delete(),
+                            AuditAction::Rollback => {}
+                        }
+                    }
+                }
+                if let (Some(metrics), Some(_)) = (self.metrics.as_ref(), epoch.as_ref()) {
+                    metrics.record_member_count(self.store.member_count());
+                }
+                epoch
+            }
+            Err(_) => {
+                if !transaction.members.is_empty() || *self.store.get_state() != transaction.state {
+                    for (value, nonce) in transaction.members {
+                        match nonce {
+                            Some(nonce) => self.store.insert_member(&value, &nonce),
+                            None => {
+                                self.store.remove_member(&value);
+                            }
+                        };
+                    }
+                    self.store.set_state(&transaction.state);
+                    // the index may hold primes reserved by the undone changes
+                    self.member_index = None;
+                }
+                None
+            }
+        };
+        // the epoch goes into the same write as the state it closed with
+        if let Some(epoch) = epoch.as_ref() {
+            self.store.push_epoch(epoch);
+        }
+        if let Err(e) = self.store.commit_transaction() {
+            // the store took a new view of the accumulator, which the index knows nothing of
+            self.member_index = None;
+            return Err(e);
+        }
+        if self.store.looks_up_members() {
+            // whatever the transaction reserved is written now
+            self.member_index = None;
+        }
+        if let Some(epoch) = epoch {
+            for event in transaction.events {
+                self.subscribers.publish(event);
+            }
+            self.subscribers.publish(AccumulatorEvent::StateChanged { epoch: self.store.epoch_count(), previous: transaction.state, state: epoch.state });
+        }
+        result
+    }
+    /// A channel that receives an event for every change committed from now on, see
+    /// [`events`].
+    pub fn subscribe(&mut self) -> Receiver<AccumulatorEvent> {
+        self.subscribers.subscribe()
+    }
+    // the epoch closed by the changes made since `transaction` began, None if they left
+    // the members and the state as they were and logged nothing
+    fn epoch_since(&self, transaction: &Transaction) -> Option<Epoch> {
+        let operations: Vec<Operation> = transaction.members
+            .iter()
+            .filter_map(|(value, before)| match self.store.get_nonce(value) {
+                after if after == *before => None,
+                // a member added again is recorded with the nonce it has now
+                Some(nonce) => {
+                    let prime: Option<BigUint> = match transaction.picked.contains(value) {
+                        true => self.store.get_prime(value, &nonce).cloned(),
+                        false => None,
+                    };
+                    Some(Operation::Add { value: value.clone(), nonce, prime })
+                }
+                None => Some(Operation::Delete { value: value.clone() }),
+            })
+            .collect();
+        if operations.is_empty() && *self.store.get_state() == transaction.state && transaction.log.is_empty() {
+            return None;
+        }
+        Some(Epoch { operations, state: self.store.get_state().clone() })
+    }
+    /// The number of epochs closed so far. Every committed transaction, and so every add
+    /// or delete outside of one, that changes anything closes an epoch, numbered from 1;
+    /// epoch 0 is the genesis state. Only stores that keep the history count epochs, see
+    /// [`Storer::push_epoch`].
+    pub fn current_epoch(&self) -> u64 {
+        self.store.epoch_count()
+    }
+    /// The state at the close of `epoch`, None for epochs the store does not hold or
+    /// fails to read back.
+    pub fn state_at(&self, epoch: u64) -> Option<BigUint> {
+        if epoch == 0 {
+            return Some(self.store.get_generator().clone());
+        }
+        self.epoch(epoch).map(|epoch| epoch.state)
+    }
+    // an epoch the store cannot read back is, for whatever is built on the history, one it
+    // does not hold
+    fn epoch(&self, number: u64) -> Option<Epoch> {
+        self.store.get_epoch(number).ok().flatten()
+    }
+    // the members at the close of `epoch` with their nonces, replayed from genesis, or
+    // from the first epoch kept if the store was compacted, and the primes the epochs
+    // recorded for them, which they do for those added with a prime of the caller's
+    fn members_at(&self, epoch: u64) -> Option<EpochMembers> {
+        let mut members: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+        let mut primes: HashMap<Vec<u8>, BigUint> = HashMap::new();
+        let first: u64 = self.store.first_epoch();
+        if epoch != 0 && epoch < first {
+            return None;
+        }
+        for number in first..=epoch {
+            for operation in self.epoch(number)?.operations {
+                match operation {
+                    Operation::Add { value, nonce, prime } => {
+                        match prime {
+                            Some(prime) => primes.insert(value.clone(), prime),
+                            None => primes.remove(&value),
+                        };
+                        members.insert(value, nonce);
+                    }
+                    Operation::Delete { value } => {
+                        primes.remove(&value);
+                        members.remove(&value);
+                    }
+                }
+            }
+        }
+        Some((members, primes))
+    }
+    /// A proof that `value` was a member at the close of `epoch`, checked against the
+    /// state at [`SetAccumulator::state_at`] rather than the current one. None if it was
+    /// not a member then, or the store holds no history going back to genesis (a store
+    /// built over members it already had, say) to rebuild that epoch's members from.
+    pub fn prove_membership_at(&self, value: &[u8], epoch: u64) -> Option<MembershipProof> {
+        let view: EpochView<T> = self.view_at(epoch)?;
+        let proof: MembershipProof = view.get_membership_proof(value)?;
+        // members replayed from an incomplete history do not add up to the recorded state
+        if !view.verify(value, &proof) {
+            return None;
+        }
+        Some(proof)
+    }
+    /// A read-only view of the accumulator at the close of `epoch`, with the state and
+    /// members it had then, for reproducing what a verifier saw at the time. Fails with
+    /// [`AccumulatorError::UnknownEpoch`] unless the store holds the history from genesis
+    /// to `epoch`, which is checked by recomputing the state from the members replayed.
+    pub fn open_at(&self, epoch: u64) -> Result<EpochView<'_, T>, AccumulatorError> {
+        let view: EpochView<T> = self.view_at(epoch).ok_or(AccumulatorError::UnknownEpoch)?;
+        let members: Vec<(&[u8], &[u8])> = view.iter_members().collect();
+        let exponent: BigUint = product_tree::product(view.primes_of(&members));
+        if self.timed(|| self.power_of_product()(self.store.get_generator(), &exponent)) != *view.get_state() {
+            return Err(AccumulatorError::UnknownEpoch);
+        }
+        Ok(view)
+    }
+    fn view_at(&self, epoch: u64) -> Option<EpochView<'_, T>> {
+        let (members, primes): EpochMembers = self.members_at(epoch)?;
+        Some(EpochView::new(self, epoch, self.state_at(epoch)?, members, primes))
+    }
+    /// Checks a proof against the state at the close of `epoch`, false for epochs the
+    /// store does not hold.
+    pub fn verify_at(&self, epoch: u64, value: &[u8], proof: &MembershipProof) -> bool {
+        let state: BigUint = match self.state_at(epoch) {
+            Some(state) => state,
+            None => return false,
+        };
+        let exponent: BigUint = member_prime(value, &proof.nonce, self.prime_mapping, self.primality);
+        if arith::modpow(&proof.witness, &exponent, self.store.get_modulus()) == state {
+            return true;
+        }
+        // a member added with a prime of the caller's is checked against the prime its epoch recorded
+        self.view_at(epoch).is_some_and(|view| view.verify(value, proof))
+    }
+    // the prime a proof of `value` with `nonce` is checked against, as the occurrence was
+    // accumulated with. The store keeps the primes callers picked, which count only while
+    // `value` is held with `nonce`, as `held` reports: any other proof is of a prime its
+    // nonce maps to.
+    fn proof_prime<F: FnOnce() -> Option<Vec<u8>>>(&self, value: &[u8], nonce: &[u8], held: F) -> BigUint {
+        match self.store.get_prime(value, nonce) {
+            Some(kept) if held().as_deref() == Some(nonce) => kept.clone(),
+            _ => member_prime(value, nonce, self.prime_mapping, self.primality),
+        }
+    }
+    /// The epochs after `since_epoch`, for a replica at that epoch to catch up with
+    /// [`SetAccumulator::apply_delta`] rather than load a new snapshot. None if the store
+    /// does not hold all of them, as the changes of the first epoch a compacted store
+    /// keeps are not held either.
+    pub fn export_delta(&self, since_epoch: u64) -> Option<Delta> {
+        if self.store.first_epoch() > 1 && since_epoch < self.store.first_epoch() {
+            return None;
+        }
+        let from: BigUint = self.state_at(since_epoch)?;
+        let epochs: Vec<Epoch> = (since_epoch + 1..=self.current_epoch()).map(|number| self.epoch(number)).collect::<Option<Vec<Epoch>>>()?;
+        Some(Delta { from, epochs })
+    }
+    /// Drops the epochs `retention` does not keep from the store, returning how many were
+    /// dropped. The first epoch kept is rewritten to add every member it had, so the
+    /// members of it and every later epoch can still be replayed; the audit log is kept
+    /// whole. Fails with [`AccumulatorError::UnknownEpoch`] if the store does not hold the
+    /// history back to the first epoch kept, and with [`AccumulatorError::Io`] if it
+    /// cannot write the compacted history. Stores that cannot compact drop nothing.
+    pub fn compact(&mut self, retention: Retention) -> Result<u64, AccumulatorError> {
+        let keep_from: u64 = self.retained_from(retention);
+        let first: u64 = self.store.first_epoch();
+        if keep_from <= first {
+            return Ok(0);
+        }
+        let view: EpochView<T> = self.open_at(keep_from)?;
+        let mut members: Vec<(&[u8], &[u8])> = view.iter_members().collect();
+        members.sort();
+        let operations: Vec<Operation> = members
+            .iter()
+            .map(|(value, nonce)| Operation::Add { value: value.to_vec(), nonce: nonce.to_vec(), prime: view.recorded_prime(value).cloned() })
+            .collect();
+        let base: Epoch = Epoch { operations, state: view.get_state().clone() };
+        self.store.compact_epochs(keep_from, &base).map_err(|e| AccumulatorError::Io(e.to_string()))?;
+        Ok(self.store.first_epoch() - first)
+    }
+    // the first epoch `retention` keeps
+    fn retained_from(&self, retention: Retention) -> u64 {
+        let current: u64 = self.current_epoch();
+        match retention {
+            Retention::KeepAll => 1,
+            Retention::KeepLast(count) => current.saturating_sub(count.max(1)) + 1,
+            Retention::KeepDaily(days) => {
+                let today: u64 = SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or(0) / SECONDS_PER_DAY;
+                let cutoff: u64 = (today + 1).saturating_sub(days) * SECONDS_PER_DAY;
+                let mut recent = self.store
+                    .iter_log()
+                    .filter_map(Result::ok)
+                    .filter(|record| record.timestamp >= cutoff)
+                    .map(|record| record.epoch);
+                match recent.next() {
+                    Some(epoch) => epoch,
+                    // nothing that recent, keep the current epoch alone
+                    None if self.store.iter_log().next().is_some() => current,
+                    // no log to tell the age of the epochs by
+                    None => 1,
+                }
+            }
+        }
+    }
+    /// Drops what the store keeps for nothing: the epochs `retention` does not keep, as
+    /// [`SetAccumulator::compact`] does, and the primes kept for former members. With
+    /// `dry_run` nothing is dropped and the report counts what would be, though stores
+    /// that cannot compact keep their epochs anyway. Nonces go with the members they
+    /// belong to, and witnesses are never kept but computed from the epochs, so those are
+    /// all there is to collect.
+    pub fn collect_garbage(&mut self, retention: Retention, dry_run: bool) -> Result<GarbageReport, AccumulatorError> {
+        let to_io = |e: io::Error| AccumulatorError::Io(e.to_string());
+        if dry_run {
+            let epochs: u64 = self.retained_from(retention).saturating_sub(self.store.first_epoch());
+            return Ok(GarbageReport { epochs, primes: self.store.collect_primes(true).map_err(to_io)? });
+        }
+        let epochs: u64 = self.compact(retention)?;
+        Ok(GarbageReport { epochs, primes: self.store.collect_primes(false).map_err(to_io)? })
+    }
+    /// Applies the epochs of `delta`, which has to start from the current state, each as
+    /// a transaction of its own, and checks each before applying it: with A the product
+    /// of the primes an epoch adds and D the product of those it deletes, its state raised
+    /// to D has to be the state before it raised to A. A member added again counts its old
+    /// prime as deleted. Stops at the first epoch that fails, keeping the ones before it.
+    pub fn apply_delta(&mut self, delta: &Delta) -> Result<(), AccumulatorError> {
+        if *self.store.get_state() != delta.from {
+            return Err(AccumulatorError::InvalidTransition);
+        }
+        for epoch in delta.epochs.iter() {
+            self.transaction(|sa| sa.apply_epoch(epoch))?;
+        }
+        Ok(())
+    }
+    fn apply_epoch(&mut self, epoch: &Epoch) -> Result<(), AccumulatorError> {
+        let mut added: Vec<(&[u8], &[u8], BigUint)> = Vec::new();
+        let mut deleted: Vec<(&[u8], Vec<u8>, BigUint)> = Vec::new();
+        let mut picked: Vec<&[u8]> = Vec::new();
+        for operation in epoch.operations.iter() {
+            let (value, nonce, prime): (&[u8], Option<&[u8]>, Option<&BigUint>) = match operation {
+                Operation::Add { value, nonce, prime } => (value, Some(nonce), prime.as_ref()),
+                Operation::Delete { value } => (value, None, None),
+            };
+            match (self.store.get_nonce(value), nonce) {
+                (None, None) => return Err(AccumulatorError::NotAMember),
+                (None, Some(_)) => {}
+                (Some(old), _) => {
+                    let prime: BigUint = self.prime_of(value, &old);
+                    deleted.push((value, old, prime));
+                }
+            }
+            match (nonce, prime) {
+                (Some(nonce), Some(prime)) => {
+                    added.push((value, nonce, prime.clone()));
+                    picked.push(value);
+                }
+                (Some(nonce), None) => added.push((value, nonce, self.prime_of(value, nonce))),
+                (None, _) => {}
+            }
+        }
+        let added_product: BigUint = product_tree::product(added.iter().map(|(_, _, prime)| prime.clone()).collect());
+        let deleted_product: BigUint = product_tree::product(deleted.iter().map(|(_, _, prime)| prime.clone()).collect());
+        let modulus: &BigUint = self.store.get_modulus();
+        let reached: BigUint = self.timed(|| arith::modpow(&epoch.state, &deleted_product, modulus));
+        if reached != self.timed(|| arith::modpow(self.store.get_state(), &added_product, modulus)) {
+            return Err(AccumulatorError::InvalidTransition);
+        }
+        for (value, _, _) in deleted.iter() {
+            self.remove_member(value);
+        }
+        for (value, nonce, prime) in added.iter() {
+            self.store.set_prime(value, nonce, prime);
+            self.insert_member(value, nonce.to_vec());
+        }
+        for value in picked {
+            self.pick(value);
+        }
+        // the index has not seen any of this, rebuild it when it is next needed
+        self.member_index = None;
+        let previous_state: BigUint = self.store.get_state().clone();
+        self.store.set_state(&epoch.state);
+        for (value, nonce, prime) in deleted.iter() {
+            self.log_operation(AuditAction::Delete, value, nonce, prime, &previous_state);
+        }
+        for (value, nonce, prime) in added.iter() {
+            self.log_operation(AuditAction::Add, value, nonce, prime, &previous_state);
+        }
+        Ok(())
+    }
+    /// Rolls the accumulator back to the members and state it had at the close of `epoch`,
+    /// so that recovering from changes that should never have been made takes no editing
+    /// of the store. The history is kept: the rollback closes a new epoch whose adds and
+    /// deletes take the members back, logged as usual and followed by a rollback record,
+    /// see [`AuditAction::Rollback`], signed with `signer`. Returns that record. Fails with
+    /// [`AccumulatorError::UnknownEpoch`] unless the store holds every epoch up to `epoch`.
+    pub fn rollback_to<S: HeadSigner>(&mut self, epoch: u64, reason: &str, signer: &S) -> Result<AuditRecord, AccumulatorError> {
+        if epoch > self.current_epoch() {
+            return Err(AccumulatorError::UnknownEpoch);
+        }
+        let (members, primes): EpochMembers = self.members_at(epoch).ok_or(AccumulatorError::UnknownEpoch)?;
+        let state: BigUint = self.state_at(epoch).ok_or(AccumulatorError::UnknownEpoch)?;
+        self.transaction(|sa| {
+            let mut operations: Vec<Operation> = sa.store
+                .iter_members()
+                .filter(|(value, _)| !members.contains_key(*value))
+                .map(|(value, _)| Operation::Delete { value: value.to_vec() })
+                .collect();
+            // members held since with another nonce are deleted and added back with their old one
+            operations.extend(
+                members
+                    .iter()
+                    .filter(|(value, nonce)| {
+                        sa.store.get_nonce(value).as_ref() != Some(*nonce)
+                            || primes.get(*value).is_some_and(|prime| sa.store.get_prime(value, nonce) != Some(prime))
+                    })
+                    .map(|(value, nonce)| Operation::Add { value: value.clone(), nonce: nonce.clone(), prime: primes.get(value).cloned() }),
+            );
+            let previous_state: BigUint = sa.store.get_state().clone();
+            sa.apply_epoch(&Epoch { operations, state })?;
+            let mut record: AuditRecord = AuditRecord {
+                action: AuditAction::Rollback,
+                epoch: sa.store.epoch_count() + 1,
+                value_hash: Sha256::digest(reason.as_bytes()).into(),
+                nonce: Vec::new(),
+                prime: BigUint::from(epoch),
+                previous_state,
+                new_state: sa.store.get_state().clone(),
+                timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or(0),
+            };
+            record.nonce = signer.sign(&record.rollback_digest());
+            if let Some(transaction) = sa.transaction.as_mut() {
+                transaction.log.push(record.clone());
+            }
+            Ok(record)
+        })
+    }
+    // notes an add or delete for the audit log, once the state it led to is set
+    fn log_operation(&mut self, action: AuditAction, value: &[u8], nonce: &[u8], prime: &BigUint, previous_state: &BigUint) {
+        let record: AuditRecord = AuditRecord {
+            action,
+            // numbered when the transaction commits
+            epoch: 0,
+            value_hash: Sha256::digest(value).into(),
+            nonce: nonce.to_vec(),
+            prime: prime.clone(),
+            previous_state: previous_state.clone(),
+            new_state: self.store.get_state().clone(),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or(0),
+        };
+        let event: Option<AccumulatorEvent> = match (self.subscribers.is_empty(), action) {
+            (true, _) => None,
+            (false, AuditAction::Add) => Some(AccumulatorEvent::Added { value: value.to_vec(), nonce: nonce.to_vec(), prime: prime.clone() }),
+            (false, AuditAction::Delete) => Some(AccumulatorEvent::Deleted { value: value.to_vec(), prime: prime.clone() }),
+            (false, AuditAction::Rollback) => None,
+        };
+        if let Some(transaction) = self.transaction.as_mut() {
+            transaction.log.push(record);
+            transaction.events.extend(event);
+        }
+    }
+    // remembers what `value` looked like before an open transaction first changed it
+    fn touch(&mut self, value: &[u8]) {
+        if let Some(transaction) = self.transaction.as_mut() {
+            if !transaction.members.contains_key(value) {
+                let nonce: Option<Vec<u8>> = self.store.get_nonce(value);
+                transaction.members.insert(value.to_vec(), nonce);
+            }
+        }
+    }
+    // notes that `value` was added with a prime of the caller's, for its epoch to record
+    fn pick(&mut self, value: &[u8]) {
+        if let Some(transaction) = self.transaction.as_mut() {
+            transaction.picked.insert(value.to_vec());
+        }
+    }
+    fn insert_member(&mut self, value: &[u8], nonce: Vec<u8>) {
+        self.touch(value);
+        self.store.insert_member(value, &nonce);
+    }
+    fn remove_member(&mut self, value: &[u8]) -> Option<Vec<u8>> {
+        if !self.store.contains_member(value) {
+            return None;
+        }
+        self.touch(value);
+        self.store.remove_member(value)
+    }
+    // drops the last occurrence of `value`, keeping it with the nonce of the others if
+    // there are any left
+    fn remove_occurrence(&mut self, value: &[u8], rest: Option<Vec<u8>>) {
+        match rest {
+            Some(rest) => self.insert_member(value, rest),
+            None => {
+                self.remove_member(value);
+            }
+        }
+    }
+    /// Adds `value`, returning its witness along with its prime and nonce. The witness of
+    /// a new member is the state before it was added, so this costs no more than the add;
+    /// only a value the [`DuplicatePolicy::Idempotent`] policy skips has its witness
+    /// computed from the other members. Like any witness it goes stale with the next change.
+    pub fn add(&mut self, value: &[u8]) -> Result<AddOutcome, AccumulatorError> {
+        self.transaction(|sa| sa.add_with_rng(value, &mut rand::thread_rng()))
+    }
+    /// Adds `value` with `nonce` instead of a fresh one, as replaying an operation log or
+    /// agreeing on the state with another node takes. No member may already hold the
+    /// prime it maps to.
+    pub fn add_with_nonce(&mut self, value: &[u8], nonce: &[u8]) -> Result<AddOutcome, AccumulatorError> {
+        if is_reserved_nonce(nonce) {
+            return Err(AccumulatorError::InvalidNonce);
+        }
+        let exponent: BigUint = hash_value_to_prime_with(value, nonce, self.prime_mapping, self.primality);
+        self.transaction(|sa| sa.add_picked(value, nonce.to_vec(), exponent))
+    }
+    /// Adds `value` under `prime`, computed elsewhere, instead of the prime its nonce maps
+    /// to. The member is held with the empty nonce and the store keeps its prime, see
+    /// [`Storer::set_prime`], so stores that keep no primes refuse it with
+    /// [`AccumulatorError::PrimesNotKept`]. Nothing ties the prime to the value, so the
+    /// witnesses of such a member prove only that its prime is accumulated: they check out
+    /// with [`verify_prime_membership`], not [`verify_membership`]. The prime passes the
+    /// accumulator's primality test or is refused with [`AccumulatorError::InvalidPrime`].
+    /// A member holds one prime of the caller's at most, so under the
+    /// [`DuplicatePolicy::Multiset`] policy a value already a member is refused with
+    /// [`AccumulatorError::AlreadyAMember`].
+    pub fn add_prime(&mut self, value: &[u8], prime: &BigUint) -> Result<AddOutcome, AccumulatorError> {
+        if !is_prime_with(prime, self.primality) {
+            return Err(AccumulatorError::InvalidPrime);
+        }
+        if self.duplicate_policy == DuplicatePolicy::Multiset && self.store.contains_member(value) {
+            return Err(AccumulatorError::AlreadyAMember);
+        }
+        self.transaction(|sa| {
+            let added: AddOutcome = sa.add_picked(value, Vec::new(), prime.clone())?;
+            if sa.store.get_prime(value, &added.nonce) != Some(&added.prime) {
+                return Err(AccumulatorError::PrimesNotKept);
+            }
+            sa.pick(value);
+            Ok(added)
+        })
+    }
+    // whether the duplicate policy lets `value` be added, Ok(false) if it is to be skipped
+    fn admits(&self, value: &[u8]) -> Result<bool, AccumulatorError> {
+        let held: Vec<u8> = match self.store.get_nonce(value) {
+            Some(held) => held,
+            None => return Ok(true),
+        };
+        match self.duplicate_policy {
+            DuplicatePolicy::ErrorOnDuplicate => Err(AccumulatorError::AlreadyAMember),
+            DuplicatePolicy::Idempotent => Ok(false),
+            // the occurrences of a member are told apart by their nonces alone, which leaves
+            // no room for one added with a prime of the caller's
+            DuplicatePolicy::Multiset if self.prime_of(value, &held) != member_prime(value, &held, self.prime_mapping, self.primality) => {
+                Err(AccumulatorError::AlreadyAMember)
+            }
+            DuplicatePolicy::Multiset => Ok(true),
+        }
+    }
+    // the outcome of adding a member again under the idempotent policy, which is that of
+    // its last occurrence
+    fn held_outcome(&self, value: &[u8]) -> AddOutcome {
+        let (witness, nonce): (BigUint, Vec<u8>) = self.get_witness(value).unwrap();
+        // the prime the witness is for, as the store keeps it rather than hashed again
+        let held: Vec<u8> = self.store.get_nonce(value).unwrap();
+        let (_, prime, _): (Vec<u8>, BigUint, Option<Vec<u8>>) = self.last_occurrence(value, &held);
+        AddOutcome { prime, nonce, witness }
+    }
+    fn add_picked(&mut self, value: &[u8], nonce: Vec<u8>, exponent: BigUint) -> Result<AddOutcome, AccumulatorError> {
+        if !self.admits(value)? {
+            return Ok(self.held_outcome(value));
+        }
+        self.get_member_index();
+        self.member_index.as_mut().unwrap().reserve(&nonce, &exponent, &self.store)?;
+        self.accumulate(value, nonce, exponent)
+    }
+    fn add_with_rng<R: Rng>(&mut self, value: &[u8], rng: &mut R) -> Result<AddOutcome, AccumulatorError> {
+        if !self.admits(value)? {
+            return Ok(self.held_outcome(value));
+        }
+        let (nonce, exponent): (Vec<u8>, BigUint) = self.draw_nonce(value, rng)?;
+        self.accumulate(value, nonce, exponent)
+    }
+    // adds `value` with a nonce and prime already reserved in the member index
+    fn accumulate(&mut self, value: &[u8], nonce: Vec<u8>, exponent: BigUint) -> Result<AddOutcome, AccumulatorError> {
+        // compute the new state from the current one
+        let new_state: BigUint = self.timed(|| self.power_of_product()(self.store.get_state(), &exponent));
+        // record the value and the nonce used for that value in the members list, and its
+        // prime for stores that keep them, before the state so persistent stores can write
+        // everything when the state is set
+        self.add_occurrence(value, &nonce, &exponent);
         // update the store with new state
+        let previous_state: BigUint = self.store.get_state().clone();
         self.store.set_state(&new_state);
-        // record the value and the nonce used for that value in the members list
-        self.store.get_members_list().insert(value.to_vec(), nonce.to_vec());
+        self.log_operation(AuditAction::Add, value, &nonce, &exponent, &previous_state);
+        // state = previous_state ^ prime, so the state before is the witness
+        Ok(AddOutcome { prime: exponent, nonce, witness: previous_state })
     }
-    pub fn get_witness(&mut self, value: &[u8]) -> Option<(BigUint, Vec<u8>)> {
-        // if this value is not in the member list, no way to compute a witness, return
-        if !self.store.get_members_list().contains_key(value) {
-            return None;
+    /// Removes `value` using the factorization of the modulus held by the store: the state
+    /// is raised to the inverse of the member's prime mod φ(n), which takes one
+    /// exponentiation regardless of how many members remain.
+    pub fn delete(&mut self, value: &[u8]) -> Result<(), AccumulatorError> {
+        self.transaction(|sa| {
+            let trapdoor: Trapdoor = sa.store.get_trapdoor().ok_or(AccumulatorError::MissingTrapdoor)?;
+            let held: Vec<u8> = sa.store.get_nonce(value).ok_or(AccumulatorError::NotAMember)?;
+            let (nonce, exponent, rest): (Vec<u8>, BigUint, Option<Vec<u8>>) = sa.last_occurrence(value, &held);
+            // remove the member before touching the state, both paths below rely on it being gone
+            sa.remove_occurrence(value, rest);
+            if let Some(member_index) = sa.member_index.as_mut() {
+                member_index.remove(&exponent, &nonce);
+            }
+            let new_state: BigUint = match exponent.modinv(&trapdoor.phi()).map(SecretBigUint::new) {
+                // state = x^prime, so state^(prime^-1 mod φ(n)) = x
+                Some(inverse) => sa.timed(|| trapdoor.modpow(sa.store.get_state(), &inverse)),
+                // the prime divides p - 1 or q - 1, which is vanishingly rare for primes this
+                // wide, and rules out the shortcut; fall back to the remaining members
+                None => sa.recompute_state(),
+            };
+            let previous_state: BigUint = sa.store.get_state().clone();
+            sa.store.set_state(&new_state);
+            sa.log_operation(AuditAction::Delete, value, &nonce, &exponent, &previous_state);
+            Ok(())
+        })
+    }
+    /// Removes `value` without the factorization of the modulus by recomputing the state
+    /// from the remaining members, which costs one hash to prime per member. Returns the
+    /// new state so replicas can be brought in sync.
+    pub fn delete_without_trapdoor(&mut self, value: &[u8]) -> Result<BigUint, AccumulatorError> {
+        self.transaction(|sa| {
+            let held: Vec<u8> = sa.store.get_nonce(value).ok_or(AccumulatorError::NotAMember)?;
+            let (nonce, exponent, rest): (Vec<u8>, BigUint, Option<Vec<u8>>) = sa.last_occurrence(value, &held);
+            sa.remove_occurrence(value, rest);
+            if let Some(member_index) = sa.member_index.as_mut() {
+                member_index.remove(&exponent, &nonce);
+            }
+            let new_state: BigUint = sa.recompute_state();
+            let previous_state: BigUint = sa.store.get_state().clone();
+            sa.store.set_state(&new_state);
+            sa.log_operation(AuditAction::Delete, value, &nonce, &exponent, &previous_state);
+            Ok(new_state)
+        })
+    }
+    /// Checks the state against the members and, if they disagree, sets the state to the
+    /// one the members give, returning whether it had to. A store that writes the members
+    /// and the state separately can be left between the two by a crash; the members were
+    /// written first, so this finishes the interrupted change.
+    pub fn recover(&mut self) -> bool {
+        let recovered: Result<bool, AccumulatorError> = self.transaction(|sa| {
+            let state: BigUint = sa.recompute_state();
+            if state == *sa.store.get_state() {
+                return Ok(false);
+            }
+            sa.store.set_state(&state);
+            Ok(true)
+        });
+        recovered == Ok(true)
+    }
+    fn recompute_state(&self) -> BigUint {
+        let members: Vec<(&[u8], &[u8])> = self.store.iter_members().collect();
+        let primes: Vec<BigUint> = self.primes_of(&members);
+        let exponent: BigUint = product_tree::product(primes);
+        self.timed(|| self.power_of_product()(self.store.get_generator(), &exponent))
+    }
+    /// Adds every value in `values` with a single modular exponentiation of the state by
+    /// the product of their primes. Either all of the values are added or none are.
+    pub fn add_batch(&mut self, values: &[&[u8]]) -> Result<(), AccumulatorError> {
+        self.transaction(|sa| sa.add_batch_with_rng(values, &mut rand::thread_rng()))
+    }
+    fn add_batch_with_rng<R: Rng>(&mut self, values: &[&[u8]], rng: &mut R) -> Result<(), AccumulatorError> {
+        let mut seen: HashSet<&[u8]> = HashSet::new();
+        let values: Vec<&[u8]> = match self.duplicate_policy {
+            DuplicatePolicy::ErrorOnDuplicate => {
+                if values.iter().any(|value| self.store.contains_member(value) || !seen.insert(value)) {
+                    return Err(AccumulatorError::AlreadyAMember);
+                }
+                values.to_vec()
+            }
+            // the first of the values repeated in the batch is the one added
+            DuplicatePolicy::Idempotent => values.iter().copied().filter(|value| !self.store.contains_member(value) && seen.insert(value)).collect(),
+            DuplicatePolicy::Multiset => values.to_vec(),
+        };
+        let mut drawn: Vec<(Vec<u8>, BigUint)> = Vec::with_capacity(values.len());
+        for value in values.iter() {
+            match self.draw_nonce(value, rng) {
+                Ok(nonce_and_exponent) => drawn.push(nonce_and_exponent),
+                Err(e) => {
+                    // hand back the nonces and primes reserved so far, nothing was added
+                    let member_index: &mut MemberIndex = self.get_member_index();
+                    for (nonce, exponent) in drawn.iter() {
+                        member_index.remove(exponent, nonce);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        // multiply the primes together over a product tree and exponentiate the state once
+        let exponent: BigUint = product_tree::product(drawn.iter().map(|(_, exponent)| exponent.clone()).collect());
+        let new_state: BigUint = self.timed(|| self.power_of_product()(self.store.get_state(), &exponent));
+        // record the values, their nonces and primes in the members list, then the state
+        for (value, (nonce, exponent)) in values.iter().zip(drawn.iter()) {
+            self.add_occurrence(value, nonce, exponent);
         }
-        // start with the value of the generator
-        let mut witness: BigUint = self.store.get_generator();
-        // get the modulus
-        let modulus: BigUint = self.store.get_modulus();
-        // for all members
-        for (member, nonce) in self.store.get_members_list() {
-            // except for the value in question
-            if member != value {
-                // compute the prime it was mapped to
-                let exponent: BigUint = hash_value_to_prime(member, nonce);
-                // exponentiate the current state of the witness mod n
-                witness = witness.modpow(&exponent, &modulus);
+        let previous_state: BigUint = self.store.get_state().clone();
+        self.store.set_state(&new_state);
+        for (value, (nonce, exponent)) in values.iter().zip(drawn.iter()) {
+            self.log_operation(AuditAction::Add, value, nonce, exponent, &previous_state);
+        }
+        Ok(())
+    }
+    /// Adds every value `values` yields without collecting them first: they are taken
+    /// a chunk at a time, and each chunk is added as by [`SetAccumulator::add_batch`], with
+    /// one exponentiation, and written to the store before the next is read. Returns how
+    /// many values were added. On an error the chunk it happened in is rolled back and
+    /// the chunks before it stay, unless the call is made inside a [`transaction`].
+    ///
+    /// [`transaction`]: SetAccumulator::transaction
+    pub fn extend<I: IntoIterator<Item = Vec<u8>>>(&mut self, values: I) -> Result<usize, AccumulatorError> {
+        self.extend_in_chunks(values, EXTEND_CHUNK)
+    }
+    fn extend_in_chunks<I: IntoIterator<Item = Vec<u8>>>(&mut self, values: I, chunk_size: usize) -> Result<usize, AccumulatorError> {
+        let mut values = values.into_iter();
+        let mut added: usize = 0;
+        loop {
+            let chunk: Vec<Vec<u8>> = values.by_ref().take(chunk_size).collect();
+            if chunk.is_empty() {
+                return Ok(added);
             }
+            let chunk: Vec<&[u8]> = chunk.iter().map(|value| value.as_slice()).collect();
+            self.add_batch(&chunk)?;
+            added += chunk.len();
         }
+    }
+    pub fn get_witness(&self, value: &[u8]) -> Option<(BigUint, Vec<u8>)> {
+        // if this value is not in the member list, no way to compute a witness, return
+        if !self.store.contains_member(value) {
+            return None;
+        }
+        let held: Vec<u8> = self.store.get_nonce(value)?;
+        // the witness is for the last occurrence, the others count as other members
+        let (nonce, _, rest): (Vec<u8>, BigUint, Option<Vec<u8>>) = self.last_occurrence(value, &held);
+        // compute the primes of all members except for the value in question
+        let others: Vec<(&[u8], &[u8])> = self.store.iter_members()
+            .filter(|(member, _)| *member != value)
+            .collect();
+        let mut primes: Vec<BigUint> = self.primes_of(&others);
+        primes.extend(rest.map(|rest| self.prime_of(value, &rest)));
+        // multiply them together over a (parallel) product tree and exponentiate the generator once
+        let exponent: BigUint = product_tree::product(primes);
+        let witness: BigUint = self.timed(|| self.power_of_product()(self.store.get_generator(), &exponent));
+        self.record_witnesses(1);
         // return the completed status of witness, and the nonce used for this value
         // which the verifier will then hash to a prime (which is deterministic), and
         // check that current_state = witness ^ map_to_prime(value, nonce) mod n
-        let nonce: Vec<u8> = self.store.get_members_list().get(value).unwrap().to_vec();
-        return Some((witness.clone(), nonce));
+        Some((witness, nonce))
+    }
+    /// Like [`SetAccumulator::get_witness`], but packaged for transmission.
+    pub fn get_membership_proof(&self, value: &[u8]) -> Option<MembershipProof> {
+        let (witness, nonce): (BigUint, Vec<u8>) = self.get_witness(value)?;
+        Some(self.package_proof(value, witness, nonce))
+    }
+    fn package_proof(&self, value: &[u8], witness: BigUint, nonce: Vec<u8>) -> MembershipProof {
+        if self.prime_mapping.certified {
+            let certificate: PrimeCertificate = pocklington::certify(value, &nonce, self.prime_mapping).1;
+            return MembershipProof::new(witness, nonce).with_certificate(certificate);
+        }
+        MembershipProof::new(witness, nonce)
+    }
+    /// Proofs for every member at once, from one product tree over all of their primes
+    /// rather than a pass over the members for each, see [`ProductTree::root_factor`].
+    pub fn get_all_witnesses(&self) -> HashMap<Vec<u8>, MembershipProof> {
+        let members: Vec<(&[u8], &[u8])> = self.store.iter_members().collect();
+        let primes: Vec<BigUint> = self.primes_of(&members);
+        let power = self.power_of_product();
+        let witnesses: Vec<BigUint> = ProductTree::new(primes).root_factor_with(self.store.get_generator(), &power);
+        self.record_witnesses(witnesses.len());
+        members
+            .into_iter()
+            .zip(witnesses)
+            .map(|((value, held), witness)| {
+                let (nonce, witness): (Vec<u8>, BigUint) = match self.last_occurrence(value, held) {
+                    (nonce, _, None) => (nonce, witness),
+                    // the other occurrences of a multiset member go into its witness
+                    (nonce, _, Some(rest)) => (nonce, power(&witness, &self.prime_of(value, &rest))),
+                };
+                (value.to_vec(), self.package_proof(value, witness, nonce))
+            })
+            .collect()
+    }
+    /// Checks a witness against the current state of the store, testing the prime the
+    /// value maps to as thoroughly as the accumulator's [`PrimalityConfig`] asks.
+    pub fn verify(&self, value: &[u8], witness: &BigUint, nonce: &[u8]) -> bool {
+        let exponent: BigUint = self.proof_prime(value, nonce, || self.store.get_nonce(value));
+        self.timed(|| arith::modpow(witness, &exponent, self.store.get_modulus())) == *self.store.get_state()
+    }
+    /// Checks many proofs against the current state at once, see [`batch`].
+    pub fn verify_batch(&self, proofs: &[(&[u8], MembershipProof)]) -> bool {
+        batch::verify_batch(self.store.get_state(), self.store.get_modulus(), self.prime_mapping, proofs)
+    }
+    /// Packages the witness of `value` against `head`, which must be the head of the
+    /// current state, into a self-contained bundle signed by `signer`.
+    pub fn get_proof_bundle<S: HeadSigner>(&mut self, value: &[u8], head: &Head, signer: &S) -> Option<ProofBundle> {
+        if head.state != *self.store.get_state() {
+            return None;
+        }
+        let (witness, nonce): (BigUint, Vec<u8>) = self.get_witness(value)?;
+        Some(ProofBundle {
+            params_digest: self.get_public_parameters().digest(),
+            head: head.clone(),
+            head_signature: signer.sign(&head.digest()),
+            value: value.to_vec(),
+            nonce,
+            witness,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl SetAccumulator<MemStore> {
+    /// An accumulator with the same settings over a [`MemStore::fork`] of the store, so
+    /// witnesses can be computed from it while this one is changed. It starts with no
+    /// subscribers, and changes to either are not seen by the other.
+    pub fn fork(&self) -> Self {
+        SetAccumulator {
+            store: self.store.fork(),
+            security_level: self.security_level,
+            primality: self.primality,
+            prime_mapping: self.prime_mapping,
+            nonce_mode: self.nonce_mode,
+            duplicate_policy: self.duplicate_policy,
+            member_index: None,
+            transaction: None,
+            subscribers: Subscribers::default(),
+            metrics: self.metrics.clone(),
+            member_limit: self.member_limit,
+        }
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
+    use crate::params::NonceKey;
     use crate::store::mem_store::MemStore;
-    use std::collections::HashMap;
     use std::{thread, thread::{JoinHandle}};
+    use rand::rngs::mock::StepRng;
+    use proptest::prelude::*;
+    use std::collections::BTreeSet;
+    use std::sync::OnceLock;
+    use crate::witness::{update_witness, update_witness_on_delete};
 
     // NOTE: unnecessarily big for test cases
     // const RSA_KEY_SIZE: usize = 3072;
     // const RSA_PRIME_SIZE: usize = RSA_KEY_SIZE / 2;
 
+    const TEST_ROUNDS: usize = 5;
+
     fn get_prime(size_in_bits: usize) -> BigUint {
         let mut rng = rand::thread_rng(); // thread-local random generator seeded by system: https://docs.rs/rand/0.8.4/rand/fn.thread_rng.html
         loop {
             let candidate: BigUint = rng.gen_biguint(size_in_bits as u64);
-            if is_prime(&candidate) {
+            if is_prime(&candidate, TEST_ROUNDS) {
                 return candidate.clone();
             }
         }
@@ -191,55 +1626,1227 @@ mod tests {
 
     #[test]
     fn test_is_prime() {
-        let zero: BigUint = BigUint::from_bytes_be(&0_u64.to_be_bytes().to_vec());
-        let one: BigUint = BigUint::from_bytes_be(&1_u64.to_be_bytes().to_vec());
-        let two: BigUint = BigUint::from_bytes_be(&2_u64.to_be_bytes().to_vec());
-        let three: BigUint = BigUint::from_bytes_be(&3_u64.to_be_bytes().to_vec());
-        let twenty_nine: BigUint = BigUint::from_bytes_be(&29_u64.to_be_bytes().to_vec());
+        let zero: BigUint = BigUint::from_bytes_be(&0_u64.to_be_bytes());
+        let one: BigUint = BigUint::from_bytes_be(&1_u64.to_be_bytes());
+        let two: BigUint = BigUint::from_bytes_be(&2_u64.to_be_bytes());
+        let three: BigUint = BigUint::from_bytes_be(&3_u64.to_be_bytes());
+        let twenty_nine: BigUint = BigUint::from_bytes_be(&29_u64.to_be_bytes());
         let eighty_seven: BigUint = twenty_nine.clone() * three;
-        assert_eq!(false, is_prime(&zero));
-        assert_eq!(false, is_prime(&one));
-        assert_eq!(true, is_prime(&two));
-        assert_eq!(true, is_prime(&twenty_nine));
-        assert_eq!(false, is_prime(&eighty_seven));
+        assert!(!is_prime(&zero, TEST_ROUNDS));
+        assert!(!is_prime(&one, TEST_ROUNDS));
+        assert!(is_prime(&two, TEST_ROUNDS));
+        assert!(is_prime(&twenty_nine, TEST_ROUNDS));
+        assert!(!is_prime(&eighty_seven, TEST_ROUNDS));
 
-        let prime: BigUint = BigUint::from_bytes_be(&55340232221128654847_u128.to_be_bytes().to_vec());
-        assert_eq!(true, is_prime(&prime));
+        let prime: BigUint = BigUint::from_bytes_be(&55340232221128654847_u128.to_be_bytes());
+        assert!(is_prime(&prime, TEST_ROUNDS));
 
-        let not_prime: BigUint = BigUint::from_bytes_be(&55340232221128654848_u128.to_be_bytes().to_vec());
-        assert_eq!(false, is_prime(&not_prime));
+        let not_prime: BigUint = BigUint::from_bytes_be(&55340232221128654848_u128.to_be_bytes());
+        assert!(!is_prime(&not_prime, TEST_ROUNDS));
 
         // these can be extended and improved
     }
 
+    fn is_prime_by_trial_division(n: u64) -> bool {
+        n >= 2 && (2..n).take_while(|d| d * d <= n).all(|d| !n.is_multiple_of(d))
+    }
+
+    #[test]
+    fn test_primality_small_inputs() {
+        for n in 0_u64..2048 {
+            let candidate: BigUint = BigUint::from(n);
+            assert_eq!(is_prime_by_trial_division(n), is_prime(&candidate, TEST_ROUNDS), "is_prime({})", n);
+            // miller_rabin on its own must cope with the inputs is_prime never hands it, and
+            // with 40 rounds a composite slipping through is out of the question
+            assert_eq!(is_prime_by_trial_division(n), miller_rabin(&candidate, 40), "miller_rabin({})", n);
+        }
+    }
+
+    #[test]
+    fn test_primality_config() {
+        // 2^67 - 1 = 193707721 * 761838257287 is a strong pseudoprime to base 2 with no
+        // small factors and too wide for the exact test, so only Baillie-PSW catches it
+        let pseudoprime: BigUint = (BigUint::one() << 67) - 1_u64;
+        assert!(is_prime_with(&pseudoprime, PrimalityConfig::new(0)));
+        assert!(!is_prime_with(&pseudoprime, PrimalityConfig::new(0).with_baillie_psw()));
+        // and the setting leaves the primes values map to alone
+        let nonce: [u8; 32] = [7; 32];
+        assert_eq!(
+            hash_value_to_prime(b"value", &nonce, PrimeMapping::default(), SecurityLevel::Bits128),
+            hash_value_to_prime_with(b"value", &nonce, PrimeMapping::default(), PrimalityConfig::new(1).with_baillie_psw()),
+        );
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params))
+            .with_primality(PrimalityConfig::new(2).with_baillie_psw());
+        assert_eq!(PrimalityConfig::new(2).with_baillie_psw(), sa.primality());
+        sa.add(b"alice").unwrap();
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+        assert!(sa.verify(b"alice", &witness, &nonce));
+        assert!(verify_membership(sa.store.get_state(), &params.modulus, b"alice", &nonce, &witness));
+    }
+
+    #[test]
+    fn test_hash_value_to_prime_is_deterministic() {
+        let nonce: [u8; 32] = [7; 32];
+        let prime: BigUint = hash_value_to_prime(b"value", &nonce, PrimeMapping::default(), SecurityLevel::Bits128);
+        // the search starts at the hash and walks up to the first prime
+        let start: BigUint = BigUint::from_bytes_be(&HashFunction::Sha256.digest(&[b"value".as_ref(), &nonce].concat()));
+        assert!(prime >= start);
+        assert!(is_prime(&prime, TEST_ROUNDS));
+        // the same inputs give the same prime, whatever the number of rounds
+        assert_eq!(prime, hash_value_to_prime(b"value", &nonce, PrimeMapping::default(), SecurityLevel::Bits112));
+        assert_eq!(prime, hash_value_to_prime(b"value", &nonce, PrimeMapping::default(), SecurityLevel::Bits128));
+        assert_ne!(prime, hash_value_to_prime(b"value", &[8; 32], PrimeMapping::default(), SecurityLevel::Bits128));
+        // while another hash function gives another prime, as wide as its digest
+        let wide: BigUint = hash_value_to_prime(b"value", &nonce, PrimeMapping::new(HashFunction::Sha512, None), SecurityLevel::Bits128);
+        assert_ne!(prime, wide);
+        assert!(wide.bits() > 256);
+    }
+
     #[test]
     fn test_add_and_verify() {
         // choose distinct primes
         let primes: (BigUint, BigUint) = get_distinct_primes(512);
-        // initialize an empty list of members <value, nonce> both Vec<u8>
-        let members: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
         // compute the modulus
         let modulus: BigUint = primes.0 * primes.1;
         // choose a generator (TODO: how do we know this is a generator?)
         let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
-        // instantiate the set-accumulator with all this config
-        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(
-            MemStore::new(
-                generator.clone(),
-                members,
-                modulus.clone(),
-                generator.clone() // TODO: empty state is generator ^ 1?
-            )
-        );
+        // instantiate the set-accumulator over an empty list of members, starting from the genesis state
+        let params: PublicParameters = PublicParameters::new(modulus.clone(), generator);
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        assert!(sa.is_empty_state());
         // add a value (value can be *ANY* sequence of bytes)
         let hello_world: String = "Hello World!".to_string();
         let value: &[u8] = hello_world.as_bytes();
-        sa.add(value);
+        sa.add(value).unwrap();
         // compute the witness of this value
         let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(value).unwrap();
         // self-compute the mapped prime using the nonce (this is a publicly available, deterministic function)
-        let exponent: BigUint = hash_value_to_prime(value, &nonce);
+        let exponent: BigUint = hash_value_to_prime(value, &nonce, sa.prime_mapping(), sa.security_level());
         // verify inclusion of this value, using the witness and the mapped prime
-        assert_eq!(sa.store.get_state(), witness.modpow(&exponent, &modulus));
+        assert_eq!(*sa.store.get_state(), witness.modpow(&exponent, &modulus));
+        assert!(!sa.is_empty_state());
+    }
+
+    #[test]
+    fn test_verify() {
+        let primes: (BigUint, BigUint) = get_distinct_primes(256);
+        let modulus: BigUint = primes.0 * primes.1;
+        let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(
+            MemStore::from_parameters(&PublicParameters::new(modulus.clone(), generator))
+        );
+        sa.add(b"alice").unwrap();
+        sa.add(b"bob").unwrap();
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+        assert!(sa.verify(b"alice", &witness, &nonce));
+        // a verifier holding only the state and modulus reaches the same verdict
+        let state: BigUint = sa.store.get_state().clone();
+        assert!(verify_membership(&state, &modulus, b"alice", &nonce, &witness));
+        assert!(!verify_membership(&state, &modulus, b"mallory", &nonce, &witness));
+        assert!(!verify_membership(&state, &modulus, b"alice", &[0; 32], &witness));
+        let proof: MembershipProof = sa.get_membership_proof(b"bob").unwrap();
+        assert!(proof.verify(&state, &modulus, b"bob", sa.prime_mapping()));
+        assert!(!proof.verify(&state, &modulus, b"alice", sa.prime_mapping()));
+        // and the witness goes stale once the state moves on
+        sa.add(b"carol").unwrap();
+        assert!(!sa.verify(b"alice", &witness, &nonce));
+        assert!(!proof.verify(sa.store.get_state(), &modulus, b"bob", sa.prime_mapping()));
+    }
+
+    #[test]
+    fn test_witness_for_many_members() {
+        let primes: (BigUint, BigUint) = get_distinct_primes(256);
+        let modulus: BigUint = primes.0 * primes.1;
+        let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(
+            MemStore::from_parameters(&PublicParameters::new(modulus.clone(), generator))
+        );
+        let values: Vec<Vec<u8>> = (0_u8..4).map(|i| vec![i; 4]).collect();
+        for value in values.iter() {
+            sa.add(value).unwrap();
+        }
+        // every member's witness must verify against the final state
+        for value in values.iter() {
+            let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(value).unwrap();
+            let exponent: BigUint = hash_value_to_prime(value, &nonce, sa.prime_mapping(), sa.security_level());
+            assert_eq!(*sa.store.get_state(), witness.modpow(&exponent, &modulus));
+        }
+        // and a value that was never added has no witness
+        assert_eq!(None, sa.get_witness(b"not a member"));
+    }
+
+    #[test]
+    fn test_security_level_validation() {
+        // a 1024 bit modulus is too small for either preset
+        let primes: (BigUint, BigUint) = get_distinct_primes(512);
+        let modulus: BigUint = primes.0 * primes.1;
+        let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
+        let store: MemStore = MemStore::from_parameters(&PublicParameters::new(modulus.clone(), generator.clone()));
+        match SetAccumulator::with_security_level(store, SecurityLevel::Bits112) {
+            Err(e) => assert_eq!(AccumulatorError::ModulusTooSmall { bits: modulus.bits(), required: 2048 }, e),
+            Ok(_) => panic!("accepted a 1024 bit modulus at 112 bit security"),
+        }
+        // only the size is validated, so any 3072 bit odd number is accepted
+        let modulus: BigUint = (BigUint::from(1_u64) << 3072) - BigUint::from(1_u64);
+        let store: MemStore = MemStore::from_parameters(&PublicParameters::new(modulus, generator.clone()));
+        let sa: SetAccumulator<MemStore> = SetAccumulator::with_security_level(store, SecurityLevel::Bits128).unwrap();
+        assert_eq!(SecurityLevel::Bits128, sa.security_level());
+        // but not with primes too narrow for the level, however large the modulus
+        let params: PublicParameters = PublicParameters::new(sa.store.get_modulus().clone(), generator).with_prime_bits(128);
+        match SetAccumulator::with_security_level(MemStore::from_parameters(&params), SecurityLevel::Bits128) {
+            Err(e) => assert_eq!(AccumulatorError::PrimesTooNarrow { bits: 128, required: 256 }, e),
+            Ok(_) => panic!("accepted 128 bit primes at 128 bit security"),
+        }
+        // which those the level sets up never are
+        let params: PublicParameters = params.with_prime_mapping(SecurityLevel::Bits112.prime_mapping());
+        let sa: SetAccumulator<MemStore> = SetAccumulator::with_security_level(MemStore::from_parameters(&params), SecurityLevel::Bits112).unwrap();
+        assert_eq!(224, sa.prime_mapping().width());
+    }
+
+    #[test]
+    fn test_with_rsa2048() {
+        let params: PublicParameters = PublicParameters::rsa2048();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::with_rsa2048(MemStore::from_parameters(&params)).unwrap();
+        assert_eq!(SecurityLevel::Bits112, sa.security_level());
+        sa.add(b"alice").unwrap();
+        let proof: MembershipProof = sa.get_membership_proof(b"alice").unwrap();
+        assert!(proof.verify(sa.store.get_state(), &params.modulus, b"alice", sa.prime_mapping()));
+        // any other modulus is turned away, whatever its size
+        let other: PublicParameters = PublicParameters::new(&params.modulus + 2_u64, params.generator.clone());
+        assert!(matches!(SetAccumulator::with_rsa2048(MemStore::from_parameters(&other)), Err(AccumulatorError::UnexpectedModulus)));
+    }
+
+    #[test]
+    fn test_deterministic_nonce_mode() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut first: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params))
+            .with_nonce_mode(NonceMode::Deterministic);
+        let mut second: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params))
+            .with_nonce_mode(NonceMode::Deterministic);
+        // two writers adding the same values, in any order, agree on the state
+        first.add(b"alice").unwrap();
+        first.add(b"bob").unwrap();
+        let values: Vec<&[u8]> = vec![b"bob", b"alice"];
+        second.add_batch(&values).unwrap();
+        assert_eq!(first.store.get_state(), second.store.get_state());
+        // the nonce is empty, so the prime is a public function of the value
+        let proof: MembershipProof = first.get_membership_proof(b"alice").unwrap();
+        assert!(proof.nonce.is_empty());
+        assert!(verify_membership(first.store.get_state(), &params.modulus, b"alice", &[], &proof.witness));
+        // and adding a value twice is refused rather than exponentiating the state again,
+        // even as a multiset, since it would be under the same prime
+        let state: BigUint = first.store.get_state().clone();
+        assert_eq!(Err(AccumulatorError::AlreadyAMember), first.add(b"alice"));
+        let mut first: SetAccumulator<MemStore> = first.with_duplicate_policy(DuplicatePolicy::Multiset);
+        assert_eq!(Err(AccumulatorError::DuplicatePrime), first.add(b"alice"));
+        assert_eq!(state, *first.store.get_state());
+    }
+
+    #[test]
+    fn test_keyed_nonce_mode() {
+        let params: &PublicParameters = get_test_parameters();
+        let key: NonceKey = NonceKey::new([7; 32]);
+        let mut first: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params))
+            .with_nonce_mode(NonceMode::Keyed(key));
+        let mut second: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params))
+            .with_nonce_mode(NonceMode::Keyed(key));
+        first.add(b"alice").unwrap();
+        first.add(b"bob").unwrap();
+        second.add_batch(&[b"bob", b"alice"]).unwrap();
+        assert_eq!(first.store.get_state(), second.store.get_state());
+        assert_eq!(Some(key.nonce(b"alice")), first.store.get_nonce(b"alice"));
+        assert_eq!(Err(AccumulatorError::AlreadyAMember), first.add(b"alice"));
+        // another key gives other primes
+        let mut other: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params))
+            .with_nonce_mode(NonceMode::Keyed(NonceKey::new([8; 32])));
+        other.add_batch(&[b"alice", b"bob"]).unwrap();
+        assert_ne!(first.store.get_state(), other.store.get_state());
+        let (witness, nonce): (BigUint, Vec<u8>) = first.get_witness(b"alice").unwrap();
+        assert!(verify_membership(first.store.get_state(), &params.modulus, b"alice", &nonce, &witness));
+    }
+
+    #[test]
+    fn test_duplicate_policy() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        assert_eq!(DuplicatePolicy::ErrorOnDuplicate, sa.duplicate_policy());
+        sa.add(b"alice").unwrap();
+        let state: BigUint = sa.store.get_state().clone();
+        assert_eq!(Err(AccumulatorError::AlreadyAMember), sa.add(b"alice"));
+        assert_eq!(Err(AccumulatorError::AlreadyAMember), sa.add_batch(&[b"bob", b"bob"]));
+        assert_eq!(state, *sa.store.get_state());
+
+        // retrying an add changes nothing
+        let mut sa: SetAccumulator<MemStore> = sa.with_duplicate_policy(DuplicatePolicy::Idempotent);
+        sa.add(b"alice").unwrap();
+        assert_eq!(state, *sa.store.get_state());
+        sa.add_batch(&[b"bob", b"alice", b"bob"]).unwrap();
+        assert_eq!((2, 1), (sa.len(), sa.multiplicity(b"bob")));
+
+        // a multiset member stays one until it is deleted as many times as it was added
+        let mut sa: SetAccumulator<MemStore> = sa.with_duplicate_policy(DuplicatePolicy::Multiset);
+        sa.add(b"alice").unwrap();
+        sa.add_batch(&[b"alice", b"bob"]).unwrap();
+        assert_eq!((3, 2, 2), (sa.multiplicity(b"alice"), sa.multiplicity(b"bob"), sa.len()));
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+        assert!(verify_membership(sa.store.get_state(), &params.modulus, b"alice", &nonce, &witness));
+        let proof: MembershipProof = sa.get_all_witnesses().remove(b"alice".as_ref()).unwrap();
+        assert_eq!((witness.clone(), nonce.clone()), (proof.witness, proof.nonce));
+        sa.delete_without_trapdoor(b"alice").unwrap();
+        sa.delete_without_trapdoor(b"alice").unwrap();
+        assert_eq!(1, sa.multiplicity(b"alice"));
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+        assert!(verify_membership(sa.store.get_state(), &params.modulus, b"alice", &nonce, &witness));
+        assert_eq!(sa.store.get_state(), &sa.recompute_state());
+        sa.delete_without_trapdoor(b"alice").unwrap();
+        assert!(!sa.contains(b"alice"));
+    }
+
+    #[test]
+    fn test_free_verifiers_on_multiset_members() {
+        use crate::aggregate::{aggregate, verify_aggregate, AggregateProof};
+        use crate::witness::WitnessUpdater;
+
+        let params: &PublicParameters = get_test_parameters();
+        let mapping: PrimeMapping = params.prime_mapping();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params))
+            .with_duplicate_policy(DuplicatePolicy::Multiset);
+        sa.add(b"alice").unwrap();
+        let genesis_witness: BigUint = sa.store.get_generator().clone();
+        for _ in 0..3 {
+            sa.add(b"bob").unwrap();
+        }
+        let state: BigUint = sa.store.get_state().clone();
+        // the witness handed out is for the last occurrence, and checks out on its own
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"bob").unwrap();
+        assert!(verify_membership(&state, &params.modulus, b"bob", &nonce, &witness));
+        // the nonce bob is held with proves all three occurrences at once, against a
+        // witness over the other members alone
+        let held: Vec<u8> = sa.store.get_nonce(b"bob").unwrap();
+        let (alice_witness, alice_nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+        let others: BigUint = sa.store.get_generator().modpow(&sa.prime_of(b"alice", &alice_nonce), &params.modulus);
+        assert_eq!(sa.prime_of(b"bob", &held), member_prime(b"bob", &held, mapping, sa.primality));
+        assert!(verify_membership(&state, &params.modulus, b"bob", &held, &others));
+        assert!(sa.verify(b"bob", &others, &held));
+        assert!(!verify_membership(&state, &params.modulus, b"bob", &nonce, &others));
+
+        // and so do the batch and aggregate verifiers
+        let proofs: Vec<(&[u8], MembershipProof)> = vec![
+            (b"alice".as_ref(), MembershipProof::new(alice_witness.clone(), alice_nonce.clone())),
+            (b"bob".as_ref(), MembershipProof::new(witness, nonce)),
+        ];
+        assert!(batch::verify_batch(&state, &params.modulus, mapping, &proofs));
+        let whole: MembershipProof = MembershipProof::new(others.clone(), held.clone());
+        let aggregated: AggregateProof = aggregate(&params.modulus, &[b"alice", b"bob"], &[proofs[0].1.clone(), whole.clone()], mapping).unwrap();
+        assert!(verify_aggregate(&state, &params.modulus, &[b"alice", b"bob"], &aggregated, mapping));
+
+        // a witness holder folds every occurrence in from the nonce they are held with
+        assert_eq!(alice_witness, update_witness(&params.modulus, &genesis_witness, b"bob", &held, mapping));
+        let epoch: u64 = sa.current_epoch();
+        sa.add(b"carol").unwrap();
+        let log: Vec<AuditRecord> = sa.store.iter_log().map(Result::unwrap).collect();
+        let fresh: MembershipProof = WitnessUpdater::new(params).refresh(b"bob", &whole, epoch, log).unwrap();
+        assert!(fresh.verify(sa.store.get_state(), &params.modulus, b"bob", mapping));
+
+        // an add that finds bob held hands back the prime its witness is for
+        let mut sa: SetAccumulator<MemStore> = sa.with_duplicate_policy(DuplicatePolicy::Idempotent);
+        let outcome: AddOutcome = sa.add(b"bob").unwrap();
+        assert_eq!(*sa.store.get_state(), outcome.witness.modpow(&outcome.prime, &params.modulus));
+        assert!(verify_membership(sa.store.get_state(), &params.modulus, b"bob", &outcome.nonce, &outcome.witness));
+    }
+
+    #[test]
+    fn test_add_outcome() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        sa.add(b"alice").unwrap();
+        let before: BigUint = sa.store.get_state().clone();
+        let outcome: AddOutcome = sa.add(b"bob").unwrap();
+        // the witness is the state before, and the same the other members give
+        assert_eq!(before, outcome.witness);
+        assert_eq!(Some((outcome.witness.clone(), outcome.nonce.clone())), sa.get_witness(b"bob"));
+        assert_eq!(hash_value_to_prime(b"bob", &outcome.nonce, sa.prime_mapping(), sa.security_level()), outcome.prime);
+        assert!(verify_membership(sa.store.get_state(), &params.modulus, b"bob", &outcome.nonce, &outcome.witness));
+        // a value added again under the idempotent policy hands back what it holds
+        let mut sa: SetAccumulator<MemStore> = sa.with_duplicate_policy(DuplicatePolicy::Idempotent);
+        assert_eq!(outcome, sa.add(b"bob").unwrap());
+    }
+
+    #[test]
+    fn test_add_with_nonce_and_prime() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        sa.add(b"alice").unwrap();
+        sa.add(b"bob").unwrap();
+        // adding the members again with their nonces gives the same state
+        let mut replica: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        let members: Vec<(Vec<u8>, Vec<u8>)> = sa.store.iter_members().map(|(value, nonce)| (value.to_vec(), nonce.to_vec())).collect();
+        for (value, nonce) in members.iter() {
+            replica.add_with_nonce(value, nonce).unwrap();
+        }
+        assert_eq!(sa.store.get_state(), replica.store.get_state());
+        let nonce: Vec<u8> = sa.store.get_nonce(b"alice").unwrap();
+        // the same nonce again is the same prime, which even a multiset cannot hold twice
+        let mut replica: SetAccumulator<MemStore> = replica.with_duplicate_policy(DuplicatePolicy::Multiset);
+        assert_eq!(Err(AccumulatorError::DuplicatePrime), replica.add_with_nonce(b"alice", &nonce));
+        assert_eq!(Err(AccumulatorError::InvalidNonce), replica.add_with_nonce(b"carol", OCCURRENCES_TAG));
+        // any other nonce is the caller's to pick
+        replica.add_with_nonce(b"carol", b"tangerine/prime/v1\x07").unwrap();
+        replica.delete_without_trapdoor(b"carol").unwrap();
+
+        let prime: BigUint = hash_value_to_prime(b"external", &[1; 32], sa.prime_mapping(), sa.security_level());
+        assert_eq!(Err(AccumulatorError::InvalidPrime), sa.add_prime(b"carol", &(&prime + 1_u64)));
+        sa.add_prime(b"carol", &prime).unwrap();
+        assert_eq!(Err(AccumulatorError::DuplicatePrime), sa.add_prime(b"dave", &prime));
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"carol").unwrap();
+        assert!(verify_prime_membership(sa.store.get_state(), &params.modulus, &prime, &witness));
+        assert!(sa.verify(b"carol", &witness, &nonce));
+        // the prime is kept by the store, the nonce is just the empty one and maps to
+        // another prime as it does for anyone hashing it
+        assert!(nonce.is_empty());
+        assert_eq!(Some(&prime), sa.store.get_prime(b"carol", &nonce));
+        assert!(!verify_membership(sa.store.get_state(), &params.modulus, b"carol", &nonce, &witness));
+        // and the other members' witnesses still check out with the free verifier
+        for member in [b"alice".as_ref(), b"bob".as_ref()] {
+            let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(member).unwrap();
+            assert!(verify_membership(sa.store.get_state(), &params.modulus, member, &nonce, &witness));
+        }
+        sa.delete_without_trapdoor(b"carol").unwrap();
+        assert_eq!(sa.store.get_state(), replica.store.get_state());
+    }
+
+    // a store that keeps no primes, as custom stores relying on the defaults do
+    struct ForgetfulStore(MemStore);
+
+    impl Storer for ForgetfulStore {
+        fn get_generator(&self) -> &BigUint {
+            self.0.get_generator()
+        }
+        fn get_modulus(&self) -> &BigUint {
+            self.0.get_modulus()
+        }
+        fn get_state(&self) -> &BigUint {
+            self.0.get_state()
+        }
+        fn set_state(&mut self, new_state: &BigUint) {
+            self.0.set_state(new_state)
+        }
+        fn get_trapdoor(&self) -> Option<Trapdoor> {
+            None
+        }
+        fn get_nonce(&self, value: &[u8]) -> Option<Vec<u8>> {
+            self.0.get_nonce(value)
+        }
+        fn insert_member(&mut self, value: &[u8], nonce: &[u8]) {
+            self.0.insert_member(value, nonce)
+        }
+        fn remove_member(&mut self, value: &[u8]) -> Option<Vec<u8>> {
+            self.0.remove_member(value)
+        }
+        fn iter_members(&self) -> impl Iterator<Item = (&[u8], &[u8])> + '_ {
+            self.0.iter_members()
+        }
+    }
+
+    #[test]
+    fn test_add_prime_round_trips() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        sa.add(b"alice").unwrap();
+        let prime: BigUint = hash_value_to_prime(b"external", &[1; 32], sa.prime_mapping(), sa.security_level());
+        let replica_epoch: u64 = sa.current_epoch();
+        let mut replica: SetAccumulator<MemStore> = SetAccumulator::import_snapshot(&sa.export_snapshot(), MemStore::from_parameters(params)).unwrap();
+        sa.add_prime(b"carol", &prime).unwrap();
+        sa.add(b"bob").unwrap();
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"carol").unwrap();
+
+        // the prime goes along with a snapshot
+        let restored: SetAccumulator<MemStore> = SetAccumulator::import_snapshot(&sa.export_snapshot(), MemStore::from_parameters(params)).unwrap();
+        assert_eq!(Some(&prime), restored.store.get_prime(b"carol", &nonce));
+        assert!(restored.verify(b"carol", &witness, &nonce));
+        assert_eq!(Some((witness.clone(), nonce.clone())), restored.get_witness(b"carol"));
+
+        // and with the epochs a replica catches up with
+        replica.apply_delta(&sa.export_delta(replica_epoch).unwrap()).unwrap();
+        assert_eq!(sa.store.get_state(), replica.store.get_state());
+        assert!(replica.verify(b"carol", &witness, &nonce));
+        let (witness, nonce): (BigUint, Vec<u8>) = replica.get_witness(b"bob").unwrap();
+        assert!(verify_membership(replica.store.get_state(), &params.modulus, b"bob", &nonce, &witness));
+
+        // and the history, for proofs at past epochs and rolling back to them
+        let epoch: u64 = sa.current_epoch() - 1;
+        let proof: MembershipProof = sa.prove_membership_at(b"carol", epoch).unwrap();
+        assert!(verify_prime_membership(&sa.state_at(epoch).unwrap(), &params.modulus, &prime, &proof.witness));
+        sa.delete_without_trapdoor(b"carol").unwrap();
+        assert!(sa.verify_at(epoch, b"carol", &proof));
+        let state: BigUint = sa.state_at(epoch).unwrap();
+        sa.rollback_to(epoch, "carol was deleted by mistake", &TestSigner(b"manager key".to_vec())).unwrap();
+        assert_eq!(state, *sa.store.get_state());
+        assert_eq!(Some(&prime), sa.store.get_prime(b"carol", &[]));
+
+        // a store that keeps no primes cannot hold the member
+        let mut forgetful: SetAccumulator<ForgetfulStore> = SetAccumulator::new(ForgetfulStore(MemStore::from_parameters(params)));
+        let state: BigUint = forgetful.store.get_state().clone();
+        assert_eq!(Err(AccumulatorError::PrimesNotKept), forgetful.add_prime(b"carol", &prime).map(|_| ()));
+        assert_eq!(state, *forgetful.store.get_state());
+        assert!(!forgetful.contains(b"carol"));
+    }
+
+    #[test]
+    fn test_verify_later_occurrences() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params))
+            .with_duplicate_policy(DuplicatePolicy::Multiset);
+        sa.add(b"alice").unwrap();
+        sa.add(b"alice").unwrap();
+        let prime: BigUint = hash_value_to_prime(b"external", &[1; 32], sa.prime_mapping(), sa.security_level());
+        sa.add_prime(b"carol", &prime).unwrap();
+        // a member holds one prime of the caller's at most, and no other occurrences with it
+        let other: BigUint = hash_value_to_prime(b"external", &[2; 32], sa.prime_mapping(), sa.security_level());
+        assert_eq!(Err(AccumulatorError::AlreadyAMember), sa.add_prime(b"alice", &other).map(|_| ()));
+        assert_eq!(Err(AccumulatorError::AlreadyAMember), sa.add(b"carol").map(|_| ()));
+        let epoch: u64 = sa.current_epoch();
+        // the witness is for the last occurrence, the picked prime is checked as the store keeps it
+        let proof: MembershipProof = sa.get_membership_proof(b"alice").unwrap();
+        assert!(sa.verify(b"alice", &proof.witness, &proof.nonce));
+        let picked: MembershipProof = sa.get_membership_proof(b"carol").unwrap();
+        assert!(sa.verify(b"carol", &picked.witness, &picked.nonce));
+        sa.add(b"bob").unwrap();
+        assert!(sa.verify_at(epoch, b"alice", &proof));
+        assert!(sa.verify_at(epoch, b"carol", &picked));
+        assert_eq!(Some(picked.clone()), sa.prove_membership_at(b"carol", epoch));
+        assert!(sa.prove_membership_at(b"carol", epoch - 1).is_none());
+        let current: MembershipProof = sa.get_membership_proof(b"carol").unwrap();
+        assert!(sa.verify(b"carol", &current.witness, &current.nonce));
+        // a picked prime proves nothing about a value that was not added with it
+        assert!(!sa.verify(b"bob", &current.witness, &current.nonce));
+        assert!(!sa.verify_at(epoch, b"bob", &picked));
+    }
+
+    #[test]
+    fn test_degenerate_rng_is_detected() {
+        let primes: (BigUint, BigUint) = get_distinct_primes(256);
+        let modulus: BigUint = primes.0 * primes.1;
+        let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(
+            MemStore::from_parameters(&PublicParameters::new(modulus, generator))
+        );
+        // a generator stuck on one output yields a usable nonce exactly once
+        let mut rng: StepRng = StepRng::new(7, 0);
+        sa.add_with_rng(b"first", &mut rng).unwrap();
+        let state: BigUint = sa.store.get_state().clone();
+        assert_eq!(Err(AccumulatorError::DegenerateRng), sa.add_with_rng(b"second", &mut rng));
+        // and the failed add leaves the accumulator untouched
+        assert_eq!(state, *sa.store.get_state());
+        assert!(!sa.store.contains_member(b"second"));
+    }
+
+    #[test]
+    fn test_delete_with_trapdoor() {
+        let primes: (BigUint, BigUint) = get_distinct_primes(256);
+        let trapdoor: Trapdoor = Trapdoor::new(primes.0, primes.1);
+        let modulus: BigUint = trapdoor.modulus();
+        let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
+        let params: PublicParameters = PublicParameters::new(modulus.clone(), generator);
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(
+            MemStore::from_parameters(&params).with_trapdoor(trapdoor)
+        );
+        let values: Vec<Vec<u8>> = (0_u8..3).map(|i| vec![i; 4]).collect();
+        for value in values.iter() {
+            sa.add(value).unwrap();
+        }
+        sa.delete(&values[1]).unwrap();
+        // the shortcut lands on the same state as recomputing from the remaining members
+        assert!(!sa.store.contains_member(&values[1]));
+        assert_eq!(sa.recompute_state(), *sa.store.get_state());
+        for value in [&values[0], &values[2]].iter() {
+            let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(value).unwrap();
+            let exponent: BigUint = hash_value_to_prime(value, &nonce, sa.prime_mapping(), sa.security_level());
+            assert_eq!(*sa.store.get_state(), witness.modpow(&exponent, &modulus));
+        }
+        assert_eq!(Err(AccumulatorError::NotAMember), sa.delete(&values[1]));
+        // deleting everything brings the accumulator back to genesis
+        sa.delete(&values[0]).unwrap();
+        sa.delete(&values[2]).unwrap();
+        assert!(sa.is_empty_state());
+
+        // without the factorization nothing is removed
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        sa.add(b"alice").unwrap();
+        assert_eq!(Err(AccumulatorError::MissingTrapdoor), sa.delete(b"alice"));
+        assert!(sa.store.contains_member(b"alice"));
+    }
+
+    #[test]
+    fn test_delete_without_trapdoor() {
+        let primes: (BigUint, BigUint) = get_distinct_primes(256);
+        let trapdoor: Trapdoor = Trapdoor::new(primes.0, primes.1);
+        let modulus: BigUint = trapdoor.modulus();
+        let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
+        let params: PublicParameters = PublicParameters::new(modulus.clone(), generator);
+        // two accumulators over the same members, only one of which holds the factorization
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        let mut manager: SetAccumulator<MemStore> = SetAccumulator::new(
+            MemStore::from_parameters(&params).with_trapdoor(trapdoor)
+        );
+        for value in [b"alice".as_ref(), b"bob".as_ref(), b"carol".as_ref()].iter() {
+            sa.add(value).unwrap();
+            let nonce: Vec<u8> = sa.store.get_nonce(value).unwrap();
+            manager.store.insert_member(value, &nonce);
+        }
+        manager.store.set_state(sa.store.get_state());
+
+        // both ways of deleting agree on the state, and the returned state is the stored one
+        let state: BigUint = sa.delete_without_trapdoor(b"bob").unwrap();
+        manager.delete(b"bob").unwrap();
+        assert_eq!(state, *sa.store.get_state());
+        assert_eq!(*manager.store.get_state(), state);
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+        assert_eq!(state, witness.modpow(&hash_value_to_prime(b"alice", &nonce, sa.prime_mapping(), sa.security_level()), &modulus));
+
+        assert_eq!(Err(AccumulatorError::NotAMember), sa.delete_without_trapdoor(b"bob"));
+        sa.delete_without_trapdoor(b"alice").unwrap();
+        assert_eq!(params.genesis_state(), sa.delete_without_trapdoor(b"carol").unwrap());
+    }
+
+    #[test]
+    fn test_exponents_reduced_with_trapdoor() {
+        let primes: (BigUint, BigUint) = get_distinct_primes(256);
+        // a base sharing a factor with the modulus, which the reduction has to get right too
+        let shared: BigUint = &primes.0 * 2_u64;
+        let trapdoor: Trapdoor = Trapdoor::new(primes.0, primes.1);
+        let modulus: BigUint = trapdoor.modulus();
+        let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
+        let params: PublicParameters = PublicParameters::new(modulus.clone(), generator);
+        let mut manager: SetAccumulator<MemStore> = SetAccumulator::new(
+            MemStore::from_parameters(&params).with_trapdoor(trapdoor)
+        );
+        // eight 256 bit primes make an exponent four times as wide as the modulus
+        let values: Vec<Vec<u8>> = (0_u8..8).map(|i| vec![i; 4]).collect();
+        manager.add_batch(&values.iter().map(|value| value.as_slice()).collect::<Vec<&[u8]>>()).unwrap();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        for value in values.iter() {
+            sa.store.insert_member(value, &manager.store.get_nonce(value).unwrap());
+        }
+        // the reduced exponents land where the full ones do
+        let state: BigUint = sa.recompute_state();
+        assert_eq!(state, *manager.store.get_state());
+        assert_eq!(state, manager.recompute_state());
+        assert_eq!(sa.get_witness(&values[3]), manager.get_witness(&values[3]));
+        assert_eq!(sa.get_all_witnesses(), manager.get_all_witnesses());
+        let exponent: BigUint = BigUint::from(3_u64).pow(400);
+        assert_eq!(shared.modpow(&exponent, &modulus), manager.power_of_product()(&shared, &exponent));
+    }
+
+    #[test]
+    fn test_add_batch() {
+        let primes: (BigUint, BigUint) = get_distinct_primes(256);
+        let modulus: BigUint = primes.0 * primes.1;
+        let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(
+            MemStore::from_parameters(&PublicParameters::new(modulus.clone(), generator))
+        );
+        let values: Vec<&[u8]> = vec![b"alice", b"bob", b"carol"];
+        sa.add_batch(&values).unwrap();
+        // the batch leaves the same state as adding one at a time would
+        assert_eq!(sa.recompute_state(), *sa.store.get_state());
+        for value in values.iter() {
+            let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(value).unwrap();
+            let exponent: BigUint = hash_value_to_prime(value, &nonce, sa.prime_mapping(), sa.security_level());
+            assert_eq!(*sa.store.get_state(), witness.modpow(&exponent, &modulus));
+        }
+
+        // a batch that runs out of nonces halfway through adds nothing
+        let state: BigUint = sa.store.get_state().clone();
+        let mut rng: StepRng = StepRng::new(7, 0);
+        assert_eq!(Err(AccumulatorError::DegenerateRng), sa.add_batch_with_rng(&[b"dave", b"erin"], &mut rng));
+        assert_eq!(state, *sa.store.get_state());
+        assert_eq!(3, sa.store.member_count());
+        // and the nonce it reserved for the first value is free again
+        sa.add_with_rng(b"dave", &mut StepRng::new(7, 0)).unwrap();
+    }
+
+    // stands in for a real signature scheme: the "signature" is a keyed hash of the digest
+    struct TestSigner(Vec<u8>);
+
+    impl bundle::HeadSigner for TestSigner {
+        fn sign(&self, digest: &[u8; 32]) -> Vec<u8> {
+            HashFunction::Sha256.digest(&[self.0.as_slice(), digest].concat())
+        }
+    }
+
+    impl bundle::HeadVerifier for TestSigner {
+        fn verify(&self, digest: &[u8; 32], signature: &[u8]) -> bool {
+            self.sign(digest) == signature
+        }
+    }
+
+    #[test]
+    fn test_proof_bundle() {
+        let primes: (BigUint, BigUint) = get_distinct_primes(256);
+        let modulus: BigUint = primes.0 * primes.1;
+        let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
+        let params: PublicParameters = PublicParameters::new(modulus, generator);
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        let signer: TestSigner = TestSigner(b"manager key".to_vec());
+        let mut operations: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        for value in [b"alice".as_ref(), b"bob".as_ref()].iter() {
+            sa.add(value).unwrap();
+            operations.push((value.to_vec(), sa.store.get_nonce(value).unwrap()));
+        }
+        let head: Head = Head::genesis(&params).next(sa.store.get_state().clone(), &operations);
+
+        // a bundle survives the round trip through bytes and verifies on its own
+        let bundle: ProofBundle = sa.get_proof_bundle(b"alice", &head, &signer).unwrap();
+        let bundle: ProofBundle = ProofBundle::from_bytes(&bundle.to_bytes()).unwrap();
+        assert!(bundle::verify_bundle(&bundle, &params, &signer));
+
+        // but not with a different key, different parameters or a different value
+        assert!(!bundle::verify_bundle(&bundle, &params, &TestSigner(b"other key".to_vec())));
+        let other: PublicParameters = PublicParameters::new(params.modulus.clone(), params.generator.clone() + 1_u64);
+        assert!(!bundle::verify_bundle(&bundle, &other, &signer));
+        let mut forged: ProofBundle = bundle.clone();
+        forged.value = b"mallory".to_vec();
+        assert!(!bundle::verify_bundle(&forged, &params, &signer));
+
+        // and no bundle is made for non-members or stale heads
+        assert!(sa.get_proof_bundle(b"mallory", &head, &signer).is_none());
+        assert!(sa.get_proof_bundle(b"alice", &Head::genesis(&params), &signer).is_none());
+    }
+
+    #[test]
+    fn test_hash_function_is_recorded() {
+        let base: &PublicParameters = get_test_parameters();
+        let params: PublicParameters = base.clone().with_hash_function(HashFunction::Blake3);
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        assert_eq!(HashFunction::Blake3, sa.hash_function());
+        assert_eq!(params, sa.get_public_parameters());
+        sa.add(b"alice").unwrap();
+        let proof: MembershipProof = sa.get_membership_proof(b"alice").unwrap();
+        assert!(sa.verify(b"alice", &proof.witness, &proof.nonce));
+        let state: BigUint = sa.store.get_state().clone();
+        assert!(verify_membership_with_hash(HashFunction::Blake3, &state, &params.modulus, b"alice", &proof.nonce, &proof.witness));
+        // checking with the default hash function maps alice to another prime
+        assert!(!verify_membership(&state, &params.modulus, b"alice", &proof.nonce, &proof.witness));
+
+        let signer: TestSigner = TestSigner(b"manager key".to_vec());
+        let head: Head = Head::genesis(&params).next(state, &[]);
+        let bundle: ProofBundle = sa.get_proof_bundle(b"alice", &head, &signer).unwrap();
+        assert!(bundle::verify_bundle(&bundle, &params, &signer));
+        assert!(!bundle::verify_bundle(&bundle, base, &signer));
+    }
+
+    #[test]
+    fn test_prime_bits() {
+        for bits in [128_u64, 384].iter() {
+            let params: PublicParameters = get_test_parameters().clone().with_prime_bits(*bits);
+            let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+            assert_eq!(params.prime_mapping(), sa.prime_mapping());
+            sa.add(b"alice").unwrap();
+            let proof: MembershipProof = sa.get_membership_proof(b"alice").unwrap();
+            let prime: BigUint = hash_value_to_prime(b"alice", &proof.nonce, sa.prime_mapping(), sa.security_level());
+            assert_eq!(*bits, prime.bits());
+            let state: BigUint = sa.store.get_state().clone();
+            assert!(verify_membership_with(params.prime_mapping(), &state, &params.modulus, b"alice", &proof.nonce, &proof.witness));
+            assert!(!verify_membership(&state, &params.modulus, b"alice", &proof.nonce, &proof.witness));
+        }
+    }
+
+    #[test]
+    fn test_certified_primes() {
+        let params: PublicParameters = get_test_parameters().clone().with_prime_mapping(PrimeMapping::default().with_certificates());
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        sa.add(b"alice").unwrap();
+        sa.add(b"bob").unwrap();
+        let state: BigUint = sa.store.get_state().clone();
+        let proof: MembershipProof = sa.get_membership_proof(b"alice").unwrap();
+        assert!(proof.certificate.is_some());
+        assert!(proof.verify_certified(&state, &params.modulus, b"alice", params.prime_mapping()));
+        assert!(verify_membership_with(params.prime_mapping(), &state, &params.modulus, b"alice", &proof.nonce, &proof.witness));
+        // the certificate is bound to the member it was issued for
+        assert!(!proof.verify_certified(&state, &params.modulus, b"bob", params.prime_mapping()));
+        assert!(!MembershipProof::new(proof.witness.clone(), proof.nonce.clone()).verify_certified(&state, &params.modulus, b"alice", params.prime_mapping()));
+    }
+
+    #[test]
+    fn test_snapshot_export_import() {
+        let params: PublicParameters = get_test_parameters().clone().with_prime_bits(128);
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        sa.add(b"alice").unwrap();
+        sa.add(b"bob").unwrap();
+        let bytes: Vec<u8> = sa.export_snapshot();
+        let restored: SetAccumulator<MemStore> = SetAccumulator::import_snapshot(&bytes, MemStore::from_parameters(&params)).unwrap();
+        assert_eq!(sa.store.get_state(), restored.store.get_state());
+        assert_eq!(sa.prime_mapping(), restored.prime_mapping());
+        let (witness, nonce): (BigUint, Vec<u8>) = restored.get_witness(b"alice").unwrap();
+        assert!(restored.verify(b"alice", &witness, &nonce));
+        // the store has to agree with the snapshot on the parameters
+        assert!(SetAccumulator::import_snapshot(&bytes, MemStore::from_parameters(get_test_parameters())).is_err());
+    }
+
+    #[test]
+    fn test_state_digest() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        let genesis: [u8; 32] = sa.state_digest();
+        sa.add(b"alice").unwrap();
+        assert_ne!(genesis, sa.state_digest());
+        // a replica agrees exactly when its state does
+        let mut replica: SetAccumulator<MemStore> = SetAccumulator::import_snapshot(&sa.export_snapshot(), MemStore::from_parameters(params)).unwrap();
+        assert_eq!(sa.state_digest(), replica.state_digest());
+        replica.add(b"bob").unwrap();
+        assert_ne!(sa.state_digest(), replica.state_digest());
+        // and one over other parameters never does
+        let other: PublicParameters = PublicParameters::new(params.modulus.clone(), params.generator.clone() + 1_u32);
+        assert_ne!(genesis, SetAccumulator::new(MemStore::from_parameters(&other)).state_digest());
+    }
+
+    #[test]
+    fn test_transaction() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        sa.add(b"alice").unwrap();
+        let state: BigUint = sa.store.get_state().clone();
+        // a failing transaction leaves nothing behind
+        let result: Result<(), AccumulatorError> = sa.transaction(|sa| {
+            sa.add(b"bob")?;
+            sa.delete_without_trapdoor(b"alice")?;
+            sa.delete_without_trapdoor(b"carol")?;
+            Ok(())
+        });
+        assert_eq!(Err(AccumulatorError::NotAMember), result);
+        assert_eq!(state, *sa.store.get_state());
+        assert_eq!(1, sa.store.member_count());
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+        assert!(sa.verify(b"alice", &witness, &nonce));
+        // and a successful one keeps everything
+        sa.transaction(|sa| {
+            sa.add(b"bob")?;
+            sa.add(b"carol")
+        }).unwrap();
+        assert_eq!(3, sa.store.member_count());
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"carol").unwrap();
+        assert!(sa.verify(b"carol", &witness, &nonce));
+    }
+
+    #[test]
+    fn test_get_all_witnesses() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        assert!(sa.get_all_witnesses().is_empty());
+        let values: Vec<Vec<u8>> = (0..9_u8).map(|i| vec![i]).collect();
+        for value in values.iter() {
+            sa.add(value).unwrap();
+        }
+        let proofs: HashMap<Vec<u8>, MembershipProof> = sa.get_all_witnesses();
+        assert_eq!(values.len(), proofs.len());
+        for value in values.iter() {
+            let proof: &MembershipProof = &proofs[value];
+            assert_eq!(Some((proof.witness.clone(), proof.nonce.clone())), sa.get_witness(value));
+            assert!(proof.verify(sa.store.get_state(), &params.modulus, value, sa.prime_mapping()));
+        }
+    }
+
+    #[test]
+    fn test_primes_are_kept() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        sa.add(b"alice").unwrap();
+        sa.add_batch(&[b"bob", b"carol"]).unwrap();
+        for value in [b"alice".as_ref(), b"bob", b"carol"] {
+            let nonce: Vec<u8> = sa.store.get_nonce(value).unwrap();
+            let prime: BigUint = hash_value_to_prime(value, &nonce, sa.prime_mapping(), sa.security_level());
+            assert_eq!(Some(&prime), sa.store.get_prime(value, &nonce));
+            // a prime is only handed out for the nonce it was found for
+            assert_eq!(None, sa.store.get_prime(value, &[0; 32]));
+        }
+        sa.delete_without_trapdoor(b"bob").unwrap();
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"carol").unwrap();
+        assert!(sa.verify(b"carol", &witness, &nonce));
+    }
+
+    #[test]
+    fn test_membership_queries() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        assert!(sa.is_empty());
+        assert_eq!(0, sa.iter_members().count());
+        sa.add_batch(&[b"alice", b"bob", b"carol"]).unwrap();
+        sa.delete_without_trapdoor(b"bob").unwrap();
+        assert!(!sa.is_empty());
+        assert_eq!(2, sa.len());
+        assert!(sa.contains(b"alice"));
+        assert!(!sa.contains(b"bob"));
+        let members: BTreeSet<&[u8]> = sa.iter_members().collect();
+        assert_eq!(BTreeSet::from([b"alice".as_ref(), b"carol".as_ref()]), members);
+    }
+
+    #[test]
+    fn test_recover() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        sa.add(b"alice").unwrap();
+        sa.add(b"bob").unwrap();
+        assert!(!sa.recover());
+        // a member that made it into the store without its state
+        let state: BigUint = sa.store.get_state().clone();
+        sa.store.insert_member(b"carol", &[0; 16]);
+        assert!(sa.recover());
+        assert_ne!(state, *sa.store.get_state());
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"carol").unwrap();
+        assert!(sa.verify(b"carol", &witness, &nonce));
+        assert!(!sa.recover());
+    }
+
+    #[test]
+    fn test_epochs() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        assert_eq!(0, sa.current_epoch());
+        assert_eq!(Some(params.genesis_state()), sa.state_at(0));
+        sa.add(b"alice").unwrap();
+        sa.add_batch(&[b"bob", b"carol"]).unwrap();
+        let proof: MembershipProof = sa.get_membership_proof(b"alice").unwrap();
+        // a failed transaction closes no epoch
+        assert!(sa.delete_without_trapdoor(b"dave").is_err());
+        sa.delete_without_trapdoor(b"bob").unwrap();
+        assert_eq!(3, sa.current_epoch());
+        assert_eq!(Some(sa.store.get_state().clone()), sa.state_at(3));
+        assert_eq!(None, sa.state_at(4));
+
+        // the proof issued at epoch 2 still checks against it, though not against now
+        assert!(sa.verify_at(2, b"alice", &proof));
+        assert!(!sa.verify_at(3, b"alice", &proof));
+        for (value, epoch) in [(b"alice".as_ref(), 1), (b"bob", 2), (b"carol", 3)] {
+            let proof: MembershipProof = sa.prove_membership_at(value, epoch).unwrap();
+            assert!(sa.verify_at(epoch, value, &proof));
+        }
+        assert!(sa.prove_membership_at(b"bob", 1).is_none());
+        assert!(sa.prove_membership_at(b"bob", 3).is_none());
+        assert!(sa.prove_membership_at(b"alice", 0).is_none());
+        assert!(!sa.verify_at(4, b"alice", &proof));
+    }
+
+    #[test]
+    fn test_compact() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        sa.add(b"alice").unwrap();
+        sa.add(b"bob").unwrap();
+        sa.delete_without_trapdoor(b"alice").unwrap();
+        sa.add(b"carol").unwrap();
+        let state: BigUint = sa.state_at(3).unwrap();
+        assert_eq!(Ok(0), sa.compact(Retention::KeepAll));
+        // everything happened today
+        assert_eq!(Ok(0), sa.compact(Retention::KeepDaily(1)));
+
+        assert_eq!(Ok(2), sa.compact(Retention::KeepLast(2)));
+        assert_eq!((3, 4), (sa.store.first_epoch(), sa.current_epoch()));
+        assert_eq!(None, sa.state_at(2));
+        assert!(sa.prove_membership_at(b"bob", 2).is_none());
+        // the epochs kept still rebuild their members, and neither lost its state
+        assert_eq!(Some(state), sa.state_at(3));
+        let proof: MembershipProof = sa.prove_membership_at(b"bob", 3).unwrap();
+        assert!(sa.verify_at(3, b"bob", &proof));
+        assert!(sa.prove_membership_at(b"alice", 3).is_none());
+        assert!(sa.open_at(4).unwrap().contains(b"carol"));
+        // only the epochs after the rewritten one can go into a delta
+        assert!(sa.export_delta(2).is_none());
+        assert_eq!(1, sa.export_delta(3).unwrap().epochs.len());
+        // the audit log is kept whole
+        assert_eq!(4, sa.store.iter_log().count());
+
+        assert_eq!(Ok(0), sa.compact(Retention::KeepLast(2)));
+        assert_eq!(Ok(1), sa.compact(Retention::KeepLast(0)));
+        sa.add(b"dave").unwrap();
+        assert!(sa.prove_membership_at(b"carol", 5).is_some());
+    }
+
+    #[test]
+    fn test_collect_garbage() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        sa.add(b"alice").unwrap();
+        sa.add(b"bob").unwrap();
+        sa.delete_without_trapdoor(b"alice").unwrap();
+        // the prime kept for alice outlived her
+        let report: GarbageReport = GarbageReport { epochs: 2, primes: 1 };
+        assert_eq!(Ok(report), sa.collect_garbage(Retention::KeepLast(1), true));
+        assert_eq!(1, sa.store.first_epoch());
+        assert_eq!(Ok(report), sa.collect_garbage(Retention::KeepLast(1), false));
+        assert_eq!(Ok(GarbageReport::default()), sa.collect_garbage(Retention::KeepLast(1), true));
+        assert!(sa.prove_membership_at(b"bob", 3).is_some());
+    }
+
+    #[test]
+    fn test_delta() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        sa.add_batch(&[b"alice", b"bob"]).unwrap();
+        let since: u64 = sa.current_epoch();
+        let mut replica: SetAccumulator<MemStore> = SetAccumulator::import_snapshot(&sa.export_snapshot(), MemStore::from_parameters(params)).unwrap();
+        sa.add(b"carol").unwrap();
+        sa.delete_without_trapdoor(b"alice").unwrap();
+        // bob again, under a new nonce
+        sa.transaction(|sa| {
+            sa.delete_without_trapdoor(b"bob")?;
+            sa.add(b"bob")
+        }).unwrap();
+
+        let delta: Delta = sa.export_delta(since).unwrap();
+        assert_eq!(3, delta.epochs.len());
+        let mut tampered: Delta = delta.clone();
+        tampered.epochs[1].state += 1_u32;
+        assert_eq!(Err(AccumulatorError::InvalidTransition), replica.apply_delta(&tampered));
+        // the epoch before the bad one stuck, so the delta no longer starts where the replica is
+        assert!(replica.contains(b"carol"));
+        assert_eq!(Err(AccumulatorError::InvalidTransition), replica.apply_delta(&delta));
+
+        // an empty replica catches up from genesis
+        let mut fresh: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        fresh.apply_delta(&sa.export_delta(0).unwrap()).unwrap();
+        assert_eq!(sa.store.get_state(), fresh.store.get_state());
+        assert_eq!(sa.store.get_nonce(b"bob"), fresh.store.get_nonce(b"bob"));
+        assert!(!fresh.contains(b"alice"));
+        assert_eq!(sa.current_epoch(), fresh.current_epoch());
+        let (witness, nonce): (BigUint, Vec<u8>) = fresh.get_witness(b"bob").unwrap();
+        assert!(sa.verify(b"bob", &witness, &nonce));
+        assert!(sa.export_delta(sa.current_epoch() + 1).is_none());
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        sa.add(b"alice").unwrap();
+        sa.add_batch(&[b"bob", b"carol"]).unwrap();
+        assert!(sa.transaction(|sa| {
+            sa.add(b"dave")?;
+            sa.delete_without_trapdoor(b"erin")
+        }).is_err());
+        sa.delete_without_trapdoor(b"alice").unwrap();
+
+        let log: Vec<AuditRecord> = sa.store.iter_log().collect::<Result<_, _>>().unwrap();
+        // the rolled back add of dave left nothing behind
+        assert_eq!(4, log.len());
+        let actions: Vec<(AuditAction, u64)> = log.iter().map(|record| (record.action, record.epoch)).collect();
+        assert_eq!(vec![(AuditAction::Add, 1), (AuditAction::Add, 2), (AuditAction::Add, 2), (AuditAction::Delete, 3)], actions);
+        // the states chain from genesis to now, the batch moving them in one step
+        assert_eq!(params.genesis_state(), log[0].previous_state);
+        assert_eq!(log[0].new_state, log[1].previous_state);
+        assert_eq!(log[1].new_state, log[2].new_state);
+        assert_eq!(*sa.store.get_state(), log[3].new_state);
+        let alice: [u8; 32] = Sha256::digest(b"alice").into();
+        assert_eq!(alice, log[3].value_hash);
+        assert_eq!(log[0].prime, hash_value_to_prime(b"alice", &log[0].nonce, sa.prime_mapping(), sa.security_level()));
+        assert_eq!(log[0].prime, log[3].prime);
+    }
+
+    #[test]
+    fn test_rollback() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        let signer: TestSigner = TestSigner(b"manager key".to_vec());
+        sa.add_batch(&[b"alice", b"bob"]).unwrap();
+        let checkpoint: BigUint = sa.store.get_state().clone();
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+        sa.add(b"mallory").unwrap();
+        sa.delete_without_trapdoor(b"bob").unwrap();
+        let head: BigUint = sa.store.get_state().clone();
+
+        let record: AuditRecord = sa.rollback_to(1, "mallory was added by mistake", &signer).unwrap();
+        // the members and state are back, and a witness from then verifies again
+        assert_eq!(checkpoint, *sa.store.get_state());
+        assert!(sa.contains(b"bob") && !sa.contains(b"mallory"));
+        assert!(sa.verify(b"alice", &witness, &nonce));
+        // as a new epoch, the ones rolled back stay in the history
+        assert_eq!(4, sa.current_epoch());
+        assert_eq!(Some(head.clone()), sa.state_at(3));
+        let log: Vec<AuditRecord> = sa.store.iter_log().collect::<Result<_, _>>().unwrap();
+        assert_eq!(Some(&record), log.last());
+        assert_eq!((4, head, BigUint::from(1_u64)), (record.epoch, record.previous_state.clone(), record.prime.clone()));
+        let reason: [u8; 32] = Sha256::digest(b"mallory was added by mistake").into();
+        assert_eq!(reason, record.value_hash);
+        assert!(record.verify_rollback(&signer));
+        assert!(!record.verify_rollback(&TestSigner(b"another key".to_vec())));
+        let mut forged: AuditRecord = record;
+        forged.prime = BigUint::from(0_u64);
+        assert!(!forged.verify_rollback(&signer));
+
+        // even a rollback that changes nothing is recorded
+        sa.rollback_to(4, "drill", &signer).unwrap();
+        assert_eq!(5, sa.current_epoch());
+        assert_eq!(Err(AccumulatorError::UnknownEpoch), sa.rollback_to(6, "", &signer).map(|_| ()));
+        // a store without the history has nothing to roll back to but genesis
+        let mut fresh: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        assert_eq!(Err(AccumulatorError::UnknownEpoch), fresh.rollback_to(1, "", &signer).map(|_| ()));
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        sa.add(b"alice").unwrap();
+        let receiver: Receiver<AccumulatorEvent> = sa.subscribe();
+        let dropped: Receiver<AccumulatorEvent> = sa.subscribe();
+        drop(dropped);
+        let genesis: BigUint = sa.store.get_state().clone();
+        sa.add_batch(&[b"bob", b"carol"]).unwrap();
+        let batch: BigUint = sa.store.get_state().clone();
+        assert!(sa.transaction(|sa| {
+            sa.add(b"dave")?;
+            sa.delete_without_trapdoor(b"erin")
+        }).is_err());
+        sa.delete_without_trapdoor(b"alice").unwrap();
+
+        // only what was committed after subscribing, each transaction closed by its state
+        let events: Vec<AccumulatorEvent> = receiver.try_iter().collect();
+        assert_eq!(5, events.len());
+        match &events[0] {
+            AccumulatorEvent::Added { value, nonce, prime } => {
+                assert_eq!(b"bob".to_vec(), *value);
+                assert_eq!(*prime, hash_value_to_prime(b"bob", nonce, sa.prime_mapping(), sa.security_level()));
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+        assert!(matches!(&events[1], AccumulatorEvent::Added { value, .. } if value == b"carol"));
+        assert_eq!(AccumulatorEvent::StateChanged { epoch: 2, previous: genesis, state: batch.clone() }, events[2]);
+        assert!(matches!(&events[3], AccumulatorEvent::Deleted { value, .. } if value == b"alice"));
+        assert_eq!(AccumulatorEvent::StateChanged { epoch: 3, previous: batch, state: sa.store.get_state().clone() }, events[4]);
+    }
+
+    #[test]
+    fn test_extend() {
+        let params: &PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+        let values = (0..10_u32).map(|n| format!("value {}", n).into_bytes());
+        assert_eq!(10, sa.extend_in_chunks(values, 4).unwrap());
+        assert_eq!(10, sa.len());
+        // a chunk of four, four and two, each an epoch
+        assert_eq!(3, sa.current_epoch());
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"value 9").unwrap();
+        assert!(sa.verify(b"value 9", &witness, &nonce));
+        assert_eq!(0, sa.extend(std::iter::empty()).unwrap());
+        assert_eq!(3, sa.current_epoch());
+    }
+
+    // one small modulus shared by every proptest case, generating one per case would dominate the run time
+    fn get_test_parameters() -> &'static PublicParameters {
+        static PARAMS: OnceLock<PublicParameters> = OnceLock::new();
+        PARAMS.get_or_init(|| {
+            let primes: (BigUint, BigUint) = get_distinct_primes(128);
+            let modulus: BigUint = primes.0 * primes.1;
+            let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
+            PublicParameters::new(modulus, generator)
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(8))]
+
+        // each step adds the picked value if it is absent and deletes it otherwise, so adds
+        // and deletes interleave; re-adding a value that is still held is not covered here
+        #[test]
+        fn prop_updates_preserve_invariants(
+            values in prop::collection::btree_set(prop::collection::vec(any::<u8>(), 0..8), 1..4),
+            steps in prop::collection::vec(any::<prop::sample::Index>(), 1..8),
+            outsider in prop::collection::vec(any::<u8>(), 8..12),
+        ) {
+            let values: Vec<Vec<u8>> = values.into_iter().collect();
+            let params: &PublicParameters = get_test_parameters();
+            let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(params));
+            let mapping: PrimeMapping = sa.prime_mapping();
+            let level: SecurityLevel = sa.security_level();
+            let mut members: BTreeSet<Vec<u8>> = BTreeSet::new();
+
+            for step in steps.iter() {
+                let value: &Vec<u8> = step.get(&values);
+                // witnesses issued before the step, kept by their holders across it
+                let issued: Vec<(Vec<u8>, MembershipProof)> = members.iter()
+                    .filter(|member| *member != value)
+                    .map(|member| (member.clone(), sa.get_membership_proof(member).unwrap()))
+                    .collect();
+                if members.remove(value) {
+                    let nonce: Vec<u8> = sa.get_membership_proof(value).unwrap().nonce;
+                    let state: BigUint = sa.delete_without_trapdoor(value).unwrap();
+                    for (member, proof) in issued.iter() {
+                        let witness: BigUint = update_witness_on_delete(&params.modulus, member, proof, value, &nonce, &state, mapping).unwrap();
+                        prop_assert!(sa.verify(member, &witness, &proof.nonce));
+                    }
+                } else {
+                    sa.add(value).unwrap();
+                    members.insert(value.clone());
+                    let nonce: Vec<u8> = sa.get_membership_proof(value).unwrap().nonce;
+                    for (member, proof) in issued.iter() {
+                        let witness: BigUint = update_witness(&params.modulus, &proof.witness, value, &nonce, mapping);
+                        prop_assert!(sa.verify(member, &witness, &proof.nonce));
+                    }
+                }
+
+                // the state is exactly what recomputing from the surviving members gives
+                let primes: Vec<BigUint> = sa.store.iter_members()
+                    .map(|(member, nonce)| hash_value_to_prime(member, nonce, mapping, level))
+                    .collect();
+                let expected: BigUint = params.generator.modpow(&product_tree::product(primes), &params.modulus);
+                prop_assert_eq!(&expected, sa.store.get_state());
+            }
+
+            for value in members.iter() {
+                // every issued witness verifies
+                let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(value).unwrap();
+                let exponent: BigUint = hash_value_to_prime(value, &nonce, mapping, level);
+                prop_assert_eq!(sa.store.get_state(), &witness.modpow(&exponent, &params.modulus));
+                // and cannot be reused for a value that was never added
+                let forged: BigUint = hash_value_to_prime(&outsider, &nonce, mapping, level);
+                prop_assert_ne!(sa.store.get_state(), &witness.modpow(&forged, &params.modulus));
+            }
+            // deleted values and non-members get no witness at all (outsiders are longer than any member)
+            for value in values.iter().filter(|value| !members.contains(*value)) {
+                prop_assert!(sa.get_witness(value).is_none());
+            }
+            prop_assert!(sa.get_witness(&outsider).is_none());
+        }
+    }
+}
+
+// Model checking harnesses, run with `cargo kani`. Every candidate below 2^64 goes to
+// primality::is_prime_u64, whose fixed Miller-Rabin bases make the answer exact, so the
+// harnesses check that path and nothing random is involved. What bounds them is the
+// size of the inputs: the prime search starts no later than 997, so it stops at a prime
+// below 1000 that the trial division reference covers. Hashing itself is left to
+// test_hash_value_to_prime_is_deterministic.
+#[cfg(kani)]
+mod verification {
+    use super::*;
+
+    #[kani::proof]
+    #[kani::unwind(170)]
+    fn is_prime_is_exact_below_one_thousand() {
+        let n: u16 = kani::any();
+        kani::assume(n < 1000);
+        let expected: bool = n >= 2 && (2..n).all(|d| n % d != 0);
+        assert_eq!(expected, is_prime(&BigUint::from(n), 1));
+    }
+
+    #[kani::proof]
+    fn miller_rabin_handles_small_and_even_candidates() {
+        let n: u64 = kani::any();
+        kani::assume(n <= 3 || n % 2 == 0);
+        assert_eq!(n == 2 || n == 3, miller_rabin(&BigUint::from(n), 1));
+    }
+
+    #[kani::proof]
+    #[kani::unwind(170)]
+    fn prime_search_stops_at_the_next_prime() {
+        let n: u16 = kani::any();
+        // 997 is the last prime below 1000, so no search runs past it
+        kani::assume(n <= 997);
+        let found: BigUint = next_prime(BigUint::from(n), PrimalityConfig::new(1));
+        let is_prime_u16 = |m: u16| -> bool { m >= 2 && (2..m).all(|d| m % d != 0) };
+        let found: u16 = found.to_u16().unwrap();
+        assert!(found >= n && is_prime_u16(found));
+        assert!((n..found).all(|m| !is_prime_u16(m)));
+        assert_eq!(BigUint::from(found), next_prime(BigUint::from(n), PrimalityConfig::new(1)));
     }
 }
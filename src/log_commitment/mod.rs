@@ -0,0 +1,144 @@
+use std::convert::TryInto;
+
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+use crate::parallel::*;
+
+use crate::params::{PublicParameters, SecurityLevel};
+use crate::store::Storer;
+use crate::{arith, hash_value_to_prime, product_tree, AccumulatorError, SetAccumulator};
+
+/// The accumulated value for `record` at `sequence`: the big-endian sequence number
+/// followed by the SHA-256 of the record.
+pub fn log_entry(sequence: u64, record: &[u8]) -> Vec<u8> {
+    [sequence.to_be_bytes().as_ref(), Sha256::digest(record).as_slice()].concat()
+}
+
+/// Shows that a record sits at a given position of the log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordProof {
+    pub sequence: u64,
+    pub nonce: Vec<u8>,
+    pub witness: BigUint,
+}
+
+/// Shows that the first `nonces.len()` records of the log are all part of the current
+/// state, with a single witness for the whole prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixProof {
+    pub nonces: Vec<Vec<u8>>,
+    pub witness: BigUint,
+}
+
+/// An append-only log committed to by a set accumulator. Every record is accumulated
+/// together with its position, so proofs cover both that a record was logged and where.
+pub struct LogCommitment<T: Storer> {
+    pub accumulator: SetAccumulator<T>,
+    length: u64,
+}
+
+impl<T: Storer> LogCommitment<T> {
+    /// Wraps an accumulator that holds nothing but log entries, continuing after the
+    /// entries already in it.
+    pub fn new(accumulator: SetAccumulator<T>) -> Self {
+        let length: u64 = accumulator.len() as u64;
+        LogCommitment { accumulator, length }
+    }
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+    /// Appends `record` and returns its sequence number.
+    pub fn append(&mut self, record: &[u8]) -> Result<u64, AccumulatorError> {
+        let sequence: u64 = self.length;
+        self.accumulator.add(&log_entry(sequence, record))?;
+        self.length += 1;
+        Ok(sequence)
+    }
+    pub fn prove_record(&mut self, sequence: u64, record: &[u8]) -> Option<RecordProof> {
+        let (witness, nonce): (BigUint, Vec<u8>) = self.accumulator.get_witness(&log_entry(sequence, record))?;
+        Some(RecordProof { sequence, nonce, witness })
+    }
+    /// Proves that `records` are the first `records.len()` records of the log.
+    pub fn prove_prefix(&mut self, records: &[&[u8]]) -> Option<PrefixProof> {
+        let entries: Vec<Vec<u8>> = records
+            .iter()
+            .enumerate()
+            .map(|(sequence, record)| log_entry(sequence as u64, record))
+            .collect();
+        let params: PublicParameters = self.accumulator.get_public_parameters();
+        let nonces: Vec<Vec<u8>> = entries
+            .iter()
+            .map(|entry| self.accumulator.store.get_nonce(entry))
+            .collect::<Option<_>>()?;
+        // the witness is the generator raised to every prime outside the prefix
+        let others: Vec<(&[u8], &[u8])> = self.accumulator.store
+            .iter_members()
+            .filter(|(member, _)| !is_prefix_entry(member, records.len() as u64))
+            .collect();
+        let primes: Vec<BigUint> = self.accumulator.primes_of(&others);
+        let witness: BigUint = arith::modpow(&params.generator, &product_tree::product(primes), &params.modulus);
+        Some(PrefixProof { nonces, witness })
+    }
+}
+
+// whether a log entry's sequence number falls below `length`
+fn is_prefix_entry(entry: &[u8], length: u64) -> bool {
+    let sequence: [u8; 8] = entry[..8].try_into().unwrap();
+    u64::from_be_bytes(sequence) < length
+}
+
+pub fn verify_record(params: &PublicParameters, state: &BigUint, record: &[u8], proof: &RecordProof) -> bool {
+    let prime: BigUint = hash_value_to_prime(&log_entry(proof.sequence, record), &proof.nonce, params.prime_mapping(), SecurityLevel::default());
+    arith::modpow(&proof.witness, &prime, &params.modulus) == *state
+}
+
+pub fn verify_prefix(params: &PublicParameters, state: &BigUint, records: &[&[u8]], proof: &PrefixProof) -> bool {
+    if records.len() != proof.nonces.len() {
+        return false;
+    }
+    let primes: Vec<BigUint> = records
+        .par_iter()
+        .zip(proof.nonces.par_iter())
+        .enumerate()
+        .map(|(sequence, (record, nonce))| hash_value_to_prime(&log_entry(sequence as u64, record), nonce, params.prime_mapping(), SecurityLevel::default()))
+        .collect();
+    arith::modpow(&proof.witness, &product_tree::product(primes), &params.modulus) == *state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::RandBigInt;
+    use crate::store::mem_store::MemStore;
+
+    #[test]
+    fn test_log_commitment() {
+        let mut rng = rand::thread_rng();
+        let modulus: BigUint = rng.gen_biguint(256) | BigUint::from(1_u64);
+        let generator: BigUint = rng.gen_biguint_below(&modulus);
+        let params: PublicParameters = PublicParameters::new(modulus, generator);
+        let mut log: LogCommitment<MemStore> = LogCommitment::new(SetAccumulator::new(MemStore::from_parameters(&params)));
+        let records: Vec<&[u8]> = vec![b"genesis", b"deposit 10", b"withdraw 3", b"deposit 5"];
+        for (i, record) in records.iter().enumerate() {
+            assert_eq!(i as u64, log.append(record).unwrap());
+        }
+        let state: BigUint = log.accumulator.store.get_state().clone();
+
+        // a record verifies at its own position only
+        let proof: RecordProof = log.prove_record(2, records[2]).unwrap();
+        assert!(verify_record(&params, &state, records[2], &proof));
+        assert!(!verify_record(&params, &state, b"withdraw 300", &proof));
+        assert!(!verify_record(&params, &state, records[2], &RecordProof { sequence: 1, ..proof }));
+        assert!(log.prove_record(1, records[2]).is_none());
+
+        // and a prefix verifies as a whole
+        let proof: PrefixProof = log.prove_prefix(&records[..3]).unwrap();
+        assert!(verify_prefix(&params, &state, &records[..3], &proof));
+        assert!(!verify_prefix(&params, &state, &[records[0], records[2], records[1]], &proof));
+        assert!(!verify_prefix(&params, &state, &records[..2], &proof));
+        assert!(log.prove_prefix(&[records[1]]).is_none());
+    }
+}
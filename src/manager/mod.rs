@@ -0,0 +1,260 @@
+//! Many accumulators, one per tenant or per purpose, kept apart by name over a single
+//! storage backend. A [`Backend`] hands out a store per namespace, laid out so that the
+//! namespaces never see each other's keys, and [`AccumulatorManager`] keeps the
+//! accumulators over them open. [`AccumulatorRegistry`] does the same for a service that
+//! hosts accumulators on behalf of tenants: it tells creating an accumulator apart from
+//! opening one, holds each tenant to a [`Quota`], and hands out the heads of all of them
+//! for publication.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+
+use num_bigint::BigUint;
+
+use crate::params::PublicParameters;
+use crate::store::mem_store::MemStore;
+use crate::store::Storer;
+use crate::SetAccumulator;
+
+/// Storage that can hold the stores of many named accumulators side by side.
+pub trait Backend {
+    type Store: Storer;
+
+    /// The store of `namespace`, created over `params` at the genesis state if the
+    /// namespace does not exist yet. An existing one must have been created over `params`.
+    fn open(&mut self, namespace: &str, params: &PublicParameters) -> io::Result<Self::Store>;
+    /// The names of every namespace, in byte order.
+    fn namespaces(&self) -> io::Result<Vec<String>>;
+    /// Drops everything kept for `namespace`, returning whether there was anything.
+    fn remove(&mut self, namespace: &str) -> io::Result<bool>;
+    /// An accumulator over the store of `namespace`, which sees nothing of any other.
+    fn open_namespace(&mut self, namespace: &str, params: &PublicParameters) -> io::Result<SetAccumulator<Self::Store>> {
+        Ok(SetAccumulator::new(self.open(namespace, params)?))
+    }
+}
+
+/// A backend that keeps nothing but the names: the members and states live in the
+/// stores it hands out, and so only as long as the manager holds them open.
+#[derive(Debug, Default)]
+pub struct MemBackend {
+    namespaces: BTreeSet<String>,
+}
+
+impl Backend for MemBackend {
+    type Store = MemStore;
+
+    fn open(&mut self, namespace: &str, params: &PublicParameters) -> io::Result<MemStore> {
+        self.namespaces.insert(namespace.to_string());
+        Ok(MemStore::from_parameters(params))
+    }
+    fn namespaces(&self) -> io::Result<Vec<String>> {
+        Ok(self.namespaces.iter().cloned().collect())
+    }
+    fn remove(&mut self, namespace: &str) -> io::Result<bool> {
+        Ok(self.namespaces.remove(namespace))
+    }
+}
+
+/// Named accumulators over one backend, all over the same parameters.
+pub struct AccumulatorManager<B: Backend> {
+    backend: B,
+    params: PublicParameters,
+    open: HashMap<String, SetAccumulator<B::Store>>,
+}
+
+impl<B: Backend> AccumulatorManager<B> {
+    pub fn new(backend: B, params: PublicParameters) -> Self {
+        AccumulatorManager { backend, params, open: HashMap::new() }
+    }
+    /// The accumulator of `namespace`, opened from the backend the first time it is asked
+    /// for and created empty if the backend has nothing under that name.
+    pub fn get_or_create(&mut self, namespace: &str) -> io::Result<&mut SetAccumulator<B::Store>> {
+        if !self.open.contains_key(namespace) {
+            let accumulator: SetAccumulator<B::Store> = self.backend.open_namespace(namespace, &self.params)?;
+            self.open.insert(namespace.to_string(), accumulator);
+        }
+        Ok(self.open.get_mut(namespace).unwrap())
+    }
+    /// The names of every namespace the backend holds, in byte order.
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        self.backend.namespaces()
+    }
+    /// Closes the accumulator of `namespace` and drops it from the backend, returning
+    /// whether there was one.
+    pub fn delete(&mut self, namespace: &str) -> io::Result<bool> {
+        self.open.remove(namespace);
+        self.backend.remove(namespace)
+    }
+}
+
+/// How much one tenant may keep, unlimited where None.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quota {
+    /// How many accumulators the tenant may create.
+    pub max_accumulators: Option<usize>,
+    /// How many members each of its accumulators may hold, see
+    /// [`SetAccumulator::with_member_limit`].
+    pub max_members: Option<usize>,
+}
+
+/// Where one named accumulator stands, as [`AccumulatorRegistry::heads`] publishes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedHead {
+    pub name: String,
+    pub epoch: u64,
+    pub state: BigUint,
+}
+
+/// Named accumulators over one backend, grouped by tenant. The tenant of a name is the
+/// part before its first '/', or the whole name if it has none, so "acme/devices" and
+/// "acme/users" count against the quota of "acme".
+pub struct AccumulatorRegistry<B: Backend> {
+    backend: B,
+    params: PublicParameters,
+    open: HashMap<String, SetAccumulator<B::Store>>,
+    default_quota: Quota,
+    quotas: HashMap<String, Quota>,
+}
+
+fn tenant_of(name: &str) -> &str {
+    name.split('/').next().unwrap()
+}
+
+impl<B: Backend> AccumulatorRegistry<B> {
+    pub fn new(backend: B, params: PublicParameters) -> Self {
+        AccumulatorRegistry { backend, params, open: HashMap::new(), default_quota: Quota::default(), quotas: HashMap::new() }
+    }
+    /// Sets the quota of every tenant without one of its own.
+    pub fn with_default_quota(mut self, quota: Quota) -> Self {
+        self.default_quota = quota;
+        self
+    }
+    /// Sets the quota of `tenant`. Accumulators it already has open keep the member limit
+    /// they were opened with.
+    pub fn with_quota(mut self, tenant: &str, quota: Quota) -> Self {
+        self.quotas.insert(tenant.to_string(), quota);
+        self
+    }
+    pub fn quota(&self, tenant: &str) -> Quota {
+        self.quotas.get(tenant).copied().unwrap_or(self.default_quota)
+    }
+    /// The names of every accumulator, in byte order.
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        self.backend.namespaces()
+    }
+    /// Creates the accumulator `name` empty. Fails with [`io::ErrorKind::AlreadyExists`]
+    /// if there is one by that name, and with [`io::ErrorKind::QuotaExceeded`] if its
+    /// tenant already has as many as its quota allows.
+    pub fn create(&mut self, name: &str) -> io::Result<&mut SetAccumulator<B::Store>> {
+        let names: Vec<String> = self.list()?;
+        if names.iter().any(|existing| existing == name) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("accumulator {} already exists", name)));
+        }
+        let tenant: &str = tenant_of(name);
+        if let Some(max) = self.quota(tenant).max_accumulators {
+            if names.iter().filter(|existing| tenant_of(existing) == tenant).count() >= max {
+                return Err(io::Error::new(io::ErrorKind::QuotaExceeded, format!("tenant {} may keep {} accumulators", tenant, max)));
+            }
+        }
+        self.load(name)
+    }
+    /// The accumulator `name`, which must have been created. Fails with
+    /// [`io::ErrorKind::NotFound`] otherwise.
+    pub fn open(&mut self, name: &str) -> io::Result<&mut SetAccumulator<B::Store>> {
+        if !self.open.contains_key(name) && !self.list()?.iter().any(|existing| existing == name) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no accumulator named {}", name)));
+        }
+        self.load(name)
+    }
+    /// Closes the accumulator `name` and drops it from the backend, returning whether
+    /// there was one.
+    pub fn delete(&mut self, name: &str) -> io::Result<bool> {
+        self.open.remove(name);
+        self.backend.remove(name)
+    }
+    /// The epoch and state of every accumulator, in byte order of their names, opening
+    /// those that are not open yet.
+    pub fn heads(&mut self) -> io::Result<Vec<NamedHead>> {
+        let mut heads: Vec<NamedHead> = Vec::new();
+        for name in self.list()? {
+            let accumulator: &mut SetAccumulator<B::Store> = self.load(&name)?;
+            let (epoch, state): (u64, BigUint) = (accumulator.store.epoch_count(), accumulator.store.get_state().clone());
+            heads.push(NamedHead { name, epoch, state });
+        }
+        Ok(heads)
+    }
+    // opens `name` under the member limit of its tenant the first time it is asked for
+    fn load(&mut self, name: &str) -> io::Result<&mut SetAccumulator<B::Store>> {
+        if !self.open.contains_key(name) {
+            let accumulator: SetAccumulator<B::Store> = self.backend.open_namespace(name, &self.params)?;
+            let accumulator: SetAccumulator<B::Store> = match self.quota(tenant_of(name)).max_members {
+                Some(limit) => accumulator.with_member_limit(limit),
+                None => accumulator,
+            };
+            self.open.insert(name.to_string(), accumulator);
+        }
+        Ok(self.open.get_mut(name).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AccumulatorError;
+
+    #[test]
+    fn test_namespaces() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut manager: AccumulatorManager<MemBackend> = AccumulatorManager::new(MemBackend::default(), params.clone());
+        manager.get_or_create("tenant-b").unwrap().add(b"alice").unwrap();
+        manager.get_or_create("tenant-a").unwrap().add(b"bob").unwrap();
+        assert_eq!(vec!["tenant-a".to_string(), "tenant-b".to_string()], manager.list().unwrap());
+        // each namespace sees its own members, and the open accumulator is handed back again
+        assert!(manager.get_or_create("tenant-b").unwrap().contains(b"alice"));
+        assert!(!manager.get_or_create("tenant-a").unwrap().contains(b"alice"));
+
+        assert!(manager.delete("tenant-b").unwrap());
+        assert!(!manager.delete("tenant-b").unwrap());
+        assert_eq!(vec!["tenant-a".to_string()], manager.list().unwrap());
+        assert_eq!(params.genesis_state(), *manager.get_or_create("tenant-b").unwrap().store.get_state());
+    }
+
+    #[test]
+    fn test_registry() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let quota: Quota = Quota { max_accumulators: Some(2), max_members: Some(2) };
+        let mut registry: AccumulatorRegistry<MemBackend> = AccumulatorRegistry::new(MemBackend::default(), params.clone()).with_default_quota(quota).with_quota("big", Quota::default());
+        registry.create("acme/users").unwrap().add(b"alice").unwrap();
+        registry.create("acme/devices").unwrap();
+        assert_eq!(io::ErrorKind::AlreadyExists, registry.create("acme/users").err().unwrap().kind());
+        assert_eq!(io::ErrorKind::QuotaExceeded, registry.create("acme/groups").err().unwrap().kind());
+        assert_eq!(io::ErrorKind::NotFound, registry.open("acme/groups").err().unwrap().kind());
+        // the tenant is the part of the name before the first '/'
+        registry.create("big/a").unwrap();
+        registry.create("big/b").unwrap();
+        registry.create("big/c").unwrap();
+
+        // the member limit undoes the change that goes past it, and lets deletes through
+        let users: &mut SetAccumulator<MemStore> = registry.open("acme/users").unwrap();
+        users.add(b"bob").unwrap();
+        let state: BigUint = users.store.get_state().clone();
+        assert_eq!(Some(AccumulatorError::QuotaExceeded { limit: 2 }), users.add(b"carol").err());
+        assert_eq!(Err(AccumulatorError::QuotaExceeded { limit: 2 }), users.add_batch(&[b"carol".as_ref(), b"dave"]));
+        assert_eq!((2, &state), (users.store.member_count(), users.store.get_state()));
+        users.delete_without_trapdoor(b"bob").unwrap();
+        users.add(b"carol").unwrap();
+        for value in [b"a".as_ref(), b"b", b"c"].iter() {
+            registry.open("big/a").unwrap().add(value).unwrap();
+        }
+
+        let heads: Vec<NamedHead> = registry.heads().unwrap();
+        let names: Vec<&str> = heads.iter().map(|head| head.name.as_str()).collect();
+        assert_eq!(vec!["acme/devices", "acme/users", "big/a", "big/b", "big/c"], names);
+        assert_eq!((4, registry.open("acme/users").unwrap().store.get_state().clone()), (heads[1].epoch, heads[1].state.clone()));
+        assert_eq!((0, params.genesis_state()), (heads[0].epoch, heads[0].state.clone()));
+
+        assert!(registry.delete("acme/devices").unwrap());
+        registry.create("acme/groups").unwrap();
+        assert_eq!(io::ErrorKind::NotFound, registry.open("acme/devices").err().unwrap().kind());
+    }
+}
@@ -0,0 +1,232 @@
+//! An accumulator with no trusted setup: a SHA-256 Merkle tree over the members, proven
+//! by inclusion paths. Proofs grow with the logarithm of the number of members, about a
+//! kilobyte at a million, and checking one takes a few dozen hashes rather than an
+//! exponentiation. Every change moves most members' leaf positions, so a proof is only
+//! good against the root it was issued for.
+//!
+//! The leaves are the members in byte order, hashed as `H(0x00 ‖ value)`, and each inner
+//! node is `H(0x01 ‖ left ‖ right)`. A node left over at the end of a level is carried up
+//! unchanged rather than paired with itself, so no two sets share a root.
+
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::accumulator::Accumulator;
+use crate::error::AccumulatorError;
+use crate::store::Storer;
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+// the root of a tree without leaves, which no leaf or node hash can collide with
+const EMPTY_DOMAIN: &[u8] = b"tangerine/merkle/empty/v1";
+
+fn hash_leaf(value: &[u8]) -> [u8; 32] {
+    let mut hasher: Sha256 = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(value);
+    hasher.finalize().into()
+}
+
+fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher: Sha256 = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+fn empty_root() -> [u8; 32] {
+    Sha256::digest(EMPTY_DOMAIN).into()
+}
+
+/// The path from a member's leaf to the root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    /// The position of the leaf among the members in byte order.
+    pub index: u64,
+    pub leaf_count: u64,
+    /// The sibling at each level that has one, from the leaves up.
+    pub siblings: Vec<[u8; 32]>,
+}
+
+/// Whether `proof` shows `value` to be a member of the tree with root `root`.
+pub fn verify_inclusion(root: &[u8; 32], value: &[u8], proof: &MerkleProof) -> bool {
+    if proof.index >= proof.leaf_count {
+        return false;
+    }
+    let (mut index, mut count): (u64, u64) = (proof.index, proof.leaf_count);
+    let mut hash: [u8; 32] = hash_leaf(value);
+    let mut siblings = proof.siblings.iter();
+    while count > 1 {
+        // the last node of a level with an odd count has no sibling and moves up as it is
+        if !(index == count - 1 && index % 2 == 0) {
+            let sibling: &[u8; 32] = match siblings.next() {
+                Some(sibling) => sibling,
+                None => return false,
+            };
+            hash = if index % 2 == 0 { hash_node(&hash, sibling) } else { hash_node(sibling, &hash) };
+        }
+        index /= 2;
+        count = count.div_ceil(2);
+    }
+    siblings.next().is_none() && hash == *root
+}
+
+/// A Merkle tree accumulator over any [`Storer`], which keeps the members and, as its
+/// state, the root. The modulus and generator the store was created with go unused.
+pub struct MerkleAccumulator<T: Storer> {
+    pub store: T,
+    // the members in byte order, and the tree over them from the leaves up
+    values: Vec<Vec<u8>>,
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl<T: Storer> MerkleAccumulator<T> {
+    /// Builds the tree over the members already in `store`.
+    pub fn new(store: T) -> Self {
+        let mut values: Vec<Vec<u8>> = store.iter_members().map(|(value, _)| value.to_vec()).collect();
+        values.sort_unstable();
+        let mut accumulator: MerkleAccumulator<T> = MerkleAccumulator { store, values, levels: Vec::new() };
+        accumulator.rebuild();
+        accumulator
+    }
+    pub fn root(&self) -> [u8; 32] {
+        match self.levels.last() {
+            Some(top) => top[0],
+            None => empty_root(),
+        }
+    }
+    pub fn contains(&self, value: &[u8]) -> bool {
+        self.values.binary_search_by(|member| member.as_slice().cmp(value)).is_ok()
+    }
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+    fn rebuild(&mut self) {
+        self.levels.clear();
+        if self.values.is_empty() {
+            return;
+        }
+        let mut level: Vec<[u8; 32]> = self.values.iter().map(|value| hash_leaf(value)).collect();
+        while level.len() > 1 {
+            let next: Vec<[u8; 32]> = level
+                .chunks(2)
+                .map(|pair| if pair.len() == 2 { hash_node(&pair[0], &pair[1]) } else { pair[0] })
+                .collect();
+            self.levels.push(level);
+            level = next;
+        }
+        self.levels.push(level);
+    }
+    // rebuilds the tree and records the members changed and the root they lead to at once
+    fn commit(&mut self, change: impl FnOnce(&mut T)) -> Result<(), AccumulatorError> {
+        self.rebuild();
+        let root: [u8; 32] = self.root();
+        self.store.begin_transaction();
+        change(&mut self.store);
+        self.store.set_state(&BigUint::from_bytes_be(&root));
+        self.store.commit_transaction()
+    }
+    /// Adds `value`, doing nothing if it is already a member.
+    pub fn add(&mut self, value: &[u8]) -> Result<(), AccumulatorError> {
+        if let Err(position) = self.values.binary_search_by(|member| member.as_slice().cmp(value)) {
+            self.values.insert(position, value.to_vec());
+            return self.commit(|store| store.insert_member(value, &[]));
+        }
+        Ok(())
+    }
+    pub fn delete(&mut self, value: &[u8]) -> Result<(), AccumulatorError> {
+        let position: usize = self.values.binary_search_by(|member| member.as_slice().cmp(value)).map_err(|_| AccumulatorError::NotAMember)?;
+        self.values.remove(position);
+        self.commit(|store| {
+            store.remove_member(value);
+        })
+    }
+    pub fn get_proof(&self, value: &[u8]) -> Option<MerkleProof> {
+        let index: usize = self.values.binary_search_by(|member| member.as_slice().cmp(value)).ok()?;
+        let mut siblings: Vec<[u8; 32]> = Vec::new();
+        let mut position: usize = index;
+        for level in self.levels.iter().take(self.levels.len() - 1) {
+            if let Some(sibling) = level.get(position ^ 1) {
+                siblings.push(*sibling);
+            }
+            position /= 2;
+        }
+        Some(MerkleProof { index: index as u64, leaf_count: self.values.len() as u64, siblings })
+    }
+}
+
+impl<T: Storer> Accumulator for MerkleAccumulator<T> {
+    type Proof = MerkleProof;
+
+    fn add(&mut self, value: &[u8]) -> Result<(), AccumulatorError> {
+        MerkleAccumulator::add(self, value)
+    }
+    fn delete(&mut self, value: &[u8]) -> Result<(), AccumulatorError> {
+        MerkleAccumulator::delete(self, value)
+    }
+    fn prove(&self, value: &[u8]) -> Option<MerkleProof> {
+        self.get_proof(value)
+    }
+    fn verify(&self, value: &[u8], proof: &MerkleProof) -> bool {
+        verify_inclusion(&self.root(), value, proof)
+    }
+    /// The root.
+    fn state_digest(&self) -> [u8; 32] {
+        self.root()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::PublicParameters;
+    use crate::store::mem_store::MemStore;
+
+    fn store() -> MemStore {
+        MemStore::from_parameters(&PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64)))
+    }
+
+    #[test]
+    fn test_merkle_accumulator() {
+        let mut ma: MerkleAccumulator<MemStore> = MerkleAccumulator::new(store());
+        assert_eq!(empty_root(), ma.root());
+        assert!(ma.get_proof(b"value 0").is_none());
+        // every tree shape up to a few levels, odd counts included
+        for n in 0..20_u32 {
+            ma.add(format!("value {}", n).as_bytes()).unwrap();
+            let root: [u8; 32] = ma.root();
+            for m in 0..=n {
+                let value: String = format!("value {}", m);
+                let proof: MerkleProof = ma.get_proof(value.as_bytes()).unwrap();
+                assert!(verify_inclusion(&root, value.as_bytes(), &proof), "{} of {}", m, n + 1);
+                assert!(!verify_inclusion(&root, b"value x", &proof));
+                let mut moved: MerkleProof = proof.clone();
+                moved.index = (moved.index + 1) % moved.leaf_count;
+                assert!(n == 0 || !verify_inclusion(&root, value.as_bytes(), &moved));
+            }
+        }
+        // the root depends on the set alone and is kept in the store
+        let root: [u8; 32] = ma.root();
+        ma.add(b"value 3").unwrap();
+        assert_eq!(root, ma.root());
+        assert_eq!(BigUint::from_bytes_be(&root), *ma.store.get_state());
+        let reloaded: MerkleAccumulator<MemStore> = MerkleAccumulator::new(ma.store);
+        assert_eq!(root, reloaded.root());
+        assert_eq!(20, reloaded.len());
+
+        let mut ma: MerkleAccumulator<MemStore> = reloaded;
+        let proof: MerkleProof = ma.get_proof(b"value 7").unwrap();
+        ma.delete(b"value 7").unwrap();
+        assert!(!ma.contains(b"value 7"));
+        assert!(!verify_inclusion(&ma.root(), b"value 7", &proof));
+        assert_eq!(Err(AccumulatorError::NotAMember), ma.delete(b"value 7"));
+        for n in (0..20_u32).filter(|n| *n != 7) {
+            ma.delete(format!("value {}", n).as_bytes()).unwrap();
+        }
+        assert_eq!(empty_root(), ma.root());
+    }
+}
@@ -0,0 +1,128 @@
+//! Counters for monitoring an accumulator in production. [`SetAccumulator::with_metrics`]
+//! hands the accumulator a [`Metrics`], which it tells about every add and delete it
+//! commits, every witness it computes and how long each of its exponentiations took.
+//! [`Counters`] keeps running totals in memory; to report to another system, such as the
+//! `metrics` crate or a Prometheus registry, implement the trait over it instead.
+//!
+//! [`SetAccumulator::with_metrics`]: crate::SetAccumulator::with_metrics
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// What an accumulator reports as it works. Every method does nothing by default, so an
+/// implementation picks the ones it cares about.
+pub trait Metrics: Send + Sync {
+    /// A committed add, one per value of a batch.
+    fn record_add(&self) {}
+    /// A committed delete.
+    fn record_delete(&self) {}
+    /// A witness computed, for a proof or otherwise.
+    fn record_witness(&self) {}
+    /// A modular exponentiation over the modulus, and how long it took.
+    fn record_modpow(&self, _elapsed: Duration) {}
+    /// The number of members after a committed change.
+    fn record_member_count(&self, _count: usize) {}
+}
+
+/// Running totals of everything an accumulator reports, safe to read from another thread
+/// while it works.
+#[derive(Debug, Default)]
+pub struct Counters {
+    adds: AtomicU64,
+    deletes: AtomicU64,
+    witnesses: AtomicU64,
+    modpows: AtomicU64,
+    modpow_nanos: AtomicU64,
+    members: AtomicU64,
+}
+
+/// The totals of [`Counters`] at one moment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub adds: u64,
+    pub deletes: u64,
+    pub witnesses: u64,
+    pub modpows: u64,
+    pub modpow_time: Duration,
+    pub members: u64,
+}
+
+impl MetricsSnapshot {
+    /// The mean time of an exponentiation, zero before the first.
+    pub fn average_modpow(&self) -> Duration {
+        match self.modpows {
+            0 => Duration::ZERO,
+            modpows => Duration::from_nanos((self.modpow_time.as_nanos() / modpows as u128) as u64),
+        }
+    }
+}
+
+impl Counters {
+    pub fn new() -> Self {
+        Counters::default()
+    }
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            adds: self.adds.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+            witnesses: self.witnesses.load(Ordering::Relaxed),
+            modpows: self.modpows.load(Ordering::Relaxed),
+            modpow_time: Duration::from_nanos(self.modpow_nanos.load(Ordering::Relaxed)),
+            members: self.members.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Metrics for Counters {
+    fn record_add(&self) {
+        self.adds.fetch_add(1, Ordering::Relaxed);
+    }
+    fn record_delete(&self) {
+        self.deletes.fetch_add(1, Ordering::Relaxed);
+    }
+    fn record_witness(&self) {
+        self.witnesses.fetch_add(1, Ordering::Relaxed);
+    }
+    fn record_modpow(&self, elapsed: Duration) {
+        self.modpows.fetch_add(1, Ordering::Relaxed);
+        self.modpow_nanos.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+    fn record_member_count(&self, count: usize) {
+        self.members.store(count as u64, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use num_bigint::BigUint;
+    use crate::params::{PublicParameters, Trapdoor};
+    use crate::store::mem_store::MemStore;
+    use crate::SetAccumulator;
+
+    #[test]
+    fn test_counters() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let trapdoor: Trapdoor = Trapdoor::new(BigUint::from(61_u64), BigUint::from(53_u64));
+        let counters: Arc<Counters> = Arc::new(Counters::new());
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params).with_trapdoor(trapdoor)).with_metrics(counters.clone());
+        assert_eq!(Duration::ZERO, counters.snapshot().average_modpow());
+
+        sa.add(b"alice").unwrap();
+        sa.add_batch(&[b"bob", b"carol"]).unwrap();
+        // a rolled back add is not counted, though its exponentiation is
+        assert!(sa.transaction(|sa| {
+            sa.add(b"dave")?;
+            sa.delete(b"erin")
+        }).is_err());
+        sa.delete(b"alice").unwrap();
+        sa.get_witness(b"bob").unwrap();
+        assert_eq!(2, sa.get_all_witnesses().len());
+
+        let snapshot: MetricsSnapshot = counters.snapshot();
+        assert_eq!((3, 1, 3, 2), (snapshot.adds, snapshot.deletes, snapshot.witnesses, snapshot.members));
+        assert_eq!(5, snapshot.modpows);
+        assert!(snapshot.average_modpow() <= snapshot.modpow_time);
+    }
+}
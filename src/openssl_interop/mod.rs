@@ -0,0 +1,69 @@
+//! Conversions to and from OpenSSL's big numbers and RSA keys, enabled by the `openssl`
+//! feature, so parameters can be bootstrapped from key material that already exists.
+
+use openssl::bn::{BigNum, BigNumRef};
+use openssl::error::ErrorStack;
+use openssl::pkey::{HasPublic, Private};
+use openssl::rsa::{Rsa, RsaRef};
+
+use num_bigint::BigUint;
+
+pub fn biguint_to_bignum(value: &BigUint) -> Result<BigNum, ErrorStack> {
+    BigNum::from_slice(&value.to_bytes_be())
+}
+
+pub fn bignum_to_biguint(value: &BigNumRef) -> BigUint {
+    BigUint::from_bytes_be(&value.to_vec())
+}
+
+/// Big-endian bytes of `value` left padded with zeros to exactly `len` bytes, the layout
+/// of OpenSSL's `BN_bn2binpad`. `None` if the value does not fit.
+pub fn to_be_bytes_padded(value: &BigUint, len: usize) -> Option<Vec<u8>> {
+    let bytes: Vec<u8> = value.to_bytes_be();
+    if bytes.len() > len {
+        return None;
+    }
+    let mut padded: Vec<u8> = vec![0; len - bytes.len()];
+    padded.extend(bytes);
+    Some(padded)
+}
+
+/// The modulus n of an RSA key, public or private.
+pub fn modulus_from_rsa<T: HasPublic>(rsa: &RsaRef<T>) -> BigUint {
+    bignum_to_biguint(rsa.n())
+}
+
+/// The prime factors p and q of a private RSA key's modulus, if the key carries them.
+pub fn factors_from_rsa(rsa: &RsaRef<Private>) -> Option<(BigUint, BigUint)> {
+    Some((bignum_to_biguint(rsa.p()?), bignum_to_biguint(rsa.q()?)))
+}
+
+/// The modulus of a PEM encoded RSA public key (SubjectPublicKeyInfo or PKCS#1) or
+/// private key.
+pub fn modulus_from_pem(pem: &[u8]) -> Result<BigUint, ErrorStack> {
+    Rsa::public_key_from_pem(pem)
+        .or_else(|_| Rsa::public_key_from_pem_pkcs1(pem))
+        .map(|rsa| modulus_from_rsa(&rsa))
+        .or_else(|_| Rsa::private_key_from_pem(pem).map(|rsa| modulus_from_rsa(&rsa)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openssl_interop() {
+        let value: BigUint = BigUint::from(0x0102030405_u64);
+        assert_eq!(value, bignum_to_biguint(&biguint_to_bignum(&value).unwrap()));
+        assert_eq!(Some(vec![0, 0, 0, 1, 2, 3, 4, 5]), to_be_bytes_padded(&value, 8));
+        assert_eq!(None, to_be_bytes_padded(&value, 4));
+
+        let rsa: Rsa<Private> = Rsa::generate(1024).unwrap();
+        let modulus: BigUint = modulus_from_rsa(&rsa);
+        assert_eq!(1024, modulus.bits());
+        let (p, q): (BigUint, BigUint) = factors_from_rsa(&rsa).unwrap();
+        assert_eq!(modulus, p * q);
+        assert_eq!(modulus, modulus_from_pem(&rsa.public_key_to_pem().unwrap()).unwrap());
+        assert_eq!(modulus, modulus_from_pem(&rsa.private_key_to_pem().unwrap()).unwrap());
+    }
+}
@@ -0,0 +1,35 @@
+//! The parallel iterators the hash to prime searches and product trees run on: rayon's
+//! with the `parallel` feature, which is on by default, and plain iterators without it,
+//! for targets without threads.
+
+#[cfg(feature = "parallel")]
+pub(crate) use rayon::prelude::*;
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) trait IntoParallelRefIterator<'a> {
+    type Iter: Iterator;
+    fn par_iter(&'a self) -> Self::Iter;
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<'a, C: ?Sized + 'a> IntoParallelRefIterator<'a> for C
+where
+    &'a C: IntoIterator,
+{
+    type Iter = <&'a C as IntoIterator>::IntoIter;
+    fn par_iter(&'a self) -> Self::Iter {
+        self.into_iter()
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) trait ParallelSlice<T> {
+    fn par_chunks(&self, chunk_size: usize) -> core::slice::Chunks<'_, T>;
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<T> ParallelSlice<T> for [T] {
+    fn par_chunks(&self, chunk_size: usize) -> core::slice::Chunks<'_, T> {
+        self.chunks(chunk_size)
+    }
+}
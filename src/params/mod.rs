@@ -0,0 +1,665 @@
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::convert::TryInto;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+
+use digest::Digest;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::Zero;
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::arith;
+use crate::primality::is_prime_u64;
+use crate::secret::{wipe, SecretBigUint};
+
+#[cfg(feature = "std")]
+use crate::snapshot::{read_biguint, write_bytes};
+
+#[cfg(feature = "std")]
+const PARAMS_MAGIC: &[u8; 4] = b"tgpp";
+// version 1 predates configurable hash functions and always means SHA-256, version 2
+// predates configurable prime sizes and always means primes as wide as the digest, and
+// version 3 predates certified primes
+#[cfg(feature = "std")]
+const PARAMS_VERSION: u8 = 4;
+
+/// The RSA-2048 modulus from the RSA Factoring Challenge, in decimal. It was generated by
+/// RSA Laboratories in 1991 and the factorization was never published, so nobody is
+/// known to hold the trapdoor.
+pub const RSA2048: &str = "\
+    2519590847565789349402718324004839857142928212620403202777713783604366202070759555626401852588078440691\
+    8290641249515082189298559149176184502808489120072844992687392807287776735971418347270261896375014971824\
+    6911650776133798590957000973304597488084284017974291006424586918171951187461215151726546322822168699875\
+    4918242243363725908514186546204357679842338718477444792073993423658482382428119816381501067481045166037\
+    7306056201619676256133844143603833904414952634432190114657544454178424020924616515723350778707749817125\
+    772467962926386356373289912154831438167899885040445364023527381951378636564391212010397122822120720357";
+
+/// The generator used with [`RSA2048`]: 2 squared, so that it is a quadratic residue.
+pub const RSA2048_GENERATOR: u64 = 4;
+
+/// Presets that tie together every parameter choice affecting the security of the
+/// accumulator, so a strong modulus is never paired with weak prime representatives.
+///
+/// Modulus sizes follow NIST SP 800-57 (2048 bits for 112-bit security, 3072 bits for
+/// 128-bit security). Prime representatives, and the digests they are found from, are
+/// twice as wide as the level, so finding two values that map to the same prime takes
+/// 2^level work by the birthday bound; [`SetAccumulator::with_security_level`](crate::SetAccumulator::with_security_level)
+/// refuses stores with narrower ones, and [`SecurityLevel::prime_mapping`] sets them up.
+///
+/// Candidates are tested with Baillie-PSW and then a few Miller-Rabin rounds with random
+/// bases. Candidates come from hashes of values an adversary may grind, so a fixed set of
+/// bases would not do, but no composite is known to pass Baillie-PSW, and the rounds on
+/// top let one that did through with probability at most 4^-5. This is what Albrecht et
+/// al., "Prime and Prejudice" (CCS 2018), recommend for candidates that may be chosen
+/// adversarially, at a fraction of the cost of the 56 or 64 rounds it takes Miller-Rabin
+/// alone to bound the error by 2^-level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SecurityLevel {
+    Bits112,
+    #[default]
+    Bits128,
+}
+
+impl SecurityLevel {
+    pub fn modulus_bits(&self) -> u64 {
+        match self {
+            SecurityLevel::Bits112 => 2048,
+            SecurityLevel::Bits128 => 3072,
+        }
+    }
+    /// The width primes and the digests they come from need at least.
+    pub fn prime_bits(&self) -> u64 {
+        match self {
+            SecurityLevel::Bits112 => 224,
+            SecurityLevel::Bits128 => 256,
+        }
+    }
+    /// Miller-Rabin rounds on random candidates, or on top of Baillie-PSW; the same at
+    /// every level, see above.
+    pub fn miller_rabin_rounds(&self) -> usize {
+        5
+    }
+    /// The primality testing the level calls for: Baillie-PSW and its Miller-Rabin rounds.
+    pub fn primality(&self) -> PrimalityConfig {
+        PrimalityConfig::new(self.miller_rabin_rounds()).with_baillie_psw()
+    }
+    /// The prime mapping an accumulator set up at this level uses: SHA-256 digests
+    /// stretched or truncated to primes of exactly [`SecurityLevel::prime_bits`] bits.
+    pub fn prime_mapping(&self) -> PrimeMapping {
+        PrimeMapping::new(HashFunction::Sha256, Some(self.prime_bits()))
+    }
+}
+
+/// How thoroughly candidates are tested before hash-to-prime takes them as primes. Each
+/// Miller-Rabin round with a random base lets a composite through with probability at
+/// most 1/4. Baillie-PSW, a base 2 Miller-Rabin round followed by a strong Lucas test, is
+/// passed by no known composite, so adding it buys confidence that does not rest on the
+/// rounds alone. Barring a composite slipping through, the prime a value maps to is the
+/// same whatever is chosen here, so this only trades search time against certainty and
+/// can differ between the accumulator and its verifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrimalityConfig {
+    pub miller_rabin_rounds: usize,
+    pub baillie_psw: bool,
+}
+
+impl PrimalityConfig {
+    pub fn new(miller_rabin_rounds: usize) -> Self {
+        PrimalityConfig { miller_rabin_rounds, baillie_psw: false }
+    }
+    /// Also runs Baillie-PSW on every candidate, ahead of the Miller-Rabin rounds.
+    pub fn with_baillie_psw(mut self) -> Self {
+        self.baillie_psw = true;
+        self
+    }
+}
+
+impl Default for PrimalityConfig {
+    fn default() -> Self {
+        SecurityLevel::default().primality()
+    }
+}
+
+/// How the nonce mixed into a value before it is hashed to a prime is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonceMode {
+    /// A fresh random 256 bit nonce per add, so the same value gets a different prime
+    /// every time it is added and primes reveal nothing about values.
+    #[default]
+    Random,
+    /// No nonce at all: the prime is found by a next-prime search from the hash of the
+    /// value alone, so anyone adding the same value gets the same prime. Values must be
+    /// unpredictable if the primes are not to give them away.
+    Deterministic,
+    /// The nonce is HMAC-SHA256 of the value under a key, so whoever holds the key gets
+    /// the same prime for a value every time, on any machine, while to everyone else the
+    /// nonces look as random as drawn ones.
+    Keyed(NonceKey),
+}
+
+/// What adding a value that is already a member does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Refuses it with [`AccumulatorError::AlreadyAMember`](crate::error::AccumulatorError::AlreadyAMember).
+    #[default]
+    ErrorOnDuplicate,
+    /// Does nothing, so adding a value is safe to retry.
+    Idempotent,
+    /// Accumulates it once more, under a prime of its own, so it takes as many deletes to
+    /// remove as it took adds. Not for deterministic or keyed nonces, which map a value to
+    /// one prime and so refuse it with
+    /// [`AccumulatorError::DuplicatePrime`](crate::error::AccumulatorError::DuplicatePrime).
+    Multiset,
+}
+
+/// The key of [`NonceMode::Keyed`]. It is left out of debug output.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct NonceKey([u8; 32]);
+
+impl NonceKey {
+    pub fn new(key: [u8; 32]) -> Self {
+        NonceKey(key)
+    }
+    /// The nonce of `value` under this key.
+    pub fn nonce(&self, value: &[u8]) -> Vec<u8> {
+        hmac_sha256(&self.0, value).to_vec()
+    }
+}
+
+impl fmt::Debug for NonceKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NonceKey(..)")
+    }
+}
+
+// HMAC as in RFC 2104, over SHA-256 with its 64 byte block
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block: [u8; 64] = [0; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&sha2::Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| -> [u8; 64] {
+        let mut padded: [u8; 64] = block;
+        padded.iter_mut().for_each(|b| *b ^= byte);
+        padded
+    };
+    let inner: [u8; 32] = sha2::Sha256::new().chain_update(pad(0x36)).chain_update(message).finalize().into();
+    let outer: [u8; 32] = sha2::Sha256::new().chain_update(pad(0x5c)).chain_update(inner).finalize().into();
+    block.zeroize();
+    outer
+}
+
+/// The hash function values and nonces are mapped to primes with. It is part of the
+/// public parameters, since a verifier has to use the same one as the accumulator. The
+/// primes are as wide as the digest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+pub enum HashFunction {
+    #[default]
+    Sha256,
+    Sha512,
+    Sha3_256,
+    Blake3,
+}
+
+/// Hashes `bytes` with any RustCrypto [`Digest`].
+pub fn digest_with<D: Digest>(bytes: &[u8]) -> Vec<u8> {
+    D::digest(bytes).to_vec()
+}
+
+impl HashFunction {
+    pub fn digest(&self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            HashFunction::Sha256 => digest_with::<sha2::Sha256>(bytes),
+            HashFunction::Sha512 => digest_with::<sha2::Sha512>(bytes),
+            HashFunction::Sha3_256 => digest_with::<sha3::Sha3_256>(bytes),
+            HashFunction::Blake3 => digest_with::<blake3::Hasher>(bytes),
+        }
+    }
+    /// The width of the digest in bits.
+    pub fn output_bits(&self) -> u64 {
+        match self {
+            HashFunction::Sha512 => 512,
+            HashFunction::Sha256 | HashFunction::Sha3_256 | HashFunction::Blake3 => 256,
+        }
+    }
+    /// The byte the hash function is recorded as in encoded parameters.
+    pub fn id(&self) -> u8 {
+        match self {
+            HashFunction::Sha256 => 1,
+            HashFunction::Sha512 => 2,
+            HashFunction::Sha3_256 => 3,
+            HashFunction::Blake3 => 4,
+        }
+    }
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(HashFunction::Sha256),
+            2 => Some(HashFunction::Sha512),
+            3 => Some(HashFunction::Sha3_256),
+            4 => Some(HashFunction::Blake3),
+            _ => None,
+        }
+    }
+}
+
+/// Everything that decides which prime a value and nonce map to, and so has to agree
+/// between whoever adds a value and whoever verifies it.
+///
+/// By default the search for a prime starts at the digest itself, so primes are about as
+/// wide as the digest. With `prime_bits` set, the digest is stretched or truncated to
+/// that many bits and the top bit is set, so every prime has exactly that width: 128 bit
+/// primes verify faster, 384 bit primes make collisions between them harder to find.
+///
+/// With `certified` set, primes are instead built with a Pocklington certificate, see
+/// [`pocklington`](crate::pocklington), and land within a bit of the same width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PrimeMapping {
+    pub hash_function: HashFunction,
+    pub prime_bits: Option<u64>,
+    pub certified: bool,
+}
+
+impl PrimeMapping {
+    pub fn new(hash_function: HashFunction, prime_bits: Option<u64>) -> Self {
+        PrimeMapping { hash_function, prime_bits, certified: false }
+    }
+    pub fn with_certificates(mut self) -> Self {
+        self.certified = true;
+        self
+    }
+    /// The width primes are aimed at.
+    pub fn width(&self) -> u64 {
+        self.prime_bits.unwrap_or_else(|| self.hash_function.output_bits())
+    }
+    // the hash function id, the prime size as a big-endian u64 (zero for the digest
+    // width) and a flags byte with bit 0 set for certified primes
+    #[cfg(feature = "std")]
+    pub(crate) fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[self.hash_function.id()])?;
+        writer.write_all(&self.prime_bits.unwrap_or(0).to_be_bytes())?;
+        writer.write_all(&[self.certified as u8])
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut bytes: [u8; 10] = [0; 10];
+        reader.read_exact(&mut bytes)?;
+        let hash_function: HashFunction = HashFunction::from_id(bytes[0])
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unknown hash function"))?;
+        let prime_bits: Option<u64> = Some(u64::from_be_bytes(bytes[1..9].try_into().unwrap())).filter(|bits| *bits != 0);
+        if bytes[9] > 1 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown prime mapping flags"));
+        }
+        Ok(PrimeMapping { hash_function, prime_bits, certified: bytes[9] == 1 })
+    }
+    /// Where the search for the prime of `bytes` starts.
+    pub(crate) fn candidate(&self, bytes: &[u8]) -> BigUint {
+        let bits: u64 = match self.prime_bits {
+            None => return BigUint::from_bytes_be(&self.hash_function.digest(bytes)),
+            Some(bits) => bits.max(2),
+        };
+        // stretch the digest with a counter when one is not wide enough
+        let mut stretched: Vec<u8> = self.hash_function.digest(bytes);
+        let mut counter: u64 = 1;
+        while (stretched.len() as u64) * 8 < bits {
+            stretched.extend(self.hash_function.digest(&[&counter.to_be_bytes(), bytes].concat()));
+            counter += 1;
+        }
+        let mut candidate: BigUint = BigUint::from_bytes_be(&stretched) >> ((stretched.len() as u64) * 8 - bits);
+        candidate.set_bit(bits - 1, true);
+        candidate
+    }
+}
+
+/// The factorization of the modulus. Whoever holds it can take roots in the group,
+/// which lets a member be removed with a single exponentiation, but it also lets them
+/// forge witnesses, so it must never leave the accumulator manager.
+///
+/// Both factors are wiped when the trapdoor is dropped, and its `Debug` output leaves
+/// them out.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Trapdoor {
+    pub p: BigUint,
+    pub q: BigUint,
+}
+
+impl Trapdoor {
+    pub fn new(p: BigUint, q: BigUint) -> Self {
+        Trapdoor { p, q }
+    }
+    pub fn modulus(&self) -> BigUint {
+        &self.p * &self.q
+    }
+    /// Euler's totient of the modulus, (p - 1)(q - 1), as secret as the factors are.
+    pub fn phi(&self) -> SecretBigUint {
+        let p_minus_one: SecretBigUint = SecretBigUint::new(&self.p - 1_u64);
+        let q_minus_one: SecretBigUint = SecretBigUint::new(&self.q - 1_u64);
+        SecretBigUint::new(&*p_minus_one * &*q_minus_one)
+    }
+    /// Carmichael's function of the modulus, lcm(p - 1, q - 1), the smallest exponent
+    /// every unit is a root of, so exponents can be reduced by it instead of φ(n).
+    pub fn lambda(&self) -> SecretBigUint {
+        let p_minus_one: SecretBigUint = SecretBigUint::new(&self.p - 1_u64);
+        let q_minus_one: SecretBigUint = SecretBigUint::new(&self.q - 1_u64);
+        SecretBigUint::new(p_minus_one.lcm(&q_minus_one))
+    }
+    /// `base^exponent mod n` from the factors: one exponentiation mod p and one mod q, each
+    /// by the exponent reduced mod its own group order, recombined with Garner's formula,
+    /// which takes about a quarter of the work of one mod n. A fault in either half would
+    /// give out a result that factors n (Boneh, DeMillo and Lipton, 1997), so after Shamir
+    /// each half is taken mod p·r and q·r for a fresh random 64 bit prime r, the two have
+    /// to agree mod r, and the recombined result has to reduce to both halves. When a
+    /// check fails the exponentiation is done again over n, so no faulty result leaves.
+    #[cfg(feature = "std")]
+    pub fn modpow(&self, base: &BigUint, exponent: &BigUint) -> BigUint {
+        self.modpow_with_rng(base, exponent, &mut rand::thread_rng())
+    }
+    /// Like [`Trapdoor::modpow`], drawing r from `rng`.
+    pub fn modpow_with_rng<R: Rng + ?Sized>(&self, base: &BigUint, exponent: &BigUint, rng: &mut R) -> BigUint {
+        let r: u64 = loop {
+            let candidate: u64 = rng.gen::<u64>() | (1 << 63) | 1;
+            if is_prime_u64(candidate) {
+                break candidate;
+            }
+        };
+        // x^(e mod order) is x^e for every x, units or not, as long as the reduced exponent
+        // stays above zero, and the moduli are square free
+        let reduce = |order: &BigUint| -> SecretBigUint {
+            if exponent.is_zero() {
+                return SecretBigUint::new(BigUint::zero());
+            }
+            SecretBigUint::new((exponent - 1_u64) % order + 1_u64)
+        };
+        let half = |prime: &BigUint| -> SecretBigUint {
+            let order: SecretBigUint = SecretBigUint::new((prime - 1_u64) * (r - 1));
+            arith::modpow_secret(base, &reduce(&order), &(prime * r)).into()
+        };
+        let (p_half, q_half): (SecretBigUint, SecretBigUint) = (half(&self.p), half(&self.q));
+        let p_part: SecretBigUint = SecretBigUint::new(&*p_half % &self.p);
+        let q_part: SecretBigUint = SecretBigUint::new(&*q_half % &self.q);
+        let q_inverse: SecretBigUint = self.q.modinv(&self.p).expect("the factors are distinct primes").into();
+        let difference: SecretBigUint = SecretBigUint::new((&*p_part + &self.p - &*q_part % &self.p) % &self.p);
+        let result: BigUint = &*q_part + &self.q * ((&*difference * &*q_inverse) % &self.p);
+        if &*p_half % r != &*q_half % r || &result % &self.p != *p_part || &result % &self.q != *q_part {
+            return arith::modpow_secret(base, &SecretBigUint::new(exponent.clone()), &self.modulus());
+        }
+        result
+    }
+}
+
+impl Zeroize for Trapdoor {
+    fn zeroize(&mut self) {
+        wipe(&mut self.p);
+        wipe(&mut self.q);
+    }
+}
+
+impl Drop for Trapdoor {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Trapdoor {}
+
+impl fmt::Debug for Trapdoor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Trapdoor(..)")
+    }
+}
+
+/// The public description of an accumulator: the RSA modulus, the generator every
+/// state is a power of and how values are mapped to primes. The generator is assumed
+/// coprime to the modulus, which a random one is but for a negligible chance, and one
+/// that is not gives away a factor of the modulus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PublicParameters {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::biguint"))]
+    pub modulus: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::biguint"))]
+    pub generator: BigUint,
+    // the defaults keep parameters serialized before these were configurable readable
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hash_function: HashFunction,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub prime_bits: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub certified_primes: bool,
+}
+
+impl PublicParameters {
+    pub fn new(modulus: BigUint, generator: BigUint) -> Self {
+        PublicParameters { modulus, generator, hash_function: HashFunction::default(), prime_bits: None, certified_primes: false }
+    }
+    pub fn with_hash_function(mut self, hash_function: HashFunction) -> Self {
+        self.hash_function = hash_function;
+        self
+    }
+    /// Maps values to primes of exactly `prime_bits` bits, see [`PrimeMapping`].
+    pub fn with_prime_bits(mut self, prime_bits: u64) -> Self {
+        self.prime_bits = Some(prime_bits);
+        self
+    }
+    pub fn with_prime_mapping(mut self, prime_mapping: PrimeMapping) -> Self {
+        self.hash_function = prime_mapping.hash_function;
+        self.prime_bits = prime_mapping.prime_bits;
+        self.certified_primes = prime_mapping.certified;
+        self
+    }
+    pub fn prime_mapping(&self) -> PrimeMapping {
+        PrimeMapping { hash_function: self.hash_function, prime_bits: self.prime_bits, certified: self.certified_primes }
+    }
+    /// The RSA-2048 challenge modulus with [`RSA2048_GENERATOR`], for deployments that
+    /// want a modulus nobody generated for them.
+    pub fn rsa2048() -> Self {
+        let modulus: BigUint = RSA2048.parse().unwrap();
+        PublicParameters::new(modulus, BigUint::from(RSA2048_GENERATOR))
+    }
+    /// The state of the accumulator over the empty set, i.e. the generator raised to the
+    /// empty product of primes.
+    pub fn genesis_state(&self) -> BigUint {
+        self.generator.clone()
+    }
+    /// SHA-256 over the length-prefixed modulus and generator, for pinning a set of
+    /// parameters without carrying them around.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher: sha2::Sha256 = sha2::Sha256::new();
+        hasher.update(b"tangerine/params/v1");
+        for field in [self.modulus.to_bytes_be(), self.generator.to_bytes_be()].iter() {
+            hasher.update((field.len() as u64).to_be_bytes());
+            hasher.update(field);
+        }
+        // the prime mapping is only appended when it differs from the default, so digests
+        // pinned before it could be configured stay valid
+        if self.prime_mapping() != PrimeMapping::default() {
+            hasher.update([self.hash_function.id()]);
+        }
+        if let Some(prime_bits) = self.prime_bits {
+            hasher.update(prime_bits.to_be_bytes());
+        }
+        if self.certified_primes {
+            hasher.update([1]);
+        }
+        hasher.finalize().into()
+    }
+    /// Encodes the parameters as the magic bytes `tgpp`, a version byte, the hash function
+    /// id, the prime size as a big-endian u64 (zero for the digest width), a flags byte
+    /// (bit 0 for certified primes) and the modulus and generator as length-prefixed
+    /// big-endian integers.
+    #[cfg(feature = "std")]
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(PARAMS_MAGIC)?;
+        writer.write_all(&[PARAMS_VERSION])?;
+        self.prime_mapping().write_to(&mut writer)?;
+        write_bytes(&mut writer, &self.modulus.to_bytes_be())?;
+        write_bytes(&mut writer, &self.generator.to_bytes_be())?;
+        writer.flush()
+    }
+    #[cfg(feature = "std")]
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut magic: [u8; 5] = [0; 5];
+        reader.read_exact(&mut magic)?;
+        if &magic[..4] != PARAMS_MAGIC || magic[4] == 0 || magic[4] > PARAMS_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a supported tangerine parameters file"));
+        }
+        let prime_mapping: PrimeMapping = if magic[4] == PARAMS_VERSION {
+            PrimeMapping::read_from(&mut reader)?
+        } else {
+            let mut hash_function: HashFunction = HashFunction::Sha256;
+            if magic[4] >= 2 {
+                let mut id: [u8; 1] = [0; 1];
+                reader.read_exact(&mut id)?;
+                hash_function = HashFunction::from_id(id[0])
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unknown hash function"))?;
+            }
+            let mut prime_bits: Option<u64> = None;
+            if magic[4] >= 3 {
+                let mut bits: [u8; 8] = [0; 8];
+                reader.read_exact(&mut bits)?;
+                prime_bits = Some(u64::from_be_bytes(bits)).filter(|bits| *bits != 0);
+            }
+            PrimeMapping::new(hash_function, prime_bits)
+        };
+        let modulus: BigUint = read_biguint(&mut reader)?;
+        let generator: BigUint = read_biguint(&mut reader)?;
+        Ok(PublicParameters::new(modulus, generator).with_prime_mapping(prime_mapping))
+    }
+    #[cfg(feature = "std")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        self.write_to(&mut bytes).unwrap();
+        bytes
+    }
+    #[cfg(feature = "std")]
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        PublicParameters::read_from(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_hmac_sha256() {
+        let hex = |digits: &str| -> Vec<u8> { (0..digits.len()).step_by(2).map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap()).collect() };
+        // RFC 4231, test cases 2 and 6
+        let mac: [u8; 32] = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"), mac.to_vec());
+        let mac: [u8; 32] = hmac_sha256(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First");
+        assert_eq!(hex("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"), mac.to_vec());
+        assert_eq!("NonceKey(..)", format!("{:?}", NonceKey::new([7; 32])));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_public_parameters_encoding() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let bytes: Vec<u8> = params.to_bytes();
+        assert_eq!(params, PublicParameters::from_bytes(&bytes).unwrap());
+        assert!(PublicParameters::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        // the digest pins both the modulus and the generator
+        assert_ne!(params.digest(), PublicParameters::new(BigUint::from(3233_u64), BigUint::from(43_u64)).digest());
+        // and the hash function, which survives the round trip too
+        let blake: PublicParameters = params.clone().with_hash_function(HashFunction::Blake3);
+        assert_ne!(params.digest(), blake.digest());
+        assert_eq!(blake, PublicParameters::from_bytes(&blake.to_bytes()).unwrap());
+        // version 1 files carry no hash function and mean SHA-256
+        let mut version_1: Vec<u8> = b"tgpp\x01".to_vec();
+        version_1.extend_from_slice(&bytes[15..]);
+        assert_eq!(params, PublicParameters::from_bytes(&version_1).unwrap());
+        let mut unknown: Vec<u8> = bytes.clone();
+        unknown[5] = 0xff;
+        assert!(PublicParameters::from_bytes(&unknown).is_err());
+        // as does the prime size
+        let narrow: PublicParameters = params.clone().with_prime_bits(128);
+        assert_ne!(params.digest(), narrow.digest());
+        assert_ne!(blake.digest(), blake.clone().with_prime_bits(128).digest());
+        assert_eq!(narrow, PublicParameters::from_bytes(&narrow.to_bytes()).unwrap());
+        // and whether primes are certified
+        let certified: PublicParameters = params.clone().with_prime_mapping(params.prime_mapping().with_certificates());
+        assert_ne!(params.digest(), certified.digest());
+        assert_eq!(certified, PublicParameters::from_bytes(&certified.to_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_prime_mapping_candidate() {
+        // by default the candidate is the digest itself
+        let mapping: PrimeMapping = PrimeMapping::default();
+        assert_eq!(BigUint::from_bytes_be(&HashFunction::Sha256.digest(b"abc")), mapping.candidate(b"abc"));
+        // and otherwise exactly as wide as asked for, narrower or wider than the digest
+        for bits in [128_u64, 256, 384, 1000].iter() {
+            let candidate: BigUint = PrimeMapping::new(HashFunction::Sha256, Some(*bits)).candidate(b"abc");
+            assert_eq!(*bits, candidate.bits());
+        }
+        assert_ne!(
+            PrimeMapping::new(HashFunction::Sha256, Some(384)).candidate(b"abc"),
+            PrimeMapping::new(HashFunction::Sha256, Some(384)).candidate(b"abd")
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_hash_functions() {
+        let functions: [HashFunction; 4] = [HashFunction::Sha256, HashFunction::Sha512, HashFunction::Sha3_256, HashFunction::Blake3];
+        for function in functions.iter() {
+            assert_eq!(Some(*function), HashFunction::from_id(function.id()));
+        }
+        // SHA-256 agrees with the hash the rest of the crate is built on
+        assert_eq!(crypto_hash::digest(crypto_hash::Algorithm::SHA256, b"abc"), HashFunction::Sha256.digest(b"abc"));
+        assert_eq!(64, HashFunction::Sha512.digest(b"abc").len());
+        assert_eq!(blake3::hash(b"abc").as_bytes().to_vec(), HashFunction::Blake3.digest(b"abc"));
+    }
+
+    #[test]
+    fn test_trapdoor() {
+        let mut trapdoor: Trapdoor = Trapdoor::new(BigUint::from(61_u64), BigUint::from(53_u64));
+        assert_eq!(BigUint::from(3233_u64), trapdoor.modulus());
+        assert_eq!(BigUint::from(3120_u64), *trapdoor.phi());
+        assert_eq!(BigUint::from(780_u64), *trapdoor.lambda());
+        // the halves recombine to the exponentiation over n, for bases sharing a factor too
+        let mut rng: StdRng = StdRng::seed_from_u64(42);
+        for base in [0_u64, 1, 42, 61, 53 * 7, 3232].iter() {
+            for exponent in [0_u64, 1, 780, 3121, 1_u64 << 40].iter() {
+                let (base, exponent): (BigUint, BigUint) = (BigUint::from(*base), BigUint::from(*exponent));
+                assert_eq!(base.modpow(&exponent, &trapdoor.modulus()), trapdoor.modpow_with_rng(&base, &exponent, &mut rng));
+            }
+        }
+        assert_eq!("Trapdoor(..)", format!("{:?}", trapdoor));
+        trapdoor.zeroize();
+        assert_eq!((BigUint::from(0_u64), BigUint::from(0_u64)), (trapdoor.p.clone(), trapdoor.q.clone()));
+    }
+
+    #[test]
+    fn test_rsa2048() {
+        let params: PublicParameters = PublicParameters::rsa2048();
+        assert_eq!(2048, params.modulus.bits());
+        assert_eq!(617, params.modulus.to_string().len());
+        assert!(params.modulus.to_string().ends_with("20720357"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64))
+            .with_hash_function(HashFunction::Sha3_256);
+        let json: String = serde_json::to_string(&params).unwrap();
+        assert_eq!(r#"{"modulus":"0ca1","generator":"2a","hash_function":"sha3_256","prime_bits":null,"certified_primes":false}"#, json);
+        assert_eq!(params, serde_json::from_str(&json).unwrap());
+        // fields that were added later fall back to their defaults
+        let old: PublicParameters = serde_json::from_str(r#"{"modulus":"0ca1","generator":"2a"}"#).unwrap();
+        assert_eq!(PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64)), old);
+    }
+}
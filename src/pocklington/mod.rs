@@ -0,0 +1,252 @@
+//! Primes that come with a Pocklington certificate, so that a verifier can check both
+//! that a number is prime and that it is the prime of a given input with a handful of
+//! modular exponentiations, instead of running a probabilistic primality test.
+//!
+//! An arbitrary prime cannot be certified this way without factoring p - 1, so certified
+//! primes are built rather than searched for. A chain starts at a small prime p0 below
+//! 2^32, checked by trial division, and each step picks an even h < p_i derived from the
+//! input and sets p_{i+1} = h p_i + 1. Pocklington's criterion says p_{i+1} is prime if
+//! p_i is a prime above its square root and some a has a^(p_{i+1} - 1) = 1 and
+//! gcd(a^h - 1, p_{i+1}) = 1. Every step roughly doubles the width, so a 256 bit prime
+//! takes four steps.
+
+use alloc::vec::Vec;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::One;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::is_prime;
+use crate::params::{HashFunction, PrimeMapping, SecurityLevel};
+
+// the width of the prime a chain starts from
+const BASE_BITS: u64 = 32;
+// how far past the derived starting point the prime of a step may lie
+const SEARCH_BOUND: u64 = 1 << 16;
+// the narrowest prime worth certifying with a chain
+pub const MIN_CERTIFIED_BITS: u64 = 2 * BASE_BITS;
+
+/// One step of a chain, p_{i+1} = h p_i + 1 with witness `a`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CertificateStep {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::biguint"))]
+    pub h: BigUint,
+    pub a: u64,
+}
+
+/// The chain of steps from a small prime to the certified one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PrimeCertificate {
+    pub base: u32,
+    pub steps: Vec<CertificateStep>,
+}
+
+fn is_prime_by_trial_division(n: u64) -> bool {
+    n >= 2 && (2..).take_while(|d: &u64| d * d <= n).all(|d| !n.is_multiple_of(d))
+}
+
+// the top `bits` bits of a stretched digest of the input for step `index`, top bit set
+fn derive(hash_function: HashFunction, input: &[u8], index: u64, bits: u64) -> BigUint {
+    let mut stretched: Vec<u8> = Vec::new();
+    let mut counter: u64 = 0;
+    while (stretched.len() as u64) * 8 < bits {
+        stretched.extend(hash_function.digest(&[b"tangerine/pocklington/v1".as_ref(), &index.to_be_bytes(), &counter.to_be_bytes(), input].concat()));
+        counter += 1;
+    }
+    let mut derived: BigUint = BigUint::from_bytes_be(&stretched) >> ((stretched.len() as u64) * 8 - bits);
+    derived.set_bit(bits - 1, true);
+    derived
+}
+
+fn base_start(hash_function: HashFunction, input: &[u8]) -> u64 {
+    derive(hash_function, input, 0, BASE_BITS).iter_u64_digits().next().unwrap() | 1
+}
+
+// the bottom of the BASE_BITS wide range every base lies in
+const BASE_LOW: u64 = 1 << (BASE_BITS - 1);
+
+// the first prime from `start` on, the search wrapping around from the top of the range
+// to its bottom rather than leave it, so the base always fits the u32 of a certificate
+fn find_base(start: u64) -> u64 {
+    let mut base: u64 = start;
+    while !is_prime_by_trial_division(base) {
+        base += 2;
+        if base >> BASE_BITS != 0 {
+            base -= BASE_LOW;
+        }
+    }
+    base
+}
+
+// whether `base` lies in the window the search from `start` covers, counting the way it wraps
+fn base_in_window(start: u64, base: u64) -> bool {
+    base >= BASE_LOW && base >> BASE_BITS == 0 && (base + BASE_LOW - start) % BASE_LOW / 2 < SEARCH_BOUND
+}
+
+// h below this width leaves too little room to search for a prime without overshooting
+const MIN_H_BITS: u64 = 16;
+
+// the width the step after a prime of `bits` bits aims for: never past the square, and
+// short of the target by enough that the last step still has room for its search
+fn next_target(bits: u64, target_bits: u64) -> u64 {
+    if target_bits.saturating_sub(bits) <= bits - 2 {
+        target_bits
+    } else {
+        (2 * bits - 2).min(target_bits - MIN_H_BITS)
+    }
+}
+
+// where the search for h starts: even, and at least 2
+fn step_start(hash_function: HashFunction, input: &[u8], index: u64, h_bits: u64) -> BigUint {
+    let mut start: BigUint = derive(hash_function, input, index, h_bits.max(2));
+    start.set_bit(0, false);
+    start
+}
+
+// the smallest a that passes Pocklington's criterion for n = h p + 1
+fn find_witness(n: &BigUint, h: &BigUint) -> Option<u64> {
+    let n_minus_one: BigUint = n - 1_u64;
+    (2_u64..256).find(|a| check_witness(n, &n_minus_one, h, *a))
+}
+
+fn check_witness(n: &BigUint, n_minus_one: &BigUint, h: &BigUint, a: u64) -> bool {
+    let a: BigUint = BigUint::from(a);
+    if !a.modpow(n_minus_one, n).is_one() {
+        return false;
+    }
+    // a^h - 1 mod n, kept from underflowing
+    let a_to_h_minus_one: BigUint = (a.modpow(h, n) + n_minus_one) % n;
+    a_to_h_minus_one.gcd(n).is_one()
+}
+
+/// Builds the certified prime of about `target_bits` bits, give or take one (at least
+/// [`MIN_CERTIFIED_BITS`]) for `input`, together with its certificate.
+pub fn certified_prime(input: &[u8], target_bits: u64, hash_function: HashFunction) -> (BigUint, PrimeCertificate) {
+    let target_bits: u64 = target_bits.max(MIN_CERTIFIED_BITS);
+    let rounds: usize = SecurityLevel::default().miller_rabin_rounds();
+    let base: u64 = find_base(base_start(hash_function, input));
+    let mut prime: BigUint = BigUint::from(base);
+    let mut steps: Vec<CertificateStep> = Vec::new();
+    let mut target: u64 = BASE_BITS;
+    while target < target_bits {
+        target = next_target(prime.bits(), target_bits);
+        let mut h: BigUint = step_start(hash_function, input, steps.len() as u64 + 1, target - prime.bits());
+        loop {
+            let candidate: BigUint = &h * &prime + 1_u64;
+            // is_prime only screens candidates, the certificate is what proves primality
+            if is_prime(&candidate, rounds) {
+                if let Some(a) = find_witness(&candidate, &h) {
+                    steps.push(CertificateStep { h, a });
+                    prime = candidate;
+                    break;
+                }
+            }
+            h += 2_u64;
+        }
+    }
+    (prime, PrimeCertificate { base: base as u32, steps })
+}
+
+/// Checks `certificate` against `input` and returns the prime it certifies. The chain is
+/// bound to the input but not unique: any base and any h inside their search windows
+/// that pass are accepted, not only the first ones [`certified_prime`] settles on, so
+/// the prime returned can differ from the one it builds for the same input and width.
+pub fn verify_certificate(input: &[u8], target_bits: u64, hash_function: HashFunction, certificate: &PrimeCertificate) -> Option<BigUint> {
+    let target_bits: u64 = target_bits.max(MIN_CERTIFIED_BITS);
+    // the base is bound to the input and prime by trial division
+    let base: u64 = u64::from(certificate.base);
+    if !base_in_window(base_start(hash_function, input), base) || !is_prime_by_trial_division(base) {
+        return None;
+    }
+    let mut prime: BigUint = BigUint::from(base);
+    let mut target: u64 = BASE_BITS;
+    for (index, step) in certificate.steps.iter().enumerate() {
+        if target >= target_bits {
+            // steps past the end of the chain
+            return None;
+        }
+        target = next_target(prime.bits(), target_bits);
+        // h is bound to the input, even, and below the previous prime, which therefore
+        // exceeds the square root of the next one
+        let start: BigUint = step_start(hash_function, input, index as u64 + 1, target - prime.bits());
+        if step.h < start || step.h.is_odd() || (&step.h - &start) >> 1 >= BigUint::from(SEARCH_BOUND) || step.h >= prime {
+            return None;
+        }
+        let n: BigUint = &step.h * &prime + 1_u64;
+        if !check_witness(&n, &(&n - 1_u64), &step.h, step.a) {
+            return None;
+        }
+        prime = n;
+    }
+    if target < target_bits {
+        return None;
+    }
+    Some(prime)
+}
+
+/// The certified prime of `value` added with `nonce`, for a mapping with certificates.
+pub fn certify(value: &[u8], nonce: &[u8], prime_mapping: PrimeMapping) -> (BigUint, PrimeCertificate) {
+    certified_prime(&[value, nonce].concat(), prime_mapping.width(), prime_mapping.hash_function)
+}
+
+/// Checks that `certificate` certifies the prime of `value` added with `nonce`.
+pub fn verify_for(value: &[u8], nonce: &[u8], prime_mapping: PrimeMapping, certificate: &PrimeCertificate) -> Option<BigUint> {
+    verify_certificate(&[value, nonce].concat(), prime_mapping.width(), prime_mapping.hash_function, certificate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_certified_prime() {
+        for bits in [64_u64, 256].iter() {
+            let (prime, certificate): (BigUint, PrimeCertificate) = certified_prime(b"alice", *bits, HashFunction::Sha256);
+            assert!(is_prime(&prime, 20));
+            // the search for h can carry the width a bit either way
+            assert!(prime.bits() + 2 >= *bits && prime.bits() <= *bits + 1, "{} bits for {}", prime.bits(), bits);
+            assert_eq!(Some(prime.clone()), verify_certificate(b"alice", *bits, HashFunction::Sha256, &certificate));
+            // building the prime again gives the same one
+            assert_eq!(prime, certified_prime(b"alice", *bits, HashFunction::Sha256).0);
+            // a certificate vouches for its own input and width only
+            assert_eq!(None, verify_certificate(b"bob", *bits, HashFunction::Sha256, &certificate));
+            assert_eq!(None, verify_certificate(b"alice", *bits + 64, HashFunction::Sha256, &certificate));
+        }
+
+        let (_, certificate): (BigUint, PrimeCertificate) = certified_prime(b"alice", 256, HashFunction::Sha256);
+        // a tampered step no longer gives a prime
+        let mut forged: PrimeCertificate = certificate.clone();
+        forged.steps[1].h += 2_u64;
+        assert_eq!(None, verify_certificate(b"alice", 256, HashFunction::Sha256, &forged));
+        // nor does one that stops short
+        let mut truncated: PrimeCertificate = certificate.clone();
+        truncated.steps.pop();
+        assert_eq!(None, verify_certificate(b"alice", 256, HashFunction::Sha256, &truncated));
+        // a composite base is caught by trial division
+        let mut composite: PrimeCertificate = certificate;
+        composite.base += 2;
+        while is_prime_by_trial_division(u64::from(composite.base)) {
+            composite.base += 2;
+        }
+        assert_eq!(None, verify_certificate(b"alice", 256, HashFunction::Sha256, &composite));
+    }
+
+    #[test]
+    fn test_base_search_wraps() {
+        // 4294967291 is the last prime below 2^32, the search from past it wraps around
+        // to the first one above 2^31 rather than leave the u32 a certificate holds
+        let start: u64 = u64::from(u32::MAX);
+        let base: u64 = find_base(start);
+        assert_eq!(2_147_483_659, base);
+        assert!(base_in_window(start, base));
+        assert_eq!(4_294_967_291, find_base(4_294_967_291));
+        assert!(!base_in_window(start, 4_294_967_291));
+        assert!(!base_in_window(start, base + (1 << 32)));
+        // starts short of the last prime find it without wrapping
+        assert_eq!(4_294_967_291, find_base(4_294_967_281));
+        assert!(base_in_window(4_294_967_281, 4_294_967_291));
+    }
+}
@@ -0,0 +1,163 @@
+//! Non-interactive proofs of knowledge of an exponent, after Boneh, Bünz and Fisch,
+//! "Batching Techniques for Accumulators with Applications to IOPs and Stateless
+//! Blockchains" (2018). A prover shows it knows `x` with `base^x = result` without
+//! revealing `x`, which lets an updater prove that a new state was reached from an old
+//! one by accumulating *something* without saying which elements were added.
+//!
+//! Challenges come from Fiat-Shamir: the statement is hashed to a prime `l`, and the
+//! proof is the quotient and remainder of `x` by `l`, so it stays a few group elements
+//! no matter how large `x` grows.
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use sha2::{Digest, Sha256};
+
+use crate::hash_value_to_prime;
+use crate::params::{PrimeMapping, PublicParameters, SecurityLevel};
+
+const POKE_DOMAIN: &[u8] = b"tangerine/poke/v1";
+const POKE2_DOMAIN: &[u8] = b"tangerine/poke2/v1";
+const POKE2_ALPHA_DOMAIN: &[u8] = b"tangerine/poke2/alpha/v1";
+
+/// PoKE*: knowledge of `x` with `g^x = result`, where `g` is the generator of the
+/// parameters. Only sound for that fixed base, see [`Poke2Proof`] for any other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PokeProof {
+    pub q: BigUint,
+    pub r: BigUint,
+}
+
+/// PoKE2: knowledge of `x` with `base^x = result` for an arbitrary `base`, such as the
+/// state of an accumulator before an update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Poke2Proof {
+    pub z: BigUint,
+    pub q: BigUint,
+    pub r: BigUint,
+}
+
+fn write_field(hasher: &mut Sha256, field: &BigUint) {
+    let bytes: Vec<u8> = field.to_bytes_be();
+    hasher.update((bytes.len() as u64).to_be_bytes());
+    hasher.update(&bytes);
+}
+
+fn transcript(domain: &[u8], params: &PublicParameters, fields: &[&BigUint]) -> Vec<u8> {
+    let mut hasher: Sha256 = Sha256::new();
+    hasher.update(domain);
+    hasher.update(params.digest());
+    for field in fields.iter() {
+        write_field(&mut hasher, field);
+    }
+    hasher.finalize().to_vec()
+}
+
+// the Fiat-Shamir challenge prime for a transcript
+fn challenge_prime(transcript: &[u8]) -> BigUint {
+    hash_value_to_prime(transcript, &[], PrimeMapping::default(), SecurityLevel::default())
+}
+
+pub fn prove_poke(params: &PublicParameters, result: &BigUint, exponent: &BigUint) -> PokeProof {
+    let l: BigUint = challenge_prime(&transcript(POKE_DOMAIN, params, &[&params.generator, result]));
+    let (q, r): (BigUint, BigUint) = exponent.div_rem(&l);
+    PokeProof { q: params.generator.modpow(&q, &params.modulus), r }
+}
+
+pub fn verify_poke(params: &PublicParameters, result: &BigUint, proof: &PokeProof) -> bool {
+    let l: BigUint = challenge_prime(&transcript(POKE_DOMAIN, params, &[&params.generator, result]));
+    if proof.r >= l {
+        return false;
+    }
+    // Q^l * g^r = g^(ql + r) = g^x
+    let lhs: BigUint = proof.q.modpow(&l, &params.modulus) * params.generator.modpow(&proof.r, &params.modulus);
+    lhs % &params.modulus == *result
+}
+
+// l and alpha for a PoKE2 statement, both bound to z
+fn poke2_challenges(params: &PublicParameters, base: &BigUint, result: &BigUint, z: &BigUint) -> (BigUint, BigUint) {
+    let l: BigUint = challenge_prime(&transcript(POKE2_DOMAIN, params, &[base, result, z]));
+    let alpha: BigUint = BigUint::from_bytes_be(&transcript(POKE2_ALPHA_DOMAIN, params, &[base, result, z, &l]));
+    (l, alpha)
+}
+
+pub fn prove_poke2(params: &PublicParameters, base: &BigUint, result: &BigUint, exponent: &BigUint) -> Poke2Proof {
+    let modulus: &BigUint = &params.modulus;
+    // z = g^x ties the exponent to the fixed generator
+    let z: BigUint = params.generator.modpow(exponent, modulus);
+    let (l, alpha): (BigUint, BigUint) = poke2_challenges(params, base, result, &z);
+    let (q, r): (BigUint, BigUint) = exponent.div_rem(&l);
+    // Q = (base * g^alpha)^q
+    let combined: BigUint = (base * params.generator.modpow(&alpha, modulus)) % modulus;
+    Poke2Proof { z, q: combined.modpow(&q, modulus), r }
+}
+
+pub fn verify_poke2(params: &PublicParameters, base: &BigUint, result: &BigUint, proof: &Poke2Proof) -> bool {
+    let modulus: &BigUint = &params.modulus;
+    let (l, alpha): (BigUint, BigUint) = poke2_challenges(params, base, result, &proof.z);
+    if proof.r >= l {
+        return false;
+    }
+    // Q^l * (base * g^alpha)^r = (base * g^alpha)^x = result * z^alpha
+    let combined: BigUint = (base * params.generator.modpow(&alpha, modulus)) % modulus;
+    let lhs: BigUint = (proof.q.modpow(&l, modulus) * combined.modpow(&proof.r, modulus)) % modulus;
+    let rhs: BigUint = (result * proof.z.modpow(&alpha, modulus)) % modulus;
+    lhs == rhs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::product_tree;
+    use crate::store::mem_store::MemStore;
+    use crate::store::Storer;
+    use crate::SetAccumulator;
+    use num_bigint::RandBigInt;
+
+    // a small modulus is enough to exercise the algebra, primality is not what is tested
+    fn get_test_parameters() -> PublicParameters {
+        let mut rng = rand::thread_rng();
+        let modulus: BigUint = rng.gen_biguint(512) | BigUint::from(1_u64);
+        let generator: BigUint = rng.gen_biguint_below(&modulus);
+        PublicParameters::new(modulus, generator)
+    }
+
+    #[test]
+    fn test_poke() {
+        let params: PublicParameters = get_test_parameters();
+        let exponent: BigUint = rand::thread_rng().gen_biguint(1024);
+        let result: BigUint = params.generator.modpow(&exponent, &params.modulus);
+        let proof: PokeProof = prove_poke(&params, &result, &exponent);
+        assert!(verify_poke(&params, &result, &proof));
+        // the proof is bound to the statement
+        assert!(!verify_poke(&params, &(&result + 1_u64), &proof));
+        let mut forged: PokeProof = proof.clone();
+        forged.r += 1_u64;
+        assert!(!verify_poke(&params, &result, &forged));
+    }
+
+    #[test]
+    fn test_poke2_proves_an_update() {
+        let params: PublicParameters = get_test_parameters();
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        sa.add(b"alice").unwrap();
+        let before: BigUint = sa.store.get_state().clone();
+        let values: Vec<&[u8]> = vec![b"bob", b"carol"];
+        sa.add_batch(&values).unwrap();
+        let after: BigUint = sa.store.get_state().clone();
+        // the updater knows the product of the primes it added, the verifier only sees the states
+        let level: SecurityLevel = sa.security_level();
+        let added: Vec<BigUint> = values
+            .iter()
+            .map(|value| hash_value_to_prime(value, &sa.store.get_nonce(value).unwrap(), PrimeMapping::default(), level))
+            .collect();
+        let exponent: BigUint = product_tree::product(added);
+
+        let proof: Poke2Proof = prove_poke2(&params, &before, &after, &exponent);
+        assert!(verify_poke2(&params, &before, &after, &proof));
+        assert!(!verify_poke2(&params, &after, &before, &proof));
+        assert!(!verify_poke2(&params, &before, &(&after + 1_u64), &proof));
+        // proving with the wrong exponent gets nowhere
+        let wrong: Poke2Proof = prove_poke2(&params, &before, &after, &(exponent + 2_u64));
+        assert!(!verify_poke2(&params, &before, &after, &wrong));
+    }
+}
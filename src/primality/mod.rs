@@ -0,0 +1,246 @@
+//! Primality tests beyond the random base Miller-Rabin rounds in the crate root.
+//!
+//! Candidates that fit in a u64 are settled exactly by Miller-Rabin to the first twelve
+//! prime bases, which no composite below 3.3 * 10^24 passes.
+//!
+//! The Baillie-PSW test is a strong probable prime test to base 2 followed by a strong
+//! Lucas probable prime test with Selfridge's parameters. The composites that fool one
+//! of the two are of a different kind from those that fool the other, and no number
+//! passing both is known to be composite.
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+const DETERMINISTIC_BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+fn mul_mod(a: u64, b: u64, n: u64) -> u64 {
+    ((a as u128 * b as u128) % n as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exponent: u64, n: u64) -> u64 {
+    let mut result: u64 = 1;
+    base %= n;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, n);
+        }
+        base = mul_mod(base, base, n);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// Whether `n` is prime, exactly and in native arithmetic.
+pub(crate) fn is_prime_u64(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    // also rules out the bases themselves as witnesses, the rounds below need a < n
+    for base in DETERMINISTIC_BASES.iter() {
+        if n.is_multiple_of(*base) {
+            return n == *base;
+        }
+    }
+    let twos: u32 = (n - 1).trailing_zeros();
+    let d: u64 = (n - 1) >> twos;
+    'bases: for base in DETERMINISTIC_BASES.iter() {
+        let mut x: u64 = pow_mod(*base, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..twos {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Whether `candidate`, which must be odd and above 3, passes Baillie-PSW.
+pub(crate) fn baillie_psw(candidate: &BigUint) -> bool {
+    is_strong_probable_prime(candidate, &BigUint::from(2_u64)) && is_strong_lucas_probable_prime(candidate)
+}
+
+// a single Miller-Rabin round with a fixed base
+pub(crate) fn is_strong_probable_prime(candidate: &BigUint, base: &BigUint) -> bool {
+    let minus_one: BigUint = candidate - 1_u64;
+    let twos: u64 = minus_one.trailing_zeros().unwrap_or(0);
+    let mut x: BigUint = base.modpow(&(&minus_one >> twos), candidate);
+    if x.is_one() || x == minus_one {
+        return true;
+    }
+    for _ in 1..twos {
+        x = (&x * &x) % candidate;
+        if x == minus_one {
+            return true;
+        }
+    }
+    false
+}
+
+// the lowest three bits of `n`, all the Jacobi symbol needs to look at
+fn low_bits(n: &BigUint) -> u64 {
+    n.iter_u64_digits().next().unwrap_or(0) & 7
+}
+
+// the Jacobi symbol (a/n) for odd n
+fn jacobi(a: &BigUint, n: &BigUint) -> i8 {
+    let mut a: BigUint = a % n;
+    let mut n: BigUint = n.clone();
+    let mut result: i8 = 1;
+    while !a.is_zero() {
+        let twos: u64 = a.trailing_zeros().unwrap_or(0);
+        a >>= twos;
+        // (2/n) is -1 exactly when n is 3 or 5 mod 8
+        if twos % 2 == 1 && matches!(low_bits(&n), 3 | 5) {
+            result = -result;
+        }
+        // quadratic reciprocity flips the sign when both are 3 mod 4
+        if low_bits(&a) & 3 == 3 && low_bits(&n) & 3 == 3 {
+            result = -result;
+        }
+        core::mem::swap(&mut a, &mut n);
+        a %= &n;
+    }
+    if n.is_one() {
+        result
+    } else {
+        0
+    }
+}
+
+// x mod n for a signed x
+fn signed_mod(x: i64, n: &BigUint) -> BigUint {
+    let magnitude: BigUint = BigUint::from(x.unsigned_abs()) % n;
+    if x < 0 && !magnitude.is_zero() {
+        n - magnitude
+    } else {
+        magnitude
+    }
+}
+
+// x / 2 mod odd n
+fn half_mod(x: &BigUint, n: &BigUint) -> BigUint {
+    let x: BigUint = x % n;
+    if x.is_even() {
+        x >> 1
+    } else {
+        (x + n) >> 1
+    }
+}
+
+// V_2j = V_j^2 - 2 Q^j, with both already reduced mod n
+fn double_v(v: &BigUint, q_power: &BigUint, n: &BigUint) -> BigUint {
+    let twice_q: BigUint = (q_power << 1) % n;
+    ((v * v) % n + n - twice_q) % n
+}
+
+// the strong Lucas test with P = 1 and Q = (1 - D) / 4 for the first D in 5, -7, 9, -11,
+// ... with (D/n) = -1: writing n + 1 = k 2^s, n passes when U_k = 0 or V_(k 2^r) = 0 for
+// some r < s, all mod n
+pub(crate) fn is_strong_lucas_probable_prime(candidate: &BigUint) -> bool {
+    let n: &BigUint = candidate;
+    // no D gives -1 for a square, so the search below would never end
+    let root: BigUint = n.sqrt();
+    if &root * &root == *n {
+        return false;
+    }
+    let mut d: i64 = 5;
+    loop {
+        match jacobi(&signed_mod(d, n), n) {
+            -1 => break,
+            // D shares a factor with n, which only a prime n equal to |D| gets away with
+            0 => return BigUint::from(d.unsigned_abs()) == *n,
+            _ => d = if d > 0 { -(d + 2) } else { 2 - d },
+        }
+    }
+    let d_mod_n: BigUint = signed_mod(d, n);
+    let q_mod_n: BigUint = signed_mod((1 - d) / 4, n);
+    let plus_one: BigUint = n + 1_u64;
+    let twos: u64 = plus_one.trailing_zeros().unwrap_or(0);
+    let k: BigUint = &plus_one >> twos;
+
+    // U_k, V_k and Q^k from U_1 = 1, V_1 = P = 1 by walking the bits of k below the top one
+    let mut u: BigUint = One::one();
+    let mut v: BigUint = One::one();
+    let mut q_power: BigUint = q_mod_n.clone();
+    for bit in (0..k.bits() - 1).rev() {
+        u = (&u * &v) % n;
+        v = double_v(&v, &q_power, n);
+        q_power = (&q_power * &q_power) % n;
+        if k.bit(bit) {
+            // U_(j+1) = (U_j + V_j) / 2 and V_(j+1) = (D U_j + V_j) / 2
+            let next_u: BigUint = half_mod(&(&u + &v), n);
+            v = half_mod(&(&d_mod_n * &u + &v), n);
+            u = next_u;
+            q_power = (&q_power * &q_mod_n) % n;
+        }
+    }
+    if u.is_zero() || v.is_zero() {
+        return true;
+    }
+    for _ in 1..twos {
+        v = double_v(&v, &q_power, n);
+        q_power = (&q_power * &q_power) % n;
+        if v.is_zero() {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_prime_by_trial_division(n: u64) -> bool {
+        n >= 2 && (2..n).take_while(|d| d * d <= n).all(|d| !n.is_multiple_of(d))
+    }
+
+    #[test]
+    fn test_is_prime_u64() {
+        for n in 0_u64..20_000 {
+            assert_eq!(is_prime_by_trial_division(n), is_prime_u64(n), "is_prime_u64({})", n);
+        }
+        // the largest primes below 2^32 and 2^64
+        assert!(is_prime_u64(4_294_967_291));
+        assert!(is_prime_u64(18_446_744_073_709_551_557));
+        assert!(!is_prime_u64(u64::MAX));
+        // strong pseudoprimes to the first four and the first nine prime bases
+        assert!(!is_prime_u64(3_215_031_751));
+        assert!(!is_prime_u64(3_825_123_056_546_413_051));
+        // a product of two primes just under 2^32
+        assert!(!is_prime_u64(4_294_967_291 * 4_294_967_279));
+    }
+
+    #[test]
+    fn test_jacobi() {
+        // (a/15) for a = 1..14, from the table of Jacobi symbols
+        let expected: [i8; 14] = [1, 1, 0, 1, 0, 0, -1, 1, 0, 0, -1, 0, -1, -1];
+        for (a, symbol) in (1_u64..15).zip(expected.iter()) {
+            assert_eq!(*symbol, jacobi(&BigUint::from(a), &BigUint::from(15_u64)), "({}/15)", a);
+        }
+    }
+
+    #[test]
+    fn test_baillie_psw() {
+        for n in (5_u64..20_000).step_by(2) {
+            assert_eq!(is_prime_by_trial_division(n), baillie_psw(&BigUint::from(n)), "baillie_psw({})", n);
+        }
+        // strong Lucas pseudoprimes get through the Lucas test but not the base 2 round
+        for n in [5459_u64, 5777, 10877, 16109, 18971].iter() {
+            assert!(is_strong_lucas_probable_prime(&BigUint::from(*n)));
+            assert!(!baillie_psw(&BigUint::from(*n)));
+        }
+        // and a strong pseudoprime to bases 2, 3 and 5 the other way around
+        let pseudoprime: BigUint = BigUint::from(25_326_001_u64);
+        assert!(is_strong_probable_prime(&pseudoprime, &BigUint::from(2_u64)));
+        assert!(!baillie_psw(&pseudoprime));
+        // a square never passes
+        assert!(!is_strong_lucas_probable_prime(&BigUint::from(1_042_441_u64)));
+    }
+}
@@ -0,0 +1,124 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use num_bigint::BigUint;
+use num_traits::One;
+use crate::arith;
+use crate::parallel::*;
+
+/// A binary tree of partial products over a list of factors.
+///
+/// Level 0 holds the factors themselves, every node on level k + 1 is the
+/// product of (at most) two adjacent nodes on level k, and the single node on
+/// the last level is the product of all factors. Nothing is reduced modulo
+/// anything, so the root is the exact integer product.
+#[derive(Debug, Clone)]
+pub struct ProductTree {
+    levels: Vec<Vec<BigUint>>,
+}
+
+impl ProductTree {
+    pub fn new(factors: Vec<BigUint>) -> ProductTree {
+        let mut levels: Vec<Vec<BigUint>> = vec![factors];
+        // keep pairing up nodes until only the root is left
+        while levels.last().unwrap().len() > 1 {
+            let next: Vec<BigUint> = levels
+                .last()
+                .unwrap()
+                .par_chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => arith::mul(left, right),
+                    _ => pair[0].clone(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        ProductTree { levels }
+    }
+    pub fn root(&self) -> BigUint {
+        // the empty product is one
+        match self.levels.last().unwrap().first() {
+            Some(root) => root.clone(),
+            None => One::one(),
+        }
+    }
+    pub fn leaves(&self) -> &[BigUint] {
+        &self.levels[0]
+    }
+    pub fn depth(&self) -> usize {
+        self.levels.len()
+    }
+    /// Product of the leaves `index * 2^level .. (index + 1) * 2^level`.
+    pub fn subtree_product(&self, level: usize, index: usize) -> Option<&BigUint> {
+        self.levels.get(level).and_then(|nodes| nodes.get(index))
+    }
+    /// `base` raised to the product of every leaf but one, mod `modulus`, for each leaf in
+    /// order. Walking down from the root, each child gets its parent's value raised to its
+    /// sibling's product, so every level costs as much as one exponentiation by the root
+    /// and all n results take O(n log n) work instead of O(n^2).
+    pub fn root_factor(&self, base: &BigUint, modulus: &BigUint) -> Vec<BigUint> {
+        self.root_factor_with(base, &|base: &BigUint, exponent: &BigUint| arith::modpow(base, exponent, modulus))
+    }
+    /// Like [`ProductTree::root_factor`], with `pow` raising a value to a subtree product,
+    /// for callers that can exponentiate faster than by the product itself.
+    pub fn root_factor_with<F: Fn(&BigUint, &BigUint) -> BigUint + Sync>(&self, base: &BigUint, pow: &F) -> Vec<BigUint> {
+        if self.levels[0].is_empty() {
+            return Vec::new();
+        }
+        let mut values: Vec<BigUint> = vec![base.clone()];
+        for nodes in self.levels.iter().rev().skip(1) {
+            values = nodes
+                .par_iter()
+                .enumerate()
+                .map(|(index, _)| match nodes.get(index ^ 1) {
+                    Some(sibling) => pow(&values[index / 2], sibling),
+                    // the odd node out was carried up unpaired
+                    None => values[index / 2].clone(),
+                })
+                .collect();
+        }
+        values
+    }
+}
+
+/// Product of all factors, computed over a product tree.
+pub fn product(factors: Vec<BigUint>) -> BigUint {
+    ProductTree::new(factors).root()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_product_tree() {
+        let factors: Vec<BigUint> = (1_u64..=11).map(BigUint::from).collect();
+        let tree: ProductTree = ProductTree::new(factors.clone());
+        // eleven leaves pair up into 6, 3, 2 and finally 1 node
+        assert_eq!(5, tree.depth());
+        assert_eq!(factors.as_slice(), tree.leaves());
+        assert_eq!(BigUint::from(39916800_u64), tree.root());
+        assert_eq!(Some(&BigUint::from(30_u64)), tree.subtree_product(1, 2));
+        assert_eq!(Some(&BigUint::from(40320_u64)), tree.subtree_product(3, 0));
+        assert_eq!(Some(&BigUint::from(990_u64)), tree.subtree_product(3, 1));
+        assert_eq!(None, tree.subtree_product(3, 2));
+
+        let one: BigUint = One::one();
+        assert_eq!(one, product(vec![]));
+        assert_eq!(BigUint::from(7_u64), product(vec![BigUint::from(7_u64)]));
+    }
+
+    #[test]
+    fn test_root_factor() {
+        let factors: Vec<BigUint> = (1_u64..=11).map(BigUint::from).collect();
+        let (base, modulus): (BigUint, BigUint) = (BigUint::from(3_u64), BigUint::from(1_000_003_u64));
+        let expected: Vec<BigUint> = (0..factors.len())
+            .map(|i| {
+                let others: BigUint = factors.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, f)| f).product();
+                base.modpow(&others, &modulus)
+            })
+            .collect();
+        assert_eq!(expected, ProductTree::new(factors).root_factor(&base, &modulus));
+        assert_eq!(vec![base.clone()], ProductTree::new(vec![BigUint::from(5_u64)]).root_factor(&base, &modulus));
+        assert!(ProductTree::new(vec![]).root_factor(&base, &modulus).is_empty());
+    }
+}
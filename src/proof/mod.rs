@@ -0,0 +1,63 @@
+use alloc::vec::Vec;
+use num_bigint::BigUint;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::arith;
+use crate::params::PrimeMapping;
+use crate::pocklington::{self, PrimeCertificate};
+use crate::verify_membership_with;
+
+/// A witness together with the nonce its value was added with, which is everything a
+/// verifier needs besides the value itself and the state it is checked against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MembershipProof {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::biguint"))]
+    pub witness: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::bytes"))]
+    pub nonce: Vec<u8>,
+    /// Present for accumulators with certified primes.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub certificate: Option<PrimeCertificate>,
+}
+
+impl MembershipProof {
+    pub fn new(witness: BigUint, nonce: Vec<u8>) -> Self {
+        MembershipProof { witness, nonce, certificate: None }
+    }
+    pub fn with_certificate(mut self, certificate: PrimeCertificate) -> Self {
+        self.certificate = Some(certificate);
+        self
+    }
+    /// Verifies against an accumulator mapping values to primes with `prime_mapping`.
+    pub fn verify(&self, state: &BigUint, modulus: &BigUint, value: &[u8], prime_mapping: PrimeMapping) -> bool {
+        verify_membership_with(prime_mapping, state, modulus, value, &self.nonce, &self.witness)
+    }
+    /// Verifies against an accumulator with certified primes, taking the prime from the
+    /// certificate so that no primality test is run.
+    pub fn verify_certified(&self, state: &BigUint, modulus: &BigUint, value: &[u8], prime_mapping: PrimeMapping) -> bool {
+        let certificate: &PrimeCertificate = match self.certificate.as_ref() {
+            Some(certificate) => certificate,
+            None => return false,
+        };
+        match pocklington::verify_for(value, &self.nonce, prime_mapping, certificate) {
+            Some(prime) => arith::modpow(&self.witness, &prime, modulus) == *state,
+            None => false,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_round_trip() {
+        let proof: MembershipProof = MembershipProof::new(BigUint::from(0x0102_u64), vec![0xab, 0x00]);
+        let json: String = serde_json::to_string(&proof).unwrap();
+        assert_eq!(r#"{"witness":"0102","nonce":"ab00"}"#, json);
+        assert_eq!(proof, serde_json::from_str(&json).unwrap());
+        assert!(serde_json::from_str::<MembershipProof>(r#"{"witness":"xyz","nonce":""}"#).is_err());
+    }
+}
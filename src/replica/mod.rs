@@ -0,0 +1,124 @@
+//! Read replicas that follow a primary by tailing the epochs it closes, so witnesses and
+//! verification can be served from as many processes as needed while one writer makes
+//! the changes. A [`Follower`] keeps its copy of the accumulator in memory and brings it
+//! up to date on every [`poll`](Follower::poll), checking each epoch leads from the state
+//! it holds to the one the primary reached, as [`SetAccumulator::apply_delta`] does.
+//!
+//! The epochs come from an [`EpochFeed`]: [`LogTail`](crate::store::file_store::LogTail)
+//! reads them from the log a [`FileStore`](crate::store::file_store::FileStore) is
+//! appending to, and, with the `redis` feature, `RedisTail` from the list a `RedisStore`
+//! pushes them onto.
+
+use std::io;
+
+use num_bigint::BigUint;
+
+use crate::error::AccumulatorError;
+use crate::params::PublicParameters;
+use crate::store::mem_store::MemStore;
+use crate::store::{Delta, Epoch, Storer};
+use crate::SetAccumulator;
+
+/// Where a follower reads the epochs of a primary from.
+pub trait EpochFeed {
+    /// The epochs that followed `epoch`, in order, as far as the primary has written them
+    /// out. Empty if it has written nothing since.
+    fn epochs_after(&mut self, epoch: u64) -> io::Result<Vec<Epoch>>;
+}
+
+/// A read-only copy of an accumulator, kept up to date from a feed of its epochs.
+pub struct Follower<F: EpochFeed> {
+    accumulator: SetAccumulator<MemStore>,
+    feed: F,
+    // the epochs of the primary applied so far: one that changes nothing, such as a
+    // rollback to the current epoch, closes no epoch of the copy
+    epoch: u64,
+}
+
+impl<F: EpochFeed> Follower<F> {
+    /// A follower at the genesis state of `params`, the parameters the primary was
+    /// created over.
+    pub fn new(params: &PublicParameters, feed: F) -> Self {
+        Follower { accumulator: SetAccumulator::new(MemStore::from_parameters(params)), feed, epoch: 0 }
+    }
+    /// Applies the epochs the primary has closed since the last poll, returning how many.
+    /// Fails with [`AccumulatorError::Io`] if the feed cannot be read and with
+    /// [`AccumulatorError::InvalidTransition`] if an epoch does not lead to the state it
+    /// claims, leaving the copy at the last epoch that did.
+    pub fn poll(&mut self) -> Result<u64, AccumulatorError> {
+        let epochs: Vec<Epoch> = self.feed.epochs_after(self.epoch).map_err(|e| AccumulatorError::Io(e.to_string()))?;
+        let mut applied: u64 = 0;
+        for epoch in epochs {
+            let from: BigUint = self.accumulator.store.get_state().clone();
+            self.accumulator.apply_delta(&Delta { from, epochs: vec![epoch] })?;
+            self.epoch += 1;
+            applied += 1;
+        }
+        Ok(applied)
+    }
+    /// The last epoch of the primary the copy has caught up with.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+    /// The copy, for witnesses and verification. Hand other threads a
+    /// [`fork`](SetAccumulator::fork) of it, which they can read while it is polled.
+    pub fn accumulator(&self) -> &SetAccumulator<MemStore> {
+        &self.accumulator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a primary whose epochs are handed over by hand
+    struct Primary(SetAccumulator<MemStore>);
+
+    impl EpochFeed for &mut Primary {
+        fn epochs_after(&mut self, epoch: u64) -> io::Result<Vec<Epoch>> {
+            Ok(self.0.export_delta(epoch).map(|delta| delta.epochs).unwrap_or_default())
+        }
+    }
+
+    #[test]
+    fn test_follow() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut primary: Primary = Primary(SetAccumulator::new(MemStore::from_parameters(&params)));
+        primary.0.add_batch(&[b"alice".as_ref(), b"bob"]).unwrap();
+        primary.0.add(b"carol").unwrap();
+        let mut follower: Follower<&mut Primary> = Follower::new(&params, &mut primary);
+        assert_eq!(Ok(2), follower.poll());
+        assert_eq!(Ok(0), follower.poll());
+        let (witness, nonce): (BigUint, Vec<u8>) = follower.accumulator().get_witness(b"carol").unwrap();
+        assert_eq!(2, follower.epoch());
+        drop(follower);
+        assert!(primary.0.verify(b"carol", &witness, &nonce));
+
+        primary.0.delete_without_trapdoor(b"alice").unwrap();
+        let state: BigUint = primary.0.store.get_state().clone();
+        let mut follower: Follower<&mut Primary> = Follower::new(&params, &mut primary);
+        assert_eq!(Ok(3), follower.poll());
+        assert_eq!((&state, false), (follower.accumulator().store.get_state(), follower.accumulator().store.contains_member(b"alice")));
+    }
+
+    #[test]
+    fn test_forged_epoch() {
+        struct Forged(Vec<Epoch>);
+        impl EpochFeed for Forged {
+            fn epochs_after(&mut self, epoch: u64) -> io::Result<Vec<Epoch>> {
+                Ok(self.0.split_off((epoch as usize).min(self.0.len())))
+            }
+        }
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut primary: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        primary.add(b"alice").unwrap();
+        primary.add(b"bob").unwrap();
+        let mut epochs: Vec<Epoch> = primary.export_delta(0).unwrap().epochs;
+        epochs[1].state = params.genesis_state();
+        let mut follower: Follower<Forged> = Follower::new(&params, Forged(epochs));
+        // the copy stops at the last epoch it could check
+        assert_eq!(Err(AccumulatorError::InvalidTransition), follower.poll());
+        assert_eq!(1, follower.epoch());
+        assert!(follower.accumulator().store.contains_member(b"alice") && !follower.accumulator().store.contains_member(b"bob"));
+    }
+}
@@ -0,0 +1,86 @@
+//! Big integers that must not outlive their use: the factors of the modulus and anything
+//! computed from them. num-bigint has no way to wipe its digits, so [`wipe`] clears a
+//! value bit by bit from the bottom up, which overwrites every digit in place before
+//! num-bigint gets to shrink or free the buffer holding it.
+//!
+//! The temporaries num-bigint allocates inside an operation are out of reach, only the
+//! values the crate holds on to are wiped.
+
+use core::ops::Deref;
+
+use num_bigint::BigUint;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Overwrites the digits of `value` with zeros and leaves it zero.
+pub fn wipe(value: &mut BigUint) {
+    // clearing a bit rewrites its digit in place, and while a higher digit is still
+    // nonzero the digits below it are neither truncated nor moved
+    for bit in 0..value.bits() {
+        value.set_bit(bit, false);
+    }
+}
+
+/// A [`BigUint`] wiped when dropped. It dereferences to the value, so it can be passed
+/// wherever a `&BigUint` is expected.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretBigUint(BigUint);
+
+impl SecretBigUint {
+    pub fn new(value: BigUint) -> Self {
+        SecretBigUint(value)
+    }
+}
+
+impl From<BigUint> for SecretBigUint {
+    fn from(value: BigUint) -> Self {
+        SecretBigUint(value)
+    }
+}
+
+impl Deref for SecretBigUint {
+    type Target = BigUint;
+
+    fn deref(&self) -> &BigUint {
+        &self.0
+    }
+}
+
+impl Zeroize for SecretBigUint {
+    fn zeroize(&mut self) {
+        wipe(&mut self.0);
+    }
+}
+
+impl Drop for SecretBigUint {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SecretBigUint {}
+
+// the value stays out of logs and panic messages
+impl core::fmt::Debug for SecretBigUint {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("SecretBigUint(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::Zero;
+
+    #[test]
+    fn test_wipe() {
+        for bits in [1_u64, 63, 64, 65, 1000].iter() {
+            let mut value: BigUint = (BigUint::from(1_u64) << *bits) - 1_u64;
+            wipe(&mut value);
+            assert!(value.is_zero());
+        }
+        let mut value: SecretBigUint = SecretBigUint::new(BigUint::from(0xdead_beef_u64) << 200);
+        assert_eq!(232, value.bits());
+        value.zeroize();
+        assert!(value.is_zero());
+    }
+}
@@ -0,0 +1,148 @@
+//! Stable serde encodings for the types the crate ships around. Big integers and byte
+//! strings are written big-endian as lowercase hex in human readable formats (JSON) and
+//! as raw bytes everywhere else (CBOR, bincode), so the encoding does not depend on the
+//! digit size num-bigint happens to use internally.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::hex::{from_hex, to_hex};
+use crate::store::{PrimeCache, ShardedMap};
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a hex string or a byte string")
+    }
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Vec<u8>, E> {
+        from_hex(v).ok_or_else(|| E::invalid_value(de::Unexpected::Str(v), &self))
+    }
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+        Ok(v.to_vec())
+    }
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+        Ok(v)
+    }
+    // some formats without a native byte string hand bytes over as a sequence
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+/// `#[serde(with = "crate::serde_support::bytes")]` for `Vec<u8>` fields.
+pub mod bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&to_hex(bytes))
+        } else {
+            serializer.serialize_bytes(bytes)
+        }
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(BytesVisitor)
+        } else {
+            deserializer.deserialize_byte_buf(BytesVisitor)
+        }
+    }
+}
+
+/// `#[serde(with = "crate::serde_support::biguint")]` for `BigUint` fields.
+pub mod biguint {
+    use num_bigint::BigUint;
+
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &BigUint, serializer: S) -> Result<S::Ok, S::Error> {
+        bytes::serialize(&value.to_bytes_be(), serializer)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigUint, D::Error> {
+        Ok(BigUint::from_bytes_be(&bytes::deserialize(deserializer)?))
+    }
+}
+
+// a byte string as a map key or value, in the encoding of `bytes`
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        bytes::serialize(self.0, serializer)
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct ByteBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for ByteBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ByteBuf, D::Error> {
+        Ok(ByteBuf(bytes::deserialize(deserializer)?))
+    }
+}
+
+/// `#[serde(with = "crate::serde_support::members")]` for the value to nonce map of a
+/// store. Members are written as a map sorted by value, so the same set always
+/// serializes to the same bytes.
+pub mod members {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(members: &ShardedMap<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+        let sorted: BTreeMap<&[u8], &[u8]> = members.iter().map(|(value, nonce)| (value, &nonce[..])).collect();
+        serializer.collect_map(sorted.into_iter().map(|(value, nonce)| (Bytes(value), Bytes(nonce))))
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ShardedMap<Vec<u8>>, D::Error> {
+        let members: HashMap<ByteBuf, ByteBuf> = HashMap::deserialize(deserializer)?;
+        Ok(members.into_iter().map(|(value, nonce)| (value.0, nonce.0)).collect())
+    }
+}
+
+// `#[serde(with = "crate::serde_support::primes")]` for the primes a store keeps: a map
+// sorted by value, like the members, of the nonce and the prime kept for it
+pub(crate) mod primes {
+    use num_bigint::BigUint;
+
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(primes: &PrimeCache, serializer: S) -> Result<S::Ok, S::Error> {
+        let sorted: BTreeMap<&[u8], (&[u8], Vec<u8>)> = primes.iter().map(|(value, nonce, prime)| (value, (nonce, prime.to_bytes_be()))).collect();
+        serializer.collect_map(sorted.iter().map(|(value, (nonce, prime))| (Bytes(value), (Bytes(nonce), Bytes(prime)))))
+    }
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PrimeCache, D::Error> {
+        let primes: HashMap<ByteBuf, (ByteBuf, ByteBuf)> = HashMap::deserialize(deserializer)?;
+        let mut cache: PrimeCache = PrimeCache::default();
+        for (value, (nonce, prime)) in primes.iter() {
+            cache.insert(&value.0, &nonce.0, &BigUint::from_bytes_be(&prime.0));
+        }
+        Ok(cache)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Members(#[serde(with = "members")] ShardedMap<Vec<u8>>);
+
+    #[test]
+    fn test_members() {
+        let mut map: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+        map.insert(b"\x02".to_vec(), vec![0xcd]);
+        map.insert(b"\x01".to_vec(), vec![0xab, 0x00]);
+        let json: String = serde_json::to_string(&Members(map.clone().into_iter().collect())).unwrap();
+        assert_eq!(r#"{"01":"ab00","02":"cd"}"#, json);
+        let restored: Members = serde_json::from_str(&json).unwrap();
+        assert_eq!(map, restored.0.iter().map(|(value, nonce)| (value.to_vec(), nonce.clone())).collect());
+    }
+}
@@ -0,0 +1,362 @@
+//! An accumulator authority over HTTP, enabled by the `service` feature, so a team can run
+//! one central accumulator without writing server glue:
+//!
+//! - `POST /add` adds the request body as a value and answers with the new state and the
+//!   prime, nonce and witness the value was added with.
+//! - `GET /witness/{value}` answers with the witness and nonce of the value, given in
+//!   hex since values are usually hashes (of certificates, say), or 404.
+//! - `GET /state` answers with the current state and the modulus.
+//!
+//! Answers are JSON objects whose big integers and byte strings are big-endian lowercase
+//! hex, as the `serde` feature writes them, errors are `{"error": "..."}`.
+//!
+//! [`AccumulatorService::handle`] is the whole API and can sit behind any HTTP server.
+//! [`AccumulatorService::serve`] is a minimal HTTP/1.1 server on the standard library
+//! for when nothing else is at hand: it answers one request per connection and one
+//! connection at a time, which an accumulator whose adds each depend on the state the
+//! last one left needs anyway. So that a slow client cannot hold the rest up, each
+//! connection has ten seconds to send its request and ten more to take the answer.
+
+use std::future::Future;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+use num_bigint::BigUint;
+
+use crate::async_accumulator::{AsyncSetAccumulator, AsyncStorer};
+use crate::error::AccumulatorError;
+use crate::hex::{from_hex, to_hex};
+
+// bodies past this are refused before they are read
+const MAX_BODY_BYTES: usize = 1 << 20;
+// and so are request lines and headers, which are read up to the blank line ending them
+const MAX_HEAD_BYTES: u64 = 16 << 10;
+// connections are served one at a time, so a client that stalls must not hold the rest up:
+// the whole request has to arrive within this, however slowly it trickles in
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// and the answer has to be taken off our hands within this
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    /// A JSON object.
+    pub body: String,
+}
+
+impl Response {
+    fn ok(fields: &[(&str, &[u8])]) -> Self {
+        let fields: Vec<String> = fields.iter().map(|(name, bytes)| format!("\"{}\":\"{}\"", name, to_hex(bytes))).collect();
+        Response { status: 200, body: format!("{{{}}}", fields.join(",")) }
+    }
+    fn error(status: u16, message: &str) -> Self {
+        // error messages can quote what the client sent
+        let mut escaped: String = String::with_capacity(message.len());
+        for c in message.chars() {
+            match c {
+                '"' | '\\' => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                }
+                c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+                c => escaped.push(c),
+            }
+        }
+        Response { status, body: format!("{{\"error\":\"{}\"}}", escaped) }
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Serves an [`AsyncSetAccumulator`], see the module documentation.
+pub struct AccumulatorService<T: AsyncStorer> {
+    pub accumulator: AsyncSetAccumulator<T>,
+}
+
+impl<T: AsyncStorer> AccumulatorService<T> {
+    pub fn new(accumulator: AsyncSetAccumulator<T>) -> Self {
+        AccumulatorService { accumulator }
+    }
+    pub async fn handle(&mut self, request: &Request) -> Response {
+        let segments: Vec<&str> = request.path.trim_start_matches('/').split('/').collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("POST", ["add"]) => self.add(&request.body).await,
+            ("GET", ["witness", value]) => match from_hex(value) {
+                Some(value) => self.witness(&value).await,
+                None => Response::error(400, "value is not hex"),
+            },
+            ("GET", ["state"]) => {
+                let state: BigUint = self.accumulator.store.get_state().await;
+                let modulus: BigUint = self.accumulator.store.get_modulus().await;
+                Response::ok(&[("state", &state.to_bytes_be()), ("modulus", &modulus.to_bytes_be())])
+            }
+            (_, ["add"]) | (_, ["witness", _]) | (_, ["state"]) => Response::error(405, "method not allowed"),
+            _ => Response::error(404, "not found"),
+        }
+    }
+    async fn add(&mut self, value: &[u8]) -> Response {
+        match self.accumulator.add(value).await {
+            Ok(added) => {
+                let state: BigUint = self.accumulator.store.get_state().await;
+                Response::ok(&[
+                    ("state", &state.to_bytes_be()),
+                    ("prime", &added.prime.to_bytes_be()),
+                    ("nonce", &added.nonce),
+                    ("witness", &added.witness.to_bytes_be()),
+                ])
+            }
+            Err(e @ AccumulatorError::AlreadyAMember) | Err(e @ AccumulatorError::DuplicatePrime) => Response::error(409, &e.to_string()),
+            Err(e) => Response::error(500, &e.to_string()),
+        }
+    }
+    async fn witness(&mut self, value: &[u8]) -> Response {
+        match self.accumulator.get_witness(value).await {
+            Some((witness, nonce)) => Response::ok(&[("witness", &witness.to_bytes_be()), ("nonce", &nonce)]),
+            None => Response::error(404, "not a member"),
+        }
+    }
+    /// Answers connections on `listener` until accepting one fails. A connection that
+    /// breaks off is dropped and the next one served.
+    pub fn serve(&mut self, listener: TcpListener) -> io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept()?;
+            // a client that goes away mid-request only loses its own answer
+            let _ = self.serve_connection(stream);
+        }
+    }
+    /// Reads one request from `stream`, answers it and closes the connection.
+    pub fn serve_connection(&mut self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let response: Response = match read_request(&mut BufReader::new(Deadline::new(&stream, REQUEST_TIMEOUT)))? {
+            Ok(request) => block_on(self.handle(&request)),
+            Err(response) => response,
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.status,
+            reason(response.status),
+            response.body.len(),
+            response.body
+        )?;
+        stream.flush()
+    }
+}
+
+// reads from a stream until a deadline, rather than allowing each read its own timeout
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl<'a> Deadline<'a> {
+    fn new(stream: &'a TcpStream, timeout: Duration) -> Self {
+        Deadline { stream, deadline: Instant::now() + timeout }
+    }
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining: Duration = self.deadline.saturating_duration_since(Instant::now());
+        // a zero timeout would mean none at all
+        if remaining.is_zero() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "request took too long"));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        let mut stream: &TcpStream = self.stream;
+        stream.read(buf)
+    }
+}
+
+// the request, or the answer to a request that cannot be served
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Result<Request, Response>> {
+    let mut head: io::Take<&mut R> = reader.by_ref().take(MAX_HEAD_BYTES);
+    let mut line: String = String::new();
+    if read_head_line(&mut head, &mut line)?.is_none() {
+        return Ok(Err(Response::error(431, "request head too large")));
+    }
+    let mut parts = line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return Ok(Err(Response::error(400, "malformed request line"))),
+    };
+    let mut content_length: usize = 0;
+    loop {
+        match read_head_line(&mut head, &mut line)? {
+            None => return Ok(Err(Response::error(431, "request head too large"))),
+            Some(0) => break,
+            Some(_) if line.trim_end().is_empty() => break,
+            Some(_) => {}
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = match value.trim().parse() {
+                    Ok(length) => length,
+                    Err(_) => return Ok(Err(Response::error(400, "malformed content length"))),
+                };
+            }
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Ok(Err(Response::error(413, "body too large")));
+    }
+    let mut body: Vec<u8> = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Ok(Request { method, path, body }))
+}
+
+// reads the next line of the request head into `line`, None if it runs past the
+// MAX_HEAD_BYTES the head may take
+fn read_head_line<R: BufRead>(head: &mut io::Take<R>, line: &mut String) -> io::Result<Option<usize>> {
+    line.clear();
+    let read: usize = head.read_line(line)?;
+    if head.limit() == 0 && !line.ends_with('\n') {
+        return Ok(None);
+    }
+    Ok(Some(read))
+}
+
+// wakes the thread blocked in block_on
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// runs `future` to completion on the current thread, parking it while the store is busy
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker: Waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut context: Context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::PublicParameters;
+    use crate::store::mem_store::MemStore;
+    use crate::verify_membership;
+    use std::thread::JoinHandle;
+
+    fn service() -> AccumulatorService<MemStore> {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        AccumulatorService::new(block_on(AsyncSetAccumulator::new(MemStore::from_parameters(&params))))
+    }
+
+    fn request(method: &str, path: &str, body: &[u8]) -> Request {
+        Request { method: method.to_string(), path: path.to_string(), body: body.to_vec() }
+    }
+
+    // the hex of `field` in a response body
+    fn field(response: &Response, field: &str) -> Vec<u8> {
+        let start: usize = response.body.find(&format!("\"{}\":\"", field)).unwrap() + field.len() + 4;
+        let end: usize = start + response.body[start..].find('"').unwrap();
+        from_hex(&response.body[start..end]).unwrap()
+    }
+
+    #[test]
+    fn test_handle() {
+        let mut service: AccumulatorService<MemStore> = service();
+        assert_eq!(200, block_on(service.handle(&request("POST", "/add", b"alice"))).status);
+        let added: Response = block_on(service.handle(&request("POST", "/add", b"bob")));
+        assert_eq!(409, block_on(service.handle(&request("POST", "/add", b"bob"))).status);
+
+        let state: Response = block_on(service.handle(&request("GET", "/state", b"")));
+        assert_eq!(field(&added, "state"), field(&state, "state"));
+        let witness: Response = block_on(service.handle(&request("GET", &format!("/witness/{}", to_hex(b"alice")), b"")));
+        assert_eq!(200, witness.status);
+        let (state, modulus): (BigUint, BigUint) =
+            (BigUint::from_bytes_be(&field(&state, "state")), BigUint::from_bytes_be(&field(&state, "modulus")));
+        assert!(verify_membership(&state, &modulus, b"alice", &field(&witness, "nonce"), &BigUint::from_bytes_be(&field(&witness, "witness"))));
+        // an add answers with the witness of the value added, good at the state it left
+        assert!(verify_membership(&state, &modulus, b"bob", &field(&added, "nonce"), &BigUint::from_bytes_be(&field(&added, "witness"))));
+        let (prime, witness): (BigUint, BigUint) =
+            (BigUint::from_bytes_be(&field(&added, "prime")), BigUint::from_bytes_be(&field(&added, "witness")));
+        assert_eq!(state, witness.modpow(&prime, &modulus));
+
+        assert_eq!(404, block_on(service.handle(&request("GET", &format!("/witness/{}", to_hex(b"carol")), b""))).status);
+        assert_eq!(400, block_on(service.handle(&request("GET", "/witness/xyz", b""))).status);
+        assert_eq!(405, block_on(service.handle(&request("GET", "/add", b""))).status);
+        assert_eq!(404, block_on(service.handle(&request("GET", "/", b""))).status);
+    }
+
+    #[test]
+    fn test_read_request() {
+        let read = |bytes: &[u8]| -> Result<Request, Response> { read_request(&mut &bytes[..]).unwrap() };
+        assert_eq!(Ok(request("POST", "/add", b"bob")), read(b"POST /add HTTP/1.1\r\nContent-Length: 3\r\n\r\nbob"));
+        assert_eq!(400, read(b"POST\r\n\r\n").unwrap_err().status);
+        // a request line or headers without end are cut off rather than buffered
+        let long_path: Vec<u8> = [b"GET /".as_ref(), &vec![b'a'; MAX_HEAD_BYTES as usize], b" HTTP/1.1\r\n\r\n"].concat();
+        assert_eq!(431, read(&long_path).unwrap_err().status);
+        let many_headers: Vec<u8> = [b"GET /state HTTP/1.1\r\n".as_ref(), &b"X: y\r\n".repeat(4096), b"\r\n"].concat();
+        assert_eq!(431, read(&many_headers).unwrap_err().status);
+        // error messages stay valid JSON whatever they quote
+        assert_eq!("{\"error\":\"a \\\"b\\\" \\\\ c\\u000a\"}", Response::error(400, "a \"b\" \\ c\n").body);
+    }
+
+    #[test]
+    fn test_serve_connection() {
+        let mut service: AccumulatorService<MemStore> = service();
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client: TcpStream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(b"POST /add HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nalice").unwrap();
+        service.serve_connection(listener.accept().unwrap().0).unwrap();
+        let mut answer: String = String::new();
+        client.read_to_string(&mut answer).unwrap();
+        assert!(answer.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(answer.contains(&format!("{{\"state\":\"{}\",", to_hex(&block_on(service.accumulator.store.get_state()).to_bytes_be()))));
+    }
+
+    #[test]
+    fn test_deadline() {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client: TcpStream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let stream: TcpStream = listener.accept().unwrap().0;
+        // a client that sends a header byte now and then never lets a single read time out
+        let drip: JoinHandle<()> = thread::spawn(move || {
+            let _ = client.write_all(b"GET /state HTTP/1.1\r\nX: ");
+            for _ in 0..40 {
+                thread::sleep(Duration::from_millis(50));
+                if client.write_all(b"y").is_err() {
+                    break;
+                }
+            }
+        });
+        let start: Instant = Instant::now();
+        assert!(read_request(&mut BufReader::new(Deadline::new(&stream, Duration::from_millis(200)))).is_err());
+        assert!(start.elapsed() < Duration::from_secs(1));
+        drop(stream);
+        drip.join().unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+//! Generation of RSA moduli for accumulators. Anyone who learns the factorization can
+//! forge witnesses, so whoever runs the setup either keeps the [`Trapdoor`] as the
+//! accumulator manager or throws it away as soon as the modulus exists.
+
+use std::thread::{self, JoinHandle};
+
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::One;
+
+use crate::is_prime;
+use crate::params::{SecurityLevel, Trapdoor};
+use crate::secret::wipe;
+
+// cheap screening before the full number of rounds is spent on a candidate pair
+const SCREENING_ROUNDS: usize = 1;
+
+/// Draws a safe prime p = 2q + 1 of `bits` bits, with q prime. The top two bits are set
+/// so that the product of two such primes has exactly twice as many bits.
+fn generate_safe_prime(bits: usize) -> BigUint {
+    let mut rng = rand::thread_rng(); // thread-local random generator seeded by system: https://docs.rs/rand/0.8.4/rand/fn.thread_rng.html
+    let f1: BigUint = One::one();
+    let top: BigUint = BigUint::from(3_u64) << (bits - 3);
+    // random candidates fail Miller-Rabin far more reliably than the 4^-rounds worst case,
+    // so the rounds of the level on their own are plenty here
+    let rounds: usize = SecurityLevel::default().miller_rabin_rounds();
+    loop {
+        // q has one bit less than p, with its top two bits and its lowest bit set
+        let mut q: BigUint = rng.gen_biguint((bits - 1) as u64) | &top | &f1;
+        if !is_prime(&q, SCREENING_ROUNDS) {
+            continue;
+        }
+        let p: BigUint = (&q << 1) + &f1;
+        if is_prime(&p, SCREENING_ROUNDS) && is_prime(&q, rounds) && is_prime(&p, rounds) {
+            // q gives p away, and rejected candidates are of no use to anyone
+            wipe(&mut q);
+            return p;
+        }
+    }
+}
+
+/// Generates an RSA modulus of `bits` bits as the product of two distinct safe primes,
+/// searching for both in parallel threads. The factorization is returned only if
+/// `keep_trapdoor` is set; otherwise it is wiped before this function returns.
+pub fn generate_modulus(bits: usize, keep_trapdoor: bool) -> (BigUint, Option<Trapdoor>) {
+    assert!(bits >= 16 && bits.is_multiple_of(2), "modulus size must be an even number of bits, at least 16");
+    let prime_bits: usize = bits / 2;
+    let join_handle_p: JoinHandle<BigUint> = thread::spawn(move || generate_safe_prime(prime_bits));
+    let join_handle_q: JoinHandle<BigUint> = thread::spawn(move || generate_safe_prime(prime_bits));
+    let p: BigUint = join_handle_p.join().unwrap();
+    let mut q: BigUint = join_handle_q.join().unwrap();
+    while p == q {
+        q = generate_safe_prime(prime_bits);
+    }
+    let trapdoor: Trapdoor = Trapdoor::new(p, q);
+    let modulus: BigUint = trapdoor.modulus();
+    if keep_trapdoor {
+        (modulus, Some(trapdoor))
+    } else {
+        (modulus, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_modulus() {
+        let (modulus, trapdoor): (BigUint, Option<Trapdoor>) = generate_modulus(192, true);
+        assert_eq!(192, modulus.bits());
+        let trapdoor: Trapdoor = trapdoor.unwrap();
+        assert_eq!(modulus, trapdoor.modulus());
+        assert_ne!(trapdoor.p, trapdoor.q);
+        for prime in [&trapdoor.p, &trapdoor.q].iter() {
+            assert_eq!(96, prime.bits());
+            assert!(is_prime(prime, 5));
+            // safe: (prime - 1) / 2 is prime as well
+            assert!(is_prime(&((*prime - 1_u64) >> 1), 5));
+        }
+
+        let (modulus, trapdoor): (BigUint, Option<Trapdoor>) = generate_modulus(128, false);
+        assert_eq!(128, modulus.bits());
+        assert!(trapdoor.is_none());
+    }
+}
@@ -0,0 +1,198 @@
+//! Sets too large for one accumulator, split over several. Computing a witness takes a
+//! pass over every other member, so at a hundred million members each one takes minutes;
+//! spread over `n` shards it takes an `n`th of that.
+//!
+//! A value belongs to the shard picked by a hash of the value alone, so anyone can tell
+//! which shard to check it against. A proof names its shard along with the witness in it,
+//! and a verifier holds the states of all the shards, or pins them with the combined
+//! digest of [`combined_digest`].
+
+use std::convert::TryInto;
+
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::accumulator::Accumulator;
+use crate::error::AccumulatorError;
+use crate::params::PublicParameters;
+use crate::proof::MembershipProof;
+use crate::store::Storer;
+use crate::{state_digest, verify_membership_with, AddOutcome, SetAccumulator};
+
+const SHARD_DOMAIN: &[u8] = b"tangerine/shard/v1";
+const COMBINED_DOMAIN: &[u8] = b"tangerine/shard/combined/v1";
+
+/// The shard of `count` that `value` belongs to.
+pub fn shard_index(value: &[u8], count: usize) -> usize {
+    let digest: [u8; 32] = Sha256::new().chain_update(SHARD_DOMAIN).chain_update(value).finalize().into();
+    (u64::from_be_bytes(digest[..8].try_into().unwrap()) % count as u64) as usize
+}
+
+/// A membership proof in one shard, and which shard that is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardedProof {
+    pub shard: u64,
+    pub proof: MembershipProof,
+}
+
+/// SHA-256 over the number of shards and the [`state_digest`](SetAccumulator::state_digest)
+/// of each, in order.
+pub fn combined_digest(params: &PublicParameters, states: &[BigUint]) -> [u8; 32] {
+    let mut hasher: Sha256 = Sha256::new();
+    hasher.update(COMBINED_DOMAIN);
+    hasher.update((states.len() as u64).to_be_bytes());
+    for state in states.iter() {
+        hasher.update(state_digest(&params.modulus, &params.generator, state));
+    }
+    hasher.finalize().into()
+}
+
+/// Whether `proof` shows `value` to be a member of the sharded set whose shards are at
+/// `states`: the proof has to be in the shard the value belongs to.
+pub fn verify_sharded(params: &PublicParameters, states: &[BigUint], value: &[u8], proof: &ShardedProof) -> bool {
+    if states.is_empty() || proof.shard != shard_index(value, states.len()) as u64 {
+        return false;
+    }
+    let state: &BigUint = &states[proof.shard as usize];
+    verify_membership_with(params.prime_mapping(), state, &params.modulus, value, &proof.proof.nonce, &proof.proof.witness)
+}
+
+/// One accumulator per shard, over stores that share their parameters.
+pub struct ShardedAccumulator<T: Storer> {
+    shards: Vec<SetAccumulator<T>>,
+}
+
+impl<T: Storer> ShardedAccumulator<T> {
+    /// A shard over each of `stores`, which have to agree on the modulus and generator.
+    /// Panics if there are none.
+    pub fn new(stores: Vec<T>) -> Result<Self, AccumulatorError> {
+        assert!(!stores.is_empty(), "a sharded accumulator needs at least one shard");
+        let shards: Vec<SetAccumulator<T>> = stores.into_iter().map(SetAccumulator::new).collect();
+        let first: PublicParameters = shards[0].get_public_parameters();
+        if shards.iter().any(|shard| shard.get_public_parameters() != first) {
+            return Err(AccumulatorError::UnexpectedModulus);
+        }
+        Ok(ShardedAccumulator { shards })
+    }
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+    pub fn shard(&self, index: usize) -> &SetAccumulator<T> {
+        &self.shards[index]
+    }
+    pub fn get_public_parameters(&self) -> PublicParameters {
+        self.shards[0].get_public_parameters()
+    }
+    fn shard_of(&mut self, value: &[u8]) -> &mut SetAccumulator<T> {
+        let index: usize = shard_index(value, self.shards.len());
+        &mut self.shards[index]
+    }
+    pub fn contains(&self, value: &[u8]) -> bool {
+        self.shards[shard_index(value, self.shards.len())].contains(value)
+    }
+    /// The number of members over all shards.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.len()).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// See [`SetAccumulator::add`], the witness is one in the value's shard.
+    pub fn add(&mut self, value: &[u8]) -> Result<AddOutcome, AccumulatorError> {
+        self.shard_of(value).add(value)
+    }
+    /// Adds the values a shard at a time, each shard with one exponentiation. A shard
+    /// that fails keeps none of its values, but the shards before it keep theirs.
+    pub fn add_batch(&mut self, values: &[&[u8]]) -> Result<(), AccumulatorError> {
+        let mut routed: Vec<Vec<&[u8]>> = vec![Vec::new(); self.shards.len()];
+        for value in values.iter() {
+            routed[shard_index(value, self.shards.len())].push(value);
+        }
+        for (shard, values) in self.shards.iter_mut().zip(routed) {
+            if !values.is_empty() {
+                shard.add_batch(&values)?;
+            }
+        }
+        Ok(())
+    }
+    /// Deletes with the trapdoor when the shard's store holds it, as
+    /// [`Accumulator::delete`] does.
+    pub fn delete(&mut self, value: &[u8]) -> Result<(), AccumulatorError> {
+        Accumulator::delete(self.shard_of(value), value)
+    }
+    pub fn get_proof(&self, value: &[u8]) -> Option<ShardedProof> {
+        let shard: usize = shard_index(value, self.shards.len());
+        let proof: MembershipProof = self.shards[shard].get_membership_proof(value)?;
+        Some(ShardedProof { shard: shard as u64, proof })
+    }
+    /// The state of every shard, in order.
+    pub fn states(&self) -> Vec<BigUint> {
+        self.shards.iter().map(|shard| shard.store.get_state().clone()).collect()
+    }
+    /// See [`combined_digest`].
+    pub fn digest(&self) -> [u8; 32] {
+        combined_digest(&self.get_public_parameters(), &self.states())
+    }
+}
+
+impl<T: Storer> Accumulator for ShardedAccumulator<T> {
+    type Proof = ShardedProof;
+
+    fn add(&mut self, value: &[u8]) -> Result<(), AccumulatorError> {
+        ShardedAccumulator::add(self, value).map(|_| ())
+    }
+    fn delete(&mut self, value: &[u8]) -> Result<(), AccumulatorError> {
+        ShardedAccumulator::delete(self, value)
+    }
+    fn prove(&self, value: &[u8]) -> Option<ShardedProof> {
+        self.get_proof(value)
+    }
+    fn verify(&self, value: &[u8], proof: &ShardedProof) -> bool {
+        verify_sharded(&self.get_public_parameters(), &self.states(), value, proof)
+    }
+    /// See [`combined_digest`].
+    fn state_digest(&self) -> [u8; 32] {
+        self.digest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mem_store::MemStore;
+
+    #[test]
+    fn test_sharded_accumulator() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let stores: Vec<MemStore> = (0..4).map(|_| MemStore::from_parameters(&params)).collect();
+        let mut sa: ShardedAccumulator<MemStore> = ShardedAccumulator::new(stores).unwrap();
+        let empty: [u8; 32] = sa.digest();
+        let values: Vec<Vec<u8>> = (0..12_u32).map(|n| format!("value {}", n).into_bytes()).collect();
+        let refs: Vec<&[u8]> = values.iter().map(|value| value.as_slice()).collect();
+        sa.add_batch(&refs).unwrap();
+        assert_eq!(12, sa.len());
+        // every value lands in the shard its hash picks, and more than one shard is used
+        for (index, value) in values.iter().enumerate() {
+            assert!(sa.shard(shard_index(value, 4)).contains(value), "{}", index);
+        }
+        assert!((0..4).filter(|index| !sa.shard(*index).is_empty()).count() > 1);
+
+        let states: Vec<BigUint> = sa.states();
+        let proof: ShardedProof = sa.get_proof(b"value 3").unwrap();
+        assert!(verify_sharded(&params, &states, b"value 3", &proof));
+        assert!(!verify_sharded(&params, &states, b"value 4", &proof));
+        let moved: ShardedProof = ShardedProof { shard: (proof.shard + 1) % 4, ..proof.clone() };
+        assert!(!verify_sharded(&params, &states, b"value 3", &moved));
+        assert_eq!(combined_digest(&params, &states), sa.digest());
+
+        sa.delete(b"value 3").unwrap();
+        assert!(!sa.contains(b"value 3"));
+        assert_ne!(combined_digest(&params, &states), sa.digest());
+        assert!(!verify_sharded(&params, &sa.states(), b"value 3", &proof));
+        assert_ne!(empty, sa.digest());
+
+        let other: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(43_u64));
+        let mixed: Vec<MemStore> = vec![MemStore::from_parameters(&params), MemStore::from_parameters(&other)];
+        assert_eq!(Some(AccumulatorError::UnexpectedModulus), ShardedAccumulator::new(mixed).err());
+    }
+}
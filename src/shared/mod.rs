@@ -0,0 +1,355 @@
+//! Sharing one accumulator between threads. Reads such as witness computation run side
+//! by side under a read lock, while changes to the members take the write lock and so
+//! wait for them, and for each other. Over a [`MemStore`], witnesses can instead be
+//! computed from a [`fork`](SharedAccumulator::fork), which holds the read lock only
+//! while it is copied, so a batch of adds is not held up by them.
+//!
+//! The epoch and state are published apart from the lock, swapped in atomically after
+//! every change, so reading them and verifying against them never wait on a writer.
+//!
+//! Built with `--cfg loom` the locks are loom's, and the model tests below explore every
+//! interleaving of adds, witnesses and subscriptions:
+//! `RUSTFLAGS="--cfg loom" cargo test --release --lib shared::loom_tests`.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::mpsc::Receiver;
+#[cfg(not(loom))]
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[cfg(not(loom))]
+use arc_swap::ArcSwap;
+#[cfg(loom)]
+use loom::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use num_bigint::BigUint;
+
+use crate::error::AccumulatorError;
+use crate::events::AccumulatorEvent;
+use crate::params::{PrimalityConfig, PrimeMapping};
+use crate::proof::MembershipProof;
+use crate::store::mem_store::MemStore;
+use crate::store::Storer;
+use crate::{arith, member_prime, AddOutcome, SetAccumulator};
+
+// loom cannot see into arc-swap, so its models swap the head under one of its own locks
+#[cfg(loom)]
+struct ArcSwap<H>(RwLock<std::sync::Arc<H>>);
+
+#[cfg(loom)]
+impl<H> ArcSwap<H> {
+    fn from_pointee(head: H) -> Self {
+        ArcSwap(RwLock::new(std::sync::Arc::new(head)))
+    }
+    fn load_full(&self) -> std::sync::Arc<H> {
+        self.0.read().unwrap().clone()
+    }
+    fn store(&self, head: std::sync::Arc<H>) {
+        *self.0.write().unwrap() = head;
+    }
+}
+
+/// The epoch an accumulator is at and its state then.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpochState {
+    pub epoch: u64,
+    pub state: BigUint,
+}
+
+// what verifying against the published state takes, none of which a change can alter
+struct Verifier {
+    modulus: BigUint,
+    prime_mapping: PrimeMapping,
+    primality: PrimalityConfig,
+}
+
+/// A [`SetAccumulator`] behind an `Arc<RwLock<_>>`. Cloning it hands out another handle to
+/// the same accumulator.
+///
+/// The store has to be `Send + Sync`, since every thread holding a handle reads it. The
+/// in-tree stores are, apart from [`SqliteStore`](crate::store::sqlite_store::SqliteStore),
+/// whose connection can only be used from one thread at a time.
+pub struct SharedAccumulator<T: Storer + Send + Sync> {
+    inner: Arc<RwLock<SetAccumulator<T>>>,
+    head: std::sync::Arc<ArcSwap<EpochState>>,
+    verifier: std::sync::Arc<Verifier>,
+}
+
+impl<T: Storer + Send + Sync> Clone for SharedAccumulator<T> {
+    fn clone(&self) -> Self {
+        SharedAccumulator { inner: Arc::clone(&self.inner), head: self.head.clone(), verifier: self.verifier.clone() }
+    }
+}
+
+/// The write lock on a [`SharedAccumulator`], which publishes the epoch and state the
+/// changes made through it lead to when it is dropped.
+pub struct WriteGuard<'a, T: Storer + Send + Sync> {
+    guard: RwLockWriteGuard<'a, SetAccumulator<T>>,
+    head: &'a ArcSwap<EpochState>,
+}
+
+impl<T: Storer + Send + Sync> Deref for WriteGuard<'_, T> {
+    type Target = SetAccumulator<T>;
+
+    fn deref(&self) -> &SetAccumulator<T> {
+        &self.guard
+    }
+}
+
+impl<T: Storer + Send + Sync> DerefMut for WriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut SetAccumulator<T> {
+        &mut self.guard
+    }
+}
+
+impl<T: Storer + Send + Sync> Drop for WriteGuard<'_, T> {
+    fn drop(&mut self) {
+        // still under the lock, so heads are published in the order they were reached
+        let head: EpochState = epoch_state(&self.guard);
+        if *self.head.load_full() != head {
+            self.head.store(std::sync::Arc::new(head));
+        }
+    }
+}
+
+fn epoch_state<T: Storer>(accumulator: &SetAccumulator<T>) -> EpochState {
+    EpochState { epoch: accumulator.current_epoch(), state: accumulator.store.get_state().clone() }
+}
+
+impl<T: Storer + Send + Sync> SharedAccumulator<T> {
+    pub fn new(accumulator: SetAccumulator<T>) -> Self {
+        let head: ArcSwap<EpochState> = ArcSwap::from_pointee(epoch_state(&accumulator));
+        let verifier: Verifier = Verifier { modulus: accumulator.store.get_modulus().clone(), prime_mapping: accumulator.prime_mapping(), primality: accumulator.primality() };
+        SharedAccumulator { inner: Arc::new(RwLock::new(accumulator)), head: std::sync::Arc::new(head), verifier: std::sync::Arc::new(verifier) }
+    }
+    /// Locks the accumulator for reading, for anything not covered by the methods below.
+    pub fn read(&self) -> RwLockReadGuard<'_, SetAccumulator<T>> {
+        // a writer that panicked may have left the members and state out of step
+        self.inner.read().expect("a thread panicked while changing the accumulator")
+    }
+    /// Locks the accumulator for writing, for anything not covered by the methods below.
+    pub fn write(&self) -> WriteGuard<'_, T> {
+        let guard: RwLockWriteGuard<'_, SetAccumulator<T>> = self.inner.write().expect("a thread panicked while changing the accumulator");
+        WriteGuard { guard, head: &self.head }
+    }
+    pub fn add(&self, value: &[u8]) -> Result<AddOutcome, AccumulatorError> {
+        self.write().add(value)
+    }
+    pub fn add_batch(&self, values: &[&[u8]]) -> Result<(), AccumulatorError> {
+        self.write().add_batch(values)
+    }
+    pub fn delete(&self, value: &[u8]) -> Result<(), AccumulatorError> {
+        self.write().delete(value)
+    }
+    pub fn delete_without_trapdoor(&self, value: &[u8]) -> Result<BigUint, AccumulatorError> {
+        self.write().delete_without_trapdoor(value)
+    }
+    pub fn get_witness(&self, value: &[u8]) -> Option<(BigUint, Vec<u8>)> {
+        self.read().get_witness(value)
+    }
+    pub fn get_membership_proof(&self, value: &[u8]) -> Option<MembershipProof> {
+        self.read().get_membership_proof(value)
+    }
+    /// Checks a witness against the published state, without waiting on a writer. A
+    /// witness that fails is checked again under the read lock, against the prime the
+    /// store keeps for a value added with [`SetAccumulator::add_prime`].
+    pub fn verify(&self, value: &[u8], witness: &BigUint, nonce: &[u8]) -> bool {
+        let verifier: &Verifier = &self.verifier;
+        let exponent: BigUint = member_prime(value, nonce, verifier.prime_mapping, verifier.primality);
+        arith::modpow(witness, &exponent, &verifier.modulus) == self.head.load_full().state || self.read().verify(value, witness, nonce)
+    }
+    /// The published epoch and state, read without waiting on a writer. It is the state a
+    /// witness read at the same time is for only if both come from one [`read`](Self::read).
+    pub fn head(&self) -> std::sync::Arc<EpochState> {
+        self.head.load_full()
+    }
+    /// The published state, see [`head`](Self::head).
+    pub fn state(&self) -> BigUint {
+        self.head.load_full().state.clone()
+    }
+    /// The published epoch, see [`head`](Self::head).
+    pub fn epoch(&self) -> u64 {
+        self.head.load_full().epoch
+    }
+    /// The whole accumulator in the [`snapshot`](crate::snapshot) format, taken under the
+    /// read lock so witnesses carry on while it is written.
+    pub fn export_snapshot(&self) -> Vec<u8> {
+        self.read().export_snapshot()
+    }
+    /// See [`SetAccumulator::subscribe`]. Changes are published under the write lock, so the
+    /// receiver gets every event of a change or none of them.
+    pub fn subscribe(&self) -> Receiver<AccumulatorEvent> {
+        self.write().subscribe()
+    }
+}
+
+impl SharedAccumulator<MemStore> {
+    /// See [`SetAccumulator::fork`]. The read lock is held for as long as the copy takes,
+    /// which does not grow with the number of members.
+    pub fn fork(&self) -> SetAccumulator<MemStore> {
+        self.read().fork()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::thread::{self, JoinHandle};
+    use crate::params::PublicParameters;
+    use crate::store::mem_store::MemStore;
+
+    fn assert_send_sync<S: Send + Sync>() {}
+
+    #[test]
+    fn test_concurrent_readers_and_writers() {
+        assert_send_sync::<SharedAccumulator<MemStore>>();
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let shared: SharedAccumulator<MemStore> = SharedAccumulator::new(SetAccumulator::new(MemStore::from_parameters(&params)));
+        shared.add(b"alice").unwrap();
+        let writers: Vec<JoinHandle<()>> = (0..4_u8)
+            .map(|i| {
+                let shared: SharedAccumulator<MemStore> = shared.clone();
+                thread::spawn(move || {
+                    shared.add(&[b'w', i]).unwrap();
+                })
+            })
+            .collect();
+        let readers: Vec<JoinHandle<()>> = (0..4)
+            .map(|_| {
+                let shared: SharedAccumulator<MemStore> = shared.clone();
+                thread::spawn(move || {
+                    // the witness and the state it is checked against come from one read
+                    let sa = shared.read();
+                    let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+                    assert!(sa.verify(b"alice", &witness, &nonce));
+                })
+            })
+            .collect();
+        for handle in writers.into_iter().chain(readers) {
+            handle.join().unwrap();
+        }
+        assert_eq!(5, shared.read().store.member_count());
+        // a snapshot only needs the read lock
+        let snapshot: Vec<u8> = {
+            let _reader = shared.read();
+            shared.export_snapshot()
+        };
+        assert_eq!(shared.read().export_snapshot(), snapshot);
+        let (witness, nonce): (BigUint, Vec<u8>) = shared.get_witness(&[b'w', 3]).unwrap();
+        assert!(shared.verify(&[b'w', 3], &witness, &nonce));
+    }
+
+    #[test]
+    fn test_head_read_during_write() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let shared: SharedAccumulator<MemStore> = SharedAccumulator::new(SetAccumulator::new(MemStore::from_parameters(&params)));
+        shared.add_batch(&[b"alice".as_ref(), b"bob"]).unwrap();
+        let (witness, nonce): (BigUint, Vec<u8>) = shared.get_witness(b"alice").unwrap();
+        let head: Arc<EpochState> = shared.head();
+        assert_eq!((1, shared.read().store.get_state()), (head.epoch, &head.state));
+
+        let mut writer: WriteGuard<MemStore> = shared.write();
+        writer.add(b"carol").unwrap();
+        // with the write lock held, another thread still reads the last published head
+        let reader: SharedAccumulator<MemStore> = shared.clone();
+        let (state, verified): (BigUint, bool) = thread::spawn(move || (reader.state(), reader.verify(b"alice", &witness, &nonce))).join().unwrap();
+        assert_eq!((head.state.clone(), true), (state, verified));
+        let state: BigUint = writer.store.get_state().clone();
+        drop(writer);
+        // and the new one once the guard is dropped
+        assert_eq!((2, state), (shared.epoch(), shared.state()));
+        let (witness, nonce): (BigUint, Vec<u8>) = shared.get_witness(b"carol").unwrap();
+        assert!(shared.verify(b"carol", &witness, &nonce));
+    }
+
+    #[test]
+    fn test_witnesses_from_forks() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let shared: SharedAccumulator<MemStore> = SharedAccumulator::new(SetAccumulator::new(MemStore::from_parameters(&params)));
+        shared.add_batch(&[b"alice".as_ref(), b"bob", b"carol"]).unwrap();
+        let readers: Vec<JoinHandle<()>> = (0..4)
+            .map(|_| {
+                let shared: SharedAccumulator<MemStore> = shared.clone();
+                thread::spawn(move || {
+                    // the fork holds the members and state of one moment, whatever is added since
+                    let fork: SetAccumulator<MemStore> = shared.fork();
+                    let (witness, nonce): (BigUint, Vec<u8>) = fork.get_witness(b"alice").unwrap();
+                    assert!(fork.verify(b"alice", &witness, &nonce));
+                })
+            })
+            .collect();
+        for i in 0..4_u8 {
+            shared.add(&[b'w', i]).unwrap();
+        }
+        for handle in readers {
+            handle.join().unwrap();
+        }
+
+        // a fork and the accumulator it was taken from change apart
+        let mut fork: SetAccumulator<MemStore> = shared.fork();
+        fork.delete_without_trapdoor(b"alice").unwrap();
+        shared.add(b"dave").unwrap();
+        assert!(shared.read().store.contains_member(b"alice") && !fork.store.contains_member(b"alice"));
+        assert!(!fork.store.contains_member(b"dave"));
+        assert_eq!((8, 6), (shared.read().store.member_count(), fork.store.member_count()));
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use std::sync::mpsc::TryRecvError;
+    use crate::params::PublicParameters;
+    use crate::store::mem_store::MemStore;
+
+    fn shared() -> SharedAccumulator<MemStore> {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        SharedAccumulator::new(SetAccumulator::new(MemStore::from_parameters(&params)))
+    }
+
+    #[test]
+    fn test_witness_during_add() {
+        loom::model(|| {
+            let shared: SharedAccumulator<MemStore> = shared();
+            shared.add(b"alice").unwrap();
+            let writer: SharedAccumulator<MemStore> = shared.clone();
+            let handle = loom::thread::spawn(move || {
+                writer.add(b"bob").unwrap();
+            });
+            {
+                // whichever side of the add it lands on, the witness fits the state it is read with
+                let sa = shared.read();
+                let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+                assert!(sa.verify(b"alice", &witness, &nonce));
+            }
+            handle.join().unwrap();
+            let (witness, nonce): (BigUint, Vec<u8>) = shared.get_witness(b"bob").unwrap();
+            assert!(shared.verify(b"bob", &witness, &nonce));
+        });
+    }
+
+    #[test]
+    fn test_subscribe_during_add() {
+        loom::model(|| {
+            let shared: SharedAccumulator<MemStore> = shared();
+            let writer: SharedAccumulator<MemStore> = shared.clone();
+            let handle = loom::thread::spawn(move || {
+                writer.add(b"alice").unwrap();
+            });
+            let receiver: Receiver<AccumulatorEvent> = shared.subscribe();
+            handle.join().unwrap();
+            // a subscriber gets both events of the add or, subscribing after it, neither
+            match receiver.try_recv() {
+                Ok(AccumulatorEvent::Added { value, .. }) => {
+                    assert_eq!(b"alice".to_vec(), value);
+                    match receiver.try_recv() {
+                        Ok(AccumulatorEvent::StateChanged { state, .. }) => assert_eq!(shared.state(), state),
+                        other => panic!("expected the state the add left, got {:?}", other),
+                    }
+                }
+                Err(TryRecvError::Empty) => assert!(shared.read().store.contains_member(b"alice")),
+                other => panic!("expected the add or nothing, got {:?}", other),
+            }
+            assert_eq!(Err(TryRecvError::Empty), receiver.try_recv());
+        });
+    }
+}
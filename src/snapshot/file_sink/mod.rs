@@ -0,0 +1,99 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::snapshot::scheduler::SnapshotSink;
+
+/// Keeps snapshots as `snapshot-<sequence>.tgsn` files in a directory.
+#[derive(Debug)]
+pub struct FileSink {
+    dir: PathBuf,
+}
+
+impl FileSink {
+    pub fn new<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(FileSink { dir: dir.as_ref().to_path_buf() })
+    }
+    pub fn path(&self, sequence: u64) -> PathBuf {
+        // zero padded so the files also sort by sequence number
+        self.dir.join(format!("snapshot-{:020}.tgsn", sequence))
+    }
+}
+
+impl SnapshotSink for FileSink {
+    fn put(&mut self, sequence: u64, write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>) -> io::Result<()> {
+        // write next to the final path and rename, so a crash never leaves a half written snapshot behind
+        let path: PathBuf = self.path(sequence);
+        let tmp: PathBuf = path.with_extension("tgsn.tmp");
+        let mut writer: BufWriter<File> = BufWriter::new(File::create(&tmp)?);
+        write(&mut writer)?;
+        let file: File = writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        fs::rename(&tmp, &path)?;
+        // make the rename itself durable
+        #[cfg(unix)]
+        File::open(&self.dir)?.sync_all()?;
+        Ok(())
+    }
+    fn list(&mut self) -> io::Result<Vec<u64>> {
+        let mut sequences: Vec<u64> = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name();
+            let sequence: Option<u64> = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("snapshot-"))
+                .and_then(|name| name.strip_suffix(".tgsn"))
+                .and_then(|sequence| sequence.parse().ok());
+            if let Some(sequence) = sequence {
+                sequences.push(sequence);
+            }
+        }
+        sequences.sort_unstable();
+        Ok(sequences)
+    }
+    fn remove(&mut self, sequence: u64) -> io::Result<()> {
+        fs::remove_file(self.path(sequence))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use num_bigint::BigUint;
+    use crate::params::PublicParameters;
+    use crate::snapshot::SnapshotReader;
+    use crate::snapshot::scheduler::{SnapshotPolicy, SnapshotScheduler};
+    use crate::store::{Storer, mem_store::MemStore};
+
+    #[test]
+    fn test_scheduled_file_snapshots() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let dir: PathBuf = std::env::temp_dir().join(format!("tangerine-snapshots-{}-{}", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut store: MemStore = MemStore::from_parameters(&params);
+        let mut scheduler: SnapshotScheduler<FileSink> =
+            SnapshotScheduler::new(FileSink::new(&dir).unwrap(), SnapshotPolicy::EveryMutations(3), 2);
+
+        // pretend to add ten members, snapshotting after every third one
+        let mut taken: Vec<u64> = Vec::new();
+        for i in 1_u64..=10 {
+            store.insert_member(&i.to_be_bytes(), &[0; 32]);
+            store.set_state(&BigUint::from(i));
+            if scheduler.after_mutation(&store).unwrap() {
+                taken.push(i);
+            }
+        }
+        assert_eq!(vec![3, 6, 9], taken);
+        assert_eq!(Some(9), scheduler.last_durable());
+        // only the two most recent are retained, and the latest holds nine members
+        assert_eq!(vec![6, 9], scheduler.sink().list().unwrap());
+        let file: File = File::open(scheduler.sink().path(9)).unwrap();
+        let reader: SnapshotReader<File> = SnapshotReader::new(file).unwrap();
+        assert_eq!(9, reader.header().member_count);
+        assert_eq!(BigUint::from(9_u64), reader.header().state);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
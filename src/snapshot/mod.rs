@@ -0,0 +1,356 @@
+pub mod file_sink;
+pub mod scheduler;
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+use num_bigint::BigUint;
+
+use crate::params::{PrimeMapping, PublicParameters};
+use crate::store::Storer;
+
+// every snapshot starts with these bytes followed by the format version
+const SNAPSHOT_MAGIC: &[u8; 4] = b"tgsn";
+// version 1 predates configurable prime mappings and always means the default one, and
+// version 2 the primes of the members
+const SNAPSHOT_VERSION: u8 = 3;
+
+/// Everything in a snapshot that comes before the members.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotHeader {
+    pub params: PublicParameters,
+    pub state: BigUint,
+    pub member_count: u64,
+}
+
+pub(crate) fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    let len: u32 = u32::try_from(bytes.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "field longer than 2^32 bytes"))?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(bytes)
+}
+
+pub(crate) fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut len: [u8; 4] = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len: u64 = u32::from_be_bytes(len) as u64;
+    // read through take() so a corrupt length can't make us allocate gigabytes up front
+    let mut bytes: Vec<u8> = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "snapshot field is truncated"));
+    }
+    Ok(bytes)
+}
+
+// split a length-prefixed field off the front of `bytes` without copying it
+pub(crate) fn split_bytes<'a>(bytes: &mut &'a [u8]) -> io::Result<&'a [u8]> {
+    let truncated = || io::Error::new(io::ErrorKind::UnexpectedEof, "snapshot field is truncated");
+    if bytes.len() < 4 {
+        return Err(truncated());
+    }
+    let (len, rest) = bytes.split_at(4);
+    let len: usize = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    if rest.len() < len {
+        return Err(truncated());
+    }
+    let (field, rest) = rest.split_at(len);
+    *bytes = rest;
+    Ok(field)
+}
+
+fn check_version(magic: &[u8]) -> io::Result<u8> {
+    if &magic[..4] != SNAPSHOT_MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a tangerine snapshot"));
+    }
+    if magic[4] == 0 || magic[4] > SNAPSHOT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported snapshot version {}", magic[4]),
+        ));
+    }
+    Ok(magic[4])
+}
+
+fn read_prime_mapping<R: Read>(reader: &mut R, version: u8) -> io::Result<PrimeMapping> {
+    if version == 1 {
+        return Ok(PrimeMapping::default());
+    }
+    PrimeMapping::read_from(reader)
+}
+
+pub(crate) fn read_biguint<R: Read>(reader: &mut R) -> io::Result<BigUint> {
+    Ok(BigUint::from_bytes_be(&read_bytes(reader)?))
+}
+
+/// Writes the store to `writer` one member at a time, so the snapshot is never
+/// assembled in memory.
+///
+/// The format is the magic bytes `tgsn`, a version byte, the prime mapping (hash
+/// function id, big-endian u64 prime size and flags byte, as in the parameters), the
+/// modulus, generator and state as length-prefixed big-endian integers, a big-endian
+/// u64 member count and then every member as a length-prefixed value followed by its
+/// length-prefixed nonce and the length-prefixed big-endian prime the store keeps for it,
+/// empty if it keeps none. All length prefixes are big-endian u32.
+pub fn write_snapshot<T: Storer, W: Write>(store: &T, mut writer: W) -> io::Result<()> {
+    writer.write_all(SNAPSHOT_MAGIC)?;
+    writer.write_all(&[SNAPSHOT_VERSION])?;
+    store.get_prime_mapping().write_to(&mut writer)?;
+    write_bytes(&mut writer, &store.get_modulus().to_bytes_be())?;
+    write_bytes(&mut writer, &store.get_generator().to_bytes_be())?;
+    write_bytes(&mut writer, &store.get_state().to_bytes_be())?;
+    writer.write_all(&(store.member_count() as u64).to_be_bytes())?;
+    for (value, nonce) in store.iter_members() {
+        write_bytes(&mut writer, value)?;
+        write_bytes(&mut writer, nonce)?;
+        write_bytes(&mut writer, &store.get_prime(value, nonce).map(BigUint::to_bytes_be).unwrap_or_default())?;
+    }
+    writer.flush()
+}
+
+// a member as a snapshot holds it, with the prime kept for it if there is one
+type SnapshotMember = (Vec<u8>, Vec<u8>, Option<BigUint>);
+
+/// Reads a snapshot back incrementally: the header is parsed up front and the members
+/// are then yielded one at a time as `(value, nonce)` pairs.
+pub struct SnapshotReader<R: Read> {
+    header: SnapshotHeader,
+    version: u8,
+    remaining: u64,
+    reader: R,
+}
+
+impl<R: Read> SnapshotReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic: [u8; 5] = [0; 5];
+        reader.read_exact(&mut magic)?;
+        let version: u8 = check_version(&magic)?;
+        let prime_mapping: PrimeMapping = read_prime_mapping(&mut reader, version)?;
+        let modulus: BigUint = read_biguint(&mut reader)?;
+        let generator: BigUint = read_biguint(&mut reader)?;
+        let state: BigUint = read_biguint(&mut reader)?;
+        let mut member_count: [u8; 8] = [0; 8];
+        reader.read_exact(&mut member_count)?;
+        let member_count: u64 = u64::from_be_bytes(member_count);
+        let header: SnapshotHeader = SnapshotHeader {
+            params: PublicParameters::new(modulus, generator).with_prime_mapping(prime_mapping),
+            state,
+            member_count,
+        };
+        Ok(SnapshotReader { header, version, remaining: member_count, reader })
+    }
+    pub fn header(&self) -> &SnapshotHeader {
+        &self.header
+    }
+    /// Inserts every member of the snapshot into `store` as it is read, along with the
+    /// prime kept for it, and then sets the state. The store must be empty and configured with the snapshot's parameters. If
+    /// reading fails part way the store is left with a partial member list and its
+    /// original state, and should be discarded.
+    pub fn restore_into<T: Storer>(mut self, store: &mut T) -> io::Result<()> {
+        let params: PublicParameters = PublicParameters::new(store.get_modulus().clone(), store.get_generator().clone())
+            .with_prime_mapping(store.get_prime_mapping());
+        if params != self.header.params {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "snapshot was taken with different public parameters",
+            ));
+        }
+        if store.member_count() != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "can only restore into an empty store"));
+        }
+        while let Some(member) = self.next_member() {
+            let (value, nonce, prime): SnapshotMember = member?;
+            if let Some(prime) = prime {
+                store.set_prime(&value, &nonce, &prime);
+            }
+            store.insert_member(&value, &nonce);
+        }
+        // only move the state once every member made it in
+        store.set_state(&self.header.state);
+        Ok(())
+    }
+    // the next member with the prime kept for it, if the snapshot has one
+    fn next_member(&mut self) -> Option<io::Result<SnapshotMember>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let version: u8 = self.version;
+        let reader: &mut R = &mut self.reader;
+        let member = read_bytes(reader).and_then(|value| {
+            let nonce: Vec<u8> = read_bytes(reader)?;
+            let prime: Option<BigUint> = match version {
+                1 | 2 => None,
+                _ => Some(read_bytes(reader)?).filter(|prime| !prime.is_empty()).map(|prime| BigUint::from_bytes_be(&prime)),
+            };
+            Ok((value, nonce, prime))
+        });
+        if member.is_err() {
+            // the stream is unusable after an error, don't keep reading garbage
+            self.remaining = 0;
+        }
+        Some(member)
+    }
+}
+
+impl<R: Read> Iterator for SnapshotReader<R> {
+    type Item = io::Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_member().map(|member| member.map(|(value, nonce, _)| (value, nonce)))
+    }
+}
+
+/// A snapshot parsed in place from a byte buffer. Fields are borrowed from the buffer
+/// and only copied into big integers when asked for, which keeps allocations down when
+/// many snapshots are inspected or only a few fields are needed.
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotView<'a> {
+    version: u8,
+    prime_mapping: PrimeMapping,
+    modulus: &'a [u8],
+    generator: &'a [u8],
+    state: &'a [u8],
+    member_count: u64,
+    members: &'a [u8],
+}
+
+impl<'a> SnapshotView<'a> {
+    pub fn parse(mut bytes: &'a [u8]) -> io::Result<Self> {
+        if bytes.len() < 5 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "snapshot is truncated"));
+        }
+        let version: u8 = check_version(&bytes[..5])?;
+        bytes = &bytes[5..];
+        let prime_mapping: PrimeMapping = read_prime_mapping(&mut bytes, version)?;
+        let modulus: &[u8] = split_bytes(&mut bytes)?;
+        let generator: &[u8] = split_bytes(&mut bytes)?;
+        let state: &[u8] = split_bytes(&mut bytes)?;
+        if bytes.len() < 8 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "snapshot is truncated"));
+        }
+        let (member_count, members) = bytes.split_at(8);
+        let member_count: u64 = u64::from_be_bytes(<[u8; 8]>::try_from(member_count).unwrap());
+        Ok(SnapshotView { version, prime_mapping, modulus, generator, state, member_count, members })
+    }
+    /// Big-endian bytes of the modulus, borrowed from the buffer.
+    pub fn modulus_bytes(&self) -> &'a [u8] {
+        self.modulus
+    }
+    /// Big-endian bytes of the generator, borrowed from the buffer.
+    pub fn generator_bytes(&self) -> &'a [u8] {
+        self.generator
+    }
+    /// Big-endian bytes of the state, borrowed from the buffer.
+    pub fn state_bytes(&self) -> &'a [u8] {
+        self.state
+    }
+    pub fn params(&self) -> PublicParameters {
+        PublicParameters::new(BigUint::from_bytes_be(self.modulus), BigUint::from_bytes_be(self.generator))
+            .with_prime_mapping(self.prime_mapping)
+    }
+    pub fn state(&self) -> BigUint {
+        BigUint::from_bytes_be(self.state)
+    }
+    pub fn member_count(&self) -> u64 {
+        self.member_count
+    }
+    /// The `(value, nonce)` pairs of the snapshot, borrowed from the buffer.
+    pub fn members(&self) -> impl Iterator<Item = io::Result<(&'a [u8], &'a [u8])>> {
+        let mut rest: &'a [u8] = self.members;
+        let mut remaining: u64 = self.member_count;
+        let version: u8 = self.version;
+        std::iter::from_fn(move || {
+            if remaining == 0 {
+                return None;
+            }
+            remaining -= 1;
+            let member = split_bytes(&mut rest).and_then(|value| {
+                let nonce: &[u8] = split_bytes(&mut rest)?;
+                // the primes are for restoring into a store, not for reading
+                if version >= 3 {
+                    split_bytes(&mut rest)?;
+                }
+                Ok((value, nonce))
+            });
+            if member.is_err() {
+                remaining = 0;
+            }
+            Some(member)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mem_store::MemStore;
+
+    fn get_store() -> MemStore {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut store: MemStore = MemStore::from_parameters(&params);
+        for i in 0_u8..10 {
+            store.insert_member(&vec![i; i as usize], &[255 - i; 32]);
+        }
+        store.set_state(&BigUint::from(1234_u64));
+        store
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let store: MemStore = get_store();
+        let mut bytes: Vec<u8> = Vec::new();
+        write_snapshot(&store, &mut bytes).unwrap();
+
+        let reader: SnapshotReader<&[u8]> = SnapshotReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(BigUint::from(1234_u64), reader.header().state);
+        assert_eq!(10, reader.header().member_count);
+        let mut restored: MemStore = MemStore::from_parameters(&reader.header().params);
+        reader.restore_into(&mut restored).unwrap();
+        assert_eq!(store.get_state(), restored.get_state());
+        assert_eq!(store.member_count(), restored.member_count());
+        for (value, nonce) in store.iter_members() {
+            assert_eq!(Some(nonce.to_vec()), restored.get_nonce(value));
+        }
+
+        // a cut off snapshot is rejected and the state is left alone
+        let reader: SnapshotReader<&[u8]> = SnapshotReader::new(&bytes[..bytes.len() - 1]).unwrap();
+        let mut truncated: MemStore = MemStore::from_parameters(&reader.header().params);
+        assert!(reader.restore_into(&mut truncated).is_err());
+        assert_eq!(BigUint::from(42_u64), *truncated.get_state());
+
+        // snapshots from before the prime mapping was recorded use the default one
+        let mut version_1: Vec<u8> = b"tgsn\x01".to_vec();
+        version_1.extend_from_slice(&bytes[15..]);
+        let reader: SnapshotReader<&[u8]> = SnapshotReader::new(version_1.as_slice()).unwrap();
+        assert_eq!(PrimeMapping::default(), reader.header().params.prime_mapping());
+        assert_eq!(10, reader.header().member_count);
+
+        // and so is anything that isn't a snapshot of this version
+        bytes[4] = SNAPSHOT_VERSION + 1;
+        assert!(SnapshotReader::new(bytes.as_slice()).is_err());
+        assert!(SnapshotReader::new(b"not a snapshot".as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_view() {
+        let store: MemStore = get_store();
+        let mut bytes: Vec<u8> = Vec::new();
+        write_snapshot(&store, &mut bytes).unwrap();
+
+        let view: SnapshotView = SnapshotView::parse(&bytes).unwrap();
+        assert_eq!(PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64)), view.params());
+        assert_eq!(BigUint::from(1234_u64).to_bytes_be().as_slice(), view.state_bytes());
+        assert_eq!(10, view.member_count());
+        let members: Vec<(&[u8], &[u8])> = view.members().collect::<io::Result<_>>().unwrap();
+        assert_eq!(10, members.len());
+        for (value, nonce) in members {
+            assert_eq!(Some(nonce.to_vec()), store.get_nonce(value));
+        }
+
+        // truncation shows up when the members are walked
+        let view: SnapshotView = SnapshotView::parse(&bytes[..bytes.len() - 1]).unwrap();
+        assert!(view.members().any(|member| member.is_err()));
+        assert!(SnapshotView::parse(&bytes[..6]).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::snapshot::write_snapshot;
+use crate::store::Storer;
+
+/// Somewhere snapshots can be kept, e.g. a directory or an object store bucket.
+pub trait SnapshotSink {
+    /// Stores the snapshot produced by `write` under `sequence`. The snapshot must be
+    /// durable once this returns `Ok`.
+    fn put(&mut self, sequence: u64, write: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>) -> io::Result<()>;
+    /// Sequence numbers of all stored snapshots, oldest first.
+    fn list(&mut self) -> io::Result<Vec<u64>>;
+    fn remove(&mut self, sequence: u64) -> io::Result<()>;
+}
+
+/// When the scheduler should take a snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotPolicy {
+    /// After every n mutations.
+    EveryMutations(u64),
+    /// On the first mutation after the interval has passed since the last snapshot.
+    Interval(Duration),
+}
+
+/// Takes snapshots of a store according to a [`SnapshotPolicy`], writes them to a
+/// [`SnapshotSink`] and prunes all but the most recent ones.
+///
+/// The scheduler has no thread of its own; call [`SnapshotScheduler::after_mutation`]
+/// after every add (or delete) and it decides whether a snapshot is due. Snapshots are
+/// keyed by the number of mutations seen so far.
+pub struct SnapshotScheduler<S: SnapshotSink> {
+    sink: S,
+    policy: SnapshotPolicy,
+    retain: usize,
+    sequence: u64,
+    last_snapshot_at: Instant,
+    last_snapshot_sequence: u64,
+    last_durable: Option<u64>,
+}
+
+impl<S: SnapshotSink> SnapshotScheduler<S> {
+    pub fn new(sink: S, policy: SnapshotPolicy, retain: usize) -> Self {
+        SnapshotScheduler {
+            sink,
+            policy,
+            retain,
+            sequence: 0,
+            last_snapshot_at: Instant::now(),
+            last_snapshot_sequence: 0,
+            last_durable: None,
+        }
+    }
+    /// Record one mutation of `store` and snapshot it if the policy says so. Returns
+    /// whether a snapshot was taken.
+    pub fn after_mutation<T: Storer>(&mut self, store: &T) -> io::Result<bool> {
+        self.sequence += 1;
+        let due: bool = match self.policy {
+            SnapshotPolicy::EveryMutations(n) => self.sequence - self.last_snapshot_sequence >= n,
+            SnapshotPolicy::Interval(interval) => self.last_snapshot_at.elapsed() >= interval,
+        };
+        if due {
+            self.snapshot(store)?;
+        }
+        Ok(due)
+    }
+    /// Snapshot `store` right away, regardless of the policy.
+    pub fn snapshot<T: Storer>(&mut self, store: &T) -> io::Result<()> {
+        let sequence: u64 = self.sequence;
+        self.sink.put(sequence, &mut |writer| write_snapshot(store, writer))?;
+        self.last_snapshot_at = Instant::now();
+        self.last_snapshot_sequence = sequence;
+        self.last_durable = Some(sequence);
+        // drop everything but the newest `retain` snapshots
+        let stored: Vec<u64> = self.sink.list()?;
+        let excess: usize = stored.len().saturating_sub(self.retain);
+        for old in stored.into_iter().take(excess) {
+            self.sink.remove(old)?;
+        }
+        Ok(())
+    }
+    /// Sequence number of the most recent snapshot known to be durable.
+    pub fn last_durable(&self) -> Option<u64> {
+        self.last_durable
+    }
+    pub fn sink(&mut self) -> &mut S {
+        &mut self.sink
+    }
+}
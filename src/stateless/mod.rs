@@ -0,0 +1,86 @@
+use num_bigint::BigUint;
+use rand::Rng;
+
+use crate::arith;
+use crate::hash_value_to_prime;
+use crate::params::{PublicParameters, SecurityLevel};
+
+/// What the manager hands back for an added value. The holder keeps all of it, because
+/// the manager does not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issued {
+    pub nonce: Vec<u8>,
+    pub prime: BigUint,
+    pub witness: BigUint,
+}
+
+/// An accumulator that keeps nothing but its current state. Accumulated values are never
+/// retained, so it suits deployments where the manager must not learn or keep the set.
+///
+/// Holders keep their own [`Issued`] witness and bring it up to date with
+/// [`StatelessAccumulator::apply_add`], using the prime published with each later add.
+pub struct StatelessAccumulator {
+    params: PublicParameters,
+    state: BigUint,
+    security_level: SecurityLevel,
+}
+
+impl StatelessAccumulator {
+    pub fn new(params: PublicParameters, security_level: SecurityLevel) -> Self {
+        let state: BigUint = params.genesis_state();
+        StatelessAccumulator { params, state, security_level }
+    }
+    pub fn from_state(params: PublicParameters, state: BigUint, security_level: SecurityLevel) -> Self {
+        StatelessAccumulator { params, state, security_level }
+    }
+    pub fn get_state(&self) -> &BigUint {
+        &self.state
+    }
+    pub fn get_public_parameters(&self) -> &PublicParameters {
+        &self.params
+    }
+    /// Adds `value` and returns its witness, which is simply the state before the add.
+    /// `prime` is the delta every other holder has to apply to their witness.
+    pub fn add(&mut self, value: &[u8]) -> Issued {
+        // without a member list there is nothing to check a nonce or prime against, a
+        // fresh 256 bit nonce makes a collision negligible
+        let nonce: [u8; 32] = rand::thread_rng().gen();
+        let prime: BigUint = hash_value_to_prime(value, &nonce, self.params.prime_mapping(), self.security_level);
+        let witness: BigUint = self.state.clone();
+        self.state = arith::modpow(&self.state, &prime, &self.params.modulus);
+        Issued { nonce: nonce.to_vec(), prime, witness }
+    }
+    /// Brings a holder's witness up to date with an add that happened after it was issued.
+    pub fn apply_add(params: &PublicParameters, witness: &BigUint, added_prime: &BigUint) -> BigUint {
+        arith::modpow(witness, added_prime, &params.modulus)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::RandBigInt;
+
+    #[test]
+    fn test_holders_keep_their_own_witnesses() {
+        // any odd modulus exercises the bookkeeping, the math is the same as with RSA
+        let mut rng = rand::thread_rng();
+        let modulus: BigUint = rng.gen_biguint(256) | BigUint::from(1_u64);
+        let generator: BigUint = rng.gen_biguint_below(&modulus);
+        let params: PublicParameters = PublicParameters::new(modulus.clone(), generator);
+        let mut sa: StatelessAccumulator = StatelessAccumulator::new(params.clone(), SecurityLevel::default());
+
+        let mut holders: Vec<Issued> = Vec::new();
+        for value in [b"alice".as_ref(), b"bob".as_ref(), b"carol".as_ref()].iter() {
+            let issued: Issued = sa.add(value);
+            // everyone who already holds a witness applies the published prime
+            for holder in holders.iter_mut() {
+                holder.witness = StatelessAccumulator::apply_add(&params, &holder.witness, &issued.prime);
+            }
+            holders.push(issued);
+        }
+        for holder in holders.iter() {
+            assert_eq!(sa.get_state(), &holder.witness.modpow(&holder.prime, &modulus));
+        }
+    }
+}
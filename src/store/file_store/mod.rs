@@ -0,0 +1,798 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::error::AccumulatorError;
+use crate::params::{HashFunction, PrimeMapping, PublicParameters, Trapdoor};
+use crate::snapshot::{read_bytes, write_bytes};
+use crate::store::{
+    read_epoch, read_record, write_epoch, write_record, AuditRecord, Epoch, MemberChange, Operation, PrimeCache, Storer, TrackedMembers,
+};
+
+const LOG_MAGIC: &[u8; 4] = b"tglg";
+// version 1 predates the audit log, its entries hold no audit records, version 2
+// predates checksums and version 3 the primes of the members added
+const LOG_VERSION: u8 = 4;
+
+// the first four bytes of SHA-256 over the body of an entry, which follow it from
+// version 3 on
+fn checksum(body: &[u8]) -> [u8; 4] {
+    let digest = Sha256::digest(body);
+    [digest[0], digest[1], digest[2], digest[3]]
+}
+
+// an epoch and the audit records of its changes as one entry of the log, prefixed by its
+// length and followed by its checksum
+fn encode_entry(epoch: &Epoch, log: &[AuditRecord], version: u8) -> io::Result<Vec<u8>> {
+    let mut body: Vec<u8> = Vec::new();
+    if version >= 4 {
+        write_epoch(&mut body, epoch)?;
+    } else {
+        let operations: Vec<Operation> = epoch
+            .operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Add { value, nonce, .. } => Operation::Add { value: value.clone(), nonce: nonce.clone(), prime: None },
+                Operation::Delete { value } => Operation::Delete { value: value.clone() },
+            })
+            .collect();
+        write_epoch(&mut body, &Epoch { operations, state: epoch.state.clone() })?;
+    }
+    if version >= 2 {
+        body.extend_from_slice(&(log.len() as u32).to_be_bytes());
+        for record in log.iter() {
+            write_record(&mut body, record)?;
+        }
+    }
+    let mut entry: Vec<u8> = Vec::with_capacity(body.len() + 8);
+    write_bytes(&mut entry, &body)?;
+    if version >= 3 {
+        entry.extend_from_slice(&checksum(&body));
+    }
+    Ok(entry)
+}
+
+// whether the bytes from an entry that failed to read to the end of the log are a write
+// that never completed: one entry running to the end, or zeros, which is what a file that
+// was extended but never written reads as after a power failure
+fn is_torn_tail(rest: &[u8], version: u8) -> bool {
+    if rest.iter().all(|byte| *byte == 0) {
+        return true;
+    }
+    let checksum_len: usize = if version >= 3 { 4 } else { 0 };
+    match rest.get(..4) {
+        Some(len) => 4 + u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize + checksum_len >= rest.len(),
+        None => true,
+    }
+}
+
+fn decode_entry(mut body: &[u8], version: u8) -> io::Result<(Epoch, Vec<AuditRecord>)> {
+    let epoch: Epoch = read_epoch(&mut body)?;
+    let mut log: Vec<AuditRecord> = Vec::new();
+    if version >= 2 {
+        let mut count: [u8; 4] = [0; 4];
+        body.read_exact(&mut count)?;
+        for _ in 0..u32::from_be_bytes(count) {
+            log.push(read_record(&mut body)?);
+        }
+    }
+    if !body.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "corrupt log entry"));
+    }
+    Ok((epoch, log))
+}
+
+// takes the advisory lock on a log, shared or exclusive, failing rather than waiting
+// if a store that conflicts with it holds it
+fn lock(file: &File, shared: bool) -> io::Result<()> {
+    let locked: Result<(), TryLockError> = if shared { file.try_lock_shared() } else { file.try_lock() };
+    match locked {
+        Ok(()) => Ok(()),
+        Err(TryLockError::WouldBlock) => Err(io::Error::new(io::ErrorKind::WouldBlock, "log is in use by another store")),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Reads a store's log: the parameters it was created over up front, then every entry
+/// in the order it was written.
+pub struct LogReader<R: Read> {
+    params: PublicParameters,
+    version: u8,
+    reader: R,
+    // bytes read up to the end of the last whole entry
+    position: u64,
+}
+
+impl LogReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        LogReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> LogReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic: [u8; 5] = [0; 5];
+        reader.read_exact(&mut magic)?;
+        if &magic[..4] != LOG_MAGIC || !(1..=LOG_VERSION).contains(&magic[4]) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a supported tangerine log"));
+        }
+        let params: Vec<u8> = read_bytes(&mut reader)?;
+        let position: u64 = (magic.len() + 4 + params.len()) as u64;
+        Ok(LogReader { params: PublicParameters::from_bytes(&params)?, version: magic[4], reader, position })
+    }
+    pub fn params(&self) -> &PublicParameters {
+        &self.params
+    }
+    /// The audit records in the rest of the log, see [`Storer::iter_log`].
+    pub fn audit_log(mut self) -> impl Iterator<Item = io::Result<AuditRecord>> {
+        std::iter::from_fn(move || self.next_entry()).flat_map(|entry| match entry {
+            Ok((_, log)) => log.into_iter().map(Ok).collect::<Vec<_>>(),
+            Err(e) => vec![Err(e)],
+        })
+    }
+    fn next_entry(&mut self) -> Option<io::Result<(Epoch, Vec<AuditRecord>)>> {
+        // a clean end of the log is only possible between entries
+        let mut len: [u8; 4] = [0; 4];
+        match self.reader.read(&mut len[..1]) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(e) => return Some(Err(e)),
+        }
+        let entry = self.reader.read_exact(&mut len[1..]).and_then(|_| {
+            let len: u64 = u32::from_be_bytes(len) as u64;
+            let mut body: Vec<u8> = Vec::new();
+            self.reader.by_ref().take(len).read_to_end(&mut body)?;
+            if body.len() as u64 != len {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "log entry is truncated"));
+            }
+            let mut end: u64 = self.position + 4 + len;
+            if self.version >= 3 {
+                let mut stored: [u8; 4] = [0; 4];
+                self.reader.read_exact(&mut stored)?;
+                if stored != checksum(&body) {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "log entry does not match its checksum"));
+                }
+                end += 4;
+            }
+            self.position = end;
+            decode_entry(&body, self.version)
+        });
+        Some(entry)
+    }
+}
+
+impl<R: Read> Iterator for LogReader<R> {
+    type Item = io::Result<Epoch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry().map(|entry| entry.map(|(epoch, _)| epoch))
+    }
+}
+
+/// Follows the log a [`FileStore`] in another process, or this one, is appending to, for
+/// a [`Follower`](crate::replica::Follower). It takes no lock on the log: it reads only
+/// the entries that were written whole, and an entry the writer is still in the middle
+/// of is read on the next poll.
+pub struct LogTail {
+    file: File,
+    params: PublicParameters,
+    version: u8,
+    // where the entries start, and where the first one not yet read does
+    start: u64,
+    position: u64,
+    epochs: u64,
+}
+
+impl LogTail {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file: File = File::open(path)?;
+        let reader: LogReader<BufReader<&File>> = LogReader::new(BufReader::new(&file))?;
+        let (params, version, start): (PublicParameters, u8, u64) = (reader.params, reader.version, reader.position);
+        Ok(LogTail { file, params, version, start, position: start, epochs: 0 })
+    }
+    /// The parameters the log was created over.
+    pub fn params(&self) -> &PublicParameters {
+        &self.params
+    }
+}
+
+impl crate::replica::EpochFeed for LogTail {
+    fn epochs_after(&mut self, epoch: u64) -> io::Result<Vec<Epoch>> {
+        if epoch < self.epochs {
+            // asked for epochs read before, read the log again from its first entry
+            self.position = self.start;
+            self.epochs = 0;
+        }
+        self.file.seek(SeekFrom::Start(self.position))?;
+        let mut reader: LogReader<BufReader<&File>> = LogReader { params: self.params.clone(), version: self.version, reader: BufReader::new(&self.file), position: self.position };
+        let mut epochs: Vec<Epoch> = Vec::new();
+        loop {
+            match reader.next_entry() {
+                Some(Ok((entry, _))) => {
+                    self.position = reader.position;
+                    self.epochs += 1;
+                    if self.epochs > epoch {
+                        epochs.push(entry);
+                    }
+                }
+                // the writer has not finished the entry yet
+                Some(Err(e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Some(Err(e)) => return Err(e),
+                None => break,
+            }
+        }
+        Ok(epochs)
+    }
+}
+
+/// A store that appends every write to a log file and rebuilds itself by replaying the
+/// log on open, so it survives restarts without anything beyond the standard library.
+/// The log doubles as the full history of the accumulator, see [`LogReader`], and each of
+/// its entries is an [`Epoch`].
+///
+/// Whatever changed in the members is written together with the state as one entry when
+/// the accumulator commits a change, and the commit waits for the entry to reach the
+/// disk. A write that fails undoes the change and fails the commit with
+/// [`AccumulatorError::Io`]. Outside a transaction [`set_state`](Storer::set_state) has no
+/// way to report errors, so there a failed write panics. The prime of every member added
+/// is written along with it, so neither it nor a prime the caller picked is lost to a
+/// restart; logs created before they were have theirs searched for again.
+///
+/// An entry cut short by a crash makes [`FileStore::open`] fail, since the log no longer
+/// says what the last write held. [`FileStore::recover`] drops it instead, undoing that
+/// write as a whole, and so it does a final entry that fails its checksum, or zeros left
+/// at the end by a power failure. Every entry ends in a checksum, and one before the end
+/// that does not match makes both fail with [`io::ErrorKind::InvalidData`]: the log was
+/// changed after it was written, and nothing after that entry can be trusted either.
+///
+/// Each entry also carries the audit records of its batch, see [`Storer::iter_log`]. Logs
+/// created before the audit log or checksums were stay in their format, without them.
+///
+/// A store holds an exclusive advisory lock on its log for as long as it is open, so a
+/// second store over the same log, in this process or another, fails to open with
+/// [`io::ErrorKind::WouldBlock`] instead of interleaving its writes. Stores opened with
+/// [`FileStore::open_read_only`] share theirs with each other, but not with a writer.
+///
+/// The trapdoor is never written to the log.
+#[derive(Debug)]
+pub struct FileStore {
+    file: File,
+    path: PathBuf,
+    // the log keeps the format it was created in
+    version: u8,
+    read_only: bool,
+    params: PublicParameters,
+    state: BigUint,
+    // the state as of the last entry, for read-only stores to go back to
+    written_state: BigUint,
+    members: TrackedMembers,
+    primes: PrimeCache,
+    trapdoor: Option<Trapdoor>,
+    in_transaction: bool,
+    // where each entry starts, and where the log ends
+    offsets: Vec<u64>,
+    end: u64,
+    // audit records waiting for the entry of their batch
+    log: Vec<AuditRecord>,
+}
+
+impl FileStore {
+    /// Opens the log at `path`, creating it over `params` at the genesis state if it does
+    /// not exist yet. An existing log must have been created over `params`.
+    pub fn open<P: AsRef<Path>>(path: P, params: &PublicParameters) -> io::Result<Self> {
+        let mut file: File = OpenOptions::new().read(true).append(true).create(true).open(path.as_ref())?;
+        lock(&file, false)?;
+        if file.metadata()?.len() == 0 {
+            let mut header: Vec<u8> = LOG_MAGIC.to_vec();
+            header.push(LOG_VERSION);
+            write_bytes(&mut header, &params.to_bytes())?;
+            file.write_all(&header)?;
+            file.sync_all()?;
+        }
+        let store: FileStore = FileStore::replay(file, path, false, false)?;
+        if store.params != *params {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "log was created with different public parameters"));
+        }
+        Ok(store)
+    }
+    /// Opens an existing log at `path` with whatever parameters it was created over.
+    pub fn open_existing<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        FileStore::replay(FileStore::lock_existing(path.as_ref())?, path, false, false)
+    }
+    /// Opens an existing log at `path` for reading only, alongside any other read-only
+    /// stores over it. Changes to the members or the state are refused with
+    /// [`AccumulatorError::ReadOnly`] when the accumulator commits them, and panic when
+    /// the state is set outside a transaction.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file: File = File::open(path.as_ref())?;
+        lock(&file, true)?;
+        FileStore::replay(file, path, false, true)
+    }
+    /// Opens an existing log at `path` like [`FileStore::open_existing`], first cutting off
+    /// a final entry that was only partly written, so the store is left as of the last
+    /// write that completed. A final entry that is whole but fails its checksum, or reads
+    /// as zeros, counts as partly written.
+    pub fn recover<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        FileStore::replay(FileStore::lock_existing(path.as_ref())?, path, true, false)
+    }
+    fn lock_existing(path: &Path) -> io::Result<File> {
+        let file: File = OpenOptions::new().read(true).append(true).open(path)?;
+        lock(&file, false)?;
+        Ok(file)
+    }
+    // rebuilds the store from the log `file` was opened on, with its lock already held
+    fn replay<P: AsRef<Path>>(file: File, path: P, truncate_torn_tail: bool, read_only: bool) -> io::Result<Self> {
+        // the clone shares the position of `file`, which a new log was just written through
+        let mut start: File = file.try_clone()?;
+        start.seek(SeekFrom::Start(0))?;
+        let mut reader: LogReader<BufReader<File>> = LogReader::new(BufReader::new(start))?;
+        let params: PublicParameters = reader.params().clone();
+        let mut state: BigUint = params.genesis_state();
+        let mut members: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+        let mut primes: HashMap<Vec<u8>, BigUint> = HashMap::new();
+        let mut offsets: Vec<u64> = Vec::new();
+        let mut offset: u64 = reader.position;
+        while let Some(entry) = reader.next() {
+            let entry: Epoch = match entry {
+                Ok(entry) => entry,
+                // only the final entry can be torn, anything after it would have been read
+                Err(e) if truncate_torn_tail && e.kind() == io::ErrorKind::UnexpectedEof => {
+                    file.set_len(reader.position)?;
+                    file.sync_all()?;
+                    break;
+                }
+                Err(e) if truncate_torn_tail && e.kind() == io::ErrorKind::InvalidData => {
+                    let mut tail: File = file.try_clone()?;
+                    tail.seek(SeekFrom::Start(reader.position))?;
+                    let mut rest: Vec<u8> = Vec::new();
+                    tail.read_to_end(&mut rest)?;
+                    if !is_torn_tail(&rest, reader.version) {
+                        return Err(e);
+                    }
+                    file.set_len(reader.position)?;
+                    file.sync_all()?;
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+            for operation in entry.operations {
+                match operation {
+                    Operation::Add { value, nonce, prime } => {
+                        match prime {
+                            Some(prime) => primes.insert(value.clone(), prime),
+                            None => primes.remove(&value),
+                        };
+                        members.insert(value, nonce);
+                    }
+                    Operation::Delete { value } => {
+                        primes.remove(&value);
+                        members.remove(&value);
+                    }
+                }
+            }
+            state = entry.state;
+            offsets.push(offset);
+            offset = reader.position;
+        }
+        let mut cache: PrimeCache = PrimeCache::default();
+        for (value, prime) in primes.iter() {
+            cache.insert(value, &members[value], prime);
+        }
+        Ok(FileStore {
+            file,
+            path: path.as_ref().to_path_buf(),
+            version: reader.version,
+            read_only,
+            params,
+            written_state: state.clone(),
+            state,
+            members: TrackedMembers::new(members),
+            primes: cache,
+            trapdoor: None,
+            in_transaction: false,
+            offsets,
+            end: reader.position,
+            log: Vec::new(),
+        })
+    }
+    pub fn with_trapdoor(mut self, trapdoor: Trapdoor) -> Self {
+        self.trapdoor = Some(trapdoor);
+        self
+    }
+    /// Blocks until every entry written so far is on disk. Each entry already is once it
+    /// has been written, so this only matters for entries written by hand.
+    pub fn sync(&self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+    // appends whatever changed since the last entry along with `new_state` as one entry,
+    // if anything changed, and waits for it to reach the disk
+    fn write_entry(&mut self, new_state: &BigUint) -> io::Result<()> {
+        let changes: Vec<MemberChange> = self.members.changes();
+        // a batch that was rolled back, or changed nothing and logged nothing, closes no epoch
+        if changes.is_empty() && *new_state == self.state && self.log.is_empty() {
+            self.members.mark_written();
+            return Ok(());
+        }
+        let operations: Vec<Operation> = changes
+            .iter()
+            .map(|(value, nonce)| match nonce {
+                Some(nonce) => Operation::Add { value: value.clone(), nonce: nonce.clone(), prime: self.primes.get(value, nonce).cloned() },
+                None => Operation::Delete { value: value.clone() },
+            })
+            .collect();
+        assert!(!self.read_only, "store was opened read-only");
+        let epoch: Epoch = Epoch { operations, state: new_state.clone() };
+        // one write per entry, so entries from different writes never interleave
+        let bytes: Vec<u8> = encode_entry(&epoch, &self.log, self.version)?;
+        if let Err(e) = self.file.write_all(&bytes).and_then(|_| self.file.sync_data()) {
+            // cut off whatever part of the entry made it, the log ends where it did
+            let _ = self.file.set_len(self.end);
+            return Err(e);
+        }
+        self.log.clear();
+        self.offsets.push(self.end);
+        self.end += bytes.len() as u64;
+        self.members.mark_written();
+        self.primes.sweep(self.members.map());
+        self.state = new_state.clone();
+        self.written_state = new_state.clone();
+        Ok(())
+    }
+    // puts the members and state back as of the last entry
+    fn revert(&mut self) {
+        self.members.revert();
+        self.state = self.written_state.clone();
+        self.log.clear();
+    }
+}
+
+impl Storer for FileStore {
+    fn get_generator(&self) -> &BigUint {
+        &self.params.generator
+    }
+    fn get_modulus(&self) -> &BigUint {
+        &self.params.modulus
+    }
+    fn get_state(&self) -> &BigUint {
+        &self.state
+    }
+    fn set_state(&mut self, new_state: &BigUint) {
+        if self.in_transaction {
+            self.state = new_state.clone();
+            return;
+        }
+        self.write_entry(new_state).expect("failed to append to the store log");
+    }
+    fn get_trapdoor(&self) -> Option<Trapdoor> {
+        self.trapdoor.clone()
+    }
+    fn get_nonce(&self, value: &[u8]) -> Option<Vec<u8>> {
+        self.members.get(value).cloned()
+    }
+    fn insert_member(&mut self, value: &[u8], nonce: &[u8]) {
+        self.members.insert(value, nonce);
+    }
+    fn remove_member(&mut self, value: &[u8]) -> Option<Vec<u8>> {
+        self.members.remove(value)
+    }
+    fn iter_members(&self) -> impl Iterator<Item = (&[u8], &[u8])> + '_ {
+        self.members.map().iter().map(|(value, nonce)| (value.as_slice(), nonce.as_slice()))
+    }
+    fn contains_member(&self, value: &[u8]) -> bool {
+        self.members.get(value).is_some()
+    }
+    fn member_count(&self) -> usize {
+        self.members.map().len()
+    }
+    fn begin_transaction(&mut self) {
+        self.in_transaction = true;
+    }
+    fn commit_transaction(&mut self) -> Result<(), AccumulatorError> {
+        self.in_transaction = false;
+        if self.read_only && (self.state != self.written_state || !self.members.changes().is_empty() || !self.log.is_empty()) {
+            self.revert();
+            return Err(AccumulatorError::ReadOnly);
+        }
+        let state: BigUint = self.state.clone();
+        self.write_entry(&state).map_err(|e| {
+            self.revert();
+            AccumulatorError::Io(e.to_string())
+        })
+    }
+    fn get_hash_function(&self) -> HashFunction {
+        self.params.hash_function
+    }
+    fn get_prime_bits(&self) -> Option<u64> {
+        self.params.prime_bits
+    }
+    fn get_prime(&self, value: &[u8], nonce: &[u8]) -> Option<&BigUint> {
+        self.primes.get(value, nonce)
+    }
+    fn set_prime(&mut self, value: &[u8], nonce: &[u8], prime: &BigUint) {
+        self.primes.insert(value, nonce, prime);
+    }
+    fn get_prime_mapping(&self) -> PrimeMapping {
+        self.params.prime_mapping()
+    }
+    // the primes are only ever kept in memory
+    fn collect_primes(&mut self, dry_run: bool) -> io::Result<u64> {
+        Ok(self.primes.collect(self.members.map(), dry_run))
+    }
+    fn epoch_count(&self) -> u64 {
+        self.offsets.len() as u64
+    }
+    fn get_epoch(&self, epoch: u64) -> io::Result<Option<Epoch>> {
+        let offset: u64 = match epoch.checked_sub(1).and_then(|index| self.offsets.get(index as usize)) {
+            Some(offset) => *offset,
+            None => return Ok(None),
+        };
+        let mut file: File = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut reader: LogReader<BufReader<File>> =
+            LogReader { params: self.params.clone(), version: self.version, reader: BufReader::new(file), position: offset };
+        match reader.next_entry() {
+            Some(entry) => entry.map(|(epoch, _)| Some(epoch)),
+            None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "log is shorter than when it was opened")),
+        }
+    }
+    fn append_log(&mut self, record: &AuditRecord) {
+        self.log.push(record.clone());
+    }
+    fn iter_log(&self) -> impl Iterator<Item = io::Result<AuditRecord>> + '_ {
+        let (reader, failed): (Option<LogReader<BufReader<File>>>, Option<io::Error>) = match LogReader::open(&self.path) {
+            Ok(reader) => (Some(reader), None),
+            Err(e) => (None, Some(e)),
+        };
+        failed.map(Err).into_iter().chain(reader.into_iter().flat_map(|reader| reader.audit_log()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proof::MembershipProof;
+    use crate::store::AuditAction;
+    use crate::SetAccumulator;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_locking() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let path: PathBuf = std::env::temp_dir().join(format!("tangerine-lock-{}-{}.tglg", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut sa: SetAccumulator<FileStore> = SetAccumulator::new(FileStore::open(&path, &params).unwrap());
+        sa.add(b"alice").unwrap();
+        // a writer keeps every other store off the log
+        assert_eq!(io::ErrorKind::WouldBlock, FileStore::open(&path, &params).unwrap_err().kind());
+        assert_eq!(io::ErrorKind::WouldBlock, FileStore::open_existing(&path).unwrap_err().kind());
+        assert_eq!(io::ErrorKind::WouldBlock, FileStore::open_read_only(&path).unwrap_err().kind());
+        let state: BigUint = sa.store.get_state().clone();
+        drop(sa);
+
+        // while readers only keep writers off it
+        let reader: FileStore = FileStore::open_read_only(&path).unwrap();
+        let mut sa: SetAccumulator<FileStore> = SetAccumulator::new(FileStore::open_read_only(&path).unwrap());
+        assert_eq!(state, *reader.get_state());
+        assert_eq!(io::ErrorKind::WouldBlock, FileStore::open(&path, &params).unwrap_err().kind());
+        // and refuse to change anything
+        assert_eq!(Err(AccumulatorError::ReadOnly), sa.add(b"bob").map(|_| ()));
+        assert_eq!(Err(AccumulatorError::ReadOnly), sa.delete_without_trapdoor(b"alice").map(|_| ()));
+        assert_eq!(state, *sa.store.get_state());
+        assert!(sa.store.contains_member(b"alice"));
+        assert!(!sa.store.contains_member(b"bob"));
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+        assert!(sa.verify(b"alice", &witness, &nonce));
+        drop(sa);
+        drop(reader);
+
+        assert!(FileStore::open(&path, &params).is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_log_tail() {
+        use crate::replica::Follower;
+
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let path: PathBuf = std::env::temp_dir().join(format!("tangerine-tail-{}-{}.tglg", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut primary: SetAccumulator<FileStore> = SetAccumulator::new(FileStore::open(&path, &params).unwrap());
+        primary.add(b"alice").unwrap();
+        // the follower reads the log while the primary holds it
+        let tail: LogTail = LogTail::open(&path).unwrap();
+        assert_eq!(params, *tail.params());
+        let mut follower: Follower<LogTail> = Follower::new(&params, tail);
+        assert_eq!(Ok(1), follower.poll());
+        primary.add_batch(&[b"bob", b"carol"]).unwrap();
+        primary.delete_without_trapdoor(b"alice").unwrap();
+        assert_eq!(Ok(2), follower.poll());
+        assert_eq!((3, primary.store.get_state()), (follower.epoch(), follower.accumulator().store.get_state()));
+        let (witness, nonce): (BigUint, Vec<u8>) = follower.accumulator().get_witness(b"bob").unwrap();
+        assert!(primary.verify(b"bob", &witness, &nonce));
+        drop(primary);
+
+        // an entry still being written is left for the next poll
+        let mut file: File = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[0, 0, 0, 40, 0x01]).unwrap();
+        assert_eq!(Ok(0), follower.poll());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_replay() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let path: PathBuf = std::env::temp_dir().join(format!("tangerine-log-{}-{}.tglg", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let state: BigUint;
+        {
+            let mut sa: SetAccumulator<FileStore> = SetAccumulator::new(FileStore::open(&path, &params).unwrap());
+            sa.add(b"alice").unwrap();
+            sa.add_batch(&[b"bob", b"carol"]).unwrap();
+            sa.delete_without_trapdoor(b"bob").unwrap();
+            state = sa.store.get_state().clone();
+            sa.store.sync().unwrap();
+        }
+        let store: FileStore = FileStore::open_existing(&path).unwrap();
+        assert_eq!(state, *store.get_state());
+        assert_eq!(2, store.member_count());
+        assert!(store.contains_member(b"carol"));
+        // each entry is an epoch, read back from the log along with the audit records
+        assert_eq!(3, store.epoch_count());
+        let actions: Vec<AuditAction> = store.iter_log().map(|record| record.unwrap().action).collect();
+        assert_eq!(vec![AuditAction::Add, AuditAction::Add, AuditAction::Add, AuditAction::Delete], actions);
+        assert_eq!(Some(state.clone()), store.get_epoch(3).unwrap().map(|epoch| epoch.state));
+        assert_eq!(None, store.get_epoch(0).unwrap());
+        let sa: SetAccumulator<FileStore> = SetAccumulator::new(store);
+        let proof: MembershipProof = sa.prove_membership_at(b"bob", 2).unwrap();
+        assert!(sa.verify_at(2, b"bob", &proof));
+        drop(sa);
+
+        // the log holds every operation in order
+        let reader: LogReader<BufReader<File>> = LogReader::open(&path).unwrap();
+        assert_eq!(params, *reader.params());
+        let entries: Vec<Epoch> = reader.collect::<io::Result<_>>().unwrap();
+        assert_eq!(3, entries.len());
+        assert_eq!(2, entries[1].operations.len());
+        assert_eq!(vec![Operation::Delete { value: b"bob".to_vec() }], entries[2].operations);
+        assert_eq!(state, entries[2].state);
+
+        // a log only opens over the parameters it was created with
+        let other: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(43_u64));
+        assert!(FileStore::open(&path, &other).is_err());
+        let mut sa: SetAccumulator<FileStore> = SetAccumulator::new(FileStore::open(&path, &params).unwrap());
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+        assert!(sa.verify(b"alice", &witness, &nonce));
+
+        // a transaction lands as a single entry
+        sa.transaction(|sa| {
+            sa.add(b"dave")?;
+            sa.delete_without_trapdoor(b"carol")
+        })
+        .unwrap();
+        drop(sa);
+        let entries: Vec<Epoch> = LogReader::open(&path).unwrap().collect::<io::Result<_>>().unwrap();
+        assert_eq!(4, entries.len());
+        assert_eq!(2, entries[3].operations.len());
+        assert_eq!(Some(entries[3].clone()), FileStore::open_existing(&path).unwrap().get_epoch(4).unwrap());
+
+        // and a torn final entry is reported rather than silently dropped
+        let len: u64 = std::fs::metadata(&path).unwrap().len();
+        OpenOptions::new().write(true).open(&path).unwrap().set_len(len - 1).unwrap();
+        assert!(FileStore::open_existing(&path).is_err());
+        // unless the log is recovered, which undoes that write
+        let mut sa: SetAccumulator<FileStore> = SetAccumulator::new(FileStore::recover(&path).unwrap());
+        assert_eq!(state, *sa.store.get_state());
+        assert!(!sa.recover());
+        sa.add(b"erin").unwrap();
+        drop(sa);
+        let entries: Vec<Epoch> = LogReader::open(&path).unwrap().collect::<io::Result<_>>().unwrap();
+        assert_eq!(4, entries.len());
+
+        // zeros after the last entry, or a last entry that fails its checksum, are a write
+        // that never completed
+        let mut bytes: Vec<u8> = std::fs::read(&path).unwrap();
+        let whole: usize = bytes.len();
+        bytes.extend_from_slice(&[0; 64]);
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, FileStore::open_existing(&path).unwrap_err().kind());
+        assert_eq!(4, FileStore::recover(&path).unwrap().epoch_count());
+        assert_eq!(whole as u64, std::fs::metadata(&path).unwrap().len());
+        bytes.truncate(whole);
+        bytes[whole - 5] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, FileStore::open_existing(&path).unwrap_err().kind());
+        let store: FileStore = FileStore::recover(&path).unwrap();
+        assert_eq!(3, store.epoch_count());
+        assert_eq!(state, *store.get_state());
+        drop(store);
+
+        // an entry changed after it was written fails its checksum, recovered or not
+        let mut bytes: Vec<u8> = std::fs::read(&path).unwrap();
+        let offset: usize = FileStore::open_existing(&path).unwrap().offsets[1] as usize + 8;
+        bytes[offset] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, FileStore::open_existing(&path).unwrap_err().kind());
+        assert_eq!(io::ErrorKind::InvalidData, FileStore::recover(&path).unwrap_err().kind());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_replay_picked_prime() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let path: PathBuf = std::env::temp_dir().join(format!("tangerine-picked-{}-{}.tglg", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let prime: BigUint = BigUint::from(65537_u64);
+        let (witness, nonce): (BigUint, Vec<u8>) = {
+            let mut sa: SetAccumulator<FileStore> = SetAccumulator::new(FileStore::open(&path, &params).unwrap());
+            sa.add(b"alice").unwrap();
+            sa.add_prime(b"carol", &prime).unwrap();
+            sa.get_witness(b"carol").unwrap()
+        };
+        // the prime is read back from the log rather than searched for again
+        let sa: SetAccumulator<FileStore> = SetAccumulator::new(FileStore::open_existing(&path).unwrap());
+        assert_eq!(Some(&prime), sa.store.get_prime(b"carol", &nonce));
+        assert!(sa.verify(b"carol", &witness, &nonce));
+        assert_eq!(Some((witness, nonce)), sa.get_witness(b"carol"));
+        let proof: MembershipProof = sa.prove_membership_at(b"carol", 2).unwrap();
+        assert!(sa.verify_at(2, b"carol", &proof));
+        drop(sa);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_write() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let path: PathBuf = std::env::temp_dir().join(format!("tangerine-write-{}-{}.tglg", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut sa: SetAccumulator<FileStore> = SetAccumulator::new(FileStore::open(&path, &params).unwrap());
+        sa.add(b"alice").unwrap();
+        let state: BigUint = sa.store.get_state().clone();
+        // a handle that cannot write fails every append, as a full disk would
+        sa.store.file = File::open(&path).unwrap();
+        match sa.add(b"bob") {
+            Err(AccumulatorError::Io(_)) => {}
+            other => panic!("expected the write to fail, got {:?}", other.map(|_| ())),
+        }
+        // and the change is undone rather than left in memory only
+        assert_eq!(state, *sa.store.get_state());
+        assert!(!sa.store.contains_member(b"bob"));
+        assert_eq!(1, sa.store.epoch_count());
+        drop(sa);
+        assert_eq!(1, FileStore::open_existing(&path).unwrap().epoch_count());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_version_1_log() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let path: PathBuf = std::env::temp_dir().join(format!("tangerine-log-v1-{}-{}.tglg", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut bytes: Vec<u8> = LOG_MAGIC.to_vec();
+        bytes.push(1);
+        write_bytes(&mut bytes, &params.to_bytes()).unwrap();
+        let epoch: Epoch = Epoch { operations: vec![Operation::Add { value: b"alice".to_vec(), nonce: vec![7; 32], prime: None }], state: BigUint::from(5_u64) };
+        bytes.extend_from_slice(&encode_entry(&epoch, &[], 1).unwrap());
+        std::fs::write(&path, &bytes).unwrap();
+
+        // a log from before the audit log keeps being written in its own format
+        let mut store: FileStore = FileStore::open(&path, &params).unwrap();
+        assert_eq!(Some(epoch), store.get_epoch(1).unwrap());
+        store.insert_member(b"bob", &[8; 32]);
+        store.set_state(&BigUint::from(6_u64));
+        drop(store);
+        let store: FileStore = FileStore::open_existing(&path).unwrap();
+        assert_eq!(2, store.epoch_count());
+        assert_eq!(2, store.member_count());
+        assert_eq!(0, store.iter_log().count());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,15 +1,47 @@
 use std::collections::HashMap;
+use std::io;
 
 use num_bigint::{BigUint};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use crate::store::Storer;
+use crate::params::{HashFunction, PrimeMapping, PublicParameters, Trapdoor};
+use crate::store::{AuditRecord, Epoch, PrimeCache, ShardedMap, Storer};
 
+/// The members are kept in a [`ShardedMap`], so [`MemStore::fork`] copies a store of any
+/// size in constant time.
+///
+/// With the `serde` feature the store serializes to its parameters, state and members,
+/// and the primes it keeps for them.
+/// The trapdoor is never serialized, a deserialized store has to be given it again with
+/// [`MemStore::with_trapdoor`].
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MemStore {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::biguint"))]
     generator: BigUint,
-    members: HashMap<Vec<u8>, Vec<u8>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::members"))]
+    members: ShardedMap<Vec<u8>>,
+    #[cfg_attr(feature = "serde", serde(rename = "modulus", with = "crate::serde_support::biguint"))]
     modulo: BigUint,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::biguint"))]
     state: BigUint,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    trapdoor: Option<Trapdoor>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    prime_mapping: PrimeMapping,
+    // the primes of the members, found again after deserializing stores serialized
+    // before they were kept
+    #[cfg_attr(feature = "serde", serde(default, with = "crate::serde_support::primes"))]
+    primes: PrimeCache,
+    // the history is not serialized, a deserialized store starts its epochs and log over
+    #[cfg_attr(feature = "serde", serde(skip))]
+    epochs: Vec<Epoch>,
+    // the number of epochs compacted away before the first one in `epochs`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    dropped: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    log: Vec<AuditRecord>,
 }
 
 impl MemStore {
@@ -19,24 +51,177 @@ impl MemStore {
         modulo: BigUint,
         state: BigUint
     ) -> Self {
-        MemStore { generator, members, modulo, state }
+        MemStore {
+            generator,
+            members: members.into_iter().collect(),
+            modulo,
+            state,
+            trapdoor: None,
+            prime_mapping: PrimeMapping::default(),
+            primes: PrimeCache::default(),
+            epochs: Vec::new(),
+            dropped: 0,
+            log: Vec::new(),
+        }
+    }
+    pub fn from_parameters(params: &PublicParameters) -> Self {
+        // an accumulator over no members, sitting at the genesis state
+        let mut store: MemStore = MemStore::new(params.generator.clone(), HashMap::new(), params.modulus.clone(), params.genesis_state());
+        store.prime_mapping = params.prime_mapping();
+        store
+    }
+    pub fn with_trapdoor(mut self, trapdoor: Trapdoor) -> Self {
+        self.trapdoor = Some(trapdoor);
+        self
+    }
+    /// A copy of the parameters, state, members and cached primes, without the epochs and
+    /// audit log. It shares the shards of the members and primes with this store until
+    /// either is written to, so it is cheap to take and can be read from another thread
+    /// while this store carries on changing.
+    pub fn fork(&self) -> Self {
+        MemStore {
+            generator: self.generator.clone(),
+            members: self.members.clone(),
+            modulo: self.modulo.clone(),
+            state: self.state.clone(),
+            trapdoor: self.trapdoor.clone(),
+            prime_mapping: self.prime_mapping,
+            primes: self.primes.clone(),
+            epochs: Vec::new(),
+            dropped: 0,
+            log: Vec::new(),
+        }
     }
 }
 
 impl Storer for MemStore {
-    fn get_generator(&mut self) -> BigUint {
-        return self.generator.clone();
-    }
-    fn get_members_list(&mut self) -> &mut HashMap<Vec<u8>, Vec<u8>> {
-        &mut self.members
+    fn get_generator(&self) -> &BigUint {
+        &self.generator
     }
-    fn get_modulus(&mut self) -> BigUint {
-        return self.modulo.clone();
+    fn get_modulus(&self) -> &BigUint {
+        &self.modulo
     }
-    fn get_state(&mut self) -> BigUint {
-        return self.state.clone();
+    fn get_state(&self) -> &BigUint {
+        &self.state
     }
     fn set_state(&mut self, new_state: &BigUint) {
         self.state = new_state.clone();
+        self.primes.sweep(&self.members);
+    }
+    fn get_trapdoor(&self) -> Option<Trapdoor> {
+        self.trapdoor.clone()
+    }
+    fn get_nonce(&self, value: &[u8]) -> Option<Vec<u8>> {
+        self.members.get(value).cloned()
+    }
+    fn insert_member(&mut self, value: &[u8], nonce: &[u8]) {
+        self.members.insert(value.to_vec(), nonce.to_vec());
+    }
+    fn remove_member(&mut self, value: &[u8]) -> Option<Vec<u8>> {
+        self.members.remove(value)
+    }
+    fn iter_members(&self) -> impl Iterator<Item = (&[u8], &[u8])> + '_ {
+        self.members.iter().map(|(value, nonce)| (value, nonce.as_slice()))
+    }
+    fn contains_member(&self, value: &[u8]) -> bool {
+        self.members.contains_key(value)
+    }
+    fn member_count(&self) -> usize {
+        self.members.len()
+    }
+    fn get_hash_function(&self) -> HashFunction {
+        self.prime_mapping.hash_function
+    }
+    fn get_prime_bits(&self) -> Option<u64> {
+        self.prime_mapping.prime_bits
+    }
+    fn get_prime(&self, value: &[u8], nonce: &[u8]) -> Option<&BigUint> {
+        self.primes.get(value, nonce)
+    }
+    fn set_prime(&mut self, value: &[u8], nonce: &[u8], prime: &BigUint) {
+        self.primes.insert(value, nonce, prime);
+    }
+    fn get_prime_mapping(&self) -> PrimeMapping {
+        self.prime_mapping
+    }
+    fn collect_primes(&mut self, dry_run: bool) -> io::Result<u64> {
+        Ok(self.primes.collect(&self.members, dry_run))
+    }
+    fn push_epoch(&mut self, epoch: &Epoch) {
+        self.epochs.push(epoch.clone());
+    }
+    fn epoch_count(&self) -> u64 {
+        self.dropped + self.epochs.len() as u64
+    }
+    fn get_epoch(&self, epoch: u64) -> io::Result<Option<Epoch>> {
+        Ok(epoch.checked_sub(self.first_epoch()).and_then(|index| self.epochs.get(index as usize)).cloned())
+    }
+    fn first_epoch(&self) -> u64 {
+        self.dropped + 1
+    }
+    fn compact_epochs(&mut self, first: u64, base: &Epoch) -> io::Result<()> {
+        let drop: usize = (first - self.first_epoch()) as usize;
+        self.epochs.drain(..drop);
+        self.epochs[0] = base.clone();
+        self.dropped = first - 1;
+        Ok(())
+    }
+    fn append_log(&mut self, record: &AuditRecord) {
+        self.log.push(record.clone());
+    }
+    fn iter_log(&self) -> impl Iterator<Item = io::Result<AuditRecord>> + '_ {
+        self.log.iter().cloned().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fork() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut store: MemStore = MemStore::from_parameters(&params);
+        for i in 0..200_u8 {
+            store.insert_member(&[i], &[0x01]);
+        }
+        store.set_prime(&[0], &[0x01], &BigUint::from(7_u64));
+        let fork: MemStore = store.fork();
+        // writes to the store copy the shards they touch and leave the fork as it was
+        store.remove_member(&[0]);
+        store.insert_member(b"alice", &[0x02]);
+        store.set_state(&BigUint::from(5_u64));
+        assert_eq!((200, 200), (store.member_count(), fork.member_count()));
+        assert!(fork.contains_member(&[0]) && !fork.contains_member(b"alice"));
+        assert_eq!(Some(&BigUint::from(7_u64)), fork.get_prime(&[0], &[0x01]));
+        assert_eq!((&params.genesis_state(), 0), (fork.get_state(), fork.epoch_count()));
+        assert_eq!(200, fork.iter_members().count());
+
+        let mut members: ShardedMap<Vec<u8>> = fork.members.clone();
+        members.retain(|value, _| value[0] % 2 == 0);
+        assert_eq!((100, 200), (members.len(), fork.members.len()));
+        assert_eq!(None, members.remove(&[1]));
+        assert_eq!(Some(vec![0x01]), members.remove(&[2]));
+        assert_eq!(99, members.iter().count());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let mut members: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+        members.insert(b"alice".to_vec(), vec![0x01]);
+        let mut store: MemStore = MemStore::new(BigUint::from(2_u64), members.clone(), BigUint::from(3233_u64), BigUint::from(4_u64))
+            .with_trapdoor(Trapdoor::new(BigUint::from(61_u64), BigUint::from(53_u64)));
+        store.set_prime(b"alice", &[0x01], &BigUint::from(65537_u64));
+        let json: String = serde_json::to_string(&store).unwrap();
+        assert!(!json.contains("trapdoor"));
+        let restored: MemStore = serde_json::from_str(&json).unwrap();
+        assert_eq!(store.get_state(), restored.get_state());
+        assert_eq!(store.get_modulus(), restored.get_modulus());
+        let restored_members: HashMap<Vec<u8>, Vec<u8>> = restored.iter_members().map(|(value, nonce)| (value.to_vec(), nonce.to_vec())).collect();
+        assert_eq!(members, restored_members);
+        assert_eq!(Some(&BigUint::from(65537_u64)), restored.get_prime(b"alice", &[0x01]));
+        assert_eq!(store.get_prime_mapping(), restored.get_prime_mapping());
+        assert_eq!(None, restored.get_trapdoor());
     }
 }
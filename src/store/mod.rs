@@ -1,12 +1,601 @@
+pub mod file_store;
 pub mod mem_store;
+#[cfg(feature = "redis")]
+pub mod redis_store;
+#[cfg(feature = "rocksdb")]
+pub mod rocks_store;
+#[cfg(feature = "sled")]
+pub mod sled_store;
+#[cfg(feature = "rusqlite")]
+pub mod sqlite_store;
 
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::io::{self, Read};
+use std::iter::FromIterator;
+use std::sync::Arc;
 use num_bigint::{BigUint};
+use sha2::{Digest, Sha256};
+
+use crate::bundle::HeadVerifier;
+use crate::error::AccumulatorError;
+use crate::params::{HashFunction, PrimeMapping, Trapdoor};
+use crate::snapshot::{read_biguint, read_bytes, write_bytes};
 
 pub trait Storer {
-    fn get_generator(&mut self) -> BigUint;
-    fn get_members_list(&mut self) -> &mut HashMap<Vec<u8>, Vec<u8>>;
-    fn get_modulus(&mut self) -> BigUint;
-    fn get_state(&mut self) -> BigUint;
+    // the big integers are lent rather than copied, a 3072 bit state is read on every operation
+    fn get_generator(&self) -> &BigUint;
+    fn get_modulus(&self) -> &BigUint;
+    fn get_state(&self) -> &BigUint;
     fn set_state(&mut self, new_state: &BigUint);
+    fn get_trapdoor(&self) -> Option<Trapdoor>;
+    /// The nonce `value` was added with, None if it is not a member.
+    fn get_nonce(&self, value: &[u8]) -> Option<Vec<u8>>;
+    /// Records `value` as a member added with `nonce`. The accumulator changes the members
+    /// only through this and [`remove_member`](Storer::remove_member), just before setting
+    /// the state the change leads to, so persistent stores can write both at once.
+    fn insert_member(&mut self, value: &[u8], nonce: &[u8]);
+    /// Drops `value` from the members, returning the nonce it was added with.
+    fn remove_member(&mut self, value: &[u8]) -> Option<Vec<u8>>;
+    /// Every member with its nonce, in no particular order.
+    fn iter_members(&self) -> impl Iterator<Item = (&[u8], &[u8])> + '_;
+    /// Whether `value` is a member. Stores that can answer without looking up the nonce
+    /// override this, and those that know their size without counting override the next.
+    fn contains_member(&self, value: &[u8]) -> bool {
+        self.get_nonce(value).is_some()
+    }
+    fn member_count(&self) -> usize {
+        self.iter_members().count()
+    }
+    /// Whether the store looks members up by their primes and nonces through the next two
+    /// methods. The accumulator otherwise reads every member in to index them, which it
+    /// has to keep two members from sharing a prime or a drawn nonce.
+    fn looks_up_members(&self) -> bool {
+        false
+    }
+    /// Whether a member is held with `prime`. A member held more than once, under
+    /// [`DuplicatePolicy::Multiset`](crate::params::DuplicatePolicy::Multiset), is found by
+    /// the product of the primes of its occurrences, which is the prime kept for it.
+    fn holds_prime(&self, _prime: &BigUint) -> io::Result<bool> {
+        Ok(false)
+    }
+    /// Whether a member is held with `nonce`, or with it among the nonces of its occurrences.
+    fn holds_nonce(&self, _nonce: &[u8]) -> io::Result<bool> {
+        Ok(false)
+    }
+    /// The hash function members are mapped to primes with. Stores that do not record one
+    /// hold accumulators built before it could be chosen, which all used SHA-256.
+    fn get_hash_function(&self) -> HashFunction {
+        HashFunction::default()
+    }
+    /// The width of the primes members are mapped to, None for as wide as the digest.
+    fn get_prime_bits(&self) -> Option<u64> {
+        None
+    }
+    /// Everything deciding the primes members are mapped to. Stores that certify their
+    /// primes override this, the others are described by the two methods above.
+    fn get_prime_mapping(&self) -> PrimeMapping {
+        PrimeMapping::new(self.get_hash_function(), self.get_prime_bits())
+    }
+    /// Marks the start of changes to the members and state that have to land together.
+    /// Stores that persist on [`set_state`](Storer::set_state) hold their writes back until
+    /// [`commit_transaction`](Storer::commit_transaction) and then write everything at once.
+    /// A rolled back transaction is undone by the accumulator through the other methods
+    /// before it is committed, so stores need no rollback of their own.
+    fn begin_transaction(&mut self) {}
+    /// Fails with [`AccumulatorError::Conflict`] for stores shared between processes when
+    /// another one wrote first. Such a store drops the writes it held back and takes a new
+    /// view of the accumulator, so the changes can be tried again over it.
+    fn commit_transaction(&mut self) -> Result<(), AccumulatorError> {
+        Ok(())
+    }
+    /// The prime kept for the member `value` with `nonce`, so witnesses need not search for
+    /// it again. Stores that keep none return None and the accumulator searches instead,
+    /// which leaves them unable to hold members added with a prime of the caller's, see
+    /// [`SetAccumulator::add_prime`](crate::SetAccumulator::add_prime).
+    fn get_prime(&self, _value: &[u8], _nonce: &[u8]) -> Option<&BigUint> {
+        None
+    }
+    /// Offers the prime of a member being added, before its state is set, for stores that
+    /// keep primes to write out along with the member.
+    fn set_prime(&mut self, _value: &[u8], _nonce: &[u8], _prime: &BigUint) {}
+    /// Records the epoch a committed batch of changes closed, for stores that keep the
+    /// history. Stores whose writes already are one per batch, as the entries of a
+    /// [`FileStore`](file_store::FileStore) log are, count those instead and ignore this.
+    fn push_epoch(&mut self, _epoch: &Epoch) {}
+    /// The number of epochs closed so far, zero for stores that keep no history.
+    fn epoch_count(&self) -> u64 {
+        0
+    }
+    /// Epoch `epoch`, counting from 1 for the first batch of changes, None for epochs the
+    /// store does not hold. Fails if the store cannot read it back, or what it reads is
+    /// corrupt.
+    fn get_epoch(&self, _epoch: u64) -> io::Result<Option<Epoch>> {
+        Ok(None)
+    }
+    /// The oldest epoch the store holds, 1 unless it was compacted. Once it was, this
+    /// epoch holds every member at its close as an add rather than the changes it made.
+    fn first_epoch(&self) -> u64 {
+        1
+    }
+    /// Drops every epoch before `first` and puts `base`, which adds every member at the
+    /// close of `first`, in its place, see [`SetAccumulator::compact`](crate::SetAccumulator::compact).
+    /// Stores that cannot drop part of their history, as an append-only
+    /// [`FileStore`](file_store::FileStore) log cannot, keep all of it.
+    fn compact_epochs(&mut self, _first: u64, _base: &Epoch) -> io::Result<()> {
+        Ok(())
+    }
+    /// Drops the primes kept for values that are no longer members, or are members with
+    /// another nonce than the prime was kept for, returning how many there were. With
+    /// `dry_run` they are only counted. Stores that keep no primes, or never leave one
+    /// behind, find none.
+    fn collect_primes(&mut self, _dry_run: bool) -> io::Result<u64> {
+        Ok(0)
+    }
+    /// Appends `record` to the audit log, for stores that keep one. The accumulator hands
+    /// over the records of a batch just before committing it, and never those of a batch
+    /// that was rolled back.
+    fn append_log(&mut self, _record: &AuditRecord) {}
+    /// Every record in the audit log in the order it was appended, nothing for stores
+    /// that keep no log. A record the store cannot read back comes out as an error.
+    fn iter_log(&self) -> impl Iterator<Item = io::Result<AuditRecord>> + '_ {
+        std::iter::empty()
+    }
+}
+
+/// Which change the audit log records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Add,
+    Delete,
+    /// A rollback to an earlier epoch, see [`SetAccumulator::rollback_to`](crate::SetAccumulator::rollback_to).
+    /// It follows the adds and deletes that took the members back, in the same epoch, and
+    /// has no value of its own: its value hash is the SHA-256 of the reason given, its
+    /// prime the number of the epoch restored and its nonce the signature over
+    /// [`AuditRecord::rollback_digest`].
+    Rollback,
+}
+
+/// One add or delete in the audit log. The value is only recorded by its SHA-256, so
+/// the log can be handed to auditors without handing over the members, who can still
+/// check any value they know of against it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    pub action: AuditAction,
+    /// The epoch the change closed with.
+    pub epoch: u64,
+    pub value_hash: [u8; 32],
+    pub nonce: Vec<u8>,
+    pub prime: BigUint,
+    /// The states around the change. Values added in one batch share both, since the
+    /// batch moves the state in one step.
+    pub previous_state: BigUint,
+    pub new_state: BigUint,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+const ROLLBACK_DOMAIN: &[u8] = b"tangerine/rollback/v1";
+
+impl AuditRecord {
+    /// What the signature of a rollback record covers: the epoch it closed, the epoch
+    /// restored, the states before and after and the hash of the reason.
+    pub fn rollback_digest(&self) -> [u8; 32] {
+        let mut hasher: Sha256 = Sha256::new();
+        hasher.update(ROLLBACK_DOMAIN);
+        hasher.update(self.epoch.to_be_bytes());
+        for field in [&self.prime, &self.previous_state, &self.new_state].iter() {
+            let bytes: Vec<u8> = field.to_bytes_be();
+            hasher.update((bytes.len() as u64).to_be_bytes());
+            hasher.update(bytes);
+        }
+        hasher.update(self.value_hash);
+        hasher.finalize().into()
+    }
+    /// Whether this is a rollback record signed by the key behind `verifier`.
+    pub fn verify_rollback<V: HeadVerifier>(&self, verifier: &V) -> bool {
+        self.action == AuditAction::Rollback && verifier.verify(&self.rollback_digest(), &self.nonce)
+    }
+}
+
+/// A change to the members within an epoch. An add carries the prime the member was
+/// held with as the store kept it, so members added with a prime of the caller's, see
+/// [`SetAccumulator::add_prime`](crate::SetAccumulator::add_prime), replay with it; None
+/// from stores that keep no primes, and the member's prime is the one its nonce maps to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Add { value: Vec<u8>, nonce: Vec<u8>, prime: Option<BigUint> },
+    Delete { value: Vec<u8> },
+}
+
+/// A batch of changes to the members and the state it left the accumulator at. Epoch 0
+/// is the genesis state, with no members, and every later one applies its deletes and
+/// adds to the members of the one before.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Epoch {
+    pub operations: Vec<Operation>,
+    pub state: BigUint,
+}
+
+/// How much of the epoch history [`SetAccumulator::compact`](crate::SetAccumulator::compact)
+/// keeps. Witnesses at past epochs are computed from the history, so they can be had for
+/// the epochs kept and no others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retention {
+    KeepAll,
+    /// The last k epochs, and at least the current one.
+    KeepLast(u64),
+    /// Every epoch closed in the last so many days, today included, counted in whole UTC
+    /// days so the history is cut at midnight. The age of an epoch is told by its audit
+    /// records, stores without a log keep everything.
+    KeepDaily(u64),
+}
+
+pub(crate) const ADD_TAG: u8 = 1;
+pub(crate) const DELETE_TAG: u8 = 2;
+const ROLLBACK_TAG: u8 = 3;
+// an add that carries its prime
+const ADD_WITH_PRIME_TAG: u8 = 4;
+
+// an epoch as the persistent stores write it: the number of operations as a u32, each
+// operation as its tag, the value and for adds the nonce and, if kept, the prime, then
+// the state
+pub(crate) fn write_epoch(writer: &mut Vec<u8>, epoch: &Epoch) -> io::Result<()> {
+    writer.extend_from_slice(&(epoch.operations.len() as u32).to_be_bytes());
+    for operation in epoch.operations.iter() {
+        match operation {
+            Operation::Add { value, nonce, prime } => {
+                writer.push(if prime.is_some() { ADD_WITH_PRIME_TAG } else { ADD_TAG });
+                write_bytes(writer, value)?;
+                write_bytes(writer, nonce)?;
+                if let Some(prime) = prime {
+                    write_bytes(writer, &prime.to_bytes_be())?;
+                }
+            }
+            Operation::Delete { value } => {
+                writer.push(DELETE_TAG);
+                write_bytes(writer, value)?;
+            }
+        }
+    }
+    write_bytes(writer, &epoch.state.to_bytes_be())
+}
+
+pub(crate) fn read_epoch(reader: &mut &[u8]) -> io::Result<Epoch> {
+    let mut count: [u8; 4] = [0; 4];
+    reader.read_exact(&mut count)?;
+    let mut operations: Vec<Operation> = Vec::new();
+    for _ in 0..u32::from_be_bytes(count) {
+        let mut tag: [u8; 1] = [0; 1];
+        reader.read_exact(&mut tag)?;
+        let value: Vec<u8> = read_bytes(reader)?;
+        operations.push(match tag[0] {
+            ADD_TAG => Operation::Add { value, nonce: read_bytes(reader)?, prime: None },
+            ADD_WITH_PRIME_TAG => Operation::Add { value, nonce: read_bytes(reader)?, prime: Some(read_biguint(reader)?) },
+            DELETE_TAG => Operation::Delete { value },
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown operation in an epoch")),
+        });
+    }
+    Ok(Epoch { operations, state: read_biguint(reader)? })
+}
+
+// an audit record as the persistent stores write it: the action as its tag, the epoch,
+// the value hash, the nonce, the prime, the states around the change and the timestamp
+pub(crate) fn write_record(writer: &mut Vec<u8>, record: &AuditRecord) -> io::Result<()> {
+    writer.push(match record.action {
+        AuditAction::Add => ADD_TAG,
+        AuditAction::Delete => DELETE_TAG,
+        AuditAction::Rollback => ROLLBACK_TAG,
+    });
+    writer.extend_from_slice(&record.epoch.to_be_bytes());
+    writer.extend_from_slice(&record.value_hash);
+    write_bytes(writer, &record.nonce)?;
+    for field in [&record.prime, &record.previous_state, &record.new_state].iter() {
+        write_bytes(writer, &field.to_bytes_be())?;
+    }
+    writer.extend_from_slice(&record.timestamp.to_be_bytes());
+    Ok(())
+}
+
+pub(crate) fn read_record(reader: &mut &[u8]) -> io::Result<AuditRecord> {
+    let mut tag: [u8; 1] = [0; 1];
+    reader.read_exact(&mut tag)?;
+    let action: AuditAction = match tag[0] {
+        ADD_TAG => AuditAction::Add,
+        DELETE_TAG => AuditAction::Delete,
+        ROLLBACK_TAG => AuditAction::Rollback,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown action in an audit record")),
+    };
+    let mut epoch: [u8; 8] = [0; 8];
+    reader.read_exact(&mut epoch)?;
+    let mut value_hash: [u8; 32] = [0; 32];
+    reader.read_exact(&mut value_hash)?;
+    let nonce: Vec<u8> = read_bytes(reader)?;
+    let prime: BigUint = read_biguint(reader)?;
+    let previous_state: BigUint = read_biguint(reader)?;
+    let new_state: BigUint = read_biguint(reader)?;
+    let mut timestamp: [u8; 8] = [0; 8];
+    reader.read_exact(&mut timestamp)?;
+    Ok(AuditRecord {
+        action,
+        epoch: u64::from_be_bytes(epoch),
+        value_hash,
+        nonce,
+        prime,
+        previous_state,
+        new_state,
+        timestamp: u64::from_be_bytes(timestamp),
+    })
+}
+
+#[cfg(any(feature = "sled", feature = "rusqlite", feature = "redis", feature = "rocksdb"))]
+pub(crate) fn encode_record(record: &AuditRecord) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    // only a field too long to length-prefix fails, which no record that fits in memory has
+    write_record(&mut bytes, record).unwrap();
+    bytes
+}
+
+#[cfg(any(feature = "sled", feature = "rusqlite", feature = "redis", feature = "rocksdb"))]
+pub(crate) fn decode_record(mut bytes: &[u8]) -> io::Result<AuditRecord> {
+    let record: AuditRecord = read_record(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "trailing bytes after an audit record"));
+    }
+    Ok(record)
+}
+
+// an epoch on its own, for stores that keep one per key or row
+#[cfg(any(feature = "sled", feature = "rusqlite", feature = "redis", feature = "rocksdb"))]
+pub(crate) fn encode_epoch(epoch: &Epoch) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    // only a field too long to length-prefix fails, which no epoch that fits in memory has
+    write_epoch(&mut bytes, epoch).unwrap();
+    bytes
+}
+
+#[cfg(any(feature = "sled", feature = "rusqlite", feature = "redis", feature = "rocksdb"))]
+pub(crate) fn decode_epoch(mut bytes: &[u8]) -> io::Result<Epoch> {
+    let epoch: Epoch = read_epoch(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "trailing bytes after an epoch"));
+    }
+    Ok(epoch)
+}
+
+/// What [`SetAccumulator::collect_garbage`](crate::SetAccumulator::collect_garbage) dropped
+/// from a store, or would drop on a dry run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GarbageReport {
+    /// Epochs older than the retention policy keeps, along with the witnesses at them.
+    pub epochs: u64,
+    /// Primes of former members, see [`Storer::collect_primes`].
+    pub primes: u64,
+}
+
+/// The epochs after one, see [`SetAccumulator::export_delta`](crate::SetAccumulator::export_delta).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delta {
+    /// The state the first epoch starts from.
+    pub from: BigUint,
+    pub epochs: Vec<Epoch>,
+}
+
+// how many shards a ShardedMap splits its entries over
+const SHARDS: usize = 64;
+
+/// A map from byte strings split over shards that are shared between its clones until
+/// written to. Cloning it takes time in the number of shards rather than entries, and a
+/// write to a map that has clones copies only the one shard it touches, so a clone taken
+/// as a snapshot can be read from another thread without any lock while the original
+/// carries on changing.
+pub struct ShardedMap<V> {
+    hasher: RandomState,
+    shards: Vec<Arc<HashMap<Vec<u8>, V>>>,
+    len: usize,
+}
+
+impl<V: Clone> ShardedMap<V> {
+    pub fn new() -> Self {
+        ShardedMap { hasher: RandomState::new(), shards: (0..SHARDS).map(|_| Arc::new(HashMap::new())).collect(), len: 0 }
+    }
+    fn shard(&self, key: &[u8]) -> usize {
+        (self.hasher.hash_one(key) % SHARDS as u64) as usize
+    }
+    pub fn get(&self, key: &[u8]) -> Option<&V> {
+        self.shards[self.shard(key)].get(key)
+    }
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.shards[self.shard(key)].contains_key(key)
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Every entry, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &V)> + '_ {
+        self.shards.iter().flat_map(|shard| shard.iter().map(|(key, value)| (key.as_slice(), value)))
+    }
+    pub fn insert(&mut self, key: Vec<u8>, value: V) -> Option<V> {
+        let index: usize = self.shard(&key);
+        let replaced: Option<V> = Arc::make_mut(&mut self.shards[index]).insert(key, value);
+        if replaced.is_none() {
+            self.len += 1;
+        }
+        replaced
+    }
+    pub fn remove(&mut self, key: &[u8]) -> Option<V> {
+        let index: usize = self.shard(key);
+        // a shard shared with a clone is only copied if there is something to remove
+        if !self.shards[index].contains_key(key) {
+            return None;
+        }
+        self.len -= 1;
+        Arc::make_mut(&mut self.shards[index]).remove(key)
+    }
+    /// Keeps the entries `keep` holds for. Shards where it holds for all of them stay
+    /// shared with the clones of the map.
+    pub fn retain<F: FnMut(&[u8], &V) -> bool>(&mut self, mut keep: F) {
+        for shard in self.shards.iter_mut() {
+            if shard.iter().all(|(key, value)| keep(key, value)) {
+                continue;
+            }
+            let shard: &mut HashMap<Vec<u8>, V> = Arc::make_mut(shard);
+            let before: usize = shard.len();
+            shard.retain(|key, value| keep(key, value));
+            self.len -= before - shard.len();
+        }
+    }
+}
+
+impl<V> Clone for ShardedMap<V> {
+    fn clone(&self) -> Self {
+        ShardedMap { hasher: self.hasher.clone(), shards: self.shards.clone(), len: self.len }
+    }
+}
+
+impl<V: Clone> Default for ShardedMap<V> {
+    fn default() -> Self {
+        ShardedMap::new()
+    }
+}
+
+impl<V: Clone> FromIterator<(Vec<u8>, V)> for ShardedMap<V> {
+    fn from_iter<I: IntoIterator<Item = (Vec<u8>, V)>>(entries: I) -> Self {
+        let mut map: ShardedMap<V> = ShardedMap::new();
+        for (key, value) in entries {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+impl<V: Clone + fmt::Debug> fmt::Debug for ShardedMap<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+// the value to nonce maps the prime cache is swept against
+pub(crate) trait NonceLookup {
+    fn nonce_of(&self, value: &[u8]) -> Option<&[u8]>;
+    fn count(&self) -> usize;
+}
+
+impl NonceLookup for HashMap<Vec<u8>, Vec<u8>> {
+    fn nonce_of(&self, value: &[u8]) -> Option<&[u8]> {
+        self.get(value).map(Vec::as_slice)
+    }
+    fn count(&self) -> usize {
+        self.len()
+    }
+}
+
+impl NonceLookup for ShardedMap<Vec<u8>> {
+    fn nonce_of(&self, value: &[u8]) -> Option<&[u8]> {
+        self.get(value).map(Vec::as_slice)
+    }
+    fn count(&self) -> usize {
+        self.len()
+    }
+}
+
+/// The primes kept for members, each with the nonce it belongs to, so a prime outlived by
+/// its member is never handed out for a later one with a different nonce.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PrimeCache {
+    primes: ShardedMap<(Vec<u8>, BigUint)>,
+}
+
+impl PrimeCache {
+    pub(crate) fn get(&self, value: &[u8], nonce: &[u8]) -> Option<&BigUint> {
+        match self.primes.get(value) {
+            Some((kept, prime)) if kept.as_slice() == nonce => Some(prime),
+            _ => None,
+        }
+    }
+    pub(crate) fn insert(&mut self, value: &[u8], nonce: &[u8], prime: &BigUint) {
+        self.primes.insert(value.to_vec(), (nonce.to_vec(), prime.clone()));
+    }
+    // every value with the nonce and prime kept for it
+    #[cfg(feature = "serde")]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8], &BigUint)> + '_ {
+        self.primes.iter().map(|(value, (nonce, prime))| (value, nonce.as_slice(), prime))
+    }
+    // for stores that never see members leave: drop the primes of former members once
+    // they make up half the cache, which keeps the sweeps amortised constant time
+    pub(crate) fn sweep<M: NonceLookup>(&mut self, members: &M) {
+        if self.primes.len() > 2 * members.count() {
+            self.collect(members, false);
+        }
+    }
+    // drops the primes of former members whatever their share, returning how many
+    pub(crate) fn collect<M: NonceLookup>(&mut self, members: &M, dry_run: bool) -> u64 {
+        let before: usize = self.primes.len();
+        if dry_run {
+            return self.primes.iter().filter(|(value, (nonce, _))| members.nonce_of(value) != Some(nonce.as_slice())).count() as u64;
+        }
+        self.primes.retain(|value, (nonce, _)| members.nonce_of(value) == Some(nonce.as_slice()));
+        (before - self.primes.len()) as u64
+    }
+}
+
+/// A member added (with its nonce) or removed (None) since a persistent store last wrote
+/// its members out.
+pub(crate) type MemberChange = (Vec<u8>, Option<Vec<u8>>);
+
+/// The members of a store that writes them out on set_state, along with what has changed
+/// since the last write.
+#[derive(Debug, Default)]
+pub(crate) struct TrackedMembers {
+    members: HashMap<Vec<u8>, Vec<u8>>,
+    // every value changed since the last write, with the nonce it had then
+    unwritten: HashMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl TrackedMembers {
+    pub(crate) fn new(members: HashMap<Vec<u8>, Vec<u8>>) -> Self {
+        TrackedMembers { members, unwritten: HashMap::new() }
+    }
+    pub(crate) fn map(&self) -> &HashMap<Vec<u8>, Vec<u8>> {
+        &self.members
+    }
+    pub(crate) fn get(&self, value: &[u8]) -> Option<&Vec<u8>> {
+        self.members.get(value)
+    }
+    pub(crate) fn insert(&mut self, value: &[u8], nonce: &[u8]) {
+        let before: Option<Vec<u8>> = self.members.insert(value.to_vec(), nonce.to_vec());
+        self.unwritten.entry(value.to_vec()).or_insert(before);
+    }
+    pub(crate) fn remove(&mut self, value: &[u8]) -> Option<Vec<u8>> {
+        let before: Option<Vec<u8>> = self.members.remove(value);
+        if before.is_some() {
+            self.unwritten.entry(value.to_vec()).or_insert_with(|| before.clone());
+        }
+        before
+    }
+    // what the next write has to contain, leaving out changes that were undone again
+    pub(crate) fn changes(&self) -> Vec<MemberChange> {
+        self.unwritten
+            .iter()
+            .filter(|(value, before)| self.members.get(*value) != before.as_ref())
+            .map(|(value, _)| (value.clone(), self.members.get(value).cloned()))
+            .collect()
+    }
+    pub(crate) fn mark_written(&mut self) {
+        self.unwritten.clear();
+    }
+    // puts the members back as they were at the last write
+    pub(crate) fn revert(&mut self) {
+        for (value, before) in self.unwritten.drain() {
+            match before {
+                Some(nonce) => self.members.insert(value, nonce),
+                None => self.members.remove(&value),
+            };
+        }
+    }
 }
@@ -0,0 +1,819 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use num_bigint::BigUint;
+use redis::{Commands, Connection, Pipeline, RedisError};
+
+use crate::error::AccumulatorError;
+use crate::manager::Backend;
+use crate::params::{HashFunction, PrimeMapping, PublicParameters, Trapdoor};
+use crate::store::{decode_epoch, decode_record, encode_epoch, encode_record, AuditRecord, Epoch, MemberChange, PrimeCache, Storer, TrackedMembers};
+
+fn to_io(e: RedisError) -> io::Error {
+    io::Error::other(e)
+}
+
+// every key a store keeps under its prefix
+const KEYS: [&str; 10] = ["params", "state", "version", "members", "primes", "epochs", "first", "log", "lease", "fence"];
+
+// what came of a write
+enum Written {
+    Version(u64),
+    // another process changed the accumulator since the view was taken
+    Conflict,
+    // the lease is not this store's to write under
+    Fenced,
+}
+
+/// A store kept in Redis under `<prefix>:params`, `<prefix>:state`, `<prefix>:version`,
+/// the `<prefix>:members` hash, the `<prefix>:primes` hash of the prime each member maps
+/// to and the `<prefix>:epochs` and `<prefix>:log` lists of every epoch and audit record,
+/// so several processes can serve the same accumulator. Once the epochs are compacted,
+/// `<prefix>:first` holds the number of the epoch at the head of the list.
+///
+/// Each store works on a consistent view of the accumulator taken when it was opened or
+/// last [refreshed](RedisStore::refresh). Whatever changed in the members is written
+/// together with the state in one MULTI/EXEC on every [`set_state`](Storer::set_state),
+/// under a WATCH on the version, which every write bumps. A write from a view that
+/// another process has since changed would be computed from a stale state, so it is
+/// refused: the accumulator's change fails with [`AccumulatorError::Conflict`] and the
+/// store takes a new view, over which the change can be made again. A write that fails in
+/// Redis itself fails the change with [`AccumulatorError::Io`] and puts the store back at
+/// its view as of the last write; should the write have gone through after all, the next
+/// one is refused as a conflict. Only a failed
+/// [`set_state`](Storer::set_state) made directly outside of a transaction has no way to
+/// be reported and panics.
+///
+/// The version only keeps writes from being computed from stale views, it does not stop
+/// two processes that both believe they are the writer from taking turns. A writer that
+/// takes the lease with [`RedisStore::acquire_lease`] gets a fencing token, the value of
+/// `<prefix>:fence` it was handed out at, held in `<prefix>:lease` until the lease
+/// expires. Every write checks the lease under the same WATCH as the version: it goes
+/// through only if the lease holds this store's token, or if neither holds one, and
+/// otherwise fails with [`AccumulatorError::Fenced`]. A writer whose lease ran out while
+/// it was stalled, and which another has since taken, so has its next write refused
+/// rather than forking the accumulator. The lease is checked as each write starts, so it
+/// should be renewed well before it expires.
+///
+/// The trapdoor is never written to Redis.
+pub struct RedisStore {
+    connection: Connection,
+    prefix: String,
+    params: PublicParameters,
+    state: BigUint,
+    // the state as of the last write, which a failed write puts the store back at
+    written_state: BigUint,
+    version: u64,
+    members: TrackedMembers,
+    primes: PrimeCache,
+    trapdoor: Option<Trapdoor>,
+    in_transaction: bool,
+    // the fencing token of the lease this store took, if it took one
+    lease: Option<u64>,
+    // the epochs from the first one held and the audit records as of the view, read once
+    // since they never change, and those waiting for the next write
+    first_epoch: u64,
+    epochs: Vec<Epoch>,
+    pending_epochs: Vec<Epoch>,
+    log: Vec<AuditRecord>,
+    pending_log: Vec<AuditRecord>,
+}
+
+impl fmt::Debug for RedisStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisStore")
+            .field("prefix", &self.prefix)
+            .field("params", &self.params)
+            .field("state", &self.state)
+            .field("version", &self.version)
+            .field("members", &self.members.map().len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl RedisStore {
+    /// Opens the store under `prefix` at the Redis server at `url`, creating it over
+    /// `params` at the genesis state if it does not exist yet. An existing store must have
+    /// been created over `params`.
+    pub fn open(url: &str, prefix: &str, params: &PublicParameters) -> io::Result<Self> {
+        let mut connection: Connection = redis::Client::open(url).and_then(|client| client.get_connection()).map_err(to_io)?;
+        // SETNX on every key, so two processes creating the store at once agree on it
+        redis::pipe()
+            .atomic()
+            .set_nx(format!("{}:params", prefix), params.to_bytes()).ignore()
+            .set_nx(format!("{}:state", prefix), params.genesis_state().to_bytes_be()).ignore()
+            .set_nx(format!("{}:version", prefix), 0_u64).ignore()
+            .query::<()>(&mut connection)
+            .map_err(to_io)?;
+        let store: RedisStore = RedisStore::load(connection, prefix)?;
+        if store.params != *params {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "store was created with different public parameters"));
+        }
+        Ok(store)
+    }
+    /// Opens an existing store under `prefix` with whatever parameters it was created over.
+    pub fn open_existing(url: &str, prefix: &str) -> io::Result<Self> {
+        let connection: Connection = redis::Client::open(url).and_then(|client| client.get_connection()).map_err(to_io)?;
+        RedisStore::load(connection, prefix)
+    }
+    fn load(mut connection: Connection, prefix: &str) -> io::Result<Self> {
+        let params: Option<Vec<u8>> = connection.get(format!("{}:params", prefix)).map_err(to_io)?;
+        let params: PublicParameters = match params {
+            Some(params) => PublicParameters::from_bytes(&params)?,
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, "not a tangerine store")),
+        };
+        let mut store: RedisStore = RedisStore {
+            connection,
+            prefix: prefix.to_string(),
+            params,
+            state: BigUint::default(),
+            written_state: BigUint::default(),
+            version: 0,
+            members: TrackedMembers::default(),
+            primes: PrimeCache::default(),
+            trapdoor: None,
+            in_transaction: false,
+            lease: None,
+            first_epoch: 1,
+            epochs: Vec::new(),
+            pending_epochs: Vec::new(),
+            log: Vec::new(),
+            pending_log: Vec::new(),
+        };
+        store.reload()?;
+        Ok(store)
+    }
+    fn key(&self, name: &str) -> String {
+        format!("{}:{}", self.prefix, name)
+    }
+    /// Takes a new view of the accumulator if another process changed it, dropping any
+    /// changes to the members that were not followed by a write.
+    pub fn refresh(&mut self) -> io::Result<()> {
+        let version: u64 = self.connection.get(self.key("version")).map_err(to_io)?;
+        if version == self.version {
+            self.revert();
+            return Ok(());
+        }
+        self.reload()
+    }
+    fn reload(&mut self) -> io::Result<()> {
+        type View = (u64, Vec<u8>, HashMap<Vec<u8>, Vec<u8>>, HashMap<Vec<u8>, Vec<u8>>, Option<u64>, Vec<Vec<u8>>, Vec<Vec<u8>>);
+        let (version, state, members, primes, epochs, log) = loop {
+            let (version, state, members, primes, first, epochs, log): View = redis::pipe()
+                .atomic()
+                .get(self.key("version"))
+                .get(self.key("state"))
+                .hgetall(self.key("members"))
+                .hgetall(self.key("primes"))
+                .get(self.key("first"))
+                .lrange(self.key("epochs"), self.epochs.len() as isize, -1)
+                .lrange(self.key("log"), self.log.len() as isize, -1)
+                .query(&mut self.connection)
+                .map_err(to_io)?;
+            // the epochs held were read past the head of the list as of this view, which
+            // has moved if another process compacted them since, so read them all again
+            let first: u64 = first.unwrap_or(1);
+            if first == self.first_epoch {
+                break (version, state, members, primes, epochs, log);
+            }
+            self.first_epoch = first;
+            self.epochs.clear();
+        };
+        for epoch in epochs {
+            self.epochs.push(decode_epoch(&epoch)?);
+        }
+        for record in log {
+            self.log.push(decode_record(&record)?);
+        }
+        self.pending_epochs.clear();
+        self.pending_log.clear();
+        self.version = version;
+        self.state = BigUint::from_bytes_be(&state);
+        self.written_state = self.state.clone();
+        self.primes = PrimeCache::default();
+        for (value, prime) in primes {
+            if let Some(nonce) = members.get(&value) {
+                self.primes.insert(&value, nonce, &BigUint::from_bytes_be(&prime));
+            }
+        }
+        self.members = TrackedMembers::new(members);
+        Ok(())
+    }
+    /// The number of writes made to the accumulator as of this store's view.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+    pub fn with_trapdoor(mut self, trapdoor: Trapdoor) -> Self {
+        self.trapdoor = Some(trapdoor);
+        self
+    }
+    /// Takes the writer lease for `ttl`, returning the fencing token it was taken with, or
+    /// None if another store holds it. Tokens only ever grow, so a later holder of the
+    /// lease always has a larger one.
+    pub fn acquire_lease(&mut self, ttl: Duration) -> io::Result<Option<u64>> {
+        let token: u64 = self.connection.incr(self.key("fence"), 1_u64).map_err(to_io)?;
+        let taken: Option<String> = redis::cmd("SET")
+            .arg(self.key("lease"))
+            .arg(token)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl.as_millis() as u64)
+            .query(&mut self.connection)
+            .map_err(to_io)?;
+        if taken.is_none() {
+            return Ok(None);
+        }
+        self.lease = Some(token);
+        Ok(Some(token))
+    }
+    /// Extends the lease to `ttl` from now, returning false if this store no longer holds it.
+    pub fn renew_lease(&mut self, ttl: Duration) -> io::Result<bool> {
+        let mut pipe: Pipeline = redis::pipe();
+        pipe.atomic().pexpire(self.key("lease"), ttl.as_millis() as i64);
+        self.under_lease(pipe)
+    }
+    /// Gives the lease up, so another writer can take it without waiting for it to expire.
+    pub fn release_lease(&mut self) -> io::Result<()> {
+        let mut pipe: Pipeline = redis::pipe();
+        pipe.atomic().del(self.key("lease"));
+        self.under_lease(pipe)?;
+        self.lease = None;
+        Ok(())
+    }
+    /// The fencing token of the lease this store took, see [`RedisStore::acquire_lease`].
+    pub fn fencing_token(&self) -> Option<u64> {
+        self.lease
+    }
+    // runs `pipe` if the lease is still this store's, returning whether it was
+    fn under_lease(&mut self, pipe: Pipeline) -> io::Result<bool> {
+        let token: u64 = match self.lease {
+            Some(token) => token,
+            None => return Ok(false),
+        };
+        redis::cmd("WATCH").arg(self.key("lease")).query::<()>(&mut self.connection).map_err(to_io)?;
+        let held: Option<u64> = self.connection.get(self.key("lease")).map_err(to_io)?;
+        if held != Some(token) {
+            redis::cmd("UNWATCH").query::<()>(&mut self.connection).map_err(to_io)?;
+            return Ok(false);
+        }
+        let done: Option<(i64,)> = pipe.query(&mut self.connection).map_err(to_io)?;
+        Ok(done.is_some())
+    }
+    // puts the store back at its view as of the last write
+    fn revert(&mut self) {
+        self.members.revert();
+        self.primes.sweep(self.members.map());
+        self.pending_epochs.clear();
+        self.pending_log.clear();
+        self.state = self.written_state.clone();
+    }
+    fn failed_reload(&mut self, e: io::Error) -> AccumulatorError {
+        self.revert();
+        AccumulatorError::Io(e.to_string())
+    }
+    fn write(&mut self, changes: &[MemberChange], new_state: &BigUint) -> redis::RedisResult<Written> {
+        redis::cmd("WATCH").arg(self.key("version")).arg(self.key("lease")).query::<()>(&mut self.connection)?;
+        let (version, lease): (u64, Option<u64>) = redis::pipe().get(self.key("version")).get(self.key("lease")).query(&mut self.connection)?;
+        if lease != self.lease || version != self.version {
+            redis::cmd("UNWATCH").query::<()>(&mut self.connection)?;
+            return Ok(if lease != self.lease { Written::Fenced } else { Written::Conflict });
+        }
+        let mut pipe: Pipeline = redis::pipe();
+        pipe.atomic();
+        for (value, nonce) in changes.iter() {
+            pipe.hdel(self.key("primes"), value).ignore();
+            match nonce {
+                Some(nonce) => {
+                    pipe.hset(self.key("members"), value, nonce).ignore();
+                    if let Some(prime) = self.primes.get(value, nonce) {
+                        pipe.hset(self.key("primes"), value, prime.to_bytes_be()).ignore();
+                    }
+                }
+                None => {
+                    pipe.hdel(self.key("members"), value).ignore();
+                }
+            };
+        }
+        for epoch in self.pending_epochs.iter() {
+            pipe.rpush(self.key("epochs"), encode_epoch(epoch)).ignore();
+        }
+        for record in self.pending_log.iter() {
+            pipe.rpush(self.key("log"), encode_record(record)).ignore();
+        }
+        pipe.set(self.key("state"), new_state.to_bytes_be()).ignore();
+        pipe.incr(self.key("version"), 1_u64);
+        // EXEC answers nil when the watched version or lease changed after the check above
+        let written: Option<(u64,)> = pipe.query(&mut self.connection)?;
+        Ok(match written {
+            Some((version,)) => Written::Version(version),
+            None => Written::Conflict,
+        })
+    }
+}
+
+impl Storer for RedisStore {
+    fn get_generator(&self) -> &BigUint {
+        &self.params.generator
+    }
+    fn get_modulus(&self) -> &BigUint {
+        &self.params.modulus
+    }
+    fn get_state(&self) -> &BigUint {
+        &self.state
+    }
+    fn set_state(&mut self, new_state: &BigUint) {
+        let in_transaction: bool = self.in_transaction;
+        self.state = new_state.clone();
+        if !in_transaction {
+            self.commit_transaction().expect("failed to write to the redis store");
+        }
+    }
+    fn get_trapdoor(&self) -> Option<Trapdoor> {
+        self.trapdoor.clone()
+    }
+    fn get_nonce(&self, value: &[u8]) -> Option<Vec<u8>> {
+        self.members.get(value).cloned()
+    }
+    fn insert_member(&mut self, value: &[u8], nonce: &[u8]) {
+        self.members.insert(value, nonce);
+    }
+    fn remove_member(&mut self, value: &[u8]) -> Option<Vec<u8>> {
+        self.members.remove(value)
+    }
+    fn iter_members(&self) -> impl Iterator<Item = (&[u8], &[u8])> + '_ {
+        self.members.map().iter().map(|(value, nonce)| (value.as_slice(), nonce.as_slice()))
+    }
+    fn contains_member(&self, value: &[u8]) -> bool {
+        self.members.get(value).is_some()
+    }
+    fn member_count(&self) -> usize {
+        self.members.map().len()
+    }
+    fn begin_transaction(&mut self) {
+        self.in_transaction = true;
+    }
+    fn commit_transaction(&mut self) -> Result<(), AccumulatorError> {
+        self.in_transaction = false;
+        let changes: Vec<MemberChange> = self.members.changes();
+        let state: BigUint = self.state.clone();
+        let written: Written = match self.write(&changes, &state) {
+            Ok(written) => written,
+            Err(e) => {
+                self.revert();
+                return Err(AccumulatorError::Io(e.to_string()));
+            }
+        };
+        match written {
+            Written::Version(version) => {
+                self.members.mark_written();
+                self.written_state = state;
+                self.primes.sweep(self.members.map());
+                self.epochs.append(&mut self.pending_epochs);
+                self.log.append(&mut self.pending_log);
+                self.version = version;
+                Ok(())
+            }
+            Written::Conflict => {
+                self.reload().map_err(|e| self.failed_reload(e))?;
+                Err(AccumulatorError::Conflict)
+            }
+            Written::Fenced => {
+                self.reload().map_err(|e| self.failed_reload(e))?;
+                Err(AccumulatorError::Fenced)
+            }
+        }
+    }
+    fn get_hash_function(&self) -> HashFunction {
+        self.params.hash_function
+    }
+    fn get_prime_bits(&self) -> Option<u64> {
+        self.params.prime_bits
+    }
+    fn get_prime(&self, value: &[u8], nonce: &[u8]) -> Option<&BigUint> {
+        self.primes.get(value, nonce)
+    }
+    fn set_prime(&mut self, value: &[u8], nonce: &[u8], prime: &BigUint) {
+        self.primes.insert(value, nonce, prime);
+    }
+    fn get_prime_mapping(&self) -> PrimeMapping {
+        self.params.prime_mapping()
+    }
+    // the primes written for values no longer members, checked against the members in
+    // Redis rather than this store's view, under a WATCH on the version so a write from
+    // another process in between is not undone
+    fn collect_primes(&mut self, dry_run: bool) -> io::Result<u64> {
+        loop {
+            redis::cmd("WATCH").arg(self.key("version")).query::<()>(&mut self.connection).map_err(to_io)?;
+            let (primes, members): (Vec<Vec<u8>>, Vec<Vec<u8>>) =
+                redis::pipe().hkeys(self.key("primes")).hkeys(self.key("members")).query(&mut self.connection).map_err(to_io)?;
+            let members: HashSet<Vec<u8>> = members.into_iter().collect();
+            let orphans: Vec<Vec<u8>> = primes.into_iter().filter(|value| !members.contains(value)).collect();
+            if dry_run || orphans.is_empty() {
+                redis::cmd("UNWATCH").query::<()>(&mut self.connection).map_err(to_io)?;
+                return Ok(orphans.len() as u64);
+            }
+            // EXEC answers nil when a write changed the members after they were read
+            let removed: Option<(u64,)> = redis::pipe().atomic().hdel(self.key("primes"), &orphans).query(&mut self.connection).map_err(to_io)?;
+            if let Some((removed,)) = removed {
+                self.primes.collect(self.members.map(), false);
+                return Ok(removed);
+            }
+        }
+    }
+    fn push_epoch(&mut self, epoch: &Epoch) {
+        self.pending_epochs.push(epoch.clone());
+    }
+    fn epoch_count(&self) -> u64 {
+        self.first_epoch - 1 + (self.epochs.len() + self.pending_epochs.len()) as u64
+    }
+    fn get_epoch(&self, epoch: u64) -> io::Result<Option<Epoch>> {
+        let index: Option<usize> = epoch.checked_sub(self.first_epoch).map(|index| index as usize);
+        Ok(index.and_then(|index| self.epochs.iter().chain(self.pending_epochs.iter()).nth(index)).cloned())
+    }
+    fn first_epoch(&self) -> u64 {
+        self.first_epoch
+    }
+    // trims the list under a WATCH on its head, so two processes compacting at once never
+    // both drop epochs counted from the same head
+    fn compact_epochs(&mut self, first: u64, base: &Epoch) -> io::Result<()> {
+        redis::cmd("WATCH").arg(self.key("first")).query::<()>(&mut self.connection).map_err(to_io)?;
+        let head: Option<u64> = self.connection.get(self.key("first")).map_err(to_io)?;
+        let compacted = || io::Error::other("the epochs were compacted by another process since this store's view was taken");
+        if head.unwrap_or(1) != self.first_epoch {
+            redis::cmd("UNWATCH").query::<()>(&mut self.connection).map_err(to_io)?;
+            return Err(compacted());
+        }
+        let dropped: usize = (first - self.first_epoch) as usize;
+        let done: Option<((), (), ())> = redis::pipe()
+            .atomic()
+            .ltrim(self.key("epochs"), dropped as isize, -1)
+            .lset(self.key("epochs"), 0, encode_epoch(base))
+            .set(self.key("first"), first)
+            .query(&mut self.connection)
+            .map_err(to_io)?;
+        if done.is_none() {
+            return Err(compacted());
+        }
+        self.epochs.drain(..dropped);
+        self.epochs[0] = base.clone();
+        self.first_epoch = first;
+        Ok(())
+    }
+    fn append_log(&mut self, record: &AuditRecord) {
+        self.pending_log.push(record.clone());
+    }
+    fn iter_log(&self) -> impl Iterator<Item = io::Result<AuditRecord>> + '_ {
+        self.log.iter().chain(self.pending_log.iter()).cloned().map(Ok)
+    }
+}
+
+/// Follows the `<prefix>:epochs` list of a [`RedisStore`], for a
+/// [`Follower`](crate::replica::Follower). Every write pushes its epoch in the same
+/// MULTI/EXEC as its state, so the list only ever holds whole epochs. A follower behind
+/// the first epoch the store still holds cannot catch up from the list, and is told so.
+pub struct RedisTail {
+    connection: Connection,
+    prefix: String,
+    params: PublicParameters,
+}
+
+impl RedisTail {
+    pub fn open(url: &str, prefix: &str) -> io::Result<Self> {
+        let mut connection: Connection = redis::Client::open(url).and_then(|client| client.get_connection()).map_err(to_io)?;
+        let params: Option<Vec<u8>> = connection.get(format!("{}:params", prefix)).map_err(to_io)?;
+        let params: PublicParameters = match params {
+            Some(params) => PublicParameters::from_bytes(&params)?,
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, "not a tangerine store")),
+        };
+        Ok(RedisTail { connection, prefix: prefix.to_string(), params })
+    }
+    /// The parameters the store was created over.
+    pub fn params(&self) -> &PublicParameters {
+        &self.params
+    }
+}
+
+impl crate::replica::EpochFeed for RedisTail {
+    fn epochs_after(&mut self, epoch: u64) -> io::Result<Vec<Epoch>> {
+        let mut first: Option<u64> = self.connection.get(format!("{}:first", self.prefix)).map_err(to_io)?;
+        loop {
+            // the first epoch compacted holds every member rather than the changes it made,
+            // so only a follower at or past it can go on from the list
+            let head: u64 = first.unwrap_or(1);
+            if head > 1 && epoch < head {
+                return Err(io::Error::new(io::ErrorKind::NotFound, format!("the epochs before {} were compacted", head)));
+            }
+            // the head is read again along with the epochs, which are past it only if a
+            // compaction in between did not move it
+            let (now, epochs): (Option<u64>, Vec<Vec<u8>>) = redis::pipe()
+                .atomic()
+                .get(format!("{}:first", self.prefix))
+                .lrange(format!("{}:epochs", self.prefix), (epoch + 1 - head) as isize, -1)
+                .query(&mut self.connection)
+                .map_err(to_io)?;
+            if now == first {
+                return epochs.iter().map(|epoch| decode_epoch(epoch)).collect();
+            }
+            first = now;
+        }
+    }
+}
+
+/// Named stores side by side on one Redis server, each under `<prefix>:ns:<name>`, with
+/// the names in the `<prefix>:namespaces` set. Every key of a store ends in one of a few
+/// fixed names without a colon, so namespaces whose names extend one another never share
+/// a key.
+#[derive(Debug, Clone)]
+pub struct RedisBackend {
+    url: String,
+    prefix: String,
+}
+
+impl RedisBackend {
+    pub fn new(url: &str, prefix: &str) -> Self {
+        RedisBackend { url: url.to_string(), prefix: prefix.to_string() }
+    }
+    fn connection(&self) -> io::Result<Connection> {
+        redis::Client::open(self.url.as_str()).and_then(|client| client.get_connection()).map_err(to_io)
+    }
+    fn store_prefix(&self, namespace: &str) -> String {
+        format!("{}:ns:{}", self.prefix, namespace)
+    }
+}
+
+impl Backend for RedisBackend {
+    type Store = RedisStore;
+
+    fn open(&mut self, namespace: &str, params: &PublicParameters) -> io::Result<RedisStore> {
+        let store: RedisStore = RedisStore::open(&self.url, &self.store_prefix(namespace), params)?;
+        self.connection()?.sadd::<_, _, ()>(format!("{}:namespaces", self.prefix), namespace).map_err(to_io)?;
+        Ok(store)
+    }
+    fn namespaces(&self) -> io::Result<Vec<String>> {
+        let mut namespaces: Vec<String> = self.connection()?.smembers(format!("{}:namespaces", self.prefix)).map_err(to_io)?;
+        namespaces.sort();
+        Ok(namespaces)
+    }
+    fn remove(&mut self, namespace: &str) -> io::Result<bool> {
+        let prefix: String = self.store_prefix(namespace);
+        let keys: Vec<String> = KEYS.iter().map(|name| format!("{}:{}", prefix, name)).collect();
+        let (removed, _): (u64, u64) = redis::pipe()
+            .atomic()
+            .srem(format!("{}:namespaces", self.prefix), namespace)
+            .del(keys)
+            .query(&mut self.connection()?)
+            .map_err(to_io)?;
+        Ok(removed == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use crate::proof::MembershipProof;
+    use crate::SetAccumulator;
+
+    #[test]
+    #[ignore = "needs a Redis server, set REDIS_URL to run it"]
+    fn test_shared_store() {
+        let url: String = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let prefix: String = format!("tangerine-test-{}-{}", std::process::id(), nanos);
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut writer: SetAccumulator<RedisStore> = SetAccumulator::new(RedisStore::open(&url, &prefix, &params).unwrap());
+        let mut reader: RedisStore = RedisStore::open_existing(&url, &prefix).unwrap();
+        writer.add(b"alice").unwrap();
+        writer.add_batch(&[b"bob", b"carol"]).unwrap();
+        writer.delete_without_trapdoor(b"bob").unwrap();
+        assert_eq!(3, writer.store.version());
+
+        // the reader sees the writes once it refreshes
+        assert_eq!(0, reader.member_count());
+        reader.refresh().unwrap();
+        assert_eq!(writer.store.get_state(), reader.get_state());
+        assert_eq!(writer.store.members.map(), reader.members.map());
+        let nonce: Vec<u8> = reader.get_nonce(b"carol").unwrap();
+        assert!(reader.get_prime(b"carol", &nonce).is_some());
+        // along with the history, which a store opened afresh reads back too
+        assert_eq!(3, reader.epoch_count());
+        assert_eq!(writer.store.get_epoch(2).unwrap(), reader.get_epoch(2).unwrap());
+        let reopened: SetAccumulator<RedisStore> = SetAccumulator::new(RedisStore::open_existing(&url, &prefix).unwrap());
+        assert_eq!(3, reopened.store.epoch_count());
+        assert_eq!(writer.store.iter_log().map(Result::unwrap).collect::<Vec<_>>(), reopened.store.iter_log().map(Result::unwrap).collect::<Vec<_>>());
+        assert_eq!(4, reopened.store.iter_log().count());
+        let proof: MembershipProof = reopened.prove_membership_at(b"bob", 2).unwrap();
+        assert!(reopened.verify_at(2, b"bob", &proof));
+
+        // and a write from a stale view is refused, leaving the store at a new view that
+        // the change can be made again over
+        writer.add(b"dave").unwrap();
+        let mut stale: SetAccumulator<RedisStore> = SetAccumulator::new(reader);
+        assert_eq!(Err(AccumulatorError::Conflict), stale.add(b"erin").map(|_| ()));
+        assert!(stale.contains(b"dave") && !stale.contains(b"erin"));
+        stale.add(b"erin").unwrap();
+        writer.store.refresh().unwrap();
+        assert_eq!(stale.store.get_state(), writer.store.get_state());
+
+        let _: () = redis::cmd("DEL")
+            .arg(KEYS.iter().map(|name| format!("{}:{}", prefix, name)).collect::<Vec<String>>())
+            .query(&mut writer.store.connection)
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server, set REDIS_URL to run it"]
+    fn test_failed_write() {
+        let url: String = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let prefix: String = format!("tangerine-test-{}-{}", std::process::id(), nanos);
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut sa: SetAccumulator<RedisStore> = SetAccumulator::new(RedisStore::open(&url, &prefix, &params).unwrap());
+        sa.add(b"alice").unwrap();
+        let state: BigUint = sa.store.get_state().clone();
+
+        // a version Redis cannot read back as a number fails the next write, which is
+        // reported rather than panicking and leaves the store at its last write
+        let version: String = sa.store.key("version");
+        sa.store.connection.set::<_, _, ()>(&version, "not a number").unwrap();
+        assert!(matches!(sa.add(b"bob"), Err(AccumulatorError::Io(_))));
+        assert!(sa.contains(b"alice") && !sa.contains(b"bob"));
+        assert_eq!(state, *sa.store.get_state());
+        assert_eq!(1, sa.store.epoch_count());
+
+        // and once Redis reads again, the change can be made again
+        sa.store.connection.set::<_, _, ()>(&version, 1_u64).unwrap();
+        sa.add(b"bob").unwrap();
+        let reopened: RedisStore = RedisStore::open_existing(&url, &prefix).unwrap();
+        assert_eq!(sa.store.get_state(), reopened.get_state());
+        assert_eq!(2, reopened.epoch_count());
+
+        let _: () = redis::cmd("DEL")
+            .arg(KEYS.iter().map(|name| format!("{}:{}", prefix, name)).collect::<Vec<String>>())
+            .query(&mut sa.store.connection)
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server, set REDIS_URL to run it"]
+    fn test_fencing() {
+        let url: String = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let prefix: String = format!("tangerine-test-{}-{}", std::process::id(), nanos);
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut a: SetAccumulator<RedisStore> = SetAccumulator::new(RedisStore::open(&url, &prefix, &params).unwrap());
+        let mut b: SetAccumulator<RedisStore> = SetAccumulator::new(RedisStore::open_existing(&url, &prefix).unwrap());
+        let first: u64 = a.store.acquire_lease(Duration::from_millis(200)).unwrap().unwrap();
+        a.add(b"alice").unwrap();
+        // while a holds the lease, b can neither take it nor write
+        assert_eq!(None, b.store.acquire_lease(Duration::from_secs(10)).unwrap());
+        assert_eq!(Err(AccumulatorError::Fenced), b.add(b"bob").map(|_| ()));
+        assert!(b.contains(b"alice") && !b.contains(b"bob"));
+        assert!(a.store.renew_lease(Duration::from_millis(200)).unwrap());
+
+        // a stalls past its lease, b takes it over with a larger token
+        std::thread::sleep(Duration::from_millis(400));
+        let second: u64 = b.store.acquire_lease(Duration::from_secs(10)).unwrap().unwrap();
+        assert!(second > first);
+        b.add(b"bob").unwrap();
+        // and a, waking up, has its write refused instead of forking the accumulator
+        assert_eq!(Err(AccumulatorError::Fenced), a.add(b"carol").map(|_| ()));
+        assert!(!a.store.renew_lease(Duration::from_secs(10)).unwrap());
+        assert_eq!(b.store.get_state(), a.store.get_state());
+
+        b.store.release_lease().unwrap();
+        assert_eq!(None, b.store.fencing_token());
+        b.add(b"carol").unwrap();
+
+        let _: () = redis::cmd("DEL")
+            .arg(KEYS.iter().map(|name| format!("{}:{}", prefix, name)).collect::<Vec<String>>())
+            .query(&mut a.store.connection)
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server, set REDIS_URL to run it"]
+    fn test_compact() {
+        use crate::replica::EpochFeed;
+        use crate::Retention;
+
+        let url: String = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let prefix: String = format!("tangerine-test-{}-{}", std::process::id(), nanos);
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut sa: SetAccumulator<RedisStore> = SetAccumulator::new(RedisStore::open(&url, &prefix, &params).unwrap());
+        let mut reader: RedisStore = RedisStore::open_existing(&url, &prefix).unwrap();
+        sa.add(b"alice").unwrap();
+        sa.add(b"bob").unwrap();
+        reader.refresh().unwrap();
+        sa.add(b"carol").unwrap();
+        assert_eq!(Ok(2), sa.compact(Retention::KeepLast(1)));
+        assert_eq!(Ok(0), sa.compact(Retention::KeepLast(1)));
+        sa.add(b"dave").unwrap();
+
+        // the dropped epochs stay dropped, and the one in their place holds every member
+        let reopened: SetAccumulator<RedisStore> = SetAccumulator::new(RedisStore::open_existing(&url, &prefix).unwrap());
+        assert_eq!((3, 4), (reopened.store.first_epoch(), reopened.current_epoch()));
+        assert_eq!(None, reopened.store.get_epoch(2).unwrap());
+        assert_eq!(3, reopened.store.get_epoch(3).unwrap().unwrap().operations.len());
+        assert_eq!(sa.store.get_epoch(4).unwrap(), reopened.store.get_epoch(4).unwrap());
+        assert!(reopened.prove_membership_at(b"alice", 3).is_some());
+        // a view taken before the compaction reads the list again from its new head
+        reader.refresh().unwrap();
+        assert_eq!((3, 4), (reader.first_epoch(), reader.epoch_count()));
+        assert_eq!(sa.store.get_epoch(4).unwrap(), reader.get_epoch(4).unwrap());
+        // and a tail goes on only from the epochs still held
+        let mut tail: RedisTail = RedisTail::open(&url, &prefix).unwrap();
+        assert!(tail.epochs_after(1).is_err());
+        assert_eq!(vec![sa.store.get_epoch(4).unwrap().unwrap()], tail.epochs_after(3).unwrap());
+
+        let _: () = redis::cmd("DEL")
+            .arg(KEYS.iter().map(|name| format!("{}:{}", prefix, name)).collect::<Vec<String>>())
+            .query(&mut sa.store.connection)
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server, set REDIS_URL to run it"]
+    fn test_collect_primes() {
+        let url: String = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let prefix: String = format!("tangerine-test-{}-{}", std::process::id(), nanos);
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut sa: SetAccumulator<RedisStore> = SetAccumulator::new(RedisStore::open(&url, &prefix, &params).unwrap());
+        sa.add(b"alice").unwrap();
+        // a prime left behind for a value that is no member
+        let primes: String = sa.store.key("primes");
+        sa.store.connection.hset::<_, _, _, ()>(&primes, b"ghost", vec![7_u8]).unwrap();
+        assert_eq!(1, sa.store.collect_primes(true).unwrap());
+        assert_eq!(1, sa.store.collect_primes(false).unwrap());
+        assert_eq!(0, sa.store.collect_primes(true).unwrap());
+        // the primes of members stay
+        let nonce: Vec<u8> = sa.store.get_nonce(b"alice").unwrap();
+        assert!(sa.store.connection.hexists::<_, _, bool>(&primes, b"alice").unwrap());
+        assert!(sa.store.get_prime(b"alice", &nonce).is_some());
+
+        let _: () = redis::cmd("DEL")
+            .arg(KEYS.iter().map(|name| format!("{}:{}", prefix, name)).collect::<Vec<String>>())
+            .query(&mut sa.store.connection)
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server, set REDIS_URL to run it"]
+    fn test_redis_tail() {
+        use crate::replica::Follower;
+
+        let url: String = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let prefix: String = format!("tangerine-test-{}-{}", std::process::id(), nanos);
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut primary: SetAccumulator<RedisStore> = SetAccumulator::new(RedisStore::open(&url, &prefix, &params).unwrap());
+        let tail: RedisTail = RedisTail::open(&url, &prefix).unwrap();
+        assert_eq!(params, *tail.params());
+        let mut follower: Follower<RedisTail> = Follower::new(&params, tail);
+        assert_eq!(Ok(0), follower.poll());
+        primary.add(b"alice").unwrap();
+        primary.add_batch(&[b"bob", b"carol"]).unwrap();
+        assert_eq!(Ok(2), follower.poll());
+        primary.delete_without_trapdoor(b"bob").unwrap();
+        assert_eq!(Ok(1), follower.poll());
+        assert_eq!(primary.store.get_state(), follower.accumulator().store.get_state());
+        let (witness, nonce): (BigUint, Vec<u8>) = follower.accumulator().get_witness(b"carol").unwrap();
+        assert!(primary.verify(b"carol", &witness, &nonce));
+
+        let _: () = redis::cmd("DEL")
+            .arg(KEYS.iter().map(|name| format!("{}:{}", prefix, name)).collect::<Vec<String>>())
+            .query(&mut primary.store.connection)
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a Redis server, set REDIS_URL to run it"]
+    fn test_namespaces() {
+        let url: String = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let prefix: String = format!("tangerine-test-{}-{}", std::process::id(), nanos);
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut backend: RedisBackend = RedisBackend::new(&url, &prefix);
+        let mut a: SetAccumulator<RedisStore> = backend.open_namespace("a", &params).unwrap();
+        a.add(b"alice").unwrap();
+        // a name extending another is a namespace of its own
+        let mut members: SetAccumulator<RedisStore> = backend.open_namespace("a:members", &params).unwrap();
+        members.add(b"bob").unwrap();
+        assert_eq!(vec!["a".to_string(), "a:members".to_string()], backend.namespaces().unwrap());
+
+        let a: RedisStore = backend.open("a", &params).unwrap();
+        assert_eq!(1, a.member_count());
+        assert!(a.contains_member(b"alice"));
+        assert!(backend.remove("a").unwrap());
+        assert!(!backend.remove("a").unwrap());
+        assert_eq!(params.genesis_state(), *backend.open("a", &params).unwrap().get_state());
+        assert!(backend.open("a:members", &params).unwrap().contains_member(b"bob"));
+        for namespace in ["a", "a:members"] {
+            backend.remove(namespace).unwrap();
+        }
+    }
+}
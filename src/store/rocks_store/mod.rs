@@ -0,0 +1,619 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use num_bigint::BigUint;
+use rocksdb::{ColumnFamily, Direction, IteratorMode, Options, WriteBatch, DB};
+
+use crate::error::AccumulatorError;
+use crate::params::{HashFunction, PrimeMapping, PublicParameters, Trapdoor};
+use crate::occurrences;
+use crate::store::{decode_epoch, decode_record, encode_epoch, encode_record, AuditRecord, Epoch, PrimeCache, Storer};
+
+// the members map values to their nonces and the primes values to the prime of the nonce
+// they are held with; the next two index the members the other way round, by that prime
+// and by the nonce of each of their occurrences, and the metadata holds everything else
+const MEMBERS: &str = "members";
+const PRIMES: &str = "primes";
+const BY_PRIME: &str = "by_prime";
+const BY_NONCE: &str = "by_nonce";
+const METADATA: &str = "metadata";
+const COLUMN_FAMILIES: [&str; 5] = [MEMBERS, PRIMES, BY_PRIME, BY_NONCE, METADATA];
+
+const PARAMS_KEY: &[u8] = b"\x00params";
+const STATE_KEY: &[u8] = b"\x00state";
+const COUNT_KEY: &[u8] = b"\x00count";
+// epochs are keyed by a four and their big-endian number, so they scan in order, and
+// audit records likewise by a five and their place in the log
+const EPOCH_PREFIX: u8 = 4;
+const LOG_PREFIX: u8 = 5;
+
+fn numbered_key(prefix: u8, number: u64) -> Vec<u8> {
+    [&[prefix][..], &number.to_be_bytes()].concat()
+}
+
+// the key of `value` under one of the nonces it is held with, the nonce coming first so
+// a scan of its prefix finds every member held with it; the deterministic nonce is shared
+fn nonce_key(nonce: &[u8], value: &[u8]) -> Vec<u8> {
+    [&(nonce.len() as u32).to_be_bytes()[..], nonce, value].concat()
+}
+
+fn io_error(e: rocksdb::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+fn corrupt(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("corrupt {} in the RocksDB store", what))
+}
+
+fn column_family<'a>(db: &'a DB, name: &str) -> &'a ColumnFamily {
+    // every store is opened with all of them
+    db.cf_handle(name).expect("missing column family")
+}
+
+// the number of the first or last metadata key under `prefix`
+fn end_number(db: &DB, prefix: u8, last: bool) -> io::Result<Option<u64>> {
+    let after: [u8; 1] = [prefix + 1];
+    let first: [u8; 1] = [prefix];
+    let mode: IteratorMode = if last { IteratorMode::From(&after, Direction::Reverse) } else { IteratorMode::From(&first, Direction::Forward) };
+    match db.iterator_cf(column_family(db, METADATA), mode).next() {
+        Some(entry) => {
+            let (key, _) = entry.map_err(io_error)?;
+            if key.first() != Some(&prefix) {
+                return Ok(None);
+            }
+            let number: [u8; 8] = key[1..].try_into().map_err(|_| corrupt("key"))?;
+            Ok(Some(u64::from_be_bytes(number)))
+        }
+        None => Ok(None),
+    }
+}
+
+// every member and its prime, read into memory for as long as the accumulator borrows
+// them to compute witnesses or states from
+#[derive(Debug)]
+struct Members {
+    members: HashMap<Vec<u8>, Vec<u8>>,
+    primes: PrimeCache,
+}
+
+/// A store kept in a RocksDB database, for accumulators with more members than fit in
+/// memory.
+///
+/// Adds, deletes and lookups of single members go to the database, so they hold nothing
+/// but the changes since the last write in memory. The members are indexed by prime and
+/// nonce as well, so an add finds out whether its prime or nonce is taken there rather
+/// than the accumulator reading every member in to index them. Whatever changed is
+/// written together with the state, the epoch it closed and its audit records in one
+/// atomic batch on every [`set_state`](Storer::set_state), and a write that fails inside
+/// a transaction fails its commit with [`AccumulatorError::Io`]. Reads the [`Storer`]
+/// methods cannot report, such as that of a nonce, panic when they fail.
+///
+/// Computing a witness or the state from scratch needs the prime of every member, so the
+/// first [`iter_members`](Storer::iter_members) reads all of them in, along with the
+/// primes written next to them, and keeps them up to date until
+/// [`release_members`](RocksStore::release_members) lets them go again.
+///
+/// The trapdoor is never written to the database.
+#[derive(Debug)]
+pub struct RocksStore {
+    db: DB,
+    params: PublicParameters,
+    state: BigUint,
+    // kept next to the state, so opening the store does not count the members
+    count: usize,
+    // the state and count as of the last write, for a failed one to go back to
+    written_state: BigUint,
+    written_count: usize,
+    // every value changed since the last write, with its nonce now, None once removed
+    unwritten: HashMap<Vec<u8>, Option<Vec<u8>>>,
+    // the primes offered for the members added since the last write, and the values they
+    // and the nonces of those members were last given to, so they are looked up without
+    // going through every change
+    primes: PrimeCache,
+    offered: HashMap<BigUint, Vec<u8>>,
+    drawn: HashMap<Vec<u8>, Vec<u8>>,
+    loaded: OnceLock<Members>,
+    trapdoor: Option<Trapdoor>,
+    in_transaction: bool,
+    epochs: u64,
+    // the oldest epoch written, later than 1 once the store was compacted
+    first_epoch: u64,
+    pending_epochs: Vec<Epoch>,
+    log_len: u64,
+    pending_log: Vec<AuditRecord>,
+}
+
+impl RocksStore {
+    /// Opens the store at `path`, creating it over `params` at the genesis state if it
+    /// does not exist yet. An existing store must have been created over `params`.
+    pub fn open<P: AsRef<Path>>(path: P, params: &PublicParameters) -> io::Result<Self> {
+        let mut options: Options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        let db: DB = DB::open_cf(&options, path, COLUMN_FAMILIES.iter()).map_err(io_error)?;
+        let metadata: &ColumnFamily = column_family(&db, METADATA);
+        if db.get_cf(metadata, PARAMS_KEY).map_err(io_error)?.is_none() {
+            let mut batch: WriteBatch = WriteBatch::default();
+            batch.put_cf(metadata, PARAMS_KEY, params.to_bytes());
+            batch.put_cf(metadata, STATE_KEY, params.genesis_state().to_bytes_be());
+            batch.put_cf(metadata, COUNT_KEY, 0_u64.to_be_bytes());
+            db.write(batch).map_err(io_error)?;
+            db.flush().map_err(io_error)?;
+        }
+        let store: RocksStore = RocksStore::load(db)?;
+        if store.params != *params {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "store was created with different public parameters"));
+        }
+        Ok(store)
+    }
+    /// Opens an existing store at `path` with whatever parameters it was created over.
+    pub fn open_existing<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let db: DB = DB::open_cf(&Options::default(), path, COLUMN_FAMILIES.iter()).map_err(io_error)?;
+        RocksStore::load(db)
+    }
+    fn load(db: DB) -> io::Result<Self> {
+        let missing = || io::Error::new(io::ErrorKind::NotFound, "not a tangerine store");
+        let metadata: &ColumnFamily = column_family(&db, METADATA);
+        let params: PublicParameters = PublicParameters::from_bytes(&db.get_cf(metadata, PARAMS_KEY).map_err(io_error)?.ok_or_else(missing)?)?;
+        let state: BigUint = BigUint::from_bytes_be(&db.get_cf(metadata, STATE_KEY).map_err(io_error)?.ok_or_else(missing)?);
+        let count: [u8; 8] = db.get_cf(metadata, COUNT_KEY).map_err(io_error)?.ok_or_else(missing)?.as_slice().try_into().map_err(|_| corrupt("member count"))?;
+        let epochs: u64 = end_number(&db, EPOCH_PREFIX, true)?.unwrap_or(0);
+        let first_epoch: u64 = end_number(&db, EPOCH_PREFIX, false)?.unwrap_or(1);
+        let log_len: u64 = end_number(&db, LOG_PREFIX, true)?.unwrap_or(0);
+        let count: usize = u64::from_be_bytes(count) as usize;
+        Ok(RocksStore {
+            db,
+            params,
+            written_state: state.clone(),
+            state,
+            count,
+            written_count: count,
+            unwritten: HashMap::new(),
+            primes: PrimeCache::default(),
+            offered: HashMap::new(),
+            drawn: HashMap::new(),
+            loaded: OnceLock::new(),
+            trapdoor: None,
+            in_transaction: false,
+            epochs,
+            first_epoch,
+            pending_epochs: Vec::new(),
+            log_len,
+            pending_log: Vec::new(),
+        })
+    }
+    pub fn with_trapdoor(mut self, trapdoor: Trapdoor) -> Self {
+        self.trapdoor = Some(trapdoor);
+        self
+    }
+    /// Blocks until everything written so far is on disk.
+    pub fn flush(&self) -> io::Result<()> {
+        self.db.flush().map_err(io_error)
+    }
+    /// Lets go of the members [`iter_members`](Storer::iter_members) read into memory, until
+    /// they are iterated over again.
+    pub fn release_members(&mut self) {
+        self.loaded.take();
+    }
+    fn cf(&self, name: &str) -> &ColumnFamily {
+        column_family(&self.db, name)
+    }
+    fn members(&self) -> &Members {
+        self.loaded.get_or_init(|| self.read_members().expect("failed to read the members from the RocksDB store"))
+    }
+    fn read_members(&self) -> io::Result<Members> {
+        let mut members: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+        for entry in self.db.iterator_cf(self.cf(MEMBERS), IteratorMode::Start) {
+            let (value, nonce) = entry.map_err(io_error)?;
+            members.insert(value.into_vec(), nonce.into_vec());
+        }
+        let mut primes: PrimeCache = PrimeCache::default();
+        for entry in self.db.iterator_cf(self.cf(PRIMES), IteratorMode::Start) {
+            let (value, prime) = entry.map_err(io_error)?;
+            // the prime written for a value changed since may be that of its old nonce
+            if self.unwritten.contains_key(&*value) {
+                continue;
+            }
+            if let Some(nonce) = members.get(&*value) {
+                primes.insert(&value, nonce, &BigUint::from_bytes_be(&prime));
+            }
+        }
+        for (value, nonce) in self.unwritten.iter() {
+            match nonce {
+                Some(nonce) => {
+                    if let Some(prime) = self.primes.get(value, nonce) {
+                        primes.insert(value, nonce, prime);
+                    }
+                    members.insert(value.clone(), nonce.clone());
+                }
+                None => {
+                    members.remove(value);
+                }
+            }
+        }
+        Ok(Members { members, primes })
+    }
+    fn written_nonce(&self, value: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        self.db.get_cf(self.cf(MEMBERS), value).map_err(io_error)
+    }
+    // whether `value`, found in an index as of the last write, still holds the nonce it
+    // was written with
+    fn still_written(&self, value: &[u8]) -> io::Result<bool> {
+        match self.unwritten.get(value) {
+            None => Ok(true),
+            Some(None) => Ok(false),
+            Some(Some(nonce)) => Ok(self.written_nonce(value)?.as_ref() == Some(nonce)),
+        }
+    }
+    // writes whatever changed since the last write in one batch along with `new_state`
+    fn write(&mut self, new_state: &BigUint) -> io::Result<()> {
+        let (members, primes, metadata): (&ColumnFamily, &ColumnFamily, &ColumnFamily) = (self.cf(MEMBERS), self.cf(PRIMES), self.cf(METADATA));
+        let (by_prime, by_nonce): (&ColumnFamily, &ColumnFamily) = (self.cf(BY_PRIME), self.cf(BY_NONCE));
+        let mut batch: WriteBatch = WriteBatch::default();
+        for (value, nonce) in self.unwritten.iter() {
+            let written: Option<Vec<u8>> = self.written_nonce(value)?;
+            let offered: Option<&BigUint> = nonce.as_ref().and_then(|nonce| self.primes.get(value, nonce));
+            // a member put back with the nonce it was written with keeps what was written
+            if written == *nonce && offered.is_none() {
+                continue;
+            }
+            if let Some(written) = written.as_ref() {
+                for occurrence in occurrences(written) {
+                    batch.delete_cf(by_nonce, nonce_key(occurrence, value));
+                }
+                if let Some(prime) = self.db.get_cf(primes, value).map_err(io_error)? {
+                    batch.delete_cf(by_prime, prime);
+                }
+            }
+            batch.delete_cf(primes, value);
+            match nonce {
+                Some(nonce) => {
+                    batch.put_cf(members, value, nonce);
+                    for occurrence in occurrences(nonce) {
+                        batch.put_cf(by_nonce, nonce_key(occurrence, value), b"");
+                    }
+                    if let Some(prime) = offered {
+                        batch.put_cf(primes, value, prime.to_bytes_be());
+                        batch.put_cf(by_prime, prime.to_bytes_be(), value);
+                    }
+                }
+                None => batch.delete_cf(members, value),
+            }
+        }
+        for (number, epoch) in (self.epochs + 1..).zip(self.pending_epochs.iter()) {
+            batch.put_cf(metadata, numbered_key(EPOCH_PREFIX, number), encode_epoch(epoch));
+        }
+        for (number, record) in (self.log_len + 1..).zip(self.pending_log.iter()) {
+            batch.put_cf(metadata, numbered_key(LOG_PREFIX, number), encode_record(record));
+        }
+        batch.put_cf(metadata, COUNT_KEY, (self.count as u64).to_be_bytes());
+        batch.put_cf(metadata, STATE_KEY, new_state.to_bytes_be());
+        self.db.write(batch).map_err(io_error)?;
+        self.epochs += self.pending_epochs.len() as u64;
+        self.pending_epochs.clear();
+        self.log_len += self.pending_log.len() as u64;
+        self.pending_log.clear();
+        self.forget_changes();
+        if let Some(loaded) = self.loaded.get_mut() {
+            loaded.primes.sweep(&loaded.members);
+        }
+        self.state = new_state.clone();
+        self.written_state = new_state.clone();
+        self.written_count = self.count;
+        Ok(())
+    }
+    fn forget_changes(&mut self) {
+        self.unwritten.clear();
+        self.primes = PrimeCache::default();
+        self.offered.clear();
+        self.drawn.clear();
+    }
+    // puts the store back at its view as of the last write
+    fn revert(&mut self) {
+        self.forget_changes();
+        self.pending_epochs.clear();
+        self.pending_log.clear();
+        self.state = self.written_state.clone();
+        self.count = self.written_count;
+        // the members read in took the changes that were dropped
+        self.loaded.take();
+    }
+}
+
+impl Storer for RocksStore {
+    fn get_generator(&self) -> &BigUint {
+        &self.params.generator
+    }
+    fn get_modulus(&self) -> &BigUint {
+        &self.params.modulus
+    }
+    fn get_state(&self) -> &BigUint {
+        &self.state
+    }
+    fn set_state(&mut self, new_state: &BigUint) {
+        if self.in_transaction {
+            self.state = new_state.clone();
+            return;
+        }
+        self.write(new_state).expect("failed to write to the RocksDB store");
+    }
+    fn get_trapdoor(&self) -> Option<Trapdoor> {
+        self.trapdoor.clone()
+    }
+    fn get_nonce(&self, value: &[u8]) -> Option<Vec<u8>> {
+        if let Some(nonce) = self.unwritten.get(value) {
+            return nonce.clone();
+        }
+        if let Some(loaded) = self.loaded.get() {
+            return loaded.members.get(value).cloned();
+        }
+        self.db.get_cf(self.cf(MEMBERS), value).expect("failed to read from the RocksDB store")
+    }
+    fn insert_member(&mut self, value: &[u8], nonce: &[u8]) {
+        if self.get_nonce(value).is_none() {
+            self.count += 1;
+        }
+        for occurrence in occurrences(nonce) {
+            self.drawn.insert(occurrence.to_vec(), value.to_vec());
+        }
+        self.unwritten.insert(value.to_vec(), Some(nonce.to_vec()));
+        if let Some(loaded) = self.loaded.get_mut() {
+            loaded.members.insert(value.to_vec(), nonce.to_vec());
+        }
+    }
+    fn remove_member(&mut self, value: &[u8]) -> Option<Vec<u8>> {
+        let nonce: Vec<u8> = self.get_nonce(value)?;
+        self.count -= 1;
+        self.unwritten.insert(value.to_vec(), None);
+        if let Some(loaded) = self.loaded.get_mut() {
+            loaded.members.remove(value);
+        }
+        Some(nonce)
+    }
+    fn iter_members(&self) -> impl Iterator<Item = (&[u8], &[u8])> + '_ {
+        self.members().members.iter().map(|(value, nonce)| (value.as_slice(), nonce.as_slice()))
+    }
+    fn member_count(&self) -> usize {
+        self.count
+    }
+    fn looks_up_members(&self) -> bool {
+        true
+    }
+    fn holds_prime(&self, prime: &BigUint) -> io::Result<bool> {
+        // the members changed since the last write are looked up among the changes
+        if let Some(value) = self.offered.get(prime) {
+            if let Some(Some(nonce)) = self.unwritten.get(value) {
+                if self.primes.get(value, nonce) == Some(prime) {
+                    return Ok(true);
+                }
+            }
+        }
+        match self.db.get_cf(self.cf(BY_PRIME), prime.to_bytes_be()).map_err(io_error)? {
+            Some(value) => self.still_written(&value),
+            None => Ok(false),
+        }
+    }
+    fn holds_nonce(&self, nonce: &[u8]) -> io::Result<bool> {
+        if let Some(value) = self.drawn.get(nonce) {
+            if let Some(Some(held)) = self.unwritten.get(value) {
+                if occurrences(held).contains(&nonce) {
+                    return Ok(true);
+                }
+            }
+        }
+        let prefix: Vec<u8> = nonce_key(nonce, &[]);
+        for entry in self.db.iterator_cf(self.cf(BY_NONCE), IteratorMode::From(&prefix, Direction::Forward)) {
+            let (key, _) = entry.map_err(io_error)?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            if self.still_written(&key[prefix.len()..])? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+    fn begin_transaction(&mut self) {
+        self.in_transaction = true;
+    }
+    fn commit_transaction(&mut self) -> Result<(), AccumulatorError> {
+        self.in_transaction = false;
+        let state: BigUint = self.state.clone();
+        self.write(&state).map_err(|e| {
+            self.revert();
+            AccumulatorError::Io(e.to_string())
+        })
+    }
+    fn get_hash_function(&self) -> HashFunction {
+        self.params.hash_function
+    }
+    fn get_prime_bits(&self) -> Option<u64> {
+        self.params.prime_bits
+    }
+    // the primes written are only at hand once the members were read in, until then a
+    // witness searches for those it needs
+    fn get_prime(&self, value: &[u8], nonce: &[u8]) -> Option<&BigUint> {
+        self.primes.get(value, nonce).or_else(|| self.loaded.get().and_then(|loaded| loaded.primes.get(value, nonce)))
+    }
+    fn set_prime(&mut self, value: &[u8], nonce: &[u8], prime: &BigUint) {
+        self.primes.insert(value, nonce, prime);
+        self.offered.insert(prime.clone(), value.to_vec());
+        if let Some(loaded) = self.loaded.get_mut() {
+            loaded.primes.insert(value, nonce, prime);
+        }
+    }
+    fn get_prime_mapping(&self) -> PrimeMapping {
+        self.params.prime_mapping()
+    }
+    fn push_epoch(&mut self, epoch: &Epoch) {
+        self.pending_epochs.push(epoch.clone());
+    }
+    fn epoch_count(&self) -> u64 {
+        self.epochs + self.pending_epochs.len() as u64
+    }
+    fn get_epoch(&self, epoch: u64) -> io::Result<Option<Epoch>> {
+        if epoch > self.epochs {
+            return Ok(self.pending_epochs.get((epoch - self.epochs - 1) as usize).cloned());
+        }
+        match self.db.get_cf(self.cf(METADATA), numbered_key(EPOCH_PREFIX, epoch)).map_err(io_error)? {
+            Some(bytes) => decode_epoch(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+    fn first_epoch(&self) -> u64 {
+        self.first_epoch
+    }
+    // the primes written for values no longer members, which a write that went astray
+    // can leave behind; those read into memory are dropped along with them
+    fn collect_primes(&mut self, dry_run: bool) -> io::Result<u64> {
+        let mut batch: WriteBatch = WriteBatch::default();
+        let mut found: u64 = 0;
+        for entry in self.db.iterator_cf(self.cf(PRIMES), IteratorMode::Start) {
+            let (value, prime) = entry.map_err(io_error)?;
+            if self.get_nonce(&value).is_none() {
+                batch.delete_cf(self.cf(PRIMES), &value);
+                batch.delete_cf(self.cf(BY_PRIME), &prime);
+                found += 1;
+            }
+        }
+        if !dry_run {
+            self.db.write(batch).map_err(io_error)?;
+            if let Some(loaded) = self.loaded.get_mut() {
+                loaded.primes.collect(&loaded.members, false);
+            }
+        }
+        Ok(found)
+    }
+    fn compact_epochs(&mut self, first: u64, base: &Epoch) -> io::Result<()> {
+        let metadata: &ColumnFamily = self.cf(METADATA);
+        let mut batch: WriteBatch = WriteBatch::default();
+        batch.delete_range_cf(metadata, numbered_key(EPOCH_PREFIX, self.first_epoch), numbered_key(EPOCH_PREFIX, first));
+        batch.put_cf(metadata, numbered_key(EPOCH_PREFIX, first), encode_epoch(base));
+        self.db.write(batch).map_err(io_error)?;
+        self.first_epoch = first;
+        Ok(())
+    }
+    fn append_log(&mut self, record: &AuditRecord) {
+        self.pending_log.push(record.clone());
+    }
+    fn iter_log(&self) -> impl Iterator<Item = io::Result<AuditRecord>> + '_ {
+        self.db
+            .iterator_cf(self.cf(METADATA), IteratorMode::From(&[LOG_PREFIX], Direction::Forward))
+            .take_while(|entry| entry.as_ref().map_or(true, |(key, _)| key.first() == Some(&LOG_PREFIX)))
+            .map(|entry| decode_record(&entry.map_err(io_error)?.1))
+            .chain(self.pending_log.iter().cloned().map(Ok))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use crate::params::{DuplicatePolicy, NonceMode};
+    use crate::proof::MembershipProof;
+    use crate::store::{AuditAction, Retention};
+    use crate::SetAccumulator;
+
+    #[test]
+    fn test_survives_reopen() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let dir: PathBuf = std::env::temp_dir().join(format!("tangerine-rocks-{}-{}", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let state: BigUint;
+        {
+            let mut sa: SetAccumulator<RocksStore> = SetAccumulator::new(RocksStore::open(&dir, &params).unwrap());
+            sa.add(b"alice").unwrap();
+            sa.add_batch(&[b"bob", b"carol"]).unwrap();
+            sa.delete_without_trapdoor(b"bob").unwrap();
+            state = sa.store.get_state().clone();
+            sa.store.flush().unwrap();
+        }
+        let store: RocksStore = RocksStore::open_existing(&dir).unwrap();
+        assert_eq!(state, *store.get_state());
+        assert_eq!(2, store.member_count());
+        // members are looked up without reading them all in
+        assert!(store.contains_member(b"carol") && !store.contains_member(b"bob"));
+        assert!(store.loaded.get().is_none());
+        // the epochs and the audit log come back along with them
+        assert_eq!(3, store.epoch_count());
+        assert_eq!(Some(state.clone()), store.get_epoch(3).unwrap().map(|epoch| epoch.state));
+        assert_eq!(None, store.get_epoch(4).unwrap());
+        let actions: Vec<AuditAction> = store.iter_log().map(|record| record.unwrap().action).collect();
+        assert_eq!(vec![AuditAction::Add, AuditAction::Add, AuditAction::Add, AuditAction::Delete], actions);
+        let sa: SetAccumulator<RocksStore> = SetAccumulator::new(store);
+        let proof: MembershipProof = sa.prove_membership_at(b"bob", 2).unwrap();
+        assert!(sa.verify_at(2, b"bob", &proof));
+        drop(sa);
+        // a store only opens over the parameters it was created with
+        let other: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(43_u64));
+        assert_eq!(io::ErrorKind::InvalidInput, RocksStore::open(&dir, &other).unwrap_err().kind());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_witnesses_from_disk() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let dir: PathBuf = std::env::temp_dir().join(format!("tangerine-rocks-witness-{}-{}", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut sa: SetAccumulator<RocksStore> = SetAccumulator::new(RocksStore::open(&dir, &params).unwrap());
+        sa.add(b"alice").unwrap();
+        sa.add(b"bob").unwrap();
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+        assert!(sa.verify(b"alice", &witness, &nonce));
+        // the members read in for the witness are kept up to date, with the prime of bob
+        assert!(sa.store.get_prime(b"bob", &sa.store.get_nonce(b"bob").unwrap()).is_some());
+        sa.add(b"carol").unwrap();
+        sa.delete_without_trapdoor(b"bob").unwrap();
+        assert_eq!(vec![b"alice".as_ref(), b"carol"], {
+            let mut members: Vec<&[u8]> = sa.iter_members().collect();
+            members.sort();
+            members
+        });
+        sa.store.release_members();
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"carol").unwrap();
+        assert!(sa.verify(b"carol", &witness, &nonce));
+        assert_eq!(Ok(1), sa.compact(Retention::KeepLast(3)));
+        assert_eq!(None, sa.store.get_epoch(1).unwrap());
+        drop(sa);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_looks_members_up() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let dir: PathBuf = std::env::temp_dir().join(format!("tangerine-rocks-lookup-{}-{}", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        {
+            let mut sa: SetAccumulator<RocksStore> = SetAccumulator::new(RocksStore::open(&dir, &params).unwrap());
+            sa.add(b"alice").unwrap();
+            sa.add(b"bob").unwrap();
+            sa.store.flush().unwrap();
+        }
+        let store: RocksStore = RocksStore::open_existing(&dir).unwrap().with_trapdoor(Trapdoor::new(BigUint::from(61_u64), BigUint::from(53_u64)));
+        let mut sa: SetAccumulator<RocksStore> = SetAccumulator::new(store).with_duplicate_policy(DuplicatePolicy::Multiset);
+        sa.add(b"carol").unwrap();
+        sa.delete(b"bob").unwrap();
+        // neither the add nor the delete read the members in
+        assert!(sa.store.loaded.get().is_none());
+        // the prime of a member written is still found taken, in the database
+        let mut sa: SetAccumulator<RocksStore> = sa.with_nonce_mode(NonceMode::Deterministic);
+        sa.add(b"dave").unwrap();
+        let state: BigUint = sa.store.get_state().clone();
+        assert_eq!(Err(AccumulatorError::DuplicatePrime), sa.add(b"dave").map(|_| ()));
+        assert_eq!(state, *sa.store.get_state());
+        assert!(sa.store.loaded.get().is_none());
+        assert!(sa.store.holds_nonce(&sa.store.get_nonce(b"carol").unwrap()).unwrap());
+        assert!(!sa.store.holds_nonce(&[7; 32]).unwrap());
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"carol").unwrap();
+        assert!(sa.verify(b"carol", &witness, &nonce));
+        drop(sa);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io;
+use std::path::Path;
+
+use num_bigint::BigUint;
+
+use crate::error::AccumulatorError;
+use crate::manager::Backend;
+use crate::params::{HashFunction, PrimeMapping, PublicParameters, Trapdoor};
+use crate::store::{decode_epoch, decode_record, encode_epoch, encode_record, AuditRecord, Epoch, MemberChange, PrimeCache, Storer, TrackedMembers};
+
+// metadata keys start with a zero byte, member keys with a one and the keys of their
+// primes with a two, so the members and primes can be scanned by prefix
+const PARAMS_KEY: &[u8] = b"\x00params";
+const STATE_KEY: &[u8] = b"\x00state";
+const MEMBER_PREFIX: u8 = 1;
+const PRIME_PREFIX: u8 = 2;
+// the keys of a named namespace start with a three and the length-prefixed name, and are
+// laid out as above after that
+const NAMESPACE_PREFIX: u8 = 3;
+// epochs are keyed by a four and their big-endian number, so they scan in order, and
+// audit records likewise by a five and their place in the log
+const EPOCH_PREFIX: u8 = 4;
+const LOG_PREFIX: u8 = 5;
+
+fn namespace_prefix(namespace: &str) -> Vec<u8> {
+    [&[NAMESPACE_PREFIX][..], &(namespace.len() as u32).to_be_bytes(), namespace.as_bytes()].concat()
+}
+
+fn prefixed_key(namespace: &[u8], prefix: u8, value: &[u8]) -> Vec<u8> {
+    let mut key: Vec<u8> = Vec::with_capacity(namespace.len() + value.len() + 1);
+    key.extend_from_slice(namespace);
+    key.push(prefix);
+    key.extend_from_slice(value);
+    key
+}
+
+// the number of the first or last key under `prefix`, for keys that end in a big-endian u64
+fn end_number(db: &sled::Db, namespace: &[u8], prefix: u8, last: bool) -> io::Result<Option<u64>> {
+    let mut keys = db.scan_prefix(prefixed_key(namespace, prefix, &[])).keys();
+    match if last { keys.next_back() } else { keys.next() } {
+        Some(key) => {
+            let key: sled::IVec = key?;
+            let number: [u8; 8] = key[key.len().saturating_sub(8)..].try_into().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "corrupt sled store key"))?;
+            Ok(Some(u64::from_be_bytes(number)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// A store kept in a sled database, so the accumulator survives restarts.
+///
+/// The members are also held in memory, so lookups never reach the database. Whatever
+/// changed since the last write is written together with the state in one atomic batch
+/// on every [`set_state`](Storer::set_state), which the accumulator calls after each
+/// change to the members. Writes reach the disk when sled next flushes, or on [`SledStore::flush`].
+/// The prime each member maps to is written next to it, so witnesses never search for it,
+/// and the epoch a batch closed and its audit records go into the same write as its state.
+///
+/// The trapdoor is never written to the database.
+#[derive(Debug)]
+pub struct SledStore {
+    db: sled::Db,
+    // the start of every key of this store, empty outside of a namespace
+    namespace: Vec<u8>,
+    params: PublicParameters,
+    state: BigUint,
+    // the state as of the last write, for a failed one to go back to
+    written_state: BigUint,
+    members: TrackedMembers,
+    primes: PrimeCache,
+    trapdoor: Option<Trapdoor>,
+    in_transaction: bool,
+    epochs: u64,
+    // the oldest epoch written, later than 1 once the store was compacted
+    first_epoch: u64,
+    pending_epochs: Vec<Epoch>,
+    log_len: u64,
+    pending_log: Vec<AuditRecord>,
+}
+
+impl SledStore {
+    /// Opens the store at `path`, creating it over `params` at the genesis state if it
+    /// does not exist yet. An existing store must have been created over `params`.
+    pub fn open<P: AsRef<Path>>(path: P, params: &PublicParameters) -> io::Result<Self> {
+        SledStore::open_in(sled::open(path)?, Vec::new(), params)
+    }
+    /// Opens an existing store at `path` with whatever parameters it was created over.
+    pub fn open_existing<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        SledStore::load(sled::open(path)?, Vec::new())
+    }
+    fn open_in(db: sled::Db, namespace: Vec<u8>, params: &PublicParameters) -> io::Result<Self> {
+        let params_key: Vec<u8> = [&namespace[..], PARAMS_KEY].concat();
+        if db.get(&params_key)?.is_none() {
+            let mut batch: sled::Batch = sled::Batch::default();
+            batch.insert(params_key, params.to_bytes());
+            batch.insert([&namespace[..], STATE_KEY].concat(), params.genesis_state().to_bytes_be());
+            db.apply_batch(batch)?;
+            db.flush()?;
+        }
+        let store: SledStore = SledStore::load(db, namespace)?;
+        if store.params != *params {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "store was created with different public parameters"));
+        }
+        Ok(store)
+    }
+    fn load(db: sled::Db, namespace: Vec<u8>) -> io::Result<Self> {
+        let missing = || io::Error::new(io::ErrorKind::NotFound, "not a tangerine store");
+        let params: PublicParameters = PublicParameters::from_bytes(&db.get([&namespace[..], PARAMS_KEY].concat())?.ok_or_else(missing)?)?;
+        let state: BigUint = BigUint::from_bytes_be(&db.get([&namespace[..], STATE_KEY].concat())?.ok_or_else(missing)?);
+        let skip: usize = namespace.len() + 1;
+        let mut members: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+        for entry in db.scan_prefix(prefixed_key(&namespace, MEMBER_PREFIX, &[])) {
+            let (key, nonce) = entry?;
+            members.insert(key[skip..].to_vec(), nonce.to_vec());
+        }
+        let mut primes: PrimeCache = PrimeCache::default();
+        for entry in db.scan_prefix(prefixed_key(&namespace, PRIME_PREFIX, &[])) {
+            let (key, prime) = entry?;
+            if let Some(nonce) = members.get(&key[skip..]) {
+                primes.insert(&key[skip..], nonce, &BigUint::from_bytes_be(&prime));
+            }
+        }
+        let epochs: u64 = end_number(&db, &namespace, EPOCH_PREFIX, true)?.unwrap_or(0);
+        let first_epoch: u64 = end_number(&db, &namespace, EPOCH_PREFIX, false)?.unwrap_or(1);
+        let log_len: u64 = end_number(&db, &namespace, LOG_PREFIX, true)?.unwrap_or(0);
+        Ok(SledStore {
+            db,
+            namespace,
+            params,
+            written_state: state.clone(),
+            state,
+            members: TrackedMembers::new(members),
+            primes,
+            trapdoor: None,
+            in_transaction: false,
+            epochs,
+            first_epoch,
+            pending_epochs: Vec::new(),
+            log_len,
+            pending_log: Vec::new(),
+        })
+    }
+    pub fn with_trapdoor(mut self, trapdoor: Trapdoor) -> Self {
+        self.trapdoor = Some(trapdoor);
+        self
+    }
+    /// Blocks until everything written so far is on disk.
+    pub fn flush(&self) -> io::Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+    // writes whatever changed since the last write in one batch along with `new_state`
+    fn write(&mut self, new_state: &BigUint) -> io::Result<()> {
+        let changes: Vec<MemberChange> = self.members.changes();
+        let mut batch: sled::Batch = sled::Batch::default();
+        for (value, nonce) in changes.iter() {
+            batch.remove(prefixed_key(&self.namespace, PRIME_PREFIX, value));
+            match nonce {
+                Some(nonce) => {
+                    batch.insert(prefixed_key(&self.namespace, MEMBER_PREFIX, value), nonce.as_slice());
+                    if let Some(prime) = self.primes.get(value, nonce) {
+                        batch.insert(prefixed_key(&self.namespace, PRIME_PREFIX, value), prime.to_bytes_be());
+                    }
+                }
+                None => batch.remove(prefixed_key(&self.namespace, MEMBER_PREFIX, value)),
+            }
+        }
+        for (number, epoch) in (self.epochs + 1..).zip(self.pending_epochs.iter()) {
+            batch.insert(prefixed_key(&self.namespace, EPOCH_PREFIX, &number.to_be_bytes()), encode_epoch(epoch));
+        }
+        for (number, record) in (self.log_len + 1..).zip(self.pending_log.iter()) {
+            batch.insert(prefixed_key(&self.namespace, LOG_PREFIX, &number.to_be_bytes()), encode_record(record));
+        }
+        batch.insert([&self.namespace[..], STATE_KEY].concat(), new_state.to_bytes_be());
+        self.db.apply_batch(batch)?;
+        self.epochs += self.pending_epochs.len() as u64;
+        self.pending_epochs.clear();
+        self.log_len += self.pending_log.len() as u64;
+        self.pending_log.clear();
+        self.members.mark_written();
+        self.primes.sweep(self.members.map());
+        self.state = new_state.clone();
+        self.written_state = new_state.clone();
+        Ok(())
+    }
+    // puts the store back at its view as of the last write
+    fn revert(&mut self) {
+        self.members.revert();
+        self.primes.sweep(self.members.map());
+        self.pending_epochs.clear();
+        self.pending_log.clear();
+        self.state = self.written_state.clone();
+    }
+}
+
+impl Storer for SledStore {
+    fn get_generator(&self) -> &BigUint {
+        &self.params.generator
+    }
+    fn get_modulus(&self) -> &BigUint {
+        &self.params.modulus
+    }
+    fn get_state(&self) -> &BigUint {
+        &self.state
+    }
+    fn set_state(&mut self, new_state: &BigUint) {
+        if self.in_transaction {
+            self.state = new_state.clone();
+            return;
+        }
+        self.write(new_state).expect("failed to write to the sled store");
+    }
+    fn get_trapdoor(&self) -> Option<Trapdoor> {
+        self.trapdoor.clone()
+    }
+    fn get_nonce(&self, value: &[u8]) -> Option<Vec<u8>> {
+        self.members.get(value).cloned()
+    }
+    fn insert_member(&mut self, value: &[u8], nonce: &[u8]) {
+        self.members.insert(value, nonce);
+    }
+    fn remove_member(&mut self, value: &[u8]) -> Option<Vec<u8>> {
+        self.members.remove(value)
+    }
+    fn iter_members(&self) -> impl Iterator<Item = (&[u8], &[u8])> + '_ {
+        self.members.map().iter().map(|(value, nonce)| (value.as_slice(), nonce.as_slice()))
+    }
+    fn contains_member(&self, value: &[u8]) -> bool {
+        self.members.get(value).is_some()
+    }
+    fn member_count(&self) -> usize {
+        self.members.map().len()
+    }
+    fn begin_transaction(&mut self) {
+        self.in_transaction = true;
+    }
+    fn commit_transaction(&mut self) -> Result<(), AccumulatorError> {
+        self.in_transaction = false;
+        let state: BigUint = self.state.clone();
+        self.write(&state).map_err(|e| {
+            self.revert();
+            AccumulatorError::Io(e.to_string())
+        })
+    }
+    fn get_hash_function(&self) -> HashFunction {
+        self.params.hash_function
+    }
+    fn get_prime_bits(&self) -> Option<u64> {
+        self.params.prime_bits
+    }
+    fn get_prime(&self, value: &[u8], nonce: &[u8]) -> Option<&BigUint> {
+        self.primes.get(value, nonce)
+    }
+    fn set_prime(&mut self, value: &[u8], nonce: &[u8], prime: &BigUint) {
+        self.primes.insert(value, nonce, prime);
+    }
+    fn get_prime_mapping(&self) -> PrimeMapping {
+        self.params.prime_mapping()
+    }
+    fn push_epoch(&mut self, epoch: &Epoch) {
+        self.pending_epochs.push(epoch.clone());
+    }
+    fn epoch_count(&self) -> u64 {
+        self.epochs + self.pending_epochs.len() as u64
+    }
+    fn get_epoch(&self, epoch: u64) -> io::Result<Option<Epoch>> {
+        if epoch > self.epochs {
+            return Ok(self.pending_epochs.get((epoch - self.epochs - 1) as usize).cloned());
+        }
+        match self.db.get(prefixed_key(&self.namespace, EPOCH_PREFIX, &epoch.to_be_bytes()))? {
+            Some(bytes) => decode_epoch(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+    fn first_epoch(&self) -> u64 {
+        self.first_epoch
+    }
+    // the primes written for values no longer members, which a write that went astray
+    // can leave behind; those kept in memory only are dropped along with them
+    fn collect_primes(&mut self, dry_run: bool) -> io::Result<u64> {
+        let skip: usize = self.namespace.len() + 1;
+        let mut batch: sled::Batch = sled::Batch::default();
+        let mut found: u64 = 0;
+        for key in self.db.scan_prefix(prefixed_key(&self.namespace, PRIME_PREFIX, &[])).keys() {
+            let key: sled::IVec = key?;
+            if self.members.get(&key[skip..]).is_none() {
+                batch.remove(key);
+                found += 1;
+            }
+        }
+        if !dry_run {
+            self.db.apply_batch(batch)?;
+            self.primes.collect(self.members.map(), false);
+        }
+        Ok(found)
+    }
+    fn compact_epochs(&mut self, first: u64, base: &Epoch) -> io::Result<()> {
+        let mut batch: sled::Batch = sled::Batch::default();
+        for number in self.first_epoch..first {
+            batch.remove(prefixed_key(&self.namespace, EPOCH_PREFIX, &number.to_be_bytes()));
+        }
+        batch.insert(prefixed_key(&self.namespace, EPOCH_PREFIX, &first.to_be_bytes()), encode_epoch(base));
+        self.db.apply_batch(batch)?;
+        self.first_epoch = first;
+        Ok(())
+    }
+    fn append_log(&mut self, record: &AuditRecord) {
+        self.pending_log.push(record.clone());
+    }
+    fn iter_log(&self) -> impl Iterator<Item = io::Result<AuditRecord>> + '_ {
+        self.db
+            .scan_prefix(prefixed_key(&self.namespace, LOG_PREFIX, &[]))
+            .values()
+            .map(|bytes| decode_record(&bytes?))
+            .chain(self.pending_log.iter().cloned().map(Ok))
+    }
+}
+
+/// Named stores side by side in one sled database, each under its own key prefix. The
+/// store [`SledStore::open`] opens at the same path is apart from all of them.
+#[derive(Debug, Clone)]
+pub struct SledBackend {
+    db: sled::Db,
+}
+
+impl SledBackend {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(SledBackend { db: sled::open(path)? })
+    }
+}
+
+impl Backend for SledBackend {
+    type Store = SledStore;
+
+    fn open(&mut self, namespace: &str, params: &PublicParameters) -> io::Result<SledStore> {
+        SledStore::open_in(self.db.clone(), namespace_prefix(namespace), params)
+    }
+    fn namespaces(&self) -> io::Result<Vec<String>> {
+        let mut namespaces: Vec<String> = Vec::new();
+        // every namespace has its parameters under its prefix, so look for those
+        for entry in self.db.scan_prefix([NAMESPACE_PREFIX]) {
+            let (key, _) = entry?;
+            if key.len() < 5 {
+                continue;
+            }
+            let len: usize = u32::from_be_bytes([key[1], key[2], key[3], key[4]]) as usize;
+            if key.len() == 5 + len + PARAMS_KEY.len() && key.ends_with(PARAMS_KEY) {
+                let name: String = String::from_utf8(key[5..5 + len].to_vec())
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "namespace is not UTF-8"))?;
+                namespaces.push(name);
+            }
+        }
+        // the length prefix orders the keys by length first
+        namespaces.sort();
+        Ok(namespaces)
+    }
+    fn remove(&mut self, namespace: &str) -> io::Result<bool> {
+        let mut batch: sled::Batch = sled::Batch::default();
+        let mut found: bool = false;
+        for entry in self.db.scan_prefix(namespace_prefix(namespace)) {
+            batch.remove(entry?.0);
+            found = true;
+        }
+        self.db.apply_batch(batch)?;
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use crate::proof::MembershipProof;
+    use crate::store::{AuditAction, Retention};
+    use crate::witness::WitnessUpdater;
+    use crate::SetAccumulator;
+
+    // sled gives up its file lock from a background thread after the last handle is
+    // dropped, so reopening straight away in the same process can briefly fail
+    fn reopen<F: Fn() -> io::Result<SledStore>>(open: F) -> io::Result<SledStore> {
+        for _ in 0..50 {
+            match open() {
+                Err(e) if e.kind() == io::ErrorKind::Other => std::thread::sleep(std::time::Duration::from_millis(20)),
+                result => return result,
+            }
+        }
+        open()
+    }
+
+    #[test]
+    fn test_survives_reopen() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let dir: PathBuf = std::env::temp_dir().join(format!("tangerine-sled-{}-{}", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let state: BigUint;
+        {
+            let mut sa: SetAccumulator<SledStore> = SetAccumulator::new(SledStore::open(&dir, &params).unwrap());
+            sa.add(b"alice").unwrap();
+            sa.add_batch(&[b"bob", b"carol"]).unwrap();
+            sa.delete_without_trapdoor(b"bob").unwrap();
+            state = sa.store.get_state().clone();
+            sa.store.flush().unwrap();
+        }
+        let store: SledStore = reopen(|| SledStore::open_existing(&dir)).unwrap();
+        assert_eq!(state, *store.get_state());
+        assert_eq!(2, store.member_count());
+        assert!(store.contains_member(b"carol"));
+        // along with the primes of the members
+        let nonce: Vec<u8> = store.get_nonce(b"carol").unwrap();
+        assert!(store.get_prime(b"carol", &nonce).is_some());
+        // and the epochs, so the history can still be proven against
+        assert_eq!(3, store.epoch_count());
+        assert_eq!(Some(state.clone()), store.get_epoch(3).unwrap().map(|epoch| epoch.state));
+        assert_eq!(None, store.get_epoch(4).unwrap());
+        // and the audit log
+        let actions: Vec<AuditAction> = store.iter_log().map(|record| record.unwrap().action).collect();
+        assert_eq!(vec![AuditAction::Add, AuditAction::Add, AuditAction::Add, AuditAction::Delete], actions);
+        assert_eq!(state, store.iter_log().last().unwrap().unwrap().new_state);
+        let sa: SetAccumulator<SledStore> = SetAccumulator::new(store);
+        let proof: MembershipProof = sa.prove_membership_at(b"bob", 2).unwrap();
+        assert!(sa.verify_at(2, b"bob", &proof));
+        // which is enough to bring an old proof up to date
+        let old: MembershipProof = sa.prove_membership_at(b"carol", 2).unwrap();
+        let fresh: MembershipProof = WitnessUpdater::new(&params).refresh(b"carol", &old, 2, sa.store.iter_log().map(Result::unwrap)).unwrap();
+        assert!(fresh.verify(&state, &params.modulus, b"carol", params.prime_mapping()));
+        let store: SledStore = sa.store;
+        assert_eq!(None, store.get_prime(b"bob", &nonce));
+        drop(store);
+        // a store only opens over the parameters it was created with
+        let other: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(43_u64));
+        assert_eq!(io::ErrorKind::InvalidInput, reopen(|| SledStore::open(&dir, &other)).unwrap_err().kind());
+        let sa: SetAccumulator<SledStore> = SetAccumulator::new(reopen(|| SledStore::open(&dir, &params)).unwrap());
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+        assert!(sa.verify(b"alice", &witness, &nonce));
+        drop(sa);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compact() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let dir: PathBuf = std::env::temp_dir().join(format!("tangerine-sled-compact-{}-{}", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        {
+            let mut sa: SetAccumulator<SledStore> = SetAccumulator::new(SledStore::open(&dir, &params).unwrap());
+            sa.add(b"alice").unwrap();
+            sa.add(b"bob").unwrap();
+            sa.add(b"carol").unwrap();
+            assert_eq!(Ok(2), sa.compact(Retention::KeepLast(1)));
+            sa.store.flush().unwrap();
+        }
+        // the dropped epochs stay dropped, and the one in their place holds every member
+        let sa: SetAccumulator<SledStore> = SetAccumulator::new(reopen(|| SledStore::open_existing(&dir)).unwrap());
+        assert_eq!((3, 3), (sa.store.first_epoch(), sa.current_epoch()));
+        assert_eq!(None, sa.store.get_epoch(2).unwrap());
+        assert_eq!(3, sa.store.get_epoch(3).unwrap().unwrap().operations.len());
+        assert!(sa.prove_membership_at(b"alice", 3).is_some());
+        drop(sa);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_collect_primes() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let dir: PathBuf = std::env::temp_dir().join(format!("tangerine-sled-gc-{}-{}", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut sa: SetAccumulator<SledStore> = SetAccumulator::new(SledStore::open(&dir, &params).unwrap());
+        sa.add(b"alice").unwrap();
+        // a prime left behind for a value that is no member
+        sa.store.db.insert(prefixed_key(&[], PRIME_PREFIX, b"ghost"), vec![7]).unwrap();
+        assert_eq!(1, sa.store.collect_primes(true).unwrap());
+        assert_eq!(1, sa.store.collect_primes(false).unwrap());
+        assert_eq!(0, sa.store.collect_primes(true).unwrap());
+        // the primes of members stay
+        let nonce: Vec<u8> = sa.store.get_nonce(b"alice").unwrap();
+        assert!(sa.store.db.get(prefixed_key(&[], PRIME_PREFIX, b"alice")).unwrap().is_some());
+        assert!(sa.store.get_prime(b"alice", &nonce).is_some());
+        drop(sa);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let dir: PathBuf = std::env::temp_dir().join(format!("tangerine-sled-ns-{}-{}", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut backend: SledBackend = SledBackend::open(&dir).unwrap();
+        let mut plain: SetAccumulator<SledStore> = SetAccumulator::new(SledStore::open_in(backend.db.clone(), Vec::new(), &params).unwrap());
+        plain.add(b"carol").unwrap();
+        let mut long: SetAccumulator<SledStore> = SetAccumulator::new(backend.open("tenant-long", &params).unwrap());
+        long.add(b"alice").unwrap();
+        let mut short: SetAccumulator<SledStore> = SetAccumulator::new(backend.open("b", &params).unwrap());
+        short.add(b"bob").unwrap();
+        assert_eq!(vec!["b".to_string(), "tenant-long".to_string()], backend.namespaces().unwrap());
+
+        // reloaded, every store holds its own members and nothing else
+        let state: BigUint = long.store.get_state().clone();
+        drop(long);
+        let long: SledStore = backend.open("tenant-long", &params).unwrap();
+        assert_eq!(state, *long.get_state());
+        assert_eq!(1, long.member_count());
+        assert!(long.contains_member(b"alice"));
+        let reopened: SledStore = SledStore::load(backend.db.clone(), Vec::new()).unwrap();
+        assert_eq!(1, reopened.member_count());
+        assert!(reopened.contains_member(b"carol"));
+
+        assert!(backend.remove("b").unwrap());
+        assert!(!backend.remove("b").unwrap());
+        assert_eq!(vec!["tenant-long".to_string()], backend.namespaces().unwrap());
+        assert_eq!(params.genesis_state(), *backend.open("b", &params).unwrap().get_state());
+        drop((plain, short, long, reopened, backend));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use num_bigint::BigUint;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+use crate::error::AccumulatorError;
+use crate::params::{HashFunction, PrimeMapping, PublicParameters, Trapdoor};
+use crate::store::{decode_epoch, decode_record, encode_epoch, encode_record, AuditRecord, Epoch, MemberChange, PrimeCache, Storer, TrackedMembers};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS tangerine_meta (key TEXT PRIMARY KEY, value BLOB NOT NULL);
+    CREATE TABLE IF NOT EXISTS tangerine_members (value BLOB PRIMARY KEY, nonce BLOB NOT NULL);
+    CREATE TABLE IF NOT EXISTS tangerine_primes (value BLOB PRIMARY KEY, prime BLOB NOT NULL);
+    CREATE TABLE IF NOT EXISTS tangerine_epochs (epoch INTEGER PRIMARY KEY, body BLOB NOT NULL);
+    CREATE TABLE IF NOT EXISTS tangerine_log (position INTEGER PRIMARY KEY, record BLOB NOT NULL);
+";
+
+fn to_io(e: rusqlite::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+fn get_meta(connection: &Connection, key: &str) -> rusqlite::Result<Option<Vec<u8>>> {
+    connection
+        .query_row("SELECT value FROM tangerine_meta WHERE key = ?1", params![key], |row| row.get(0))
+        .optional()
+}
+
+fn set_meta(transaction: &Transaction, key: &str, value: &[u8]) -> rusqlite::Result<()> {
+    transaction.execute("INSERT OR REPLACE INTO tangerine_meta (key, value) VALUES (?1, ?2)", params![key, value])?;
+    Ok(())
+}
+
+/// A store kept in a SQLite database, in the `tangerine_meta`, `tangerine_members`,
+/// `tangerine_primes`, `tangerine_epochs` and `tangerine_log` tables, so it can live next
+/// to an application's own tables. The primes table holds the prime each member maps to,
+/// so witnesses never search for it, and the last two every epoch and audit record,
+/// written in the same transaction as the state they closed with.
+///
+/// The members are also held in memory, so lookups never reach the database. Whatever
+/// changed since the last write is written together with the state in one transaction on
+/// every [`set_state`](Storer::set_state), which the accumulator calls after each change
+/// to the members.
+///
+/// The trapdoor is never written to the database.
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Connection,
+    params: PublicParameters,
+    state: BigUint,
+    // the state as of the last write, for a failed one to go back to
+    written_state: BigUint,
+    members: TrackedMembers,
+    primes: PrimeCache,
+    trapdoor: Option<Trapdoor>,
+    in_transaction: bool,
+    first_epoch: u64,
+    epochs: u64,
+    pending_epochs: Vec<Epoch>,
+    log_len: u64,
+    pending_log: Vec<AuditRecord>,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating the store over `params` at the genesis state
+    /// if it does not hold one yet. An existing store must have been created over `params`.
+    pub fn open<P: AsRef<Path>>(path: P, params: &PublicParameters) -> io::Result<Self> {
+        SqliteStore::create(Connection::open(path).map_err(to_io)?, params)
+    }
+    /// Creates or loads the store in an already open connection, see [`SqliteStore::open`].
+    pub fn create(mut connection: Connection, params: &PublicParameters) -> io::Result<Self> {
+        connection.execute_batch(SCHEMA).map_err(to_io)?;
+        if get_meta(&connection, "params").map_err(to_io)?.is_none() {
+            let transaction: Transaction = connection.transaction().map_err(to_io)?;
+            set_meta(&transaction, "params", &params.to_bytes()).map_err(to_io)?;
+            set_meta(&transaction, "state", &params.genesis_state().to_bytes_be()).map_err(to_io)?;
+            transaction.commit().map_err(to_io)?;
+        }
+        let store: SqliteStore = SqliteStore::load(connection)?;
+        if store.params != *params {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "store was created with different public parameters"));
+        }
+        Ok(store)
+    }
+    /// Loads the store from an open connection with whatever parameters it was created over.
+    pub fn load(connection: Connection) -> io::Result<Self> {
+        connection.execute_batch(SCHEMA).map_err(to_io)?;
+        let missing = || io::Error::new(io::ErrorKind::NotFound, "not a tangerine store");
+        let params: PublicParameters = PublicParameters::from_bytes(&get_meta(&connection, "params").map_err(to_io)?.ok_or_else(missing)?)?;
+        let state: BigUint = BigUint::from_bytes_be(&get_meta(&connection, "state").map_err(to_io)?.ok_or_else(missing)?);
+        let members: HashMap<Vec<u8>, Vec<u8>> = {
+            let mut statement = connection.prepare("SELECT value, nonce FROM tangerine_members").map_err(to_io)?;
+            let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).map_err(to_io)?;
+            rows.collect::<rusqlite::Result<_>>().map_err(to_io)?
+        };
+        let mut primes: PrimeCache = PrimeCache::default();
+        {
+            let mut statement = connection.prepare("SELECT value, prime FROM tangerine_primes").map_err(to_io)?;
+            let rows = statement.query_map([], |row| Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, Vec<u8>>(1)?))).map_err(to_io)?;
+            for row in rows {
+                let (value, prime): (Vec<u8>, Vec<u8>) = row.map_err(to_io)?;
+                if let Some(nonce) = members.get(&value) {
+                    primes.insert(&value, nonce, &BigUint::from_bytes_be(&prime));
+                }
+            }
+        }
+        let epochs: i64 = connection
+            .query_row("SELECT COALESCE(MAX(epoch), 0) FROM tangerine_epochs", [], |row| row.get(0))
+            .map_err(to_io)?;
+        // compacting drops the rows of the epochs before the first one kept
+        let first_epoch: i64 = connection
+            .query_row("SELECT COALESCE(MIN(epoch), 1) FROM tangerine_epochs", [], |row| row.get(0))
+            .map_err(to_io)?;
+        let log_len: i64 = connection
+            .query_row("SELECT COALESCE(MAX(position), 0) FROM tangerine_log", [], |row| row.get(0))
+            .map_err(to_io)?;
+        Ok(SqliteStore {
+            connection,
+            params,
+            written_state: state.clone(),
+            state,
+            members: TrackedMembers::new(members),
+            primes,
+            trapdoor: None,
+            in_transaction: false,
+            first_epoch: first_epoch as u64,
+            epochs: epochs as u64,
+            pending_epochs: Vec::new(),
+            log_len: log_len as u64,
+            pending_log: Vec::new(),
+        })
+    }
+    pub fn with_trapdoor(mut self, trapdoor: Trapdoor) -> Self {
+        self.trapdoor = Some(trapdoor);
+        self
+    }
+    fn commit(&mut self, changes: &[MemberChange], new_state: &BigUint) -> rusqlite::Result<()> {
+        let transaction: Transaction = self.connection.transaction()?;
+        for (value, nonce) in changes.iter() {
+            transaction.execute("DELETE FROM tangerine_primes WHERE value = ?1", params![value])?;
+            match nonce {
+                Some(nonce) => {
+                    transaction.execute(
+                        "INSERT OR REPLACE INTO tangerine_members (value, nonce) VALUES (?1, ?2)",
+                        params![value, nonce],
+                    )?;
+                    if let Some(prime) = self.primes.get(value, nonce) {
+                        transaction.execute(
+                            "INSERT INTO tangerine_primes (value, prime) VALUES (?1, ?2)",
+                            params![value, prime.to_bytes_be()],
+                        )?;
+                    }
+                }
+                None => {
+                    transaction.execute("DELETE FROM tangerine_members WHERE value = ?1", params![value])?;
+                }
+            };
+        }
+        for (number, epoch) in (self.epochs + 1..).zip(self.pending_epochs.iter()) {
+            transaction.execute(
+                "INSERT INTO tangerine_epochs (epoch, body) VALUES (?1, ?2)",
+                params![number as i64, encode_epoch(epoch)],
+            )?;
+        }
+        for (position, record) in (self.log_len + 1..).zip(self.pending_log.iter()) {
+            transaction.execute(
+                "INSERT INTO tangerine_log (position, record) VALUES (?1, ?2)",
+                params![position as i64, encode_record(record)],
+            )?;
+        }
+        set_meta(&transaction, "state", &new_state.to_bytes_be())?;
+        transaction.commit()
+    }
+    // writes whatever changed since the last write in one transaction along with `new_state`
+    fn write(&mut self, new_state: &BigUint) -> rusqlite::Result<()> {
+        let changes: Vec<MemberChange> = self.members.changes();
+        self.commit(&changes, new_state)?;
+        self.epochs += self.pending_epochs.len() as u64;
+        self.pending_epochs.clear();
+        self.log_len += self.pending_log.len() as u64;
+        self.pending_log.clear();
+        self.members.mark_written();
+        self.primes.sweep(self.members.map());
+        self.state = new_state.clone();
+        self.written_state = new_state.clone();
+        Ok(())
+    }
+    // puts the store back at its view as of the last write
+    fn revert(&mut self) {
+        self.members.revert();
+        self.primes.sweep(self.members.map());
+        self.pending_epochs.clear();
+        self.pending_log.clear();
+        self.state = self.written_state.clone();
+    }
+}
+
+impl Storer for SqliteStore {
+    fn get_generator(&self) -> &BigUint {
+        &self.params.generator
+    }
+    fn get_modulus(&self) -> &BigUint {
+        &self.params.modulus
+    }
+    fn get_state(&self) -> &BigUint {
+        &self.state
+    }
+    fn set_state(&mut self, new_state: &BigUint) {
+        if self.in_transaction {
+            self.state = new_state.clone();
+            return;
+        }
+        self.write(new_state).expect("failed to write to the sqlite store");
+    }
+    fn get_trapdoor(&self) -> Option<Trapdoor> {
+        self.trapdoor.clone()
+    }
+    fn get_nonce(&self, value: &[u8]) -> Option<Vec<u8>> {
+        self.members.get(value).cloned()
+    }
+    fn insert_member(&mut self, value: &[u8], nonce: &[u8]) {
+        self.members.insert(value, nonce);
+    }
+    fn remove_member(&mut self, value: &[u8]) -> Option<Vec<u8>> {
+        self.members.remove(value)
+    }
+    fn iter_members(&self) -> impl Iterator<Item = (&[u8], &[u8])> + '_ {
+        self.members.map().iter().map(|(value, nonce)| (value.as_slice(), nonce.as_slice()))
+    }
+    fn contains_member(&self, value: &[u8]) -> bool {
+        self.members.get(value).is_some()
+    }
+    fn member_count(&self) -> usize {
+        self.members.map().len()
+    }
+    fn begin_transaction(&mut self) {
+        self.in_transaction = true;
+    }
+    fn commit_transaction(&mut self) -> Result<(), AccumulatorError> {
+        self.in_transaction = false;
+        let state: BigUint = self.state.clone();
+        self.write(&state).map_err(|e| {
+            self.revert();
+            AccumulatorError::Io(e.to_string())
+        })
+    }
+    fn get_hash_function(&self) -> HashFunction {
+        self.params.hash_function
+    }
+    fn get_prime_bits(&self) -> Option<u64> {
+        self.params.prime_bits
+    }
+    fn get_prime(&self, value: &[u8], nonce: &[u8]) -> Option<&BigUint> {
+        self.primes.get(value, nonce)
+    }
+    fn set_prime(&mut self, value: &[u8], nonce: &[u8], prime: &BigUint) {
+        self.primes.insert(value, nonce, prime);
+    }
+    fn get_prime_mapping(&self) -> PrimeMapping {
+        self.params.prime_mapping()
+    }
+    // the primes written for values no longer members, which a write that went astray
+    // can leave behind; those kept in memory only are dropped along with them
+    fn collect_primes(&mut self, dry_run: bool) -> io::Result<u64> {
+        const ORPHANS: &str = "FROM tangerine_primes WHERE value NOT IN (SELECT value FROM tangerine_members)";
+        if dry_run {
+            let found: i64 = self.connection.query_row(&format!("SELECT COUNT(*) {}", ORPHANS), [], |row| row.get(0)).map_err(to_io)?;
+            return Ok(found as u64);
+        }
+        let found: usize = self.connection.execute(&format!("DELETE {}", ORPHANS), []).map_err(to_io)?;
+        self.primes.collect(self.members.map(), false);
+        Ok(found as u64)
+    }
+    fn push_epoch(&mut self, epoch: &Epoch) {
+        self.pending_epochs.push(epoch.clone());
+    }
+    fn epoch_count(&self) -> u64 {
+        self.epochs + self.pending_epochs.len() as u64
+    }
+    fn get_epoch(&self, epoch: u64) -> io::Result<Option<Epoch>> {
+        if epoch > self.epochs {
+            return Ok(self.pending_epochs.get((epoch - self.epochs - 1) as usize).cloned());
+        }
+        let body: Option<Vec<u8>> = self.connection
+            .query_row("SELECT body FROM tangerine_epochs WHERE epoch = ?1", params![epoch as i64], |row| row.get(0))
+            .optional()
+            .map_err(to_io)?;
+        body.map(|body| decode_epoch(&body)).transpose()
+    }
+    fn first_epoch(&self) -> u64 {
+        self.first_epoch
+    }
+    fn compact_epochs(&mut self, first: u64, base: &Epoch) -> io::Result<()> {
+        let transaction: Transaction = self.connection.transaction().map_err(to_io)?;
+        transaction.execute("DELETE FROM tangerine_epochs WHERE epoch < ?1", params![first as i64]).map_err(to_io)?;
+        transaction
+            .execute("INSERT OR REPLACE INTO tangerine_epochs (epoch, body) VALUES (?1, ?2)", params![first as i64, encode_epoch(base)])
+            .map_err(to_io)?;
+        transaction.commit().map_err(to_io)?;
+        self.first_epoch = first;
+        Ok(())
+    }
+    fn append_log(&mut self, record: &AuditRecord) {
+        self.pending_log.push(record.clone());
+    }
+    fn iter_log(&self) -> impl Iterator<Item = io::Result<AuditRecord>> + '_ {
+        // the rows borrow the statement, so they are read up front
+        let written: Vec<io::Result<AuditRecord>> = match self.connection.prepare("SELECT record FROM tangerine_log ORDER BY position") {
+            Ok(mut statement) => match statement.query_map([], |row| row.get::<_, Vec<u8>>(0)) {
+                Ok(rows) => rows.map(|row| decode_record(&row.map_err(to_io)?)).collect(),
+                Err(e) => vec![Err(to_io(e))],
+            },
+            Err(e) => vec![Err(to_io(e))],
+        };
+        written.into_iter().chain(self.pending_log.iter().cloned().map(Ok))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use crate::proof::MembershipProof;
+    use crate::store::AuditAction;
+    use crate::witness::WitnessUpdater;
+    use crate::{Retention, SetAccumulator};
+
+    #[test]
+    fn test_survives_reopen() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let path: PathBuf = std::env::temp_dir().join(format!("tangerine-sqlite-{}-{}.db", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let state: BigUint;
+        {
+            let mut sa: SetAccumulator<SqliteStore> = SetAccumulator::new(SqliteStore::open(&path, &params).unwrap());
+            sa.add(b"alice").unwrap();
+            sa.add_batch(&[b"bob", b"carol"]).unwrap();
+            sa.delete_without_trapdoor(b"bob").unwrap();
+            state = sa.store.get_state().clone();
+        }
+        let store: SqliteStore = SqliteStore::load(Connection::open(&path).unwrap()).unwrap();
+        assert_eq!(state, *store.get_state());
+        assert_eq!(2, store.member_count());
+        assert!(store.contains_member(b"carol"));
+        // along with the primes of the members
+        let nonce: Vec<u8> = store.get_nonce(b"carol").unwrap();
+        assert!(store.get_prime(b"carol", &nonce).is_some());
+        // and the epochs, so the history can still be proven against
+        assert_eq!(3, store.epoch_count());
+        assert_eq!(Some(state.clone()), store.get_epoch(3).unwrap().map(|epoch| epoch.state));
+        assert_eq!(None, store.get_epoch(4).unwrap());
+        // and the audit log
+        let actions: Vec<AuditAction> = store.iter_log().map(|record| record.unwrap().action).collect();
+        assert_eq!(vec![AuditAction::Add, AuditAction::Add, AuditAction::Add, AuditAction::Delete], actions);
+        assert_eq!(state, store.iter_log().last().unwrap().unwrap().new_state);
+        let sa: SetAccumulator<SqliteStore> = SetAccumulator::new(store);
+        let proof: MembershipProof = sa.prove_membership_at(b"bob", 2).unwrap();
+        assert!(sa.verify_at(2, b"bob", &proof));
+        // which is enough to bring an old proof up to date
+        let old: MembershipProof = sa.prove_membership_at(b"carol", 2).unwrap();
+        let fresh: MembershipProof = WitnessUpdater::new(&params).refresh(b"carol", &old, 2, sa.store.iter_log().map(Result::unwrap)).unwrap();
+        assert!(fresh.verify(&state, &params.modulus, b"carol", params.prime_mapping()));
+        let store: SqliteStore = sa.store;
+        drop(store);
+        // a store only opens over the parameters it was created with
+        let other: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(43_u64));
+        assert!(SqliteStore::open(&path, &other).is_err());
+        let sa: SetAccumulator<SqliteStore> = SetAccumulator::new(SqliteStore::open(&path, &params).unwrap());
+        let (witness, nonce): (BigUint, Vec<u8>) = sa.get_witness(b"alice").unwrap();
+        assert!(sa.verify(b"alice", &witness, &nonce));
+        drop(sa);
+        // an empty database holds no store
+        assert!(SqliteStore::load(Connection::open_in_memory().unwrap()).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_write() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let path: PathBuf = std::env::temp_dir().join(format!("tangerine-sqlite-write-{}-{}.db", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut sa: SetAccumulator<SqliteStore> = SetAccumulator::new(SqliteStore::open(&path, &params).unwrap());
+        sa.add(b"alice").unwrap();
+        let state: BigUint = sa.store.get_state().clone();
+        // without the log table every write of an audit record fails
+        sa.store.connection.execute_batch("DROP TABLE tangerine_log").unwrap();
+        match sa.add(b"bob") {
+            Err(AccumulatorError::Io(_)) => {}
+            other => panic!("expected the write to fail, got {:?}", other.map(|_| ())),
+        }
+        // and the change is undone rather than left in memory only
+        assert_eq!(state, *sa.store.get_state());
+        assert!(!sa.store.contains_member(b"bob"));
+        assert_eq!(1, sa.store.epoch_count());
+        drop(sa);
+        let store: SqliteStore = SqliteStore::load(Connection::open(&path).unwrap()).unwrap();
+        assert_eq!(state, *store.get_state());
+        assert!(!store.contains_member(b"bob"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_compact() {
+        let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let path: PathBuf = std::env::temp_dir().join(format!("tangerine-sqlite-compact-{}-{}.db", std::process::id(), nanos));
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        {
+            let mut sa: SetAccumulator<SqliteStore> = SetAccumulator::new(SqliteStore::open(&path, &params).unwrap());
+            sa.add(b"alice").unwrap();
+            sa.add(b"bob").unwrap();
+            sa.add(b"carol").unwrap();
+            assert_eq!(Ok(2), sa.compact(Retention::KeepLast(1)));
+            assert_eq!(Ok(0), sa.compact(Retention::KeepLast(1)));
+        }
+        // the dropped epochs stay dropped, and the one in their place holds every member
+        let mut sa: SetAccumulator<SqliteStore> = SetAccumulator::new(SqliteStore::open(&path, &params).unwrap());
+        assert_eq!((3, 3), (sa.store.first_epoch(), sa.current_epoch()));
+        assert_eq!(None, sa.store.get_epoch(2).unwrap());
+        assert_eq!(3, sa.store.get_epoch(3).unwrap().unwrap().operations.len());
+        assert!(sa.prove_membership_at(b"alice", 3).is_some());
+        // and the epochs written after it follow on from it
+        sa.add(b"dave").unwrap();
+        assert_eq!(4, sa.current_epoch());
+        assert!(sa.prove_membership_at(b"dave", 4).is_some());
+        drop(sa);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
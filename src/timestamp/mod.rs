@@ -0,0 +1,242 @@
+//! RFC 3161 timestamping of accumulator heads.
+//!
+//! A head's digest is sent to a time-stamp authority (TSA), which returns a signed
+//! token saying it saw that digest at a certain time. The transport is left to the
+//! caller through [`TimestampAuthority`], usually an HTTP POST of the request with
+//! content type `application/timestamp-query`.
+//!
+//! [`verify_timestamp`] checks that a token covers a given head, but it does not check
+//! the TSA's CMS signature over the token. That needs the TSA certificate chain and
+//! should be done with a CMS implementation, e.g. `openssl ts -verify`.
+
+use std::io;
+
+use rand::Rng;
+
+use crate::head::Head;
+
+// DER tags used by the time-stamp protocol
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+const GENERALIZED_TIME: u8 = 0x18;
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+const BOOLEAN: u8 = 0x01;
+const CONTEXT_0: u8 = 0xa0;
+
+// AlgorithmIdentifier { id-sha256, NULL } without the outer SEQUENCE header
+const SHA256_ALGORITHM: &[u8] = &[0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00];
+// 1.2.840.113549.1.7.2
+const SIGNED_DATA_OID: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+// 1.2.840.113549.1.9.16.1.4
+const TST_INFO_OID: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x01, 0x04];
+
+/// Something that can deliver a time-stamp request to a TSA.
+pub trait TimestampAuthority {
+    /// Sends a DER encoded `TimeStampReq` and returns the DER encoded `TimeStampResp`.
+    fn submit(&mut self, request: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+/// A head together with the time-stamp token issued for its digest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampedHead {
+    pub head: Head,
+    /// DER encoded `TimeStampToken` (a CMS `ContentInfo`).
+    pub token: Vec<u8>,
+}
+
+/// The fields of a time-stamp token that matter for checking it against a head.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampInfo {
+    /// The SHA-256 digest the TSA signed.
+    pub imprint: Vec<u8>,
+    /// When the TSA saw it, as a GeneralizedTime string such as `20240102030405Z`.
+    pub gen_time: String,
+    pub nonce: Option<Vec<u8>>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn encode(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = vec![tag];
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        let len: Vec<u8> = content.len().to_be_bytes().iter().copied().skip_while(|b| *b == 0).collect();
+        out.push(0x80 | len.len() as u8);
+        out.extend(len);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn encode_unsigned(bytes: &[u8]) -> Vec<u8> {
+    // DER integers are minimal two's complement, so strip leading zeros and keep it positive
+    let mut content: Vec<u8> = bytes.iter().copied().skip_while(|b| *b == 0).collect();
+    if content.first().is_none_or(|b| b & 0x80 != 0) {
+        content.insert(0, 0);
+    }
+    encode(INTEGER, &content)
+}
+
+// split one DER element off the front of `bytes`, returning its tag and contents
+fn read<'a>(bytes: &mut &'a [u8]) -> io::Result<(u8, &'a [u8])> {
+    if bytes.len() < 2 {
+        return Err(invalid("truncated DER element"));
+    }
+    let tag: u8 = bytes[0];
+    let (len, header): (usize, usize) = if bytes[1] < 0x80 {
+        (bytes[1] as usize, 2)
+    } else {
+        let n: usize = (bytes[1] & 0x7f) as usize;
+        if n == 0 || n > 4 || bytes.len() < 2 + n {
+            return Err(invalid("unsupported DER length"));
+        }
+        (bytes[2..2 + n].iter().fold(0, |len, b| (len << 8) | *b as usize), 2 + n)
+    };
+    if bytes.len() < header + len {
+        return Err(invalid("truncated DER element"));
+    }
+    let content: &[u8] = &bytes[header..header + len];
+    *bytes = &bytes[header + len..];
+    Ok((tag, content))
+}
+
+fn expect<'a>(bytes: &mut &'a [u8], tag: u8) -> io::Result<&'a [u8]> {
+    match read(bytes)? {
+        (found, content) if found == tag => Ok(content),
+        _ => Err(invalid("unexpected DER element")),
+    }
+}
+
+/// DER encoded `TimeStampReq` for the SHA-256 `digest`, asking for the TSA certificate
+/// to be included in the token.
+pub fn timestamp_request(digest: &[u8; 32], nonce: &[u8]) -> Vec<u8> {
+    let imprint: Vec<u8> = encode(SEQUENCE, &[encode(SEQUENCE, SHA256_ALGORITHM), encode(OCTET_STRING, digest)].concat());
+    let cert_req: Vec<u8> = encode(BOOLEAN, &[0xff]);
+    encode(SEQUENCE, &[encode_unsigned(&[1]), imprint, encode_unsigned(nonce), cert_req].concat())
+}
+
+/// Reads the signed `TSTInfo` out of a DER encoded time-stamp token.
+pub fn timestamp_info(token: &[u8]) -> io::Result<TimestampInfo> {
+    let mut token: &[u8] = token;
+    let mut content_info: &[u8] = expect(&mut token, SEQUENCE)?;
+    if expect(&mut content_info, OBJECT_IDENTIFIER)? != SIGNED_DATA_OID {
+        return Err(invalid("time-stamp token is not CMS signed data"));
+    }
+    let mut explicit: &[u8] = expect(&mut content_info, CONTEXT_0)?;
+    let mut signed_data: &[u8] = expect(&mut explicit, SEQUENCE)?;
+    expect(&mut signed_data, INTEGER)?;
+    expect(&mut signed_data, SET)?;
+    let mut encapsulated: &[u8] = expect(&mut signed_data, SEQUENCE)?;
+    if expect(&mut encapsulated, OBJECT_IDENTIFIER)? != TST_INFO_OID {
+        return Err(invalid("signed data does not hold a TSTInfo"));
+    }
+    let mut explicit: &[u8] = expect(&mut encapsulated, CONTEXT_0)?;
+    let mut octets: &[u8] = expect(&mut explicit, OCTET_STRING)?;
+    let mut tst_info: &[u8] = expect(&mut octets, SEQUENCE)?;
+    expect(&mut tst_info, INTEGER)?;
+    expect(&mut tst_info, OBJECT_IDENTIFIER)?;
+    let mut imprint: &[u8] = expect(&mut tst_info, SEQUENCE)?;
+    if expect(&mut imprint, SEQUENCE)? != SHA256_ALGORITHM {
+        return Err(invalid("time-stamp token does not use SHA-256"));
+    }
+    let imprint: Vec<u8> = expect(&mut imprint, OCTET_STRING)?.to_vec();
+    expect(&mut tst_info, INTEGER)?;
+    let gen_time: String = String::from_utf8(expect(&mut tst_info, GENERALIZED_TIME)?.to_vec())
+        .map_err(|_| invalid("genTime is not a valid string"))?;
+    // after genTime come the optional accuracy, ordering and nonce, of which only the nonce is an INTEGER
+    let mut nonce: Option<Vec<u8>> = None;
+    while !tst_info.is_empty() {
+        let (tag, content) = read(&mut tst_info)?;
+        if tag == INTEGER {
+            nonce = Some(content.iter().copied().skip_while(|b| *b == 0).collect());
+            break;
+        }
+    }
+    Ok(TimestampInfo { imprint, gen_time, nonce })
+}
+
+/// Checks that `token` was issued for `head` and returns what it says.
+pub fn verify_timestamp(head: &Head, token: &[u8]) -> io::Result<TimestampInfo> {
+    let info: TimestampInfo = timestamp_info(token)?;
+    if info.imprint != head.digest() {
+        return Err(invalid("time-stamp token is for a different head"));
+    }
+    Ok(info)
+}
+
+/// Has `tsa` time-stamp the digest of `head`, checks that the granted token is for
+/// this head and this request, and returns the two together.
+pub fn timestamp_head<A: TimestampAuthority>(tsa: &mut A, head: Head) -> io::Result<TimestampedHead> {
+    let nonce: [u8; 8] = rand::thread_rng().gen();
+    let response: Vec<u8> = tsa.submit(&timestamp_request(&head.digest(), &nonce))?;
+    let mut response: &[u8] = &response;
+    let mut response: &[u8] = expect(&mut response, SEQUENCE)?;
+    let mut status: &[u8] = expect(&mut response, SEQUENCE)?;
+    // 0 is granted and 1 is granted with modifications, anything else carries no token
+    match expect(&mut status, INTEGER)? {
+        [0] | [1] => {}
+        _ => return Err(io::Error::other("time-stamp request was rejected")),
+    }
+    let token: Vec<u8> = response.to_vec();
+    let info: TimestampInfo = verify_timestamp(&head, &token)?;
+    let expected: Vec<u8> = nonce.iter().copied().skip_while(|b| *b == 0).collect();
+    if info.nonce != Some(expected) {
+        return Err(invalid("time-stamp token does not answer this request"));
+    }
+    Ok(TimestampedHead { head, token })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use crate::params::PublicParameters;
+
+    // answers with a well formed but unsigned token echoing the request's imprint and nonce
+    struct FakeTsa;
+
+    impl TimestampAuthority for FakeTsa {
+        fn submit(&mut self, request: &[u8]) -> io::Result<Vec<u8>> {
+            let mut request: &[u8] = request;
+            let mut request: &[u8] = expect(&mut request, SEQUENCE)?;
+            expect(&mut request, INTEGER)?;
+            let imprint_der: Vec<u8> = encode(SEQUENCE, expect(&mut request, SEQUENCE)?);
+            let nonce: Vec<u8> = encode(INTEGER, expect(&mut request, INTEGER)?);
+            let tst_info: Vec<u8> = encode(SEQUENCE, &[
+                encode_unsigned(&[1]),
+                encode(OBJECT_IDENTIFIER, &[0x2a, 0x03]),
+                imprint_der,
+                encode_unsigned(&[0x2a]),
+                encode(GENERALIZED_TIME, b"20240102030405Z"),
+                nonce,
+            ].concat());
+            let encapsulated: Vec<u8> = encode(SEQUENCE, &[
+                encode(OBJECT_IDENTIFIER, TST_INFO_OID),
+                encode(CONTEXT_0, &encode(OCTET_STRING, &tst_info)),
+            ].concat());
+            let signed_data: Vec<u8> = encode(SEQUENCE, &[encode_unsigned(&[3]), encode(SET, &[]), encapsulated, encode(SET, &[])].concat());
+            let token: Vec<u8> = encode(SEQUENCE, &[encode(OBJECT_IDENTIFIER, SIGNED_DATA_OID), encode(CONTEXT_0, &signed_data)].concat());
+            let status: Vec<u8> = encode(SEQUENCE, &encode_unsigned(&[0]));
+            Ok(encode(SEQUENCE, &[status, token].concat()))
+        }
+    }
+
+    #[test]
+    fn test_timestamp_head() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let genesis: Head = Head::genesis(&params);
+        let head: Head = genesis.next(BigUint::from(7_u64), &[(b"a".to_vec(), vec![1; 32])]);
+        let timestamped: TimestampedHead = timestamp_head(&mut FakeTsa, head.clone()).unwrap();
+        let info: TimestampInfo = verify_timestamp(&head, &timestamped.token).unwrap();
+        assert_eq!("20240102030405Z", info.gen_time);
+        assert_eq!(head.digest().to_vec(), info.imprint);
+        // the token does not vouch for any other head
+        assert!(verify_timestamp(&genesis, &timestamped.token).is_err());
+        assert!(timestamp_info(&timestamped.token[..10]).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+//! Commitments to sequences of byte strings, opened one position at a time, for uses
+//! where the order of the values matters and not just which values are present.
+//!
+//! The value at each position is mapped to a prime the way members are, with the position
+//! bound into the hash: the nonce is a domain tag followed by the index as a big-endian
+//! u64, so the same value at two positions maps to two unrelated primes. The commitment
+//! is the generator raised to the product of all of them, and the opening of a position
+//! is the generator raised to the product of the others, checked as an accumulator
+//! witness is.
+//!
+//! Past the committer, nobody can open a position to a value other than the one
+//! committed, which would need a root of the commitment. The committer can, by
+//! multiplying in primes for several values at one position, so the commitment does not
+//! bind whoever computed it.
+
+use num_bigint::BigUint;
+
+use crate::arith;
+use crate::hash_value_to_prime;
+use crate::params::{PublicParameters, SecurityLevel};
+use crate::parallel::*;
+use crate::product_tree::{self, ProductTree};
+
+// keeps position primes apart from the primes of members added with any nonce
+const POSITION_DOMAIN: &[u8] = b"tangerine/vector/v1";
+
+fn position_nonce(index: u64) -> Vec<u8> {
+    [POSITION_DOMAIN, &index.to_be_bytes()].concat()
+}
+
+fn position_prime(params: &PublicParameters, index: u64, value: &[u8]) -> BigUint {
+    hash_value_to_prime(value, &position_nonce(index), params.prime_mapping(), SecurityLevel::default())
+}
+
+/// A value at a position, and the witness that it is the one committed there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opening {
+    pub index: u64,
+    pub value: Vec<u8>,
+    pub witness: BigUint,
+}
+
+/// A committed vector, held by the committer to open positions of it.
+pub struct VectorCommitment {
+    params: PublicParameters,
+    values: Vec<Vec<u8>>,
+    primes: Vec<BigUint>,
+    commitment: BigUint,
+}
+
+impl VectorCommitment {
+    pub fn commit(params: &PublicParameters, values: &[Vec<u8>]) -> Self {
+        let indexed: Vec<(u64, &Vec<u8>)> = (0_u64..).zip(values.iter()).collect();
+        let primes: Vec<BigUint> = indexed.par_iter().map(|(index, value)| position_prime(params, *index, value)).collect();
+        let commitment: BigUint = arith::modpow(&params.generator, &product_tree::product(primes.clone()), &params.modulus);
+        VectorCommitment { params: params.clone(), values: values.to_vec(), primes, commitment }
+    }
+    pub fn commitment(&self) -> &BigUint {
+        &self.commitment
+    }
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+    /// The opening of position `index`, None past the end of the vector.
+    pub fn open(&self, index: usize) -> Option<Opening> {
+        let value: &Vec<u8> = self.values.get(index)?;
+        let others: Vec<BigUint> = self.primes.iter().enumerate().filter(|(i, _)| *i != index).map(|(_, prime)| prime.clone()).collect();
+        let witness: BigUint = arith::modpow(&self.params.generator, &product_tree::product(others), &self.params.modulus);
+        Some(Opening { index: index as u64, value: value.clone(), witness })
+    }
+    /// The openings of every position at once, see [`ProductTree::root_factor`].
+    pub fn open_all(&self) -> Vec<Opening> {
+        let witnesses: Vec<BigUint> = ProductTree::new(self.primes.clone()).root_factor(&self.params.generator, &self.params.modulus);
+        (0_u64..)
+            .zip(self.values.iter())
+            .zip(witnesses)
+            .map(|((index, value), witness)| Opening { index, value: value.clone(), witness })
+            .collect()
+    }
+}
+
+/// Whether `opening` shows its value to be the one at its position in `commitment`.
+pub fn verify_opening(params: &PublicParameters, commitment: &BigUint, opening: &Opening) -> bool {
+    let prime: BigUint = position_prime(params, opening.index, &opening.value);
+    arith::modpow(&opening.witness, &prime, &params.modulus) == *commitment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_commitment() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let values: Vec<Vec<u8>> = vec![b"alice".to_vec(), b"bob".to_vec(), b"alice".to_vec(), b"carol".to_vec()];
+        let vc: VectorCommitment = VectorCommitment::commit(&params, &values);
+        assert_eq!(4, vc.len());
+        assert!(vc.open(4).is_none());
+        let openings: Vec<Opening> = vc.open_all();
+        for (index, opening) in openings.iter().enumerate() {
+            assert_eq!(Some(opening), vc.open(index).as_ref());
+            assert!(verify_opening(&params, vc.commitment(), opening));
+        }
+        // neither another value nor another position passes for the committed one
+        let swapped: Opening = Opening { value: b"bob".to_vec(), ..openings[0].clone() };
+        assert!(!verify_opening(&params, vc.commitment(), &swapped));
+        let moved: Opening = Opening { index: 1, ..openings[0].clone() };
+        assert!(!verify_opening(&params, vc.commitment(), &moved));
+        assert_eq!(params.genesis_state(), *VectorCommitment::commit(&params, &[]).commitment());
+    }
+}
@@ -0,0 +1,129 @@
+//! Read-only views of an accumulator as it was at the close of a past epoch, rebuilt from
+//! the history the store keeps, see [`SetAccumulator::open_at`].
+
+use std::collections::HashMap;
+
+use num_bigint::BigUint;
+
+use crate::proof::MembershipProof;
+use crate::store::Storer;
+use crate::{arith, member_prime, product_tree, SetAccumulator};
+
+/// The state and members of an accumulator at the close of one epoch, answering the
+/// queries a verifier could have made then. It borrows the accumulator for its
+/// parameters and the primes the store kept, and changes nothing.
+pub struct EpochView<'a, T: Storer> {
+    accumulator: &'a SetAccumulator<T>,
+    epoch: u64,
+    state: BigUint,
+    members: HashMap<Vec<u8>, Vec<u8>>,
+    // the primes the epochs recorded for the members, see Operation::Add
+    primes: HashMap<Vec<u8>, BigUint>,
+}
+
+impl<'a, T: Storer> EpochView<'a, T> {
+    pub(crate) fn new(
+        accumulator: &'a SetAccumulator<T>,
+        epoch: u64,
+        state: BigUint,
+        members: HashMap<Vec<u8>, Vec<u8>>,
+        primes: HashMap<Vec<u8>, BigUint>,
+    ) -> Self {
+        EpochView { accumulator, epoch, state, members, primes }
+    }
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+    pub fn get_state(&self) -> &BigUint {
+        &self.state
+    }
+    pub fn contains(&self, value: &[u8]) -> bool {
+        self.members.contains_key(value)
+    }
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+    /// The nonce `value` was held with then, None if it was not a member.
+    pub fn get_nonce(&self, value: &[u8]) -> Option<&[u8]> {
+        self.members.get(value).map(Vec::as_slice)
+    }
+    /// Every member then with its nonce, in no particular order.
+    pub fn iter_members(&self) -> impl Iterator<Item = (&[u8], &[u8])> + '_ {
+        self.members.iter().map(|(value, nonce)| (value.as_slice(), nonce.as_slice()))
+    }
+    // the prime the epochs recorded for `value`, which they do for members added with a
+    // prime of the caller's
+    pub(crate) fn recorded_prime(&self, value: &[u8]) -> Option<&BigUint> {
+        self.primes.get(value)
+    }
+    // the primes of `members` then, those recorded for them or else the ones the
+    // accumulator finds for their nonces
+    pub(crate) fn primes_of(&self, members: &[(&[u8], &[u8])]) -> Vec<BigUint> {
+        let rest: Vec<(&[u8], &[u8])> = members.iter().filter(|(member, _)| !self.primes.contains_key(*member)).copied().collect();
+        let mut found = self.accumulator.primes_of(&rest).into_iter();
+        members
+            .iter()
+            .map(|(member, _)| match self.primes.get(*member) {
+                Some(prime) => prime.clone(),
+                None => found.next().unwrap(),
+            })
+            .collect()
+    }
+    /// The proof of membership `value` would have been issued then, with the witness
+    /// computed from the other members of the epoch.
+    pub fn get_membership_proof(&self, value: &[u8]) -> Option<MembershipProof> {
+        let sa: &SetAccumulator<T> = self.accumulator;
+        let (nonce, _, rest): (Vec<u8>, BigUint, Option<Vec<u8>>) = sa.last_occurrence(value, self.members.get(value)?);
+        let others: Vec<(&[u8], &[u8])> = self.iter_members().filter(|(member, _)| *member != value).collect();
+        let mut primes: Vec<BigUint> = self.primes_of(&others);
+        primes.extend(rest.map(|rest| sa.prime_of(value, &rest)));
+        let exponent: BigUint = product_tree::product(primes);
+        let witness: BigUint = sa.timed(|| sa.power_of_product()(sa.store.get_generator(), &exponent));
+        sa.record_witnesses(1);
+        Some(sa.package_proof(value, witness, nonce))
+    }
+    /// Checks a proof of membership against the state of the epoch.
+    pub fn verify(&self, value: &[u8], proof: &MembershipProof) -> bool {
+        let sa: &SetAccumulator<T> = self.accumulator;
+        let exponent: BigUint = match self.primes.get(value) {
+            Some(prime) if self.get_nonce(value) == Some(proof.nonce.as_slice()) => prime.clone(),
+            _ => member_prime(value, &proof.nonce, sa.prime_mapping, sa.primality),
+        };
+        arith::modpow(&proof.witness, &exponent, sa.store.get_modulus()) == self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AccumulatorError;
+    use crate::params::PublicParameters;
+    use crate::store::mem_store::MemStore;
+
+    #[test]
+    fn test_open_at() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        sa.add(b"alice").unwrap();
+        sa.add(b"bob").unwrap();
+        let state: BigUint = sa.store.get_state().clone();
+        sa.delete_without_trapdoor(b"alice").unwrap();
+        sa.add(b"carol").unwrap();
+
+        let view: EpochView<MemStore> = sa.open_at(2).unwrap();
+        assert_eq!((2, &state), (view.epoch(), view.get_state()));
+        assert_eq!(2, view.len());
+        assert!(view.contains(b"alice") && !view.contains(b"carol"));
+        // the proof it issues is checked against the state then, as a verifier then would have
+        let proof: MembershipProof = view.get_membership_proof(b"alice").unwrap();
+        assert!(view.verify(b"alice", &proof));
+        assert!(view.get_membership_proof(b"carol").is_none());
+
+        assert_eq!(params.genesis_state(), *sa.open_at(0).unwrap().get_state());
+        assert!(sa.open_at(0).unwrap().is_empty());
+        assert_eq!(Some(AccumulatorError::UnknownEpoch), sa.open_at(5).err());
+    }
+}
@@ -0,0 +1,156 @@
+//! JavaScript bindings through wasm-bindgen, enabled by the `wasm` feature, for browsers
+//! checking membership proofs handed out by a service running the accumulator.
+//!
+//! Values are byte strings (`Uint8Array` on the JavaScript side). Big integers and
+//! nonces are big-endian lowercase hex strings, the same encoding the `serde` feature
+//! gives [`MembershipProof`](crate::proof::MembershipProof) in JSON, so a proof fetched
+//! from the service can be passed through as it arrives. Errors are thrown as strings.
+//!
+//! Build for the browser without the default features, threads are not available there:
+//!
+//! ```sh
+//! cargo build --target wasm32-unknown-unknown --no-default-features --features wasm
+//! ```
+
+use num_bigint::BigUint;
+use wasm_bindgen::prelude::*;
+
+use crate::hex::{from_hex, to_hex};
+use crate::params::PublicParameters;
+use crate::store::mem_store::MemStore;
+use crate::store::Storer;
+use crate::SetAccumulator;
+
+fn parse_biguint(name: &str, hex: &str) -> Result<BigUint, String> {
+    from_hex(hex)
+        .map(|bytes| BigUint::from_bytes_be(&bytes))
+        .ok_or_else(|| format!("{} is not an even number of hex digits", name))
+}
+
+fn parse_bytes(name: &str, hex: &str) -> Result<Vec<u8>, String> {
+    from_hex(hex).ok_or_else(|| format!("{} is not an even number of hex digits", name))
+}
+
+/// An accumulator held in memory, for clients that keep a small set of their own.
+#[wasm_bindgen]
+pub struct Accumulator {
+    inner: SetAccumulator<MemStore>,
+}
+
+#[wasm_bindgen]
+impl Accumulator {
+    /// An empty accumulator over the hex `modulus` and `generator`, mapping values to
+    /// primes the default way.
+    #[wasm_bindgen(constructor)]
+    pub fn new(modulus: &str, generator: &str) -> Result<Accumulator, String> {
+        let params: PublicParameters = PublicParameters::new(parse_biguint("modulus", modulus)?, parse_biguint("generator", generator)?);
+        Ok(Accumulator { inner: SetAccumulator::new(MemStore::from_parameters(&params)) })
+    }
+    /// An empty accumulator over parameters in the encoding of
+    /// [`PublicParameters::to_bytes`], which carries the prime mapping too.
+    #[wasm_bindgen(js_name = fromParameters)]
+    pub fn from_parameters(params: &[u8]) -> Result<Accumulator, String> {
+        let params: PublicParameters = PublicParameters::from_bytes(params).map_err(|e| e.to_string())?;
+        Ok(Accumulator { inner: SetAccumulator::new(MemStore::from_parameters(&params)) })
+    }
+    pub fn add(&mut self, value: &[u8]) -> Result<(), String> {
+        self.inner.add(value).map(|_| ()).map_err(|e| e.to_string())
+    }
+    /// The current state in hex.
+    pub fn state(&self) -> String {
+        to_hex(&self.inner.store.get_state().to_bytes_be())
+    }
+    /// The modulus in hex, which [`verify`] needs along with the state.
+    pub fn modulus(&self) -> String {
+        to_hex(&self.inner.store.get_modulus().to_bytes_be())
+    }
+    /// The witness of `value` and the nonce it was added with, `undefined` for values
+    /// that are not members.
+    #[wasm_bindgen(js_name = getWitness)]
+    pub fn get_witness(&self, value: &[u8]) -> Option<Witness> {
+        self.inner.get_witness(value).map(|(witness, nonce)| Witness { witness, nonce })
+    }
+    pub fn verify(&self, value: &[u8], witness: &str, nonce: &str) -> Result<bool, String> {
+        Ok(self.inner.verify(value, &parse_biguint("witness", witness)?, &parse_bytes("nonce", nonce)?))
+    }
+}
+
+/// A witness and the nonce it goes with, read out as hex.
+#[wasm_bindgen]
+pub struct Witness {
+    witness: BigUint,
+    nonce: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl Witness {
+    #[wasm_bindgen(getter)]
+    pub fn witness(&self) -> String {
+        to_hex(&self.witness.to_bytes_be())
+    }
+    #[wasm_bindgen(getter)]
+    pub fn nonce(&self) -> String {
+        to_hex(&self.nonce)
+    }
+}
+
+/// Checks that `witness` proves membership of `value` in the accumulator at `state`
+/// over `modulus`, for accumulators mapping values to primes the default way, see
+/// [`crate::verify_membership`].
+#[wasm_bindgen]
+pub fn verify(state: &str, modulus: &str, value: &[u8], nonce: &str, witness: &str) -> Result<bool, String> {
+    Ok(crate::verify_membership(
+        &parse_biguint("state", state)?,
+        &parse_biguint("modulus", modulus)?,
+        value,
+        &parse_bytes("nonce", nonce)?,
+        &parse_biguint("witness", witness)?,
+    ))
+}
+
+/// [`verify`] against parameters in the encoding of [`PublicParameters::to_bytes`], for
+/// accumulators with any prime mapping.
+#[wasm_bindgen(js_name = verifyWithParameters)]
+pub fn verify_with_parameters(params: &[u8], state: &str, value: &[u8], nonce: &str, witness: &str) -> Result<bool, String> {
+    let params: PublicParameters = PublicParameters::from_bytes(params).map_err(|e| e.to_string())?;
+    Ok(crate::verify_membership_with(
+        params.prime_mapping(),
+        &parse_biguint("state", state)?,
+        &params.modulus,
+        value,
+        &parse_bytes("nonce", nonce)?,
+        &parse_biguint("witness", witness)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::HashFunction;
+
+    #[test]
+    fn test_round_trip_through_hex() {
+        let mut accumulator: Accumulator = Accumulator::new("0ca1", "2a").unwrap();
+        accumulator.add(b"alice").unwrap();
+        accumulator.add(b"bob").unwrap();
+        let witness: Witness = accumulator.get_witness(b"alice").unwrap();
+        assert!(accumulator.verify(b"alice", &witness.witness(), &witness.nonce()).unwrap());
+        assert!(verify(&accumulator.state(), &accumulator.modulus(), b"alice", &witness.nonce(), &witness.witness()).unwrap());
+        assert!(!verify(&accumulator.state(), &accumulator.modulus(), b"bob", &witness.nonce(), &witness.witness()).unwrap());
+        assert!(accumulator.get_witness(b"carol").is_none());
+        // malformed hex is an error rather than a failed proof
+        assert!(verify("abc", &accumulator.modulus(), b"alice", &witness.nonce(), &witness.witness()).is_err());
+        assert!(Accumulator::new("0ca1", "zz").is_err());
+    }
+
+    #[test]
+    fn test_verify_with_parameters() {
+        let params: PublicParameters =
+            PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64)).with_hash_function(HashFunction::Blake3);
+        let mut accumulator: Accumulator = Accumulator::from_parameters(&params.to_bytes()).unwrap();
+        accumulator.add(b"alice").unwrap();
+        let witness: Witness = accumulator.get_witness(b"alice").unwrap();
+        assert!(verify_with_parameters(&params.to_bytes(), &accumulator.state(), b"alice", &witness.nonce(), &witness.witness()).unwrap());
+        assert!(verify_with_parameters(b"tgpp", &accumulator.state(), b"alice", &witness.nonce(), &witness.witness()).is_err());
+    }
+}
@@ -0,0 +1,376 @@
+//! The binary encoding of proofs, for sending them between versions of tangerine and to
+//! implementations in other languages.
+//!
+//! Every encoding starts with the magic bytes `tgwf`, a version byte and a byte for the
+//! kind of proof, followed by the fields of the proof. Integers are big-endian: fixed
+//! width ones as they are, arbitrary ones length-prefixed like byte strings, with a u32
+//! length. The kinds and their fields are
+//!
+//! 1. [`MembershipProof`]: the witness, the nonce and a byte that is 1 if a prime
+//!    certificate follows and 0 if not. A certificate is its base as a u32, the number of
+//!    steps as a u32 and each step's `h` followed by its `a` as a u64.
+//! 2. [`AbsenceProof`]: `a` and `d`.
+//! 3. [`PokeProof`]: `q` and `r`.
+//! 4. [`Poke2Proof`]: `z`, `q` and `r`.
+//!
+//! Nothing may follow the last field. A later version may add kinds, or fields at the end
+//! of a kind, but never changes the ones before, so every version reads the encodings of
+//! the ones before it; encodings from a version newer than [`WIRE_VERSION`] are refused.
+//!
+//! Each kind can also be parsed in place, as a [`MembershipProofView`], [`AbsenceProofView`],
+//! [`PokeProofView`] or [`Poke2ProofView`] borrowing its fields from the buffer, so a
+//! service verifying many proofs only copies out the integers it gets to.
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+use num_bigint::BigUint;
+
+use crate::kv::AbsenceProof;
+use crate::pocklington::{CertificateStep, PrimeCertificate};
+use crate::poke::{Poke2Proof, PokeProof};
+use crate::proof::MembershipProof;
+use crate::snapshot::{split_bytes, write_bytes};
+
+const WIRE_MAGIC: &[u8; 4] = b"tgwf";
+/// The version written, and the newest one read.
+pub const WIRE_VERSION: u8 = 1;
+
+const MEMBERSHIP_KIND: u8 = 1;
+const ABSENCE_KIND: u8 = 2;
+const POKE_KIND: u8 = 3;
+const POKE2_KIND: u8 = 4;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn encode<F: FnOnce(&mut Vec<u8>) -> io::Result<()>>(kind: u8, fields: F) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    bytes.extend_from_slice(WIRE_MAGIC);
+    bytes.extend_from_slice(&[WIRE_VERSION, kind]);
+    // writing to a vector only fails on a field too long to length-prefix, which no
+    // proof over a modulus that fits in memory has
+    fields(&mut bytes).unwrap();
+    bytes
+}
+
+// checks the header and hands the fields, and the version they were written in, to `fields`
+fn decode<'a, T, F: FnOnce(&mut &'a [u8], u8) -> io::Result<T>>(mut bytes: &'a [u8], kind: u8, fields: F) -> io::Result<T> {
+    let mut header: [u8; 6] = [0; 6];
+    bytes.read_exact(&mut header)?;
+    if &header[..4] != WIRE_MAGIC {
+        return Err(invalid("not a tangerine proof"));
+    }
+    if header[4] == 0 || header[4] > WIRE_VERSION {
+        return Err(invalid(&format!("unsupported proof encoding version {}", header[4])));
+    }
+    if header[5] != kind {
+        return Err(invalid(&format!("expected a proof of kind {}, found kind {}", kind, header[5])));
+    }
+    let decoded: T = fields(&mut bytes, header[4])?;
+    if !bytes.is_empty() {
+        return Err(invalid("trailing bytes after the proof"));
+    }
+    Ok(decoded)
+}
+
+fn write_biguint<W: Write>(writer: &mut W, value: &BigUint) -> io::Result<()> {
+    write_bytes(writer, &value.to_bytes_be())
+}
+
+fn write_certificate<W: Write>(writer: &mut W, certificate: &PrimeCertificate) -> io::Result<()> {
+    let steps: u32 = u32::try_from(certificate.steps.len()).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many certificate steps"))?;
+    writer.write_all(&certificate.base.to_be_bytes())?;
+    writer.write_all(&steps.to_be_bytes())?;
+    for step in certificate.steps.iter() {
+        write_biguint(writer, &step.h)?;
+        writer.write_all(&step.a.to_be_bytes())?;
+    }
+    Ok(())
+}
+
+fn split_fixed<const N: usize>(bytes: &mut &[u8]) -> io::Result<[u8; N]> {
+    let mut fixed: [u8; N] = [0; N];
+    bytes.read_exact(&mut fixed)?;
+    Ok(fixed)
+}
+
+// the encoding of a certificate, checked to be whole, at the start of `bytes`
+fn split_certificate<'a>(bytes: &mut &'a [u8]) -> io::Result<&'a [u8]> {
+    let start: &'a [u8] = bytes;
+    let fixed: [u8; 8] = split_fixed(bytes)?;
+    let count: u32 = u32::from_be_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]);
+    for _ in 0..count {
+        split_bytes(bytes)?;
+        split_fixed::<8>(bytes)?;
+    }
+    Ok(&start[..start.len() - bytes.len()])
+}
+
+// a certificate split off by split_certificate, which cannot be cut short
+fn read_certificate(mut bytes: &[u8]) -> PrimeCertificate {
+    let fixed: [u8; 8] = split_fixed(&mut bytes).unwrap();
+    let base: u32 = u32::from_be_bytes([fixed[0], fixed[1], fixed[2], fixed[3]]);
+    let mut steps: Vec<CertificateStep> = Vec::new();
+    while !bytes.is_empty() {
+        let h: BigUint = BigUint::from_bytes_be(split_bytes(&mut bytes).unwrap());
+        steps.push(CertificateStep { h, a: u64::from_be_bytes(split_fixed(&mut bytes).unwrap()) });
+    }
+    PrimeCertificate { base, steps }
+}
+
+impl MembershipProof {
+    /// The encoding of the proof, see [`wire`](crate::wire).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode(MEMBERSHIP_KIND, |writer| {
+            write_biguint(writer, &self.witness)?;
+            write_bytes(writer, &self.nonce)?;
+            match self.certificate.as_ref() {
+                Some(certificate) => {
+                    writer.write_all(&[1])?;
+                    write_certificate(writer, certificate)
+                }
+                None => writer.write_all(&[0]),
+            }
+        })
+    }
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        MembershipProofView::parse(bytes).map(|view| view.to_proof())
+    }
+}
+
+/// A [`MembershipProof`] parsed in place from its encoding. The fields are borrowed from
+/// the buffer and only copied out when asked for.
+#[derive(Debug, Clone, Copy)]
+pub struct MembershipProofView<'a> {
+    witness: &'a [u8],
+    nonce: &'a [u8],
+    certificate: Option<&'a [u8]>,
+}
+
+impl<'a> MembershipProofView<'a> {
+    pub fn parse(bytes: &'a [u8]) -> io::Result<Self> {
+        decode(bytes, MEMBERSHIP_KIND, |reader, _version| {
+            let witness: &[u8] = split_bytes(reader)?;
+            let nonce: &[u8] = split_bytes(reader)?;
+            let certificate: Option<&[u8]> = match split_fixed::<1>(reader)?[0] {
+                0 => None,
+                1 => Some(split_certificate(reader)?),
+                _ => return Err(invalid("bad certificate flag")),
+            };
+            Ok(MembershipProofView { witness, nonce, certificate })
+        })
+    }
+    /// Big-endian bytes of the witness, borrowed from the buffer.
+    pub fn witness_bytes(&self) -> &'a [u8] {
+        self.witness
+    }
+    pub fn witness(&self) -> BigUint {
+        BigUint::from_bytes_be(self.witness)
+    }
+    pub fn nonce(&self) -> &'a [u8] {
+        self.nonce
+    }
+    pub fn certificate(&self) -> Option<PrimeCertificate> {
+        self.certificate.map(read_certificate)
+    }
+    pub fn to_proof(&self) -> MembershipProof {
+        MembershipProof { witness: self.witness(), nonce: self.nonce.to_vec(), certificate: self.certificate() }
+    }
+}
+
+impl AbsenceProof {
+    /// The encoding of the proof, see [`wire`](crate::wire).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode(ABSENCE_KIND, |writer| {
+            write_biguint(writer, &self.a)?;
+            write_biguint(writer, &self.d)
+        })
+    }
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        AbsenceProofView::parse(bytes).map(|view| view.to_proof())
+    }
+}
+
+/// An [`AbsenceProof`] parsed in place from its encoding, see [`MembershipProofView`].
+#[derive(Debug, Clone, Copy)]
+pub struct AbsenceProofView<'a> {
+    a: &'a [u8],
+    d: &'a [u8],
+}
+
+impl<'a> AbsenceProofView<'a> {
+    pub fn parse(bytes: &'a [u8]) -> io::Result<Self> {
+        decode(bytes, ABSENCE_KIND, |reader, _version| Ok(AbsenceProofView { a: split_bytes(reader)?, d: split_bytes(reader)? }))
+    }
+    /// Big-endian bytes of `a`, borrowed from the buffer.
+    pub fn a_bytes(&self) -> &'a [u8] {
+        self.a
+    }
+    /// Big-endian bytes of `d`, borrowed from the buffer.
+    pub fn d_bytes(&self) -> &'a [u8] {
+        self.d
+    }
+    pub fn to_proof(&self) -> AbsenceProof {
+        AbsenceProof { a: BigUint::from_bytes_be(self.a), d: BigUint::from_bytes_be(self.d) }
+    }
+}
+
+impl PokeProof {
+    /// The encoding of the proof, see [`wire`](crate::wire).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode(POKE_KIND, |writer| {
+            write_biguint(writer, &self.q)?;
+            write_biguint(writer, &self.r)
+        })
+    }
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        PokeProofView::parse(bytes).map(|view| view.to_proof())
+    }
+}
+
+/// A [`PokeProof`] parsed in place from its encoding, see [`MembershipProofView`].
+#[derive(Debug, Clone, Copy)]
+pub struct PokeProofView<'a> {
+    q: &'a [u8],
+    r: &'a [u8],
+}
+
+impl<'a> PokeProofView<'a> {
+    pub fn parse(bytes: &'a [u8]) -> io::Result<Self> {
+        decode(bytes, POKE_KIND, |reader, _version| Ok(PokeProofView { q: split_bytes(reader)?, r: split_bytes(reader)? }))
+    }
+    /// Big-endian bytes of `q`, borrowed from the buffer.
+    pub fn q_bytes(&self) -> &'a [u8] {
+        self.q
+    }
+    /// Big-endian bytes of `r`, borrowed from the buffer.
+    pub fn r_bytes(&self) -> &'a [u8] {
+        self.r
+    }
+    pub fn to_proof(&self) -> PokeProof {
+        PokeProof { q: BigUint::from_bytes_be(self.q), r: BigUint::from_bytes_be(self.r) }
+    }
+}
+
+impl Poke2Proof {
+    /// The encoding of the proof, see [`wire`](crate::wire).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode(POKE2_KIND, |writer| {
+            write_biguint(writer, &self.z)?;
+            write_biguint(writer, &self.q)?;
+            write_biguint(writer, &self.r)
+        })
+    }
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        Poke2ProofView::parse(bytes).map(|view| view.to_proof())
+    }
+}
+
+/// A [`Poke2Proof`] parsed in place from its encoding, see [`MembershipProofView`].
+#[derive(Debug, Clone, Copy)]
+pub struct Poke2ProofView<'a> {
+    z: &'a [u8],
+    q: &'a [u8],
+    r: &'a [u8],
+}
+
+impl<'a> Poke2ProofView<'a> {
+    pub fn parse(bytes: &'a [u8]) -> io::Result<Self> {
+        decode(bytes, POKE2_KIND, |reader, _version| {
+            Ok(Poke2ProofView { z: split_bytes(reader)?, q: split_bytes(reader)?, r: split_bytes(reader)? })
+        })
+    }
+    /// Big-endian bytes of `z`, borrowed from the buffer.
+    pub fn z_bytes(&self) -> &'a [u8] {
+        self.z
+    }
+    /// Big-endian bytes of `q`, borrowed from the buffer.
+    pub fn q_bytes(&self) -> &'a [u8] {
+        self.q
+    }
+    /// Big-endian bytes of `r`, borrowed from the buffer.
+    pub fn r_bytes(&self) -> &'a [u8] {
+        self.r
+    }
+    pub fn to_proof(&self) -> Poke2Proof {
+        Poke2Proof { z: BigUint::from_bytes_be(self.z), q: BigUint::from_bytes_be(self.q), r: BigUint::from_bytes_be(self.r) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips() {
+        let proof: MembershipProof = MembershipProof::new(BigUint::from(0x0102_u64), vec![0xab, 0x00]);
+        let bytes: Vec<u8> = proof.to_bytes();
+        // pinned, so that a change to the format cannot go unnoticed
+        assert_eq!(b"tgwf\x01\x01\x00\x00\x00\x02\x01\x02\x00\x00\x00\x02\xab\x00\x00".to_vec(), bytes);
+        assert_eq!(proof, MembershipProof::from_bytes(&bytes).unwrap());
+        let certified: MembershipProof = proof.clone().with_certificate(PrimeCertificate {
+            base: 4_294_967_291,
+            steps: vec![CertificateStep { h: BigUint::from(1_u64) << 40_u32, a: 3 }],
+        });
+        assert_eq!(certified, MembershipProof::from_bytes(&certified.to_bytes()).unwrap());
+
+        let absence: AbsenceProof = AbsenceProof { a: BigUint::from(7_u64), d: BigUint::from(0_u64) };
+        assert_eq!(absence, AbsenceProof::from_bytes(&absence.to_bytes()).unwrap());
+        let poke: PokeProof = PokeProof { q: BigUint::from(5_u64), r: BigUint::from(6_u64) };
+        assert_eq!(poke, PokeProof::from_bytes(&poke.to_bytes()).unwrap());
+        let poke2: Poke2Proof = Poke2Proof { z: BigUint::from(1_u64), q: BigUint::from(2_u64), r: BigUint::from(3_u64) };
+        assert_eq!(poke2, Poke2Proof::from_bytes(&poke2.to_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_views() {
+        let proof: MembershipProof = MembershipProof::new(BigUint::from(0x0102_u64), vec![0xab, 0x00]).with_certificate(PrimeCertificate {
+            base: 3,
+            steps: vec![CertificateStep { h: BigUint::from(1_u64) << 40_u32, a: 3 }, CertificateStep { h: BigUint::from(5_u64), a: 2 }],
+        });
+        let bytes: Vec<u8> = proof.to_bytes();
+        let view: MembershipProofView = MembershipProofView::parse(&bytes).unwrap();
+        // the fields point into the buffer rather than at copies
+        assert_eq!(&[0x01, 0x02], view.witness_bytes());
+        assert!(bytes.as_ptr_range().contains(&view.witness_bytes().as_ptr()));
+        assert_eq!(&[0xab, 0x00], view.nonce());
+        assert_eq!(proof.certificate, view.certificate());
+        assert_eq!(proof, view.to_proof());
+        let uncertified: MembershipProof = MembershipProof::new(BigUint::from(9_u64), Vec::new());
+        assert_eq!(uncertified, MembershipProofView::parse(&uncertified.to_bytes()).unwrap().to_proof());
+
+        let absence: AbsenceProof = AbsenceProof { a: BigUint::from(7_u64), d: BigUint::from(0_u64) };
+        let bytes: Vec<u8> = absence.to_bytes();
+        let view: AbsenceProofView = AbsenceProofView::parse(&bytes).unwrap();
+        assert_eq!((&[7_u8][..], &[0_u8][..]), (view.a_bytes(), view.d_bytes()));
+        assert_eq!(absence, view.to_proof());
+        let poke: PokeProof = PokeProof { q: BigUint::from(5_u64), r: BigUint::from(6_u64) };
+        assert_eq!(poke, PokeProofView::parse(&poke.to_bytes()).unwrap().to_proof());
+        let poke2: Poke2Proof = Poke2Proof { z: BigUint::from(1_u64), q: BigUint::from(2_u64), r: BigUint::from(3_u64) };
+        assert_eq!(poke2, Poke2ProofView::parse(&poke2.to_bytes()).unwrap().to_proof());
+
+        // a certificate cut short is refused when parsed, not when it is asked for
+        let bytes: Vec<u8> = proof.to_bytes();
+        assert!(MembershipProofView::parse(&bytes[..bytes.len() - 3]).is_err());
+        assert!(PokeProofView::parse(&absence.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_rejects_malformed() {
+        let bytes: Vec<u8> = MembershipProof::new(BigUint::from(9_u64), vec![1]).to_bytes();
+        assert!(MembershipProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(MembershipProof::from_bytes(&[&bytes[..], &[0]].concat()).is_err());
+        let mut newer: Vec<u8> = bytes.clone();
+        newer[4] = WIRE_VERSION + 1;
+        assert!(MembershipProof::from_bytes(&newer).is_err());
+        let mut flag: Vec<u8> = bytes.clone();
+        *flag.last_mut().unwrap() = 2;
+        assert!(MembershipProof::from_bytes(&flag).is_err());
+        // a proof of one kind does not decode as another
+        let poke: Vec<u8> = PokeProof { q: BigUint::from(5_u64), r: BigUint::from(6_u64) }.to_bytes();
+        assert!(AbsenceProof::from_bytes(&poke).is_err());
+        assert!(PokeProof::from_bytes(b"tgpb\x01\x03").is_err());
+    }
+}
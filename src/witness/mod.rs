@@ -0,0 +1,164 @@
+//! Keeping witnesses current without the members list. Every change to the state is
+//! published along with the value and nonce involved, and witness holders fold it into
+//! their own witness instead of asking the manager for a fresh one.
+//!
+//! [`WitnessUpdater`] does the same from the audit log a store keeps, for witness holders
+//! that were away for a while and catch up on everything since the epoch their witness
+//! was good for.
+
+use num_bigint::BigUint;
+
+use crate::aggregate::shamir_trick;
+use crate::arith;
+use crate::member_prime;
+use crate::params::{PrimeMapping, PublicParameters, SecurityLevel};
+use crate::proof::MembershipProof;
+use crate::store::{AuditAction, AuditRecord};
+
+/// Brings a witness up to date after `value_added` was accumulated with `nonce_added`.
+/// The old witness satisfied old_witness^x = old_state, and the new state is
+/// old_state^prime, so raising the witness to the added prime is all it takes. The prime
+/// is found with `prime_mapping`, which has to be the accumulator's.
+pub fn update_witness(modulus: &BigUint, old_witness: &BigUint, value_added: &[u8], nonce_added: &[u8], prime_mapping: PrimeMapping) -> BigUint {
+    let prime: BigUint = member_prime(value_added, nonce_added, prime_mapping, SecurityLevel::default().primality());
+    arith::modpow(old_witness, &prime, modulus)
+}
+
+/// Brings the witness in `proof` for `value` up to date after `deleted_value` was removed,
+/// leaving the accumulator at `new_state`, without the trapdoor. With x the prime of
+/// `value` and y the deleted prime, both witness^x and new_state^y equal the old state,
+/// so Shamir's trick yields its x y-th root, which is an x-th root of `new_state`.
+/// Returns None if `value` itself was deleted or the inputs do not belong together.
+pub fn update_witness_on_delete(
+    modulus: &BigUint,
+    value: &[u8],
+    proof: &MembershipProof,
+    deleted_value: &[u8],
+    deleted_nonce: &[u8],
+    new_state: &BigUint,
+    prime_mapping: PrimeMapping,
+) -> Option<BigUint> {
+    let prime: BigUint = member_prime(value, &proof.nonce, prime_mapping, SecurityLevel::default().primality());
+    let deleted_prime: BigUint = member_prime(deleted_value, deleted_nonce, prime_mapping, SecurityLevel::default().primality());
+    shamir_trick(modulus, &proof.witness, &prime, new_state, &deleted_prime)
+}
+
+/// Folds the changes of an audit log, see [`Storer::iter_log`](crate::store::Storer::iter_log),
+/// into stale witnesses. The records carry the primes and states involved, so a refresh
+/// takes one exponentiation per add and one application of Shamir's trick per delete,
+/// and never the members list.
+#[derive(Debug, Clone)]
+pub struct WitnessUpdater {
+    modulus: BigUint,
+    prime_mapping: PrimeMapping,
+}
+
+impl WitnessUpdater {
+    pub fn new(params: &PublicParameters) -> Self {
+        WitnessUpdater { modulus: params.modulus.clone(), prime_mapping: params.prime_mapping() }
+    }
+    /// Brings `proof` for `value`, good at the end of epoch `since`, up to date with the
+    /// records of every later epoch in `log`, which has to hold all of them in the order
+    /// they were made. The proof returned is good for the state of the last record.
+    /// Returns None if `value` was deleted, or the log does not fit the proof.
+    pub fn refresh<I: IntoIterator<Item = AuditRecord>>(&self, value: &[u8], proof: &MembershipProof, since: u64, log: I) -> Option<MembershipProof> {
+        let prime: BigUint = member_prime(value, &proof.nonce, self.prime_mapping, SecurityLevel::default().primality());
+        let mut witness: BigUint = proof.witness.clone();
+        for record in log.into_iter().filter(|record| record.epoch > since) {
+            witness = match record.action {
+                AuditAction::Add => arith::modpow(&witness, &record.prime, &self.modulus),
+                // the same prime twice has no Bezout coefficients, so deleting the value
+                // itself ends here
+                AuditAction::Delete => shamir_trick(&self.modulus, &witness, &prime, &record.new_state, &record.prime)?,
+                // the adds and deletes before it in its epoch already made the changes
+                AuditAction::Rollback => witness,
+            };
+        }
+        Some(MembershipProof { witness, ..proof.clone() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::{HashFunction, PublicParameters, Trapdoor};
+    use crate::setup::generate_modulus;
+    use crate::proof::MembershipProof;
+    use crate::store::mem_store::MemStore;
+    use crate::store::Storer;
+    use crate::SetAccumulator;
+    use num_bigint::RandBigInt;
+
+    #[test]
+    fn test_update_witness() {
+        let mut rng = rand::thread_rng();
+        let modulus: BigUint = rng.gen_biguint(512) | BigUint::from(1_u64);
+        let generator: BigUint = rng.gen_biguint_below(&modulus);
+        // primes found with another hash than the default, which the updates have to follow
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(
+            &PublicParameters::new(modulus.clone(), generator).with_hash_function(HashFunction::Sha512),
+        ));
+        sa.add(b"alice").unwrap();
+        let mut proof: MembershipProof = sa.get_membership_proof(b"alice").unwrap();
+        for value in [b"bob".as_ref(), b"carol".as_ref()].iter() {
+            sa.add(value).unwrap();
+            assert!(!proof.verify(sa.store.get_state(), &modulus, b"alice", sa.prime_mapping()));
+            let nonce: Vec<u8> = sa.store.get_nonce(value).unwrap();
+            proof.witness = update_witness(&modulus, &proof.witness, value, &nonce, sa.prime_mapping());
+            assert!(proof.verify(sa.store.get_state(), &modulus, b"alice", sa.prime_mapping()));
+        }
+        // the updated witness is the one the manager would hand out
+        assert_eq!(sa.get_membership_proof(b"alice").unwrap(), proof);
+    }
+
+    #[test]
+    fn test_update_witness_on_delete() {
+        // a proper modulus, since Shamir's trick has to invert the witness
+        let (modulus, _): (BigUint, Option<Trapdoor>) = generate_modulus(128, false);
+        let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(
+            MemStore::from_parameters(&PublicParameters::new(modulus.clone(), generator))
+        );
+        let values: Vec<&[u8]> = vec![b"alice", b"bob", b"carol"];
+        sa.add_batch(&values).unwrap();
+        let mut proof: MembershipProof = sa.get_membership_proof(b"alice").unwrap();
+        let deleted_proof: MembershipProof = sa.get_membership_proof(b"bob").unwrap();
+        let deleted_nonce: Vec<u8> = sa.store.get_nonce(b"bob").unwrap();
+        let new_state: BigUint = sa.delete_without_trapdoor(b"bob").unwrap();
+        assert!(!proof.verify(&new_state, &modulus, b"alice", sa.prime_mapping()));
+
+        proof.witness = update_witness_on_delete(&modulus, b"alice", &proof, b"bob", &deleted_nonce, &new_state, sa.prime_mapping()).unwrap();
+        assert!(proof.verify(&new_state, &modulus, b"alice", sa.prime_mapping()));
+        assert_eq!(sa.get_membership_proof(b"alice").unwrap(), proof);
+        // the deleted member has nothing to update to
+        assert!(update_witness_on_delete(&modulus, b"bob", &deleted_proof, b"bob", &deleted_nonce, &new_state, sa.prime_mapping()).is_none());
+    }
+
+    #[test]
+    fn test_witness_updater() {
+        let (modulus, _): (BigUint, Option<Trapdoor>) = generate_modulus(128, false);
+        let generator: BigUint = rand::thread_rng().gen_biguint_below(&modulus);
+        let params: PublicParameters = PublicParameters::new(modulus.clone(), generator);
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        sa.add_batch(&[b"alice", b"bob"]).unwrap();
+        let since: u64 = sa.current_epoch();
+        let stale: MembershipProof = sa.get_membership_proof(b"alice").unwrap();
+        let bob: MembershipProof = sa.get_membership_proof(b"bob").unwrap();
+        sa.add(b"carol").unwrap();
+        sa.add_batch(&[b"dave", b"erin"]).unwrap();
+        sa.delete_without_trapdoor(b"carol").unwrap();
+        sa.transaction(|sa| {
+            sa.add(b"frank")?;
+            sa.delete_without_trapdoor(b"dave").map(|_| ())
+        }).unwrap();
+        sa.delete_without_trapdoor(b"bob").unwrap();
+
+        let updater: WitnessUpdater = WitnessUpdater::new(&params);
+        let fresh: MembershipProof = updater.refresh(b"alice", &stale, since, sa.store.iter_log().map(Result::unwrap)).unwrap();
+        assert!(fresh.verify(sa.store.get_state(), &modulus, b"alice", sa.prime_mapping()));
+        assert_eq!(sa.get_membership_proof(b"alice").unwrap(), fresh);
+        // nothing since the current epoch leaves the proof as it is
+        assert_eq!(fresh, updater.refresh(b"alice", &fresh, sa.current_epoch(), sa.store.iter_log().map(Result::unwrap)).unwrap());
+        assert!(updater.refresh(b"bob", &bob, since, sa.store.iter_log().map(Result::unwrap)).is_none());
+    }
+}
@@ -0,0 +1,74 @@
+/* C interface to the tangerine RSA accumulator, see tangerine-ffi/src/lib.rs.
+ *
+ * Big integers are big-endian bytes, values and nonces plain byte strings, each passed
+ * as a pointer and a length. A pointer may be NULL when its length is zero. Functions
+ * return TG_OK or a negative TG_ERROR_ code, except tg_verify, which returns 1 for a
+ * proof that holds and 0 for one that does not, or that could not be checked. */
+
+#ifndef TANGERINE_H
+#define TANGERINE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define TG_OK 0
+#define TG_ERROR_NULL_POINTER -1
+#define TG_ERROR_INVALID_PARAMETERS -2
+#define TG_ERROR_NOT_A_MEMBER -3
+#define TG_ERROR_DUPLICATE_PRIME -4
+#define TG_ERROR_DEGENERATE_RNG -5
+#define TG_ERROR_ACCUMULATOR -6
+#define TG_ERROR_PANIC -7
+#define TG_ERROR_ALREADY_A_MEMBER -8
+
+/* An accumulator over a store in memory. */
+typedef struct TgAccumulator TgAccumulator;
+
+/* Bytes allocated by the library, released with tg_buffer_free. An empty buffer has a
+ * NULL data. */
+typedef struct TgBuffer {
+    uint8_t *data;
+    size_t len;
+} TgBuffer;
+
+/* A new, empty accumulator over the modulus and generator, mapping values to primes the
+ * default way. Released with tg_accumulator_free. */
+int32_t tg_accumulator_new(const uint8_t *modulus, size_t modulus_len,
+                           const uint8_t *generator, size_t generator_len,
+                           TgAccumulator **out);
+
+/* The same over parameters in tangerine's binary encoding, which carries the prime
+ * mapping too. */
+int32_t tg_accumulator_from_parameters(const uint8_t *params, size_t params_len, TgAccumulator **out);
+
+void tg_accumulator_free(TgAccumulator *accumulator);
+
+int32_t tg_add(TgAccumulator *accumulator, const uint8_t *value, size_t value_len);
+
+int32_t tg_get_state(const TgAccumulator *accumulator, TgBuffer *state);
+
+/* The witness of a value and the nonce it was added with, or TG_ERROR_NOT_A_MEMBER. */
+int32_t tg_get_witness(const TgAccumulator *accumulator, const uint8_t *value, size_t value_len,
+                       TgBuffer *witness, TgBuffer *nonce);
+
+/* Whether the witness proves membership of the value at the state, without a handle: 1 if
+ * it does, 0 if it does not, an argument is NULL with a nonzero length, or checking failed.
+ * Never negative, so it can be used as a condition directly. */
+int32_t tg_verify(const uint8_t *state, size_t state_len,
+                  const uint8_t *modulus, size_t modulus_len,
+                  const uint8_t *value, size_t value_len,
+                  const uint8_t *nonce, size_t nonce_len,
+                  const uint8_t *witness, size_t witness_len);
+
+/* Releases the bytes of a buffer and empties it. */
+void tg_buffer_free(TgBuffer *buffer);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
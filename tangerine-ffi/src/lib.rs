@@ -0,0 +1,351 @@
+//! A C interface to tangerine, for services in other languages that want the accumulator
+//! without reimplementing the hash to prime mapping. `include/tangerine.h` declares it.
+//!
+//! An accumulator is an opaque handle from [`tg_accumulator_new`], released with
+//! [`tg_accumulator_free`]. Big integers are passed as big-endian bytes and values and
+//! nonces as plain byte strings, each as a pointer and a length. A pointer may be null
+//! when its length is zero. Bytes handed back are in a [`TgBuffer`] owned by the caller,
+//! who releases it with [`tg_buffer_free`].
+//!
+//! Functions return [`TG_OK`] or one of the negative `TG_ERROR_` codes, except
+//! [`tg_verify`], which returns 1 for a proof that holds and 0 for one that does not.
+//! A panic never crosses the boundary, it is reported as [`TG_ERROR_PANIC`].
+
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use num_bigint::BigUint;
+use tangerine::params::PublicParameters;
+use tangerine::store::mem_store::MemStore;
+use tangerine::store::Storer;
+use tangerine::{AccumulatorError, SetAccumulator};
+
+pub const TG_OK: i32 = 0;
+/// A required pointer was null.
+pub const TG_ERROR_NULL_POINTER: i32 = -1;
+/// The parameters could not be decoded.
+pub const TG_ERROR_INVALID_PARAMETERS: i32 = -2;
+pub const TG_ERROR_NOT_A_MEMBER: i32 = -3;
+pub const TG_ERROR_DUPLICATE_PRIME: i32 = -4;
+pub const TG_ERROR_DEGENERATE_RNG: i32 = -5;
+/// Any other [`AccumulatorError`].
+pub const TG_ERROR_ACCUMULATOR: i32 = -6;
+pub const TG_ERROR_PANIC: i32 = -7;
+pub const TG_ERROR_ALREADY_A_MEMBER: i32 = -8;
+
+/// An accumulator over a store in memory.
+pub struct TgAccumulator {
+    inner: SetAccumulator<MemStore>,
+}
+
+/// Bytes allocated by the library. An empty buffer has a null `data`.
+#[repr(C)]
+pub struct TgBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl TgBuffer {
+    fn empty() -> Self {
+        TgBuffer { data: ptr::null_mut(), len: 0 }
+    }
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        if bytes.is_empty() {
+            return TgBuffer::empty();
+        }
+        let boxed: Box<[u8]> = bytes.into_boxed_slice();
+        let len: usize = boxed.len();
+        TgBuffer { data: Box::into_raw(boxed) as *mut u8, len }
+    }
+}
+
+fn error_code(error: AccumulatorError) -> i32 {
+    match error {
+        AccumulatorError::NotAMember => TG_ERROR_NOT_A_MEMBER,
+        AccumulatorError::DuplicatePrime => TG_ERROR_DUPLICATE_PRIME,
+        AccumulatorError::DegenerateRng => TG_ERROR_DEGENERATE_RNG,
+        AccumulatorError::AlreadyAMember => TG_ERROR_ALREADY_A_MEMBER,
+        _ => TG_ERROR_ACCUMULATOR,
+    }
+}
+
+// the bytes at `data`, which may be null only when `len` is zero
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    if len == 0 {
+        Some(&[])
+    } else if data.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(data, len))
+    }
+}
+
+fn catch<F: FnOnce() -> i32>(f: F) -> i32 {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(TG_ERROR_PANIC)
+}
+
+fn new_handle(params: &PublicParameters) -> *mut TgAccumulator {
+    Box::into_raw(Box::new(TgAccumulator { inner: SetAccumulator::new(MemStore::from_parameters(params)) }))
+}
+
+/// Stores a new, empty accumulator over the big-endian `modulus` and `generator` in
+/// `out`, mapping values to primes the default way.
+///
+/// # Safety
+///
+/// `modulus` and `generator` must point to `modulus_len` and `generator_len` readable
+/// bytes and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn tg_accumulator_new(
+    modulus: *const u8,
+    modulus_len: usize,
+    generator: *const u8,
+    generator_len: usize,
+    out: *mut *mut TgAccumulator,
+) -> i32 {
+    catch(|| {
+        let (modulus, generator) = match (bytes(modulus, modulus_len), bytes(generator, generator_len)) {
+            (Some(modulus), Some(generator)) if !out.is_null() => (modulus, generator),
+            _ => return TG_ERROR_NULL_POINTER,
+        };
+        let params: PublicParameters = PublicParameters::new(BigUint::from_bytes_be(modulus), BigUint::from_bytes_be(generator));
+        *out = new_handle(&params);
+        TG_OK
+    })
+}
+
+/// [`tg_accumulator_new`] over parameters in the encoding of
+/// [`PublicParameters::to_bytes`], which carries the prime mapping too.
+///
+/// # Safety
+///
+/// `params` must point to `params_len` readable bytes and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn tg_accumulator_from_parameters(params: *const u8, params_len: usize, out: *mut *mut TgAccumulator) -> i32 {
+    catch(|| {
+        let params: &[u8] = match bytes(params, params_len) {
+            Some(params) if !out.is_null() => params,
+            _ => return TG_ERROR_NULL_POINTER,
+        };
+        match PublicParameters::from_bytes(params) {
+            Ok(params) => {
+                *out = new_handle(&params);
+                TG_OK
+            }
+            Err(_) => TG_ERROR_INVALID_PARAMETERS,
+        }
+    })
+}
+
+/// Releases an accumulator. Null is ignored.
+///
+/// # Safety
+///
+/// `accumulator` must come from this library and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tg_accumulator_free(accumulator: *mut TgAccumulator) {
+    if !accumulator.is_null() {
+        drop(Box::from_raw(accumulator));
+    }
+}
+
+/// Adds `value` to the accumulator.
+///
+/// # Safety
+///
+/// `accumulator` must be a live handle and `value` must point to `value_len` readable
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn tg_add(accumulator: *mut TgAccumulator, value: *const u8, value_len: usize) -> i32 {
+    catch(|| {
+        let (accumulator, value) = match (accumulator.as_mut(), bytes(value, value_len)) {
+            (Some(accumulator), Some(value)) => (accumulator, value),
+            _ => return TG_ERROR_NULL_POINTER,
+        };
+        match accumulator.inner.add(value) {
+            Ok(_) => TG_OK,
+            Err(e) => error_code(e),
+        }
+    })
+}
+
+/// Writes the big-endian state of the accumulator to `state`.
+///
+/// # Safety
+///
+/// `accumulator` must be a live handle and `state` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn tg_get_state(accumulator: *const TgAccumulator, state: *mut TgBuffer) -> i32 {
+    catch(|| {
+        let accumulator: &TgAccumulator = match accumulator.as_ref() {
+            Some(accumulator) if !state.is_null() => accumulator,
+            _ => return TG_ERROR_NULL_POINTER,
+        };
+        *state = TgBuffer::from_vec(accumulator.inner.store.get_state().to_bytes_be());
+        TG_OK
+    })
+}
+
+/// Writes the big-endian witness of `value` and the nonce it was added with to
+/// `witness` and `nonce`, or returns [`TG_ERROR_NOT_A_MEMBER`] and leaves both alone.
+///
+/// # Safety
+///
+/// `accumulator` must be a live handle, `value` must point to `value_len` readable bytes
+/// and `witness` and `nonce` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn tg_get_witness(
+    accumulator: *const TgAccumulator,
+    value: *const u8,
+    value_len: usize,
+    witness: *mut TgBuffer,
+    nonce: *mut TgBuffer,
+) -> i32 {
+    catch(|| {
+        let (accumulator, value) = match (accumulator.as_ref(), bytes(value, value_len)) {
+            (Some(accumulator), Some(value)) if !witness.is_null() && !nonce.is_null() => (accumulator, value),
+            _ => return TG_ERROR_NULL_POINTER,
+        };
+        match accumulator.inner.get_witness(value) {
+            Some((w, n)) => {
+                *witness = TgBuffer::from_vec(w.to_bytes_be());
+                *nonce = TgBuffer::from_vec(n);
+                TG_OK
+            }
+            None => TG_ERROR_NOT_A_MEMBER,
+        }
+    })
+}
+
+/// Checks that `witness` proves membership of `value` in the accumulator at `state`
+/// over `modulus`, for accumulators mapping values to primes the default way. Needs no
+/// handle, so a verifier never holds the set.
+///
+/// Returns 1 for a proof that holds and 0 otherwise, including when an argument is null
+/// or checking it panicked: C takes any other value as true, and a proof that could not
+/// be checked must not pass an `if (tg_verify(...))`.
+///
+/// # Safety
+///
+/// Every pointer must point to as many readable bytes as its length says.
+#[no_mangle]
+pub unsafe extern "C" fn tg_verify(
+    state: *const u8,
+    state_len: usize,
+    modulus: *const u8,
+    modulus_len: usize,
+    value: *const u8,
+    value_len: usize,
+    nonce: *const u8,
+    nonce_len: usize,
+    witness: *const u8,
+    witness_len: usize,
+) -> i32 {
+    catch(|| {
+        let inputs = (
+            bytes(state, state_len),
+            bytes(modulus, modulus_len),
+            bytes(value, value_len),
+            bytes(nonce, nonce_len),
+            bytes(witness, witness_len),
+        );
+        let (state, modulus, value, nonce, witness) = match inputs {
+            (Some(state), Some(modulus), Some(value), Some(nonce), Some(witness)) => (state, modulus, value, nonce, witness),
+            _ => return 0,
+        };
+        let state: BigUint = BigUint::from_bytes_be(state);
+        let modulus: BigUint = BigUint::from_bytes_be(modulus);
+        let witness: BigUint = BigUint::from_bytes_be(witness);
+        tangerine::verify_membership(&state, &modulus, value, nonce, &witness) as i32
+    })
+    // a panic is a proof that was not checked either
+    .max(0)
+}
+
+/// Releases the bytes in `buffer` and empties it. An empty buffer is left alone.
+///
+/// # Safety
+///
+/// `buffer` must be null or hold bytes from this library that were not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn tg_buffer_free(buffer: *mut TgBuffer) {
+    if let Some(buffer) = buffer.as_mut() {
+        if !buffer.data.is_null() {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len)));
+        }
+        *buffer = TgBuffer::empty();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn buffer_bytes(buffer: &TgBuffer) -> Vec<u8> {
+        bytes(buffer.data, buffer.len).unwrap().to_vec()
+    }
+
+    #[test]
+    fn test_add_witness_verify() {
+        unsafe {
+            let modulus: Vec<u8> = BigUint::from(3233_u64).to_bytes_be();
+            let generator: Vec<u8> = BigUint::from(42_u64).to_bytes_be();
+            let mut accumulator: *mut TgAccumulator = ptr::null_mut();
+            assert_eq!(TG_OK, tg_accumulator_new(modulus.as_ptr(), modulus.len(), generator.as_ptr(), generator.len(), &mut accumulator));
+            assert_eq!(TG_OK, tg_add(accumulator, b"alice".as_ptr(), 5));
+            assert_eq!(TG_OK, tg_add(accumulator, b"bob".as_ptr(), 3));
+
+            let mut state: TgBuffer = TgBuffer::empty();
+            let mut witness: TgBuffer = TgBuffer::empty();
+            let mut nonce: TgBuffer = TgBuffer::empty();
+            assert_eq!(TG_OK, tg_get_state(accumulator, &mut state));
+            assert_eq!(TG_OK, tg_get_witness(accumulator, b"alice".as_ptr(), 5, &mut witness, &mut nonce));
+            assert_eq!(TG_ERROR_NOT_A_MEMBER, tg_get_witness(accumulator, b"carol".as_ptr(), 5, &mut witness, &mut nonce));
+
+            let (state_bytes, witness_bytes, nonce_bytes) = (buffer_bytes(&state), buffer_bytes(&witness), buffer_bytes(&nonce));
+            let verify = |value: &[u8]| {
+                tg_verify(
+                    state_bytes.as_ptr(),
+                    state_bytes.len(),
+                    modulus.as_ptr(),
+                    modulus.len(),
+                    value.as_ptr(),
+                    value.len(),
+                    nonce_bytes.as_ptr(),
+                    nonce_bytes.len(),
+                    witness_bytes.as_ptr(),
+                    witness_bytes.len(),
+                )
+            };
+            assert_eq!(1, verify(b"alice"));
+            assert_eq!(0, verify(b"bob"));
+
+            tg_buffer_free(&mut state);
+            tg_buffer_free(&mut witness);
+            tg_buffer_free(&mut nonce);
+            assert!(state.data.is_null());
+            tg_accumulator_free(accumulator);
+        }
+    }
+
+    #[test]
+    fn test_bad_arguments() {
+        unsafe {
+            let mut accumulator: *mut TgAccumulator = ptr::null_mut();
+            assert_eq!(TG_ERROR_NULL_POINTER, tg_add(ptr::null_mut(), ptr::null(), 0));
+            assert_eq!(TG_ERROR_NULL_POINTER, tg_accumulator_new(ptr::null(), 1, ptr::null(), 0, &mut accumulator));
+            assert_eq!(TG_ERROR_INVALID_PARAMETERS, tg_accumulator_from_parameters(b"tgpp".as_ptr(), 4, &mut accumulator));
+            assert!(accumulator.is_null());
+            let params: Vec<u8> = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64)).to_bytes();
+            assert_eq!(TG_OK, tg_accumulator_from_parameters(params.as_ptr(), params.len(), &mut accumulator));
+            // values may be empty, with a null pointer
+            assert_eq!(TG_OK, tg_add(accumulator, ptr::null(), 0));
+            tg_accumulator_free(accumulator);
+            // a proof that cannot be read does not hold, rather than coming back as a nonzero error
+            let one: [u8; 1] = [1];
+            assert_eq!(0, tg_verify(ptr::null(), 1, one.as_ptr(), 1, ptr::null(), 0, ptr::null(), 0, one.as_ptr(), 1));
+            tg_accumulator_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Node.js bindings to tangerine through N-API, for services that run the accumulator
+//! themselves and find the big-integer arithmetic of the `wasm` build too slow.
+//!
+//! Parameters are a `Buffer` in the encoding of [`PublicParameters::to_bytes`], so they
+//! carry the prime mapping with them. Values, nonces, states and witnesses are `Buffer`s
+//! too, big integers big-endian as they are through the C interface. Errors are thrown
+//! as `Error`s with the message of the [`AccumulatorError`](tangerine::AccumulatorError).
+//!
+//! `npm run build` builds the addon into `tangerine.node`, which `index.js` loads.
+
+use napi::bindgen_prelude::{AsyncTask, Buffer};
+use napi::{Env, Error, Result, Task};
+use napi_derive::napi;
+use num_bigint::BigUint;
+use tangerine::params::{PublicParameters, RSA2048_GENERATOR};
+use tangerine::setup::generate_modulus;
+use tangerine::store::mem_store::MemStore;
+use tangerine::store::Storer;
+use tangerine::SetAccumulator;
+
+fn parse_parameters(params: &[u8]) -> Result<PublicParameters> {
+    PublicParameters::from_bytes(params).map_err(|e| Error::from_reason(format!("parameters could not be decoded: {}", e)))
+}
+
+// generating a modulus takes seconds at real sizes, so it runs on the libuv pool rather
+// than the event loop
+pub struct Setup {
+    bits: Option<u32>,
+}
+
+impl Task for Setup {
+    type Output = Vec<u8>;
+    type JsValue = Buffer;
+
+    fn compute(&mut self) -> Result<Vec<u8>> {
+        let params: PublicParameters = match self.bits {
+            None => PublicParameters::rsa2048(),
+            Some(bits) if bits >= 16 && bits % 2 == 0 => {
+                // the factorization is not kept, nobody gets to forge witnesses
+                PublicParameters::new(generate_modulus(bits as usize, false).0, BigUint::from(RSA2048_GENERATOR))
+            }
+            Some(_) => return Err(Error::from_reason("modulus bits must be an even number, at least 16")),
+        };
+        Ok(params.to_bytes())
+    }
+    fn resolve(&mut self, _: Env, output: Vec<u8>) -> Result<Buffer> {
+        Ok(output.into())
+    }
+}
+
+/// Resolves to fresh parameters over a modulus of `modulusBits` bits whose factorization
+/// is thrown away, or to the RSA-2048 challenge modulus when no size is given.
+#[napi(ts_return_type = "Promise<Buffer>")]
+pub fn setup(modulus_bits: Option<u32>) -> AsyncTask<Setup> {
+    AsyncTask::new(Setup { bits: modulus_bits })
+}
+
+/// A witness and the nonce it goes with.
+#[napi(object)]
+pub struct Witness {
+    pub witness: Buffer,
+    pub nonce: Buffer,
+}
+
+/// An accumulator held in memory.
+#[napi]
+pub struct Accumulator {
+    inner: SetAccumulator<MemStore>,
+}
+
+#[napi]
+impl Accumulator {
+    /// An empty accumulator over `params` from [`setup`].
+    #[napi(constructor)]
+    pub fn new(params: Buffer) -> Result<Self> {
+        let params: PublicParameters = parse_parameters(&params)?;
+        Ok(Accumulator { inner: SetAccumulator::new(MemStore::from_parameters(&params)) })
+    }
+    /// Adds `value` and returns the nonce it was added with.
+    #[napi]
+    pub fn add(&mut self, value: Buffer) -> Result<Buffer> {
+        let added = self.inner.add(&value).map_err(|e| Error::from_reason(e.to_string()))?;
+        Ok(added.nonce.into())
+    }
+    #[napi]
+    pub fn has(&self, value: Buffer) -> bool {
+        self.inner.contains(&value)
+    }
+    #[napi(getter)]
+    pub fn size(&self) -> u32 {
+        self.inner.len() as u32
+    }
+    #[napi(getter)]
+    pub fn state(&self) -> Buffer {
+        self.inner.store.get_state().to_bytes_be().into()
+    }
+    /// The witness of `value`, `null` for values that are not members.
+    #[napi]
+    pub fn witness(&self, value: Buffer) -> Option<Witness> {
+        self.inner.get_witness(&value).map(|(witness, nonce)| Witness { witness: witness.to_bytes_be().into(), nonce: nonce.into() })
+    }
+    /// Checks `witness` against the current state.
+    #[napi]
+    pub fn verify(&self, value: Buffer, witness: Witness) -> bool {
+        self.inner.verify(&value, &BigUint::from_bytes_be(&witness.witness), &witness.nonce)
+    }
+}
+
+/// Checks that `witness` proves `value` a member of the accumulator over `params` at
+/// `state`, without the accumulator at hand.
+#[napi]
+pub fn verify(params: Buffer, state: Buffer, value: Buffer, witness: Witness) -> Result<bool> {
+    let params: PublicParameters = parse_parameters(&params)?;
+    Ok(tangerine::verify_membership_with(
+        params.prime_mapping(),
+        &BigUint::from_bytes_be(&state),
+        &params.modulus,
+        &value,
+        &witness.nonce,
+        &BigUint::from_bytes_be(&witness.witness),
+    ))
+}
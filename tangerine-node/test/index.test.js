@@ -0,0 +1,30 @@
+const test = require('node:test');
+const assert = require('node:assert');
+
+const { setup, Accumulator, verify } = require('..');
+
+test('add, witness and verify', async () => {
+  const params = await setup(64);
+  const accumulator = new Accumulator(params);
+  const nonce = accumulator.add(Buffer.from('alice'));
+  accumulator.add(Buffer.from('bob'));
+  assert.strictEqual(accumulator.size, 2);
+  assert.ok(accumulator.has(Buffer.from('alice')));
+
+  const witness = accumulator.witness(Buffer.from('alice'));
+  assert.ok(witness.nonce.equals(nonce));
+  assert.ok(accumulator.verify(Buffer.from('alice'), witness));
+  // a verifier needs only the parameters and the state
+  assert.ok(verify(params, accumulator.state, Buffer.from('alice'), witness));
+  assert.strictEqual(accumulator.witness(Buffer.from('carol')), null);
+
+  assert.throws(() => accumulator.add(Buffer.from('alice')));
+  assert.throws(() => new Accumulator(Buffer.from('tgpp')));
+});
+
+test('setup', async () => {
+  // without a size the parameters are over the RSA-2048 challenge modulus
+  const params = await setup();
+  assert.ok(new Accumulator(params).state.length > 0);
+  await assert.rejects(setup(15));
+});
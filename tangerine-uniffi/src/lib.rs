@@ -0,0 +1,215 @@
+//! UniFFI bindings to the verifying side of tangerine, for apps that check membership
+//! proofs on the device rather than asking a server to: parsing public parameters,
+//! verifying witnesses and proofs, and following the heads an accumulator publishes as a
+//! light client. The Swift and Kotlin sources are generated from the built library:
+//!
+//! `cargo run -p tangerine-uniffi --features tangerine-uniffi/bindgen --bin uniffi-bindgen -- generate --library target/debug/libtangerine_uniffi.so --language swift --out-dir out`
+//!
+//! and likewise with `--language kotlin`. Big integers cross as big-endian bytes, as they
+//! do through the C interface, and proofs in the [`wire`](tangerine::wire) encoding.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use num_bigint::BigUint;
+use tangerine::head::Head;
+use tangerine::params::PublicParameters;
+use tangerine::proof::MembershipProof;
+use tangerine::verify_membership_with;
+
+uniffi::setup_scaffolding!();
+
+#[derive(Debug, PartialEq, Eq, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum TangerineError {
+    /// The parameters could not be decoded.
+    InvalidParameters,
+    /// The proof could not be decoded.
+    InvalidProof,
+    /// A head's digests are not 32 bytes long.
+    InvalidHead,
+    /// The heads do not extend the one the light client is at.
+    BrokenChain,
+}
+
+impl fmt::Display for TangerineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TangerineError::InvalidParameters => write!(f, "parameters could not be decoded"),
+            TangerineError::InvalidProof => write!(f, "proof could not be decoded"),
+            TangerineError::InvalidHead => write!(f, "head digests must be 32 bytes"),
+            TangerineError::BrokenChain => write!(f, "heads do not extend the current head"),
+        }
+    }
+}
+
+/// The public parameters of an accumulator.
+#[derive(uniffi::Object)]
+pub struct Parameters {
+    inner: PublicParameters,
+}
+
+#[uniffi::export]
+impl Parameters {
+    /// Decodes parameters in the encoding of [`PublicParameters::to_bytes`].
+    #[uniffi::constructor]
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Arc<Self>, TangerineError> {
+        let inner: PublicParameters = PublicParameters::from_bytes(&bytes).map_err(|_| TangerineError::InvalidParameters)?;
+        Ok(Arc::new(Parameters { inner }))
+    }
+    /// Parameters over `modulus` and `generator` with the default prime mapping.
+    #[uniffi::constructor]
+    pub fn new(modulus: Vec<u8>, generator: Vec<u8>) -> Arc<Self> {
+        Arc::new(Parameters { inner: PublicParameters::new(BigUint::from_bytes_be(&modulus), BigUint::from_bytes_be(&generator)) })
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.to_bytes()
+    }
+    pub fn digest(&self) -> Vec<u8> {
+        self.inner.digest().to_vec()
+    }
+    pub fn modulus(&self) -> Vec<u8> {
+        self.inner.modulus.to_bytes_be()
+    }
+    /// The head an accumulator over these parameters starts from.
+    pub fn genesis_head(&self) -> PublishedHead {
+        PublishedHead::from(&Head::genesis(&self.inner))
+    }
+}
+
+/// A [`Head`] as the accumulator publishes it.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct PublishedHead {
+    pub sequence: u64,
+    pub state: Vec<u8>,
+    pub previous: Vec<u8>,
+    pub operations: Vec<u8>,
+}
+
+impl From<&Head> for PublishedHead {
+    fn from(head: &Head) -> Self {
+        PublishedHead { sequence: head.sequence, state: head.state.to_bytes_be(), previous: head.previous.to_vec(), operations: head.operations.to_vec() }
+    }
+}
+
+fn to_head(head: &PublishedHead) -> Result<Head, TangerineError> {
+    let digest = |bytes: &[u8]| -> Result<[u8; 32], TangerineError> {
+        let mut digest: [u8; 32] = [0; 32];
+        if bytes.len() != digest.len() {
+            return Err(TangerineError::InvalidHead);
+        }
+        digest.copy_from_slice(bytes);
+        Ok(digest)
+    };
+    Ok(Head { sequence: head.sequence, state: BigUint::from_bytes_be(&head.state), previous: digest(&head.previous)?, operations: digest(&head.operations)? })
+}
+
+// a wire encoded proof checked against `state`, by its certificate where the parameters
+// call for certified primes
+fn check_proof(params: &PublicParameters, state: &BigUint, value: &[u8], proof: &[u8]) -> Result<bool, TangerineError> {
+    let proof: MembershipProof = MembershipProof::from_bytes(proof).map_err(|_| TangerineError::InvalidProof)?;
+    if params.prime_mapping().certified {
+        return Ok(proof.verify_certified(state, &params.modulus, value, params.prime_mapping()));
+    }
+    Ok(proof.verify(state, &params.modulus, value, params.prime_mapping()))
+}
+
+/// Whether `witness` and `nonce` prove `value` to be a member at `state`.
+#[uniffi::export]
+pub fn verify_witness(params: Arc<Parameters>, state: Vec<u8>, value: Vec<u8>, nonce: Vec<u8>, witness: Vec<u8>) -> bool {
+    let params: &PublicParameters = &params.inner;
+    verify_membership_with(params.prime_mapping(), &BigUint::from_bytes_be(&state), &params.modulus, &value, &nonce, &BigUint::from_bytes_be(&witness))
+}
+
+/// Whether the wire encoded `proof` proves `value` to be a member at `state`.
+#[uniffi::export]
+pub fn verify_proof(params: Arc<Parameters>, state: Vec<u8>, value: Vec<u8>, proof: Vec<u8>) -> Result<bool, TangerineError> {
+    check_proof(&params.inner, &BigUint::from_bytes_be(&state), &value, &proof)
+}
+
+/// Keeps track of the latest head of an accumulator, starting from one the app trusts,
+/// and checks proofs against it. A head is only taken once it is shown to extend the
+/// current one, so a server cannot rewrite the states the client has already seen.
+#[derive(uniffi::Object)]
+pub struct LightClient {
+    params: PublicParameters,
+    head: Mutex<Head>,
+}
+
+impl LightClient {
+    fn current(&self) -> Head {
+        self.head.lock().unwrap().clone()
+    }
+}
+
+#[uniffi::export]
+impl LightClient {
+    #[uniffi::constructor]
+    pub fn new(params: Arc<Parameters>, trusted: PublishedHead) -> Result<Arc<Self>, TangerineError> {
+        Ok(Arc::new(LightClient { params: params.inner.clone(), head: Mutex::new(to_head(&trusted)?) }))
+    }
+    pub fn head(&self) -> PublishedHead {
+        PublishedHead::from(&self.current())
+    }
+    /// Moves on to the last of `heads`, each of which has to extend the one before it,
+    /// the first the current head. Returns the sequence number reached, and on failure
+    /// stays where it was.
+    pub fn advance(&self, heads: Vec<PublishedHead>) -> Result<u64, TangerineError> {
+        let mut current = self.head.lock().unwrap();
+        let mut reached: Head = current.clone();
+        for head in heads.iter() {
+            let head: Head = to_head(head)?;
+            if !head.follows(&reached) {
+                return Err(TangerineError::BrokenChain);
+            }
+            reached = head;
+        }
+        *current = reached;
+        Ok(current.sequence)
+    }
+    /// [`verify_witness`] against the current head.
+    pub fn verify_witness(&self, value: Vec<u8>, nonce: Vec<u8>, witness: Vec<u8>) -> bool {
+        let state: BigUint = self.current().state;
+        verify_membership_with(self.params.prime_mapping(), &state, &self.params.modulus, &value, &nonce, &BigUint::from_bytes_be(&witness))
+    }
+    /// [`verify_proof`] against the current head.
+    pub fn verify_proof(&self, value: Vec<u8>, proof: Vec<u8>) -> Result<bool, TangerineError> {
+        check_proof(&self.params, &self.current().state, &value, &proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tangerine::store::mem_store::MemStore;
+    use tangerine::store::Storer;
+    use tangerine::SetAccumulator;
+
+    #[test]
+    fn test_light_client() {
+        let params: PublicParameters = PublicParameters::new(BigUint::from(3233_u64), BigUint::from(42_u64));
+        let mut sa: SetAccumulator<MemStore> = SetAccumulator::new(MemStore::from_parameters(&params));
+        let parameters: Arc<Parameters> = Parameters::from_bytes(params.to_bytes()).unwrap();
+        assert_eq!(Err(TangerineError::InvalidParameters), Parameters::from_bytes(vec![0x01]).map(|_| ()));
+        let client: Arc<LightClient> = LightClient::new(parameters.clone(), parameters.genesis_head()).unwrap();
+
+        // the server publishes a head per batch, chained to the one before
+        let genesis: Head = Head::genesis(&params);
+        let nonce: Vec<u8> = sa.add(b"alice").unwrap().nonce;
+        let first: Head = genesis.next(sa.store.get_state().clone(), &[(b"alice".to_vec(), nonce)]);
+        let nonce: Vec<u8> = sa.add(b"bob").unwrap().nonce;
+        let second: Head = first.next(sa.store.get_state().clone(), &[(b"bob".to_vec(), nonce)]);
+        let heads: Vec<PublishedHead> = vec![PublishedHead::from(&first), PublishedHead::from(&second)];
+        // a head that skips one is refused, and the client stays where it was
+        assert_eq!(Err(TangerineError::BrokenChain), client.advance(heads[1..].to_vec()));
+        assert_eq!(0, client.head().sequence);
+        assert_eq!(Ok(2), client.advance(heads));
+
+        let proof: MembershipProof = sa.get_membership_proof(b"alice").unwrap();
+        assert_eq!(Ok(true), client.verify_proof(b"alice".to_vec(), proof.to_bytes()));
+        assert!(client.verify_witness(b"alice".to_vec(), proof.nonce.clone(), proof.witness.to_bytes_be()));
+        assert!(verify_witness(parameters.clone(), sa.store.get_state().to_bytes_be(), b"alice".to_vec(), proof.nonce.clone(), proof.witness.to_bytes_be()));
+        assert_eq!(Ok(true), verify_proof(parameters, sa.store.get_state().to_bytes_be(), b"alice".to_vec(), proof.to_bytes()));
+        assert_eq!(Err(TangerineError::InvalidProof), client.verify_proof(b"alice".to_vec(), vec![0x01]));
+    }
+}